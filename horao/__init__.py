@@ -64,6 +64,21 @@ def init_api() -> Starlette:
         logging.warning("CORS is set to *")
     routes = [
         Route("/ping", endpoint=horao.api.alive_controller.is_alive, methods=["GET"]),
+        Route(
+            "/version",
+            endpoint=horao.api.version_controller.get_version,
+            methods=["GET"],
+        ),
+        Route(
+            "/alerts",
+            endpoint=horao.api.alerts_controller.get_alerts,
+            methods=["GET"],
+        ),
+        Route(
+            "/servers",
+            endpoint=horao.api.servers_controller.get_servers,
+            methods=["GET"],
+        ),
         Route("/openapi.json", endpoint=openapi_schema, include_in_schema=False),
     ]
     module_root = os.path.dirname(os.path.dirname(__file__))
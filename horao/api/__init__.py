@@ -1,2 +1,6 @@
 # -*- coding: utf-8 -*-#
+from horao.api.alerts_controller import get_alerts
 from horao.api.alive_controller import is_alive
+from horao.api.health_controller import health_response
+from horao.api.servers_controller import get_servers, servers_response
+from horao.api.version_controller import get_version
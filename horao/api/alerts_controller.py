@@ -0,0 +1,39 @@
+# -*- coding: utf-8 -*-#
+import logging
+
+from starlette.authentication import requires
+from starlette.requests import Request  # type: ignore
+from starlette.responses import JSONResponse  # type: ignore
+
+from horao.controllers.alerting import AlertState
+
+
+@requires("authenticated")
+async def get_alerts(request: Request) -> JSONResponse:
+    """
+    responses:
+      200:
+        description: alerts known to the alert manager, filtered with ?state=firing|resolved
+        examples:
+          [{"rule": "down", "serial_number": "sw1", "message": "sw1 is down", "state": "firing", "since": 0, "grouped": []}]
+      400:
+        description: Unknown state
+      403:
+        description: Unauthorized
+      503:
+        description: No alert manager configured
+    """
+    logging.info(f"Calling Get Alerts ({request})")
+    manager = getattr(request.app.state, "alert_manager", None)
+    if manager is None:
+        return JSONResponse(status_code=503, content={"error": "alerting disabled"})
+    state = request.query_params.get("state")
+    states = {s.name.lower(): s for s in AlertState}
+    if state is not None and state not in states:
+        return JSONResponse(status_code=400, content={"error": f"unknown state {state}"})
+    return JSONResponse(
+        status_code=200,
+        content=[
+            a.to_dict() for a in manager.alerts(states[state] if state else None)
+        ],
+    )
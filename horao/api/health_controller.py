@@ -0,0 +1,24 @@
+# -*- coding: utf-8 -*-#
+"""Health check
+
+The health response is framework-agnostic, it returns the HTTP status code and the JSON body so it can be plugged into
+any router.
+"""
+import json
+from typing import Tuple
+
+from horao.models.hardware import DataCenter
+from horao.models.health import HealthState, health_report
+from horao.models.network import DataCenterNetwork
+
+
+def health_response(dc: DataCenter, network: DataCenterNetwork) -> Tuple[int, str]:
+    """
+    Health check response for the given datacenter and network
+    :param dc: datacenter to report on
+    :param network: network to report on
+    :return: tuple of HTTP status code (200 when healthy or degraded, 503 when critical) and JSON body
+    """
+    report = health_report(dc, network)
+    status_code = 503 if report.state == HealthState.Critical else 200
+    return status_code, json.dumps(report.to_dict())
@@ -0,0 +1,93 @@
+# -*- coding: utf-8 -*-#
+"""Server listing
+
+Lists the servers of the model a page at a time, filtered with the query parameters status, tag and min_cores. The
+page is selected with page_size and cursor, the response carries the cursor of the next page (null on the last page).
+The total number of matching servers is only computed when asked for with total=true.
+
+Like the health check, the response is framework-agnostic and can be plugged into any router.
+"""
+import json
+import logging
+from typing import Any, Dict, Mapping, Tuple
+
+from starlette.authentication import requires
+from starlette.requests import Request  # type: ignore
+from starlette.responses import JSONResponse  # type: ignore
+
+from horao.models.hardware import InvalidCursor, Server
+from horao.models.status import DeviceStatus
+from horao.models.store import ModelStore
+
+DEFAULT_PAGE_SIZE = 100
+MAX_PAGE_SIZE = 1000
+
+
+def _summary(server: Server) -> Dict[str, Any]:
+    return {
+        "serial_number": server.serial_number,
+        "name": server.name,
+        "status": server.status.name,
+        "tenant": server.tenant,
+        "cores": sum(c.cores for c in server.cpu),
+        "labels": server.labels,
+    }
+
+
+def servers_response(store: ModelStore, params: Mapping[str, str]) -> Tuple[int, str]:
+    """
+    Page of the servers of the store
+    :param store: store holding the model
+    :param params: query parameters (page_size, cursor, total, status, tag, min_cores)
+    :return: tuple of HTTP status code (200, or 400 for invalid parameters) and JSON body
+    """
+    try:
+        page_size = min(int(params.get("page_size", DEFAULT_PAGE_SIZE)), MAX_PAGE_SIZE)
+        statuses = {s.name.lower(): s for s in DeviceStatus}
+        with store.read():
+            query = store.datacenter.query()
+            if "status" in params:
+                if params["status"].lower() not in statuses:
+                    raise ValueError(f"unknown status {params['status']}")
+                query.with_status(statuses[params["status"].lower()])
+            if "tag" in params:
+                query.with_tag(params["tag"])
+            if "min_cores" in params:
+                query.with_min_cores(int(params["min_cores"]))
+            page = query.page(
+                page_size, params.get("cursor"), params.get("total") == "true"
+            )
+    except InvalidCursor:
+        return 400, json.dumps({"error": "invalid cursor"})
+    except ValueError as e:
+        return 400, json.dumps({"error": str(e)})
+    content: Dict[str, Any] = {
+        "items": [_summary(s) for s in page.items],
+        "next_cursor": page.next_cursor,
+    }
+    if page.total is not None:
+        content["total"] = page.total
+    return 200, json.dumps(content)
+
+
+@requires("authenticated")
+async def get_servers(request: Request) -> JSONResponse:
+    """
+    responses:
+      200:
+        description: page of the servers, filtered with ?status=up|down, ?tag=gpu or ?tag=role=compute, ?min_cores=32
+        examples:
+          {"items": [{"serial_number": "srv1", "name": "srv1", "status": "Up", "tenant": null, "cores": 32, "labels": {}}], "next_cursor": null}
+      400:
+        description: Invalid page size, cursor or filter
+      403:
+        description: Unauthorized
+      503:
+        description: No model loaded
+    """
+    logging.info(f"Calling Get Servers ({request})")
+    store = getattr(request.app.state, "store", None)
+    if store is None:
+        return JSONResponse(status_code=503, content={"error": "no model loaded"})
+    status_code, body = servers_response(store, request.query_params)
+    return JSONResponse(status_code=status_code, content=json.loads(body))
@@ -0,0 +1,25 @@
+# -*- coding: utf-8 -*-#
+import logging
+
+from starlette.authentication import requires
+from starlette.requests import Request  # type: ignore
+from starlette.responses import JSONResponse  # type: ignore
+
+from horao.models.versioning import MODEL_SCHEMA_VERSION
+
+
+@requires("authenticated")
+async def get_version(request: Request) -> JSONResponse:
+    """
+    responses:
+      200:
+        description: model schema version.
+        examples:
+          {"schema_version": "1.0"}
+      403:
+        description: Unauthorized
+    """
+    logging.info(f"Calling Get Version ({request})")
+    return JSONResponse(
+        status_code=200, content={"schema_version": MODEL_SCHEMA_VERSION}
+    )
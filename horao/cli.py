@@ -0,0 +1,112 @@
+# -*- coding: utf-8 -*-#
+"""Command line interface
+
+    horao history <serial> --field status --since 2024-01-01T00:00 [--until ...] [--store snapshots]
+    horao forecast [--horizon-days 30] [--since ...] [--store snapshots]
+    horao zones [--ignore-status] [--json] [--store snapshots]
+"""
+import argparse
+import json
+import sys
+from datetime import datetime
+from enum import Enum
+from typing import List, Optional
+
+from horao.models.forecasting import exhaustion_estimates
+from horao.models.snapshot import SnapshotStore
+from horao.models.zones import server_zones
+from horao.settings import Settings
+
+
+def _timestamp(value: str) -> float:
+    try:
+        return float(value)
+    except ValueError:
+        return datetime.fromisoformat(value).timestamp()
+
+
+def _format(value) -> str:
+    return value.name if isinstance(value, Enum) else str(value)
+
+
+def history(args: argparse.Namespace) -> int:
+    store = SnapshotStore(args.store)
+    for timestamp, value in store.history_of(
+        args.serial, args.field, args.since, args.until
+    ):
+        print(f"{datetime.fromtimestamp(timestamp).isoformat()}\t{_format(value)}")
+    return 0
+
+
+def forecast(args: argparse.Namespace) -> int:
+    store = SnapshotStore(args.store)
+    for estimate in exhaustion_estimates(
+        store, args.horizon_days * 86400, args.since, args.until
+    ):
+        print(
+            f"{estimate.kind} {estimate.serial_number}\t{estimate.current:g}/{estimate.limit:g}\t"
+            f"full at {datetime.fromtimestamp(estimate.exhausted_at).isoformat()}"
+        )
+    return 0
+
+
+def zones(args: argparse.Namespace) -> int:
+    store = SnapshotStore(args.store)
+    timestamps = store.timestamps()
+    if not timestamps:
+        print(f"No snapshots in {args.store}", file=sys.stderr)
+        return 1
+    snapshot = store.load(timestamps[-1])
+    reports = [
+        server_zones(snapshot.datacenter, network, args.ignore_status)
+        for network in snapshot.networks
+    ]
+    if args.json:
+        print(json.dumps([r.to_dict() for r in reports], indent=2))
+        return 0
+    for report in reports:
+        for server in report.servers:
+            # firewalls of a protected server, the unmediated path to an exposed server
+            detail = ",".join(server.firewalls) or " -> ".join(server.example_path)
+            print(
+                f"{report.network}\t{server.serial_number}\t{server.zone.name}\t{detail}"
+            )
+    return 0
+
+
+def main(argv: Optional[List[str]] = None) -> int:
+    settings = Settings.from_env()
+    parser = argparse.ArgumentParser(prog="horao")
+    commands = parser.add_subparsers(dest="command", required=True)
+    history_parser = commands.add_parser(
+        "history", help="time series of a device field from the snapshot store"
+    )
+    history_parser.add_argument("serial", help="serial number of the device")
+    history_parser.add_argument("--field", default="status", help="(dotted) field")
+    history_parser.add_argument("--since", type=_timestamp, help="ISO date or epoch")
+    history_parser.add_argument("--until", type=_timestamp, help="ISO date or epoch")
+    history_parser.add_argument("--store", default=settings.snapshot_path)
+    history_parser.set_defaults(handler=history)
+    forecast_parser = commands.add_parser(
+        "forecast", help="projected exhaustion of capacity from the snapshot store"
+    )
+    forecast_parser.add_argument("--horizon-days", type=float, default=30)
+    forecast_parser.add_argument("--since", type=_timestamp, help="ISO date or epoch")
+    forecast_parser.add_argument("--until", type=_timestamp, help="ISO date or epoch")
+    forecast_parser.add_argument("--store", default=settings.snapshot_path)
+    forecast_parser.set_defaults(handler=forecast)
+    zones_parser = commands.add_parser(
+        "zones", help="security zones of the servers in the latest snapshot"
+    )
+    zones_parser.add_argument(
+        "--ignore-status", action="store_true", help="treat devices that are down as up"
+    )
+    zones_parser.add_argument("--json", action="store_true", help="output as JSON")
+    zones_parser.add_argument("--store", default=settings.snapshot_path)
+    zones_parser.set_defaults(handler=zones)
+    args = parser.parse_args(argv)
+    return args.handler(args)
+
+
+if __name__ == "__main__":
+    sys.exit(main())
@@ -0,0 +1,221 @@
+# -*- coding: utf-8 -*-#
+"""Alerting
+
+This module turns the state of the model into alerts. Rules are evaluated periodically against the model, alerts are
+deduplicated per rule and device so an incident does not flood the receivers; a re-notification is only sent after
+the configured interval. Port alerts of a switch that is down are grouped under the switch alert.
+"""
+import itertools
+import json
+import logging
+import time
+import urllib.error
+import urllib.request
+from enum import Enum, auto
+from typing import Callable, Dict, List, Optional, Tuple
+
+from horao.models.hardware import DataCenter
+from horao.models.network import DataCenterNetwork, Switch
+from horao.models.status import DeviceStatus
+
+
+class AlertRuleType(Enum):
+    # a server or network device is down
+    DeviceDown = auto()
+    # a linked switch port is down
+    PortDown = auto()
+    # a server bond has lost its redundancy
+    RedundancyLost = auto()
+    # a disk is filled beyond the threshold (fraction)
+    DiskUsage = auto()
+
+
+class AlertRule:
+    def __init__(self, name: str, rule_type: AlertRuleType, threshold: float = 0):
+        self.name = name
+        self.rule_type = rule_type
+        self.threshold = threshold
+
+
+class AlertState(Enum):
+    Firing = auto()
+    Resolved = auto()
+
+
+class Alert:
+    def __init__(self, rule: str, serial_number: str, message: str, since: float):
+        self.rule = rule
+        self.serial_number = serial_number
+        self.message = message
+        self.state = AlertState.Firing
+        self.since = since
+        self.last_notified = since
+        self.grouped: List[str] = []
+
+    def to_dict(self) -> dict:
+        return {
+            "rule": self.rule,
+            "serial_number": self.serial_number,
+            "message": self.message,
+            "state": self.state.name.lower(),
+            "since": self.since,
+            "grouped": self.grouped,
+        }
+
+
+class AlertEvent:
+    def __init__(self, alert: Alert, state: AlertState, timestamp: float):
+        self.alert = alert
+        self.state = state
+        self.timestamp = timestamp
+
+    def to_dict(self) -> dict:
+        return dict(
+            self.alert.to_dict(),
+            state=self.state.name.lower(),
+            timestamp=self.timestamp,
+        )
+
+
+class AlertManager:
+    def __init__(
+        self,
+        rules: List[AlertRule],
+        renotify_interval: float = 3600,
+        sink: Optional[Callable[[AlertEvent], None]] = None,
+    ):
+        """
+        Evaluates alert rules and keeps track of the alert state
+        :param rules: rules to evaluate
+        :param renotify_interval: seconds before a firing alert is notified again
+        :param sink: receiver of the alert events (e.g. a WebhookSink)
+        """
+        self.rules = rules
+        self.renotify_interval = renotify_interval
+        self.sink = sink
+        self._alerts: Dict[Tuple[str, str], Alert] = {}
+
+    def alerts(self, state: Optional[AlertState] = None) -> List[Alert]:
+        """
+        Known alerts, optionally filtered by state
+        :param state: state to filter on
+        :return: list of alerts
+        """
+        return [a for a in self._alerts.values() if state is None or a.state == state]
+
+    @staticmethod
+    def _conditions(
+        rule: AlertRule, dc: DataCenter, network: DataCenterNetwork
+    ) -> Dict[str, Tuple[str, List[str]]]:
+        conditions: Dict[str, Tuple[str, List[str]]] = {}
+        if rule.rule_type == AlertRuleType.DeviceDown:
+            for device in itertools.chain(dc.servers(), network.devices()):
+                if device.status != DeviceStatus.Up:
+                    conditions[device.serial_number] = (f"{device.name} is down", [])
+        elif rule.rule_type == AlertRuleType.PortDown:
+            for left, right, link in network.links():
+                for device in (left, right):
+                    port = network.peer_port(link, device)
+                    if isinstance(device, Switch) and port.status != DeviceStatus.Up:
+                        conditions[port.serial_number] = (
+                            f"port {port.name} of {device.name} is down",
+                            [device.serial_number],
+                        )
+        elif rule.rule_type == AlertRuleType.RedundancyLost:
+            for serial_number in dc.bonds_without_redundancy(network):
+                conditions[serial_number] = (
+                    f"{serial_number} lost bond redundancy",
+                    [],
+                )
+        elif rule.rule_type == AlertRuleType.DiskUsage:
+            for server in dc.servers():
+                for disk in server.disk or []:
+                    if disk.usage_gb is not None and disk.size_gb:
+                        if disk.usage_gb / disk.size_gb >= rule.threshold:
+                            conditions[disk.serial_number] = (
+                                f"disk {disk.name} of {server.name} is {disk.usage_gb / disk.size_gb:.0%} full",
+                                [],
+                            )
+        return conditions
+
+    def evaluate(
+        self, dc: DataCenter, network: DataCenterNetwork, now: Optional[float] = None
+    ) -> List[AlertEvent]:
+        """
+        Evaluate all rules against the model, firing new alerts, re-notifying alerts that are firing longer than the
+        re-notify interval and resolving alerts whose condition cleared.
+        :param dc: datacenter to evaluate
+        :param network: network to evaluate
+        :param now: timestamp of the evaluation (defaults to the current time)
+        :return: list of events that were emitted
+        """
+        now = time.time() if now is None else now
+        active: Dict[Tuple[str, str], Tuple[str, List[str]]] = {}
+        for rule in self.rules:
+            for serial_number, condition in self._conditions(rule, dc, network).items():
+                active[(rule.name, serial_number)] = condition
+        # port alerts of a device that is down are collapsed into the device alert
+        down = {
+            serial_number: (rule_name, serial_number)
+            for rule_name, serial_number in active
+            if any(
+                r.name == rule_name and r.rule_type == AlertRuleType.DeviceDown
+                for r in self.rules
+            )
+        }
+        grouped: Dict[Tuple[str, str], List[str]] = {}
+        for key, (_, parents) in list(active.items()):
+            parent = next((down[p] for p in parents if p in down), None)
+            if parent:
+                grouped.setdefault(parent, []).append(key[1])
+                del active[key]
+        events = []
+        for key, (message, _) in active.items():
+            alert = self._alerts.get(key)
+            if alert is None or alert.state == AlertState.Resolved:
+                alert = Alert(key[0], key[1], message, now)
+                self._alerts[key] = alert
+                events.append(AlertEvent(alert, AlertState.Firing, now))
+            elif now - alert.last_notified >= self.renotify_interval:
+                alert.last_notified = now
+                events.append(AlertEvent(alert, AlertState.Firing, now))
+            alert.grouped = sorted(grouped.get(key, []))
+        for key, alert in self._alerts.items():
+            if key not in active and alert.state == AlertState.Firing:
+                alert.state = AlertState.Resolved
+                alert.since = now
+                events.append(AlertEvent(alert, AlertState.Resolved, now))
+        if self.sink:
+            for event in events:
+                self.sink(event)
+        return events
+
+
+class WebhookSink:
+    def __init__(self, url: str, retries: int = 3, backoff: float = 1.0):
+        """
+        Posts alert events as JSON to a webhook, failed posts are retried with an exponential backoff
+        :param url: url to post to
+        :param retries: number of retries after the first attempt
+        :param backoff: seconds to wait before the first retry, doubled on every retry
+        """
+        self.url = url
+        self.retries = retries
+        self.backoff = backoff
+
+    def __call__(self, event: AlertEvent) -> None:
+        request = urllib.request.Request(
+            self.url,
+            data=json.dumps(event.to_dict()).encode("utf-8"),
+            headers={"Content-Type": "application/json"},
+            method="POST",
+        )
+        for attempt in range(self.retries + 1):
+            try:
+                with urllib.request.urlopen(request, timeout=10):
+                    return
+            except (urllib.error.URLError, OSError) as e:
+                logging.warning(f"Posting alert to {self.url} failed ({e})")
+                if attempt < self.retries:
+                    time.sleep(self.backoff * 2**attempt)
+        logging.error(f"Giving up posting alert to {self.url}")
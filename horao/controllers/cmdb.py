@@ -0,0 +1,306 @@
+# -*- coding: utf-8 -*-#
+"""CMDB synchronization
+
+Keeps a CMDB in sync with the model without full exports: the changes of the model store are collected, batched over
+a window (cmdb_batch_window seconds, at most cmdb_batch_size changes per batch) and posted to the CMDB as a generic
+JSON change feed. Synchronization is enabled by configuring cmdb_endpoint.
+
+Every change has a sequence number, delivery is at-least-once: the sequence number of the last change the CMDB
+acknowledged (a 2xx response) is kept in a cursor file, a failed post is retried with an exponential backoff (see the
+retry settings) and a batch that exhausts its retries is appended to a dead-letter file. Batches are posted one at a
+time in order of sequence and a retried batch is resent as a whole, so the changes to an entity arrive in order. The
+highest sequence number that was posted is kept next to the cursor ('<cursor>.issued'), a restarted feed continues
+after it, so the numbers of dead-lettered (or unacknowledged) batches are never reused for other changes.
+
+Only the events of the store are followed, not its audit log: the log is written by the store alone, and every
+mutation it records as successful is also published as an event (failed mutations change nothing), so following both
+would report every change twice.
+
+A change in the feed:
+
+    {"sequence": 12, "timestamp": 1700000000.0, "kind": "switch", "serial_number": "sw1", "operation": "update",
+     "changes": {"status": {"old": "Up", "new": "Down"}}}
+"""
+import json
+import logging
+import os
+import time
+import urllib.error
+import urllib.request
+from typing import Any, Callable, Dict, List, Optional
+
+from horao.models.audit import Operation
+from horao.models.store import ModelChanged, ModelEvent, ModelStore, StatusChanged
+from horao.settings import Settings
+
+
+class Change:
+    def __init__(
+        self,
+        sequence: int,
+        timestamp: float,
+        kind: str,
+        serial_number: str,
+        operation: str,
+        changes: Dict[str, Dict[str, Any]],
+    ):
+        """
+        Change of an entity of the model
+        :param sequence: position in the change feed, starting at 1
+        :param timestamp: time of the change
+        :param kind: kind of the entity (e.g. 'switch', 'server', 'link')
+        :param serial_number: serial number of the entity, 'left:right' for a link
+        :param operation: 'create', 'update' or 'delete'
+        :param changes: old and new value per changed field
+        """
+        self.sequence = sequence
+        self.timestamp = timestamp
+        self.kind = kind
+        self.serial_number = serial_number
+        self.operation = operation
+        self.changes = changes
+
+    def to_dict(self) -> Dict[str, Any]:
+        return {
+            "sequence": self.sequence,
+            "timestamp": self.timestamp,
+            "kind": self.kind,
+            "serial_number": self.serial_number,
+            "operation": self.operation,
+            "changes": self.changes,
+        }
+
+
+class CmdbSync:
+    def __init__(
+        self,
+        settings: Optional[Settings] = None,
+        clock: Callable[[], float] = time.time,
+        sleep: Callable[[float], None] = time.sleep,
+    ):
+        """
+        Change feed towards a CMDB
+        :param settings: endpoint, batching and retry settings, read from the environment if not given
+        :param clock: function returning the current time
+        :param sleep: function used to wait between attempts
+        :raises: ValueError if no endpoint is configured
+        """
+        self.settings = settings if settings else Settings.from_env()
+        if not self.settings.cmdb_endpoint:
+            raise ValueError("No CMDB endpoint configured (cmdb_endpoint)")
+        self.clock = clock
+        self.sleep = sleep
+        self.store: Optional[ModelStore] = None
+        self.cursor = self._read_sequence(self.settings.cmdb_cursor_path)
+        self._issued = max(self.cursor, self._read_sequence(self._issued_path))
+        self._sequence = self._issued
+        self._pending: List[Change] = []
+        self._window_start: Optional[float] = None
+
+    @property
+    def _issued_path(self) -> str:
+        return f"{self.settings.cmdb_cursor_path}.issued"
+
+    @staticmethod
+    def _read_sequence(path: str) -> int:
+        try:
+            with open(path, "r") as f:
+                return int(f.read().strip() or 0)
+        except FileNotFoundError:
+            return 0
+        except (OSError, ValueError) as e:
+            raise ValueError(f"Cursor {path} is unreadable ({e})")
+
+    @staticmethod
+    def _write_sequence(path: str, sequence: int) -> None:
+        # write and rename, a crash never leaves a truncated cursor behind
+        temporary = f"{path}.tmp"
+        with open(temporary, "w") as f:
+            f.write(str(sequence))
+        os.replace(temporary, path)
+
+    def _write_cursor(self, sequence: int) -> None:
+        self._write_sequence(self.settings.cmdb_cursor_path, sequence)
+        self.cursor = sequence
+
+    def attach(self, store: ModelStore) -> None:
+        """
+        Subscribe to the changes of a store
+        :param store: store to follow
+        :return: None
+        """
+        self.store = store
+        store.subscribe(self.on_event, individual=True)
+
+    def _kind(self, serial_number: str) -> str:
+        if self.store is None:
+            return "device"
+        try:
+            device = self.store.datacenter.find_device(
+                serial_number, self.store.networks
+            )
+        except ValueError:
+            return "device"
+        return type(device).__name__.lower()
+
+    def _add(
+        self,
+        kind: str,
+        serial_number: str,
+        operation: str,
+        changes: Dict[str, Dict[str, Any]],
+        timestamp: Optional[float] = None,
+    ) -> None:
+        self._sequence += 1
+        now = self.clock()
+        self._pending.append(
+            Change(
+                self._sequence,
+                now if timestamp is None else timestamp,
+                kind,
+                serial_number,
+                operation,
+                changes,
+            )
+        )
+        if self._window_start is None:
+            self._window_start = now
+
+    def on_event(self, event: ModelEvent) -> None:
+        """
+        Record the change carried by an event of the store, batches of status changes are recorded through their
+        individual changes
+        :param event: event of the store
+        :return: None
+        """
+        if isinstance(event, StatusChanged):
+            self._add(
+                self._kind(event.serial_number),
+                event.serial_number,
+                "update",
+                {"status": {"old": event.previous.name, "new": event.status.name}},
+                event.context.timestamp,
+            )
+        elif isinstance(event, ModelChanged):
+            payload = event.payload
+            if event.operation in (Operation.Link, Operation.Unlink):
+                link = ["network", "left", "right"]
+                created = event.operation == Operation.Link
+                self._add(
+                    "link",
+                    f"{payload['left']}:{payload['right']}",
+                    "create" if created else "delete",
+                    {
+                        f: {
+                            "old": None if created else payload[f],
+                            "new": payload[f] if created else None,
+                        }
+                        for f in link
+                    },
+                )
+            elif event.operation == Operation.AddRule:
+                self._add(
+                    "firewall",
+                    payload["firewall"],
+                    "update",
+                    {"rules": {"old": None, "new": payload["rule"]}},
+                )
+            elif event.operation == Operation.RemoveRule:
+                self._add(
+                    "firewall",
+                    payload["firewall"],
+                    "update",
+                    {"rules": {"old": payload["name"], "new": None}},
+                )
+            elif event.operation == Operation.SetTenant:
+                self._add(
+                    "server",
+                    payload["serial_number"],
+                    "update",
+                    {"tenant": {"old": payload["previous"], "new": payload["tenant"]}},
+                )
+
+    @property
+    def pending(self) -> List[Change]:
+        return list(self._pending)
+
+    def due(self) -> bool:
+        """
+        Whether a batch should be posted: the window has passed or a full batch is waiting
+        :return: True if there is a batch to post
+        """
+        if not self._pending or self._window_start is None:
+            return False
+        return (
+            len(self._pending) >= self.settings.cmdb_batch_size
+            or self.clock() - self._window_start >= self.settings.cmdb_batch_window
+        )
+
+    def tick(self) -> int:
+        """
+        Post the pending changes if they are due, to be called periodically
+        :return: number of changes acknowledged
+        """
+        return self.flush() if self.due() else 0
+
+    def flush(self) -> int:
+        """
+        Post all pending changes, batch by batch
+        :return: number of changes acknowledged
+        """
+        acknowledged = 0
+        while self._pending:
+            batch = self._pending[: max(1, self.settings.cmdb_batch_size)]
+            # recorded before the numbers leave the process, never handed out again
+            if batch[-1].sequence > self._issued:
+                self._write_sequence(self._issued_path, batch[-1].sequence)
+                self._issued = batch[-1].sequence
+            if self._deliver(batch):
+                self._write_cursor(batch[-1].sequence)
+                acknowledged += len(batch)
+            else:
+                self._dead_letter(batch)
+            self._pending = self._pending[len(batch) :]
+        self._window_start = None
+        return acknowledged
+
+    def _post(self, batch: List[Change]) -> None:
+        headers = {"Content-Type": "application/json"}
+        if self.settings.cmdb_auth_header:
+            headers["Authorization"] = self.settings.cmdb_auth_header.reveal()
+        request = urllib.request.Request(
+            self.settings.cmdb_endpoint,
+            data=json.dumps({"changes": [c.to_dict() for c in batch]}).encode("utf-8"),
+            headers=headers,
+            method="POST",
+        )
+        with urllib.request.urlopen(request, timeout=10):
+            pass
+
+    def _deliver(self, batch: List[Change]) -> bool:
+        attempts = max(1, self.settings.retry_max_attempts)
+        for attempt in range(attempts):
+            try:
+                self._post(batch)
+                return True
+            except (urllib.error.URLError, OSError) as e:
+                logging.warning(
+                    f"Posting changes {batch[0].sequence}-{batch[-1].sequence} to the CMDB failed ({e}), "
+                    f"attempt {attempt + 1} out of {attempts}"
+                )
+                if attempt < attempts - 1:
+                    self.sleep(
+                        min(
+                            self.settings.retry_max_delay,
+                            self.settings.retry_base_delay * 2**attempt,
+                        )
+                    )
+        return False
+
+    def _dead_letter(self, batch: List[Change]) -> None:
+        logging.error(
+            f"Giving up posting changes {batch[0].sequence}-{batch[-1].sequence} to the CMDB, "
+            f"written to {self.settings.cmdb_dead_letter_path}"
+        )
+        with open(self.settings.cmdb_dead_letter_path, "a") as f:
+            f.write(json.dumps({"changes": [c.to_dict() for c in batch]}) + "\n")
@@ -0,0 +1,76 @@
+# -*- coding: utf-8 -*-#
+"""Inventory providers
+
+Providers load the datacenter inventory from a source of truth (e.g. a CMDB). Loading from a remote source can fail
+transiently, the RetryingProvider retries those failures with an exponential backoff. Invalid inventories are raised
+as ValueError and are never retried.
+"""
+import logging
+import random
+import time
+from abc import ABC, abstractmethod
+from typing import Callable, Optional
+
+from horao.models.hardware import DataCenter
+from horao.settings import Settings
+
+
+class TransientError(Exception):
+    """Raised by a provider when loading failed for a reason that may go away (timeouts, unavailable source)."""
+
+    pass
+
+
+class InventoryProvider(ABC):
+    @abstractmethod
+    def load(self) -> DataCenter:
+        """
+        Load the inventory
+        :return: DataCenter
+        :raises: TransientError if loading failed temporarily, ValueError if the inventory is invalid
+        """
+        pass
+
+
+class RetryingProvider(InventoryProvider):
+    def __init__(
+        self,
+        provider: InventoryProvider,
+        settings: Optional[Settings] = None,
+        sleep: Callable[[float], None] = time.sleep,
+    ):
+        """
+        Wraps a provider, retrying transient failures
+        :param provider: provider to wrap
+        :param settings: retry settings, read from the environment if not given
+        :param sleep: function used to wait between attempts
+        """
+        self.provider = provider
+        self.settings = settings if settings else Settings.from_env()
+        self.sleep = sleep
+
+    def delay(self, attempt: int) -> float:
+        """
+        Seconds to wait after the given (zero based) failed attempt
+        :param attempt: number of the attempt that failed
+        :return: delay including jitter
+        """
+        delay = min(
+            self.settings.retry_max_delay, self.settings.retry_base_delay * 2**attempt
+        )
+        return delay + random.uniform(0, self.settings.retry_jitter * delay)
+
+    def load(self) -> DataCenter:
+        attempts = max(1, self.settings.retry_max_attempts)
+        for attempt in range(attempts):
+            try:
+                return self.provider.load()
+            except TransientError as e:
+                if attempt == attempts - 1:
+                    raise
+                delay = self.delay(attempt)
+                logging.warning(
+                    f"Loading inventory failed ({e}), attempt {attempt + 1} out of {attempts}, retrying in {delay:.1f}s"
+                )
+                self.sleep(delay)
+        raise TransientError("no attempts made")
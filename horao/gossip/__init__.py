@@ -0,0 +1,2 @@
+# -*- coding: utf-8 -*-#
+"""Gossip between peers, dissemination of model updates."""
@@ -0,0 +1,190 @@
+# -*- coding: utf-8 -*-#
+"""Dissemination of updates
+
+Updates are piggybacked on the datagrams exchanged between peers. When more updates are pending than fit in a
+datagram, the updates with the highest priority go first: status transitions away from Up of core and distribution
+devices, followed by other status updates and finally numeric refreshes (e.g. disk usage). The priority is derived
+from the content of an update by a PriorityFunction, operators can plug in their own.
+
+An update that is not sent in a round ages, every gossip_aging_rounds rounds of waiting raise its priority by one
+level so low priority updates are never starved. An update is piggybacked on gossip_max_retransmissions datagrams,
+after that it is dropped from the queue and counted in dropped_updates. A newer update for the same device and field
+replaces a pending one.
+
+Updates are dicts, status updates have the format of Decoder.status_batch (serial_number, status, source, timestamp),
+refreshes carry a serial_number, field and value. Datagrams are written in the binary encoding (see binary.py).
+"""
+from abc import ABC, abstractmethod
+from typing import Any, Dict, List, Optional, Set, Tuple
+
+from horao.models.binary import pack, unpack
+from horao.models.network import (
+    DataCenterNetwork,
+    Router,
+    RouterType,
+    Switch,
+    SwitchType,
+)
+from horao.models.status import DeviceStatus
+from horao.settings import Settings
+
+# status transition away from Up of a core or distribution device
+PRIORITY_CRITICAL = 3
+# any other status update
+PRIORITY_STATUS = 2
+# numeric refreshes
+PRIORITY_REFRESH = 1
+
+
+class PriorityFunction(ABC):
+    @abstractmethod
+    def priority(self, update: Dict[str, Any]) -> int:
+        """
+        Priority of an update, higher goes first
+        :param update: update to prioritize
+        :return: priority
+        """
+        pass
+
+
+class DefaultPriority(PriorityFunction):
+    def __init__(self, core_devices: Optional[Set[str]] = None):
+        """
+        Default priorities (see PRIORITY_CRITICAL, PRIORITY_STATUS and PRIORITY_REFRESH)
+        :param core_devices: serial numbers of the core and distribution devices
+        """
+        self.core_devices = core_devices if core_devices else set()
+
+    @classmethod
+    def of(cls, networks: List[DataCenterNetwork]) -> "DefaultPriority":
+        """
+        Default priorities for the core and distribution devices of the networks
+        :param networks: networks of the datacenter
+        :return: DefaultPriority
+        """
+        return cls(
+            {
+                n.serial_number
+                for network in networks
+                for n in network.graph.nodes
+                if (
+                    isinstance(n, Switch)
+                    and n.switch_type in (SwitchType.Core, SwitchType.Distribution)
+                )
+                or (isinstance(n, Router) and n.router_type == RouterType.Core)
+            }
+        )
+
+    def priority(self, update: Dict[str, Any]) -> int:
+        if "status" not in update:
+            return PRIORITY_REFRESH
+        if (
+            update["status"] != DeviceStatus.Up.name
+            and update.get("serial_number") in self.core_devices
+        ):
+            return PRIORITY_CRITICAL
+        return PRIORITY_STATUS
+
+
+class PendingUpdate:
+    def __init__(self, update: Dict[str, Any], priority: int, sequence: int):
+        """
+        Update waiting to be (re)transmitted
+        :param priority: priority of the update
+        :param sequence: order in which the update was queued
+        """
+        self.update = update
+        self.priority = priority
+        self.sequence = sequence
+        # rounds since the update was last sent
+        self.age = 0
+        self.transmissions = 0
+
+    @property
+    def key(self) -> Tuple[str, str]:
+        return self.update.get("serial_number", ""), self.update.get("field", "status")
+
+
+class Dissemination:
+    def __init__(
+        self,
+        priority: Optional[PriorityFunction] = None,
+        settings: Optional[Settings] = None,
+    ):
+        """
+        Queue of the updates to piggyback on outgoing datagrams
+        :param priority: priority function, DefaultPriority (without core devices) if not given
+        :param settings: settings for the retransmissions and aging, defaults if not given
+        """
+        settings = settings if settings else Settings()
+        self.priority = priority if priority else DefaultPriority()
+        self.max_retransmissions = settings.gossip_max_retransmissions
+        self.aging_rounds = settings.gossip_aging_rounds
+        self.dropped_updates = 0
+        self._pending: Dict[Tuple[str, str], PendingUpdate] = {}
+        self._sequence = 0
+
+    def __len__(self) -> int:
+        return len(self._pending)
+
+    def enqueue(self, update: Dict[str, Any]) -> None:
+        """
+        Queue an update, replacing a pending update for the same device and field
+        :param update: update to disseminate
+        :return: None
+        :raises: ValueError if the update has no serial number
+        """
+        if "serial_number" not in update:
+            raise ValueError(f"Update {update} has no serial number")
+        pending = PendingUpdate(update, self.priority.priority(update), self._sequence)
+        self._sequence += 1
+        self._pending[pending.key] = pending
+
+    def effective_priority(self, pending: PendingUpdate) -> int:
+        """
+        Priority of a pending update, including the levels gained by waiting
+        :param pending: pending update
+        :return: priority
+        """
+        return pending.priority + pending.age // self.aging_rounds
+
+    def assemble(self, max_bytes: int) -> bytes:
+        """
+        Fill a datagram with the pending updates, highest (effective) priority first, the oldest first within a
+        priority. Updates that do not fit wait for the next round.
+        :param max_bytes: size of the datagram
+        :return: datagram
+        """
+        ordered = sorted(
+            self._pending.values(),
+            key=lambda p: (-self.effective_priority(p), -p.age, p.sequence),
+        )
+        selected: List[PendingUpdate] = []
+        for pending in ordered:
+            if len(pack([p.update for p in selected + [pending]])) <= max_bytes:
+                selected.append(pending)
+        for pending in list(self._pending.values()):
+            if pending not in selected:
+                pending.age += 1
+                continue
+            pending.age = 0
+            pending.transmissions += 1
+            if pending.transmissions >= self.max_retransmissions:
+                del self._pending[pending.key]
+                self.dropped_updates += 1
+        return pack([p.update for p in selected])
+
+    @staticmethod
+    def updates(datagram: bytes) -> List[Dict[str, Any]]:
+        """
+        Updates carried by a received datagram
+        :param datagram: datagram
+        :return: updates
+        :raises: ValueError if the datagram is malformed
+        """
+        updates = unpack(datagram)
+        if not isinstance(updates, list) or not all(
+            isinstance(u, dict) for u in updates
+        ):
+            raise ValueError("A datagram should contain a list of updates")
+        return updates
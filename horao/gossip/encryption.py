@@ -0,0 +1,180 @@
+# -*- coding: utf-8 -*-#
+"""Encryption of the gossip datagrams
+
+Gossip may travel over networks that are not trusted, datagrams can be encrypted with a key shared by all peers
+(gossip_encryption_key) using ChaCha20-Poly1305. An encrypted datagram starts with a header (MAGIC and a format
+version) followed by a random 12 byte nonce and the ciphertext, the header is authenticated with the ciphertext. Peers
+without the key cannot decode the datagrams, tampered datagrams are rejected.
+
+The EncryptedTransport wraps any transport, the gossip logic is not aware of the encryption. Datagrams that cannot
+be decrypted are dropped (and counted), like datagrams lost on the wire. To switch a running cluster to encryption
+without a restart, the peers are first given the key in Grace mode (gossip_encryption_mode), in which plaintext
+datagrams are still accepted, and switched to Required once all peers have the key. Peers in Grace mode send
+encrypted datagrams, so until all peers have the key the others miss them.
+
+The encryption depends on the 'cryptography' package, installed with the crypto extra (pip install horao[crypto]).
+"""
+import base64
+import binascii
+import logging
+import os
+from typing import Callable, Optional
+
+from horao.gossip.transport import Transport
+from horao.settings import GossipEncryptionMode, Settings
+
+MAGIC = b"HRE"
+FORMAT_VERSION = 1
+HEADER = MAGIC + bytes([FORMAT_VERSION])
+NONCE_SIZE = 12
+KEY_SIZE = 32
+
+
+class DecryptionFailed(ValueError):
+    """Raised when a datagram cannot be decrypted, it was encrypted with another key or has been tampered with."""
+
+    def __init__(self, reason: str):
+        super().__init__(f"Cannot decrypt datagram: {reason}")
+        self.reason = reason
+
+
+def generate_key() -> str:
+    """
+    Generate a key for the gossip_encryption_key setting
+    :return: base64 encoded random key
+    """
+    return base64.b64encode(os.urandom(KEY_SIZE)).decode()
+
+
+class GossipCipher:
+    def __init__(self, key: bytes):
+        """
+        Encryption of datagrams with a shared key
+        :param key: 32 byte key
+        :raises: ValueError if the key has the wrong size
+        :raises: ImportError if the cryptography package is not installed
+        """
+        if len(key) != KEY_SIZE:
+            raise ValueError(f"Gossip key should be {KEY_SIZE} bytes, not {len(key)}")
+        try:
+            from cryptography.hazmat.primitives.ciphers.aead import ChaCha20Poly1305
+        except ImportError as e:
+            raise ImportError(
+                "Gossip encryption requires the cryptography package (horao[crypto])"
+            ) from e
+        self._aead = ChaCha20Poly1305(key)
+
+    @classmethod
+    def from_settings(cls, settings: Settings) -> Optional["GossipCipher"]:
+        """
+        Cipher of the configured key
+        :param settings: settings with the gossip_encryption_key
+        :return: GossipCipher, None if no key is configured
+        :raises: ValueError if the key is not valid base64 or has the wrong size
+        """
+        if not settings.gossip_encryption_key:
+            return None
+        try:
+            key = base64.b64decode(
+                settings.gossip_encryption_key.reveal(), validate=True
+            )
+        except binascii.Error:
+            raise ValueError("Gossip key (gossip_encryption_key) is not valid base64")
+        return cls(key)
+
+    def encrypt(self, datagram: bytes) -> bytes:
+        """
+        Encrypt a datagram
+        :param datagram: plaintext datagram
+        :return: header, nonce and ciphertext
+        """
+        nonce = os.urandom(NONCE_SIZE)
+        return HEADER + nonce + self._aead.encrypt(nonce, datagram, HEADER)
+
+    def decrypt(self, frame: bytes) -> bytes:
+        """
+        Decrypt a datagram
+        :param frame: header, nonce and ciphertext
+        :return: plaintext datagram
+        :raises: DecryptionFailed if the frame is not encrypted, or not with this key
+        """
+        from cryptography.exceptions import InvalidTag
+
+        if not is_encrypted(frame):
+            raise DecryptionFailed("not an encrypted datagram")
+        if frame[len(MAGIC)] != FORMAT_VERSION:
+            raise DecryptionFailed(f"unsupported format version {frame[len(MAGIC)]}")
+        nonce = frame[len(HEADER) : len(HEADER) + NONCE_SIZE]
+        try:
+            return self._aead.decrypt(
+                nonce, frame[len(HEADER) + NONCE_SIZE :], HEADER
+            )
+        except InvalidTag:
+            raise DecryptionFailed("wrong key or tampered datagram")
+
+
+def is_encrypted(frame: bytes) -> bool:
+    """
+    Whether a datagram is encrypted
+    :param frame: datagram as received
+    :return: True if the datagram starts with the encryption header
+    """
+    return frame.startswith(MAGIC) and len(frame) >= len(HEADER) + NONCE_SIZE
+
+
+class EncryptedTransport(Transport):
+    def __init__(
+        self,
+        transport: Transport,
+        cipher: GossipCipher,
+        mode: GossipEncryptionMode = GossipEncryptionMode.Required,
+    ):
+        """
+        Transport encrypting the datagrams of another transport
+        :param transport: transport carrying the encrypted datagrams
+        :param cipher: cipher of the shared key
+        :param mode: Grace to also accept plaintext datagrams
+        """
+        self.transport = transport
+        self.cipher = cipher
+        self.mode = mode
+        # datagrams that were dropped because they could not be decrypted or were not encrypted
+        self.rejected = 0
+
+    @classmethod
+    def wrap(cls, transport: Transport, settings: Settings) -> Transport:
+        """
+        Encrypt a transport as configured
+        :param transport: transport to wrap
+        :param settings: settings with the gossip_encryption_key and gossip_encryption_mode
+        :return: EncryptedTransport, the transport itself if no key is configured
+        :raises: ValueError if the key is not valid
+        """
+        cipher = GossipCipher.from_settings(settings)
+        if cipher is None:
+            return transport
+        return cls(
+            transport, cipher, GossipEncryptionMode[settings.gossip_encryption_mode]
+        )
+
+    def send(self, peer: str, datagram: bytes) -> None:
+        self.transport.send(peer, self.cipher.encrypt(datagram))
+
+    def on_receive(self, handler: Callable[[str, bytes], None]) -> None:
+        def receive(peer: str, frame: bytes) -> None:
+            if not is_encrypted(frame):
+                if self.mode == GossipEncryptionMode.Grace:
+                    handler(peer, frame)
+                    return
+                self.rejected += 1
+                logging.warning(f"Dropping plaintext datagram of {peer}")
+                return
+            try:
+                datagram = self.cipher.decrypt(frame)
+            except DecryptionFailed as e:
+                self.rejected += 1
+                logging.warning(f"Dropping datagram of {peer}: {e.reason}")
+                return
+            handler(peer, datagram)
+
+        self.transport.on_receive(receive)
@@ -0,0 +1,126 @@
+# -*- coding: utf-8 -*-#
+"""Membership of the gossip cluster
+
+The membership keeps the peers known to this node with their incarnation and status. The transport feeds it what it
+learns from the exchanged datagrams (a peer joined, is suspected, left), the membership turns this into events for the
+consumers, for instance to reconcile the inventory when a peer changes.
+
+Every subscriber gets its own queue and receives all events published after it subscribed, in order. The membership
+is meant to be driven from the event loop the subscribers read their queues on.
+"""
+import asyncio
+from enum import Enum, auto
+from typing import Dict, List, Optional
+
+
+class PeerStatus(Enum):
+    Alive = auto()
+    Suspect = auto()
+
+
+class PeerEvent:
+    """Base class of the events published by the membership."""
+
+    def __init__(self, peer: str, incarnation: int):
+        self.peer = peer
+        self.incarnation = incarnation
+
+    def __repr__(self):
+        return f"<{type(self).__name__} {self.peer}@{self.incarnation}>"
+
+
+class Joined(PeerEvent):
+    pass
+
+
+class StatusChanged(PeerEvent):
+    def __init__(
+        self, peer: str, incarnation: int, previous: PeerStatus, status: PeerStatus
+    ):
+        super().__init__(peer, incarnation)
+        self.previous = previous
+        self.status = status
+
+
+class Left(PeerEvent):
+    pass
+
+
+class Peer:
+    def __init__(self, name: str, incarnation: int, status: PeerStatus):
+        self.name = name
+        self.incarnation = incarnation
+        self.status = status
+
+
+class Membership:
+    def __init__(self):
+        self.peers: Dict[str, Peer] = {}
+        self._subscribers: List[asyncio.Queue] = []
+
+    def subscribe(self) -> asyncio.Queue:
+        """
+        Receive the events of the membership
+        :return: queue the events are put on
+        """
+        queue: asyncio.Queue = asyncio.Queue()
+        self._subscribers.append(queue)
+        return queue
+
+    def unsubscribe(self, queue: asyncio.Queue) -> None:
+        """
+        Stop receiving events
+        :param queue: queue returned by subscribe
+        :return: None
+        """
+        if queue in self._subscribers:
+            self._subscribers.remove(queue)
+
+    def _publish(self, event: PeerEvent) -> None:
+        for queue in self._subscribers:
+            queue.put_nowait(event)
+
+    def join(self, peer: str, incarnation: int) -> None:
+        """
+        A peer joined, or rejoined with a new incarnation (which makes it alive again)
+        :param peer: name of the peer
+        :param incarnation: incarnation of the peer
+        :return: None
+        """
+        known = self.peers.get(peer)
+        if known is None:
+            self.peers[peer] = Peer(peer, incarnation, PeerStatus.Alive)
+            self._publish(Joined(peer, incarnation))
+        elif incarnation > known.incarnation:
+            known.incarnation = incarnation
+            self.set_status(peer, PeerStatus.Alive, incarnation)
+
+    def set_status(
+        self, peer: str, status: PeerStatus, incarnation: Optional[int] = None
+    ) -> None:
+        """
+        Change the status of a peer, news about an older incarnation is ignored
+        :param peer: name of the peer
+        :param status: status
+        :param incarnation: incarnation the status applies to, the current incarnation if not given
+        :return: None
+        :raises: ValueError if the peer is unknown
+        """
+        if peer not in self.peers:
+            raise ValueError(f"Peer {peer} is unknown")
+        known = self.peers[peer]
+        if incarnation is not None and incarnation < known.incarnation:
+            return
+        if known.status != status:
+            previous, known.status = known.status, status
+            self._publish(StatusChanged(peer, known.incarnation, previous, status))
+
+    def leave(self, peer: str) -> None:
+        """
+        A peer left (or was declared dead), unknown peers are ignored
+        :param peer: name of the peer
+        :return: None
+        """
+        known = self.peers.pop(peer, None)
+        if known is not None:
+            self._publish(Left(peer, known.incarnation))
@@ -0,0 +1,267 @@
+# -*- coding: utf-8 -*-#
+"""Probe statistics
+
+Peers probe each other with ping/ack exchanges. The transport reports every probe it sends, the ack it receives and
+the probes that time out, ProbeStatistics keeps the round trip times and losses per peer over the last
+gossip_probe_window probes and derives rolling percentiles. When a peer restarts (its incarnation changes) the
+statistics of the previous incarnation no longer apply and are reset.
+
+The measurements are correlated with the model: given the servers the members run on, correlate compares the median
+RTT of a peer with the modelled RTT of the path between the servers (gossip_hop_latency_ms per device on the path, both
+ways) and flags the peers exceeding it by more than gossip_rtt_excess_factor. Peers that lose more than
+gossip_loss_threshold of their probes are flagged when a device on the modelled path is not up, that device is named as
+the suspect hop.
+"""
+from collections import deque
+from typing import Deque, Dict, List, Optional, Tuple
+
+import networkx as nx  # type: ignore
+
+from horao.models.hardware import DataCenter
+from horao.models.network import DataCenterNetwork, NetworkDevice
+from horao.models.status import DeviceStatus
+from horao.settings import Settings
+
+
+def percentile(values: List[float], p: float) -> Optional[float]:
+    """
+    Percentile of a list of values (nearest rank)
+    :param values: values
+    :param p: percentile, between 0 and 100
+    :return: value at the percentile, None if there are no values
+    """
+    if not values:
+        return None
+    ordered = sorted(values)
+    rank = max(1, -(-len(ordered) * p // 100))
+    return ordered[int(rank) - 1]
+
+
+class PeerStatistics:
+    def __init__(self, peer: str, incarnation: int, window: int):
+        """
+        Probe statistics of a peer
+        :param peer: name of the peer
+        :param incarnation: incarnation of the peer the statistics apply to
+        :param window: number of most recent probes kept
+        """
+        self.peer = peer
+        self.incarnation = incarnation
+        # round trip time in seconds per probe, None for a lost probe
+        self.outcomes: Deque[Optional[float]] = deque(maxlen=window)
+
+    @property
+    def rtts(self) -> List[float]:
+        return [o for o in self.outcomes if o is not None]
+
+    @property
+    def probes(self) -> int:
+        return len(self.outcomes)
+
+    @property
+    def loss(self) -> float:
+        """
+        Fraction of the probes in the window that were lost
+        :return: fraction, 0 if there were no probes
+        """
+        if not self.outcomes:
+            return 0.0
+        return sum(1 for o in self.outcomes if o is None) / len(self.outcomes)
+
+    def rtt_percentile_ms(self, p: float) -> Optional[float]:
+        """
+        Percentile of the round trip times in the window
+        :param p: percentile, between 0 and 100
+        :return: milliseconds, None if no probe was acknowledged
+        """
+        value = percentile(self.rtts, p)
+        return None if value is None else value * 1000
+
+    def to_dict(self) -> dict:
+        return {
+            "peer": self.peer,
+            "incarnation": self.incarnation,
+            "probes": self.probes,
+            "loss": self.loss,
+            "rtt_p50_ms": self.rtt_percentile_ms(50),
+            "rtt_p95_ms": self.rtt_percentile_ms(95),
+            "rtt_p99_ms": self.rtt_percentile_ms(99),
+        }
+
+
+class ProbeStatistics:
+    def __init__(self, settings: Optional[Settings] = None):
+        """
+        Probe statistics of all peers, fed by the transport
+        :param settings: settings for the window, defaults if not given
+        """
+        settings = settings if settings else Settings()
+        self.window = settings.gossip_probe_window
+        self.peers: Dict[str, PeerStatistics] = {}
+        # probes awaiting an ack by peer and sequence number, with the time they were sent
+        self._outstanding: Dict[Tuple[str, int], float] = {}
+
+    def _peer(self, peer: str, incarnation: int) -> PeerStatistics:
+        statistics = self.peers.get(peer)
+        if statistics is None or statistics.incarnation != incarnation:
+            statistics = PeerStatistics(peer, incarnation, self.window)
+            self.peers[peer] = statistics
+            self._outstanding = {
+                k: v for k, v in self._outstanding.items() if k[0] != peer
+            }
+        return statistics
+
+    def probe_sent(
+        self, peer: str, incarnation: int, sequence: int, timestamp: float
+    ) -> None:
+        """
+        Record a ping sent to a peer, a new incarnation of the peer resets its statistics
+        :param peer: name of the peer
+        :param incarnation: incarnation of the peer as known by the membership
+        :param sequence: sequence number of the ping
+        :param timestamp: time the ping was sent in seconds
+        :return: None
+        """
+        self._peer(peer, incarnation)
+        self._outstanding[(peer, sequence)] = timestamp
+
+    def ack_received(self, peer: str, sequence: int, timestamp: float) -> None:
+        """
+        Record the ack of a ping, acks of unknown (or reset) pings are ignored
+        :param peer: name of the peer
+        :param sequence: sequence number of the acknowledged ping
+        :param timestamp: time the ack was received in seconds
+        :return: None
+        """
+        sent = self._outstanding.pop((peer, sequence), None)
+        if sent is not None:
+            self.peers[peer].outcomes.append(max(0.0, timestamp - sent))
+
+    def probe_timed_out(self, peer: str, sequence: int) -> None:
+        """
+        Record a ping that was not acknowledged in time
+        :param peer: name of the peer
+        :param sequence: sequence number of the ping
+        :return: None
+        """
+        if self._outstanding.pop((peer, sequence), None) is not None:
+            self.peers[peer].outcomes.append(None)
+
+    def to_dict(self) -> dict:
+        return {p: s.to_dict() for p, s in sorted(self.peers.items())}
+
+
+class PeerFinding:
+    def __init__(
+        self,
+        peer: str,
+        reason: str,
+        measured_ms: Optional[float],
+        modelled_ms: Optional[float],
+        suspect_hop: Optional[str] = None,
+    ):
+        """
+        Peer whose measurements disagree with the model
+        :param peer: name of the peer
+        :param reason: 'latency' or 'loss'
+        :param measured_ms: median round trip time
+        :param modelled_ms: modelled round trip time, None if the servers are not connected in the model
+        :param suspect_hop: serial number of the device on the modelled path that is not up
+        """
+        self.peer = peer
+        self.reason = reason
+        self.measured_ms = measured_ms
+        self.modelled_ms = modelled_ms
+        self.suspect_hop = suspect_hop
+
+    def to_dict(self) -> dict:
+        return {
+            "peer": self.peer,
+            "reason": self.reason,
+            "measured_ms": self.measured_ms,
+            "modelled_ms": self.modelled_ms,
+            "suspect_hop": self.suspect_hop,
+        }
+
+
+def modelled_path(
+    dc: DataCenter,
+    networks: List[DataCenterNetwork],
+    from_server: str,
+    to_server: str,
+) -> Optional[List[NetworkDevice]]:
+    """
+    Shortest path between the NICs of two servers over any of the networks
+    :param dc: datacenter containing the servers
+    :param networks: networks to route over
+    :param from_server: serial number of the first server
+    :param to_server: serial number of the second server
+    :return: devices between the NICs, None if the servers are not connected
+    :raises: ValueError if a server does not exist
+    """
+    sources = dc.find_device(from_server, networks).nic
+    targets = dc.find_device(to_server, networks).nic
+    best: Optional[List[NetworkDevice]] = None
+    for network in networks:
+        view = network.graph_view()
+        for source in [n for n in sources if n in view]:
+            for target in [n for n in targets if n in view]:
+                try:
+                    path = nx.shortest_path(view, source, target)
+                except nx.NetworkXNoPath:
+                    continue
+                if best is None or len(path) - 2 < len(best):
+                    best = path[1:-1]
+    return best
+
+
+def correlate(
+    statistics: ProbeStatistics,
+    members: Dict[str, str],
+    local: str,
+    dc: DataCenter,
+    networks: List[DataCenterNetwork],
+    settings: Optional[Settings] = None,
+) -> List[PeerFinding]:
+    """
+    Compare the probe statistics of the peers with the modelled paths between the servers they run on
+    :param statistics: probe statistics of this node
+    :param members: serial number of the server per member name
+    :param local: name of this member
+    :param dc: datacenter containing the servers
+    :param networks: networks to route over
+    :param settings: hop latency, excess factor and loss threshold, defaults if not given
+    :return: findings, in order of peer
+    :raises: ValueError if the local member is not mapped to a server
+    """
+    settings = settings if settings else Settings()
+    if local not in members:
+        raise ValueError(f"Member {local} is not mapped to a server")
+    findings = []
+    for peer, peer_statistics in sorted(statistics.peers.items()):
+        if peer not in members or peer == local:
+            continue
+        path = modelled_path(dc, networks, members[local], members[peer])
+        modelled = (
+            None
+            if path is None
+            else 2 * len(path) * settings.gossip_hop_latency_ms
+        )
+        suspect = next(
+            (
+                d.serial_number
+                for d in path or []
+                if getattr(d, "status", DeviceStatus.Up) != DeviceStatus.Up
+            ),
+            None,
+        )
+        measured = peer_statistics.rtt_percentile_ms(50)
+        if (
+            measured is not None
+            and modelled is not None
+            and measured > modelled * settings.gossip_rtt_excess_factor
+        ):
+            findings.append(PeerFinding(peer, "latency", measured, modelled, suspect))
+        if peer_statistics.loss > settings.gossip_loss_threshold and suspect:
+            findings.append(PeerFinding(peer, "loss", measured, modelled, suspect))
+    return findings
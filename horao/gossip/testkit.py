@@ -0,0 +1,478 @@
+# -*- coding: utf-8 -*-#
+"""Fault injection for the gossip layer
+
+Reproducible tests of the gossip logic without sockets: an in-memory transport delivers the datagrams of the members on
+a simulated clock and applies programmable faults (loss per link, fixed delays, partitions between sets of members,
+duplicated datagrams), members can crash (losing all state) and restart. All randomness comes from a seeded
+generator, a scenario with the same seed always plays out the same.
+
+The members run the gossip loop of SimulatedNode:
+
+- every gossip_interval a member increments its heartbeat and sends its heartbeat table and the pending status updates
+  (see Dissemination) to a random live peer
+- every anti_entropy_interval a member exchanges its full state (push-pull) with a random known member, live or not,
+  which reconciles members after a partition heals and brings a restarted member up to date
+- a member whose heartbeat did not advance for fail_timeout is considered to have left
+- status updates carry a version, the highest version wins, so receiving an update twice changes nothing
+- messages carry the model schema version of the sender, a member drops (and logs) the messages of members whose
+  schema version it cannot read (see versioning.can_read), such members never join its membership
+
+A Scenario asserts convergence: all live members agree on the live members and on the disseminated statuses.
+
+The testkit is meant for tests, the application does not import it.
+"""
+import heapq
+import logging
+import random
+from typing import Any, Callable, Dict, FrozenSet, List, Optional, Set, Tuple
+
+from horao.gossip.dissemination import Dissemination
+from horao.gossip.membership import Membership
+from horao.gossip.transport import Transport
+from horao.models.binary import pack, unpack
+from horao.models.versioning import MODEL_SCHEMA_VERSION, can_read
+
+DATAGRAM_SIZE = 1200
+
+
+class SimulatedClock:
+    def __init__(self, start: float = 0.0):
+        """
+        Clock that only moves when advanced, callbacks run at their scheduled (simulated) time
+        :param start: time to start at in seconds
+        """
+        self.now = start
+        self._scheduled: List[Tuple[float, int, Callable[[], None]]] = []
+        self._sequence = 0
+
+    def time(self) -> float:
+        return self.now
+
+    def call_later(self, delay: float, callback: Callable[[], None]) -> None:
+        """
+        Run a callback after a delay
+        :param delay: seconds from now
+        :param callback: function to run
+        :return: None
+        """
+        heapq.heappush(
+            self._scheduled, (self.now + max(0.0, delay), self._sequence, callback)
+        )
+        self._sequence += 1
+
+    def advance(self, seconds: float) -> None:
+        """
+        Move the clock forward, running the callbacks that become due in order of their time
+        :param seconds: seconds to advance
+        :return: None
+        """
+        until = self.now + seconds
+        while self._scheduled and self._scheduled[0][0] <= until:
+            at, _, callback = heapq.heappop(self._scheduled)
+            self.now = at
+            callback()
+        self.now = until
+
+
+class FaultRules:
+    def __init__(self, loss: float = 0.0, delay: float = 0.0, duplication: float = 0.0):
+        """
+        Faults applied to the datagrams of an in-memory network
+        :param loss: probability a datagram is dropped
+        :param delay: seconds a datagram takes to arrive
+        :param duplication: probability a datagram is delivered twice
+        """
+        self.loss = loss
+        self.delay = delay
+        self.duplication = duplication
+        self.link_loss: Dict[Tuple[str, str], float] = {}
+        self.link_delay: Dict[Tuple[str, str], float] = {}
+        # datagrams from the first set of members to the second are dropped
+        self.partitions: List[Tuple[FrozenSet[str], FrozenSet[str]]] = []
+
+    def drop(self, sender: str, receiver: str, probability: float) -> "FaultRules":
+        """
+        Loss of the datagrams from one member to another, overriding the default loss
+        :param sender: sending member
+        :param receiver: receiving member
+        :param probability: probability a datagram is dropped
+        :return: the rules
+        """
+        self.link_loss[(sender, receiver)] = probability
+        return self
+
+    def slow(self, sender: str, receiver: str, delay: float) -> "FaultRules":
+        """
+        Delay of the datagrams from one member to another, overriding the default delay
+        :param sender: sending member
+        :param receiver: receiving member
+        :param delay: seconds a datagram takes to arrive
+        :return: the rules
+        """
+        self.link_delay[(sender, receiver)] = delay
+        return self
+
+    def partition(
+        self, left: Set[str], right: Set[str], symmetric: bool = True
+    ) -> "FaultRules":
+        """
+        Drop all datagrams between two sets of members
+        :param left: members on one side
+        :param right: members on the other side
+        :param symmetric: also drop the datagrams from right to left, only left to right if False
+        :return: the rules
+        """
+        self.partitions.append((frozenset(left), frozenset(right)))
+        if symmetric:
+            self.partitions.append((frozenset(right), frozenset(left)))
+        return self
+
+    def heal(self) -> "FaultRules":
+        """
+        Remove all partitions
+        :return: the rules
+        """
+        self.partitions = []
+        return self
+
+    def blocked(self, sender: str, receiver: str) -> bool:
+        return any(sender in a and receiver in b for a, b in self.partitions)
+
+
+class InMemoryNetwork:
+    def __init__(
+        self, clock: SimulatedClock, rules: Optional[FaultRules] = None, seed: int = 0
+    ):
+        """
+        Network delivering the datagrams between in-memory transports
+        :param clock: clock the deliveries are scheduled on
+        :param rules: faults to apply, none if not given
+        :param seed: seed of the random faults
+        """
+        self.clock = clock
+        self.rules = rules if rules else FaultRules()
+        self.random = random.Random(seed)
+        self._handlers: Dict[str, Callable[[str, bytes], None]] = {}
+        self.sent = 0
+        self.dropped = 0
+        self.duplicated = 0
+
+    def transport(self, member: str) -> "InMemoryTransport":
+        return InMemoryTransport(self, member)
+
+    def attach(self, member: str, handler: Callable[[str, bytes], None]) -> None:
+        self._handlers[member] = handler
+
+    def detach(self, member: str) -> None:
+        self._handlers.pop(member, None)
+
+    def deliver(self, sender: str, receiver: str, datagram: bytes) -> None:
+        """
+        Deliver a datagram subject to the fault rules, datagrams to detached members are lost
+        :param sender: sending member
+        :param receiver: receiving member
+        :param datagram: datagram
+        :return: None
+        """
+        self.sent += 1
+        link = (sender, receiver)
+        loss = self.rules.link_loss.get(link, self.rules.loss)
+        if self.rules.blocked(sender, receiver) or self.random.random() < loss:
+            self.dropped += 1
+            return
+        copies = 1
+        if self.random.random() < self.rules.duplication:
+            copies = 2
+            self.duplicated += 1
+        delay = self.rules.link_delay.get(link, self.rules.delay)
+
+        def arrive() -> None:
+            handler = self._handlers.get(receiver)
+            if handler is not None:
+                handler(sender, datagram)
+
+        for _ in range(copies):
+            self.clock.call_later(delay, arrive)
+
+
+class InMemoryTransport(Transport):
+    def __init__(self, network: InMemoryNetwork, member: str):
+        self.network = network
+        self.member = member
+
+    def send(self, peer: str, datagram: bytes) -> None:
+        self.network.deliver(self.member, peer, datagram)
+
+    def on_receive(self, handler: Callable[[str, bytes], None]) -> None:
+        self.network.attach(self.member, handler)
+
+    def close(self) -> None:
+        self.network.detach(self.member)
+
+
+class SimulatedNode:
+    def __init__(
+        self,
+        name: str,
+        transport: InMemoryTransport,
+        clock: SimulatedClock,
+        seeds: List[str],
+        seed: int = 0,
+        gossip_interval: float = 1.0,
+        anti_entropy_interval: float = 5.0,
+        fail_timeout: float = 5.0,
+        schema_version: str = MODEL_SCHEMA_VERSION,
+    ):
+        """
+        Member running the gossip loop (see the module documentation)
+        :param name: name of the member
+        :param transport: transport of the member
+        :param clock: clock the timers run on
+        :param seeds: members known at start
+        :param seed: seed of the peer selection
+        :param gossip_interval: seconds between gossip rounds
+        :param anti_entropy_interval: seconds between full state exchanges
+        :param fail_timeout: seconds without a heartbeat after which a member has left
+        :param schema_version: model schema version the member speaks (e.g. to simulate a peer of another version)
+        """
+        self.name = name
+        self.transport = transport
+        self.clock = clock
+        self.seeds = [s for s in seeds if s != name]
+        self.random = random.Random(seed)
+        self.gossip_interval = gossip_interval
+        self.anti_entropy_interval = anti_entropy_interval
+        self.fail_timeout = fail_timeout
+        self.schema_version = schema_version
+        # messages that were dropped because of an incompatible schema version
+        self.rejected = 0
+        self.crashed = False
+        self._generation = 0
+        self._reset()
+
+    def _reset(self) -> None:
+        self.incarnation = 1
+        self.heartbeat = 0
+        self.membership = Membership()
+        self.dissemination = Dissemination()
+        # incarnation and heartbeat per member, with the time the heartbeat last advanced
+        self.heartbeats: Dict[str, Tuple[int, int]] = {s: (0, 0) for s in self.seeds}
+        self.advanced: Dict[str, float] = {}
+        # version and status per serial number
+        self.state: Dict[str, Tuple[int, str]] = {}
+
+    def start(self) -> None:
+        """
+        Start the timers, the first gossip round starts within a gossip interval
+        :return: None
+        """
+        self.transport.on_receive(self.receive)
+        generation = self._generation
+        self.clock.call_later(
+            self.random.uniform(0, self.gossip_interval),
+            lambda: self._gossip(generation),
+        )
+        self.clock.call_later(
+            self.random.uniform(0, self.anti_entropy_interval),
+            lambda: self._anti_entropy(generation),
+        )
+
+    def crash(self) -> None:
+        """
+        Stop the member, it no longer sends or receives
+        :return: None
+        """
+        self.crashed = True
+        self._generation += 1
+        self.transport.close()
+
+    def restart(self) -> None:
+        """
+        Restart a crashed member without any of its previous state
+        :return: None
+        """
+        self.crashed = False
+        self._reset()
+        self.start()
+
+    @property
+    def live_members(self) -> Set[str]:
+        return set(self.membership.peers) | {self.name}
+
+    def publish(self, serial_number: str, status: str) -> None:
+        """
+        Change the status of a device and disseminate it
+        :param serial_number: serial number of the device
+        :param status: status
+        :return: None
+        """
+        version = self.state.get(serial_number, (0, ""))[0] + 1
+        self._apply(serial_number, version, status)
+
+    def _apply(self, serial_number: str, version: int, status: str) -> None:
+        if (version, status) <= self.state.get(serial_number, (0, "")):
+            return
+        self.state[serial_number] = (version, status)
+        self.dissemination.enqueue(
+            {"serial_number": serial_number, "status": status, "version": version}
+        )
+
+    def _message(self, kind: str, **content: Any) -> bytes:
+        table = {m: list(h) for m, h in self.heartbeats.items()}
+        table[self.name] = [self.incarnation, self.heartbeat]
+        return pack(
+            {
+                "kind": kind,
+                "schema_version": self.schema_version,
+                "heartbeats": table,
+                **content,
+            }
+        )
+
+    def _gossip(self, generation: int) -> None:
+        if generation != self._generation:
+            return
+        self.heartbeat += 1
+        now = self.clock.time()
+        for member in sorted(self.membership.peers):
+            if now - self.advanced.get(member, now) > self.fail_timeout:
+                self.membership.leave(member)
+        peers = sorted(self.membership.peers) or self.seeds
+        if peers:
+            updates = Dissemination.updates(self.dissemination.assemble(DATAGRAM_SIZE))
+            self.transport.send(
+                self.random.choice(peers), self._message("gossip", updates=updates)
+            )
+        self.clock.call_later(self.gossip_interval, lambda: self._gossip(generation))
+
+    def _state(self) -> Dict[str, List[Any]]:
+        return {s: list(v) for s, v in self.state.items()}
+
+    def _anti_entropy(self, generation: int) -> None:
+        if generation != self._generation:
+            return
+        known = sorted(m for m in self.heartbeats if m != self.name)
+        if known:
+            self.transport.send(
+                self.random.choice(known),
+                self._message("sync", state=self._state(), reply=True),
+            )
+        self.clock.call_later(
+            self.anti_entropy_interval, lambda: self._anti_entropy(generation)
+        )
+
+    def receive(self, sender: str, datagram: bytes) -> None:
+        """
+        Handle a datagram of a peer
+        :param sender: name of the sending member
+        :param datagram: datagram
+        :return: None
+        """
+        if self.crashed:
+            return
+        message = unpack(datagram)
+        if not can_read(str(message.get("schema_version")), self.schema_version):
+            self.rejected += 1
+            logging.warning(
+                f"Dropping message of {sender}: schema version {message.get('schema_version')} "
+                f"cannot be read by {self.schema_version}"
+            )
+            return
+        self._merge_heartbeats(message["heartbeats"])
+        for update in message.get("updates", []):
+            self._apply(update["serial_number"], update["version"], update["status"])
+        for serial_number, (version, status) in message.get("state", {}).items():
+            self._apply(serial_number, version, status)
+        if message.get("reply"):
+            self.transport.send(
+                sender, self._message("sync", state=self._state(), reply=False)
+            )
+
+    def _merge_heartbeats(self, table: Dict[str, List[int]]) -> None:
+        now = self.clock.time()
+        for member, (incarnation, heartbeat) in sorted(table.items()):
+            if member == self.name:
+                # others remember an incarnation of ours from before a restart, refute it
+                if incarnation >= self.incarnation:
+                    self.incarnation = incarnation + 1
+                continue
+            if (incarnation, heartbeat) <= self.heartbeats.get(member, (0, 0)):
+                continue
+            self.heartbeats[member] = (incarnation, heartbeat)
+            self.advanced[member] = now
+            self.membership.join(member, incarnation)
+
+
+class Scenario:
+    def __init__(
+        self,
+        members: List[str],
+        rules: Optional[FaultRules] = None,
+        seed: int = 0,
+        gossip_interval: float = 1.0,
+        anti_entropy_interval: float = 5.0,
+        fail_timeout: float = 5.0,
+    ):
+        """
+        Members gossiping over an in-memory network, all members are each other's seeds
+        :param members: names of the members
+        :param rules: faults of the network, none if not given
+        :param seed: seed of all randomness of the scenario
+        :param gossip_interval: seconds between gossip rounds
+        :param anti_entropy_interval: seconds between full state exchanges
+        :param fail_timeout: seconds without a heartbeat after which a member has left
+        """
+        self.clock = SimulatedClock()
+        self.network = InMemoryNetwork(self.clock, rules, seed)
+        self.nodes = {
+            name: SimulatedNode(
+                name,
+                self.network.transport(name),
+                self.clock,
+                members,
+                seed + i,
+                gossip_interval,
+                anti_entropy_interval,
+                fail_timeout,
+            )
+            for i, name in enumerate(members)
+        }
+        for node in self.nodes.values():
+            node.start()
+
+    @property
+    def rules(self) -> FaultRules:
+        return self.network.rules
+
+    def live(self) -> List[SimulatedNode]:
+        return [n for _, n in sorted(self.nodes.items()) if not n.crashed]
+
+    def converged(self) -> bool:
+        """
+        All live members agree on the live members and on the statuses
+        :return: True if converged
+        """
+        live = self.live()
+        names = {n.name for n in live}
+        return all(n.live_members == names for n in live) and all(
+            n.state == live[0].state for n in live
+        )
+
+    def run(self, seconds: float) -> None:
+        self.clock.advance(seconds)
+
+    def run_until_converged(self, within: float, step: float = 0.5) -> float:
+        """
+        Run until the members converge
+        :param within: simulated seconds the members have to converge in
+        :param step: seconds between convergence checks
+        :return: simulated seconds it took
+        :raises: AssertionError if the members did not converge in time
+        """
+        start = self.clock.time()
+        while self.clock.time() - start < within:
+            self.run(step)
+            if self.converged():
+                return self.clock.time() - start
+        views = {n.name: sorted(n.live_members) for n in self.live()}
+        raise AssertionError(f"Members did not converge within {within}s: {views}")
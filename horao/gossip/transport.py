@@ -0,0 +1,29 @@
+# -*- coding: utf-8 -*-#
+"""Transport of the gossip datagrams
+
+The gossip logic only sends and receives datagrams addressed by member name, it does not care how they travel. A
+transport delivers datagrams at most once and in no particular order, it may drop them (as UDP does).
+"""
+from abc import ABC, abstractmethod
+from typing import Callable
+
+
+class Transport(ABC):
+    @abstractmethod
+    def send(self, peer: str, datagram: bytes) -> None:
+        """
+        Send a datagram to a peer, without waiting for or guaranteeing delivery
+        :param peer: name of the member to send to
+        :param datagram: datagram
+        :return: None
+        """
+        pass
+
+    @abstractmethod
+    def on_receive(self, handler: Callable[[str, bytes], None]) -> None:
+        """
+        Register the handler of the received datagrams
+        :param handler: called with the name of the sending member and the datagram
+        :return: None
+        """
+        pass
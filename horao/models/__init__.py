@@ -3,14 +3,18 @@
 
 This module contains the classes that are used to model the hardware and software resources of the system.
 """
-from horao.models.status import DeviceStatus
+from horao.models.status import DeviceStatus, LifecycleState
 from horao.models.osi_layers import (
     LinkLayer,
     Protocol,
     Port,
     FirewallRule,
+    PortMatcher,
     IpAddress,
+    Packet,
     Route,
+    Link,
+    PortTable,
 )
 from horao.models.network import (
     Router,
@@ -21,6 +25,7 @@ from horao.models.network import (
     DataCenterNetwork,
     NetworkType,
     NetworkTopology,
+    AddressPlan,
 )
 from horao.models.hardware import (
     RAM,
@@ -0,0 +1,202 @@
+# -*- coding: utf-8 -*-#
+"""Pseudonymization of the model
+
+Snapshots are shared with vendors and attached to bug reports, this module rewrites the identifying values of a model
+(names, serial numbers, MAC addresses, IP addresses and the values of the metadata) into pseudonyms so the shared
+copy does not leak them. The pseudonyms are derived with a keyed HMAC: the same key gives the same pseudonyms, so
+equal values map to equal pseudonyms and the references between the devices (links, bond members, firewalls of
+segments, address pools) stay consistent. Everything else (statuses, speeds, counts, types) is left intact, which keeps the topology and capacity
+analyses working on the shared copy.
+
+IP addresses are mapped prefix-preserving (in the style of Crypto-PAn): two addresses that share their first n bits
+map to addresses that share their first n bits. A subnet keeps its prefix length and maps to the subnet of the mapped
+addresses, so subnets that contain each other still do after the mapping.
+
+The mapping from the pseudonyms back to the original values is only known to the holder of the key, it is collected
+by the Pseudonymizer and can be used to reveal the original values in findings that come back.
+"""
+import hashlib
+import hmac
+import ipaddress
+from typing import Any, Dict, TypeVar, Union
+
+from horao.models.serialization import Decoder, Encoder
+from horao.models.snapshot import Snapshot
+
+T = TypeVar("T")
+
+# fields holding a name, serial number or MAC address, by the kind of pseudonym
+IDENTIFIERS = {
+    "name": "name",
+    "serial_number": "sn",
+    "mac": "mac",
+}
+# fields holding a list of serial numbers
+REFERENCES = ["members", "firewalls", "devices"]
+# fields holding a subnet or a list of subnets in CIDR notation (address pools)
+SUBNETS = ["network", "allocations"]
+# fields that are left as they are, free form values chosen by the operator
+UNTOUCHED = ["labels"]
+# fields holding attributes of the operator (e.g. owner, asset tag), the keys are kept and every value is replaced
+METADATA = ["metadata"]
+
+
+class Pseudonymizer:
+    def __init__(self, key: Union[str, bytes]):
+        """
+        Pseudonymizer, keeps track of the pseudonyms it handed out
+        :param key: secret key of the HMAC
+        """
+        self._key = key.encode() if isinstance(key, str) else key
+        self._originals: Dict[str, str] = {}
+
+    def _digest(self, kind: str, value: str) -> bytes:
+        return hmac.new(self._key, f"{kind}:{value}".encode(), hashlib.sha256).digest()
+
+    def identifier(self, kind: str, value: str) -> str:
+        """
+        Pseudonym of a name, serial number, MAC address or metadata value
+        :param kind: kind of the value ('name', 'sn', 'mac' or 'meta')
+        :param value: value to pseudonymize
+        :return: pseudonym
+        """
+        digest = self._digest(kind, value)
+        if kind == "mac":
+            # locally administered, unicast
+            pseudonym = ":".join(
+                f"{b:02x}" for b in bytes([digest[0] & 0xFC | 0x02]) + digest[1:6]
+            )
+        else:
+            pseudonym = f"{kind}-{digest[:6].hex()}"
+        self._originals[pseudonym] = value
+        return pseudonym
+
+    def address(self, value: str) -> str:
+        """
+        Prefix-preserving pseudonym of an IP address, every bit is flipped depending on the bits in front of it
+        :param value: IP address
+        :return: pseudonym
+        :raises: ValueError if the value is not an IP address
+        """
+        address = ipaddress.ip_address(value)
+        bits = address.max_prefixlen
+        original = int(address)
+        mapped = 0
+        for i in range(bits):
+            prefix = original >> (bits - i)
+            flip = self._digest(f"ip{bits}/{i}", str(prefix))[0] & 1
+            bit = (original >> (bits - i - 1)) & 1
+            mapped = mapped << 1 | (bit ^ flip)
+        pseudonym = str(type(address)(mapped))
+        self._originals[pseudonym] = value
+        return pseudonym
+
+    def subnet(self, value: str) -> str:
+        """
+        Pseudonym of a subnet, the subnet of the same size containing the pseudonym of its network address
+        :param value: subnet in CIDR notation
+        :return: pseudonym in CIDR notation
+        :raises: ValueError if the value is not a subnet
+        """
+        network = ipaddress.ip_network(value)
+        pseudonym = str(
+            ipaddress.ip_network(
+                f"{self.address(str(network.network_address))}/{network.prefixlen}",
+                strict=False,
+            )
+        )
+        self._originals[pseudonym] = value
+        return pseudonym
+
+    def _ip_address(self, data: Dict[str, Any]) -> Dict[str, Any]:
+        # IpAddress, the address of a subnet is its network address (e.g. the subnets of a segment)
+        result = dict(data)
+        address, netmask = data.get("address"), data.get("netmask")
+        if address:
+            try:
+                network = ipaddress.ip_network(f"{address}/{netmask}", strict=False)
+            except ValueError:
+                network = None
+            if network is not None and str(network.network_address) == address:
+                result["address"] = self.subnet(str(network)).split("/")[0]
+            else:
+                result["address"] = self.address(address)
+        if data.get("gateway"):
+            result["gateway"] = self.address(data["gateway"])
+        return result
+
+    def _rewrite(self, value: Any) -> Any:
+        if isinstance(value, list):
+            return [self._rewrite(v) for v in value]
+        if not isinstance(value, dict):
+            return value
+        if {"address", "netmask", "gateway"} <= set(value):
+            return self._ip_address(value)
+        result = {}
+        for field, v in value.items():
+            if field in UNTOUCHED:
+                result[field] = v
+            elif field in METADATA and isinstance(v, dict):
+                # keys of the operator are not model fields, they never match the identifier rules
+                result[field] = {
+                    key: self.identifier("meta", str(meta)) for key, meta in v.items()
+                }
+            elif field in IDENTIFIERS and isinstance(v, str):
+                result[field] = self.identifier(IDENTIFIERS[field], v)
+            elif field in REFERENCES and isinstance(v, list):
+                result[field] = [
+                    self.identifier("sn", s) if isinstance(s, str) else self._rewrite(s)
+                    for s in v
+                ]
+            elif field in SUBNETS and isinstance(v, str):
+                result[field] = self.subnet(v)
+            elif field in SUBNETS and isinstance(v, list):
+                result[field] = [self.subnet(s) for s in v]
+            else:
+                result[field] = self._rewrite(v)
+        return result
+
+    def pseudonymize(self, model: T) -> T:
+        """
+        Pseudonymized copy of a model object, a list of them or a snapshot
+        :param model: model to pseudonymize, left unchanged
+        :return: pseudonymized copy
+        """
+        if isinstance(model, list):
+            return [self.pseudonymize(m) for m in model]  # type: ignore
+        if isinstance(model, Snapshot):
+            return Snapshot(  # type: ignore
+                model.timestamp,
+                self.pseudonymize(model.datacenter),
+                self.pseudonymize(model.networks),
+                model.schema_version,
+                self.pseudonymize(model.pools),
+            )
+        return Decoder().decode(self._rewrite(Encoder().encode(model)), type(model))
+
+    def export_mapping(self) -> Dict[str, str]:
+        """
+        Mapping of the pseudonyms handed out so far to their original values
+        :return: dict of pseudonym to original value
+        """
+        return dict(self._originals)
+
+    def reveal(self, text: str) -> str:
+        """
+        Replace the pseudonyms in a text (e.g. a finding on the shared copy) with their original values
+        :param text: text to de-pseudonymize
+        :return: text with the original values
+        """
+        for pseudonym in sorted(self._originals, key=len, reverse=True):
+            text = text.replace(pseudonym, self._originals[pseudonym])
+        return text
+
+
+def pseudonymize(model: T, key: Union[str, bytes]) -> T:
+    """
+    Pseudonymized copy of a model object, a list of them or a snapshot
+    :param model: model to pseudonymize
+    :param key: secret key of the HMAC
+    :return: pseudonymized copy
+    """
+    return Pseudonymizer(key).pseudonymize(model)
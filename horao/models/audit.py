@@ -0,0 +1,250 @@
+# -*- coding: utf-8 -*-#
+"""Audit log
+
+Every mutation of the model store is recorded as an AuditRecord in an append-only log: who (actor) did what
+(operation and its payload) when, and whether it succeeded. Records are written to a pluggable sink, an in-memory
+ring for tests and small deployments or a JSON-lines file with rotation. Records serialize stably (sorted keys), so
+the log can be replayed onto a base snapshot to reproduce the fingerprint of the model (see ModelStore.replay).
+"""
+import json
+import os
+import threading
+import time
+from abc import ABC, abstractmethod
+from collections import deque
+from enum import Enum, auto
+from typing import Any, Deque, Dict, Iterator, List, Optional
+
+
+class Operation(Enum):
+    SetStatus = auto()
+    Link = auto()
+    Unlink = auto()
+    AddRule = auto()
+    RemoveRule = auto()
+    SetTenant = auto()
+
+
+# payload keys that refer to devices
+SERIAL_KEYS = ("serial_number", "left", "right", "firewall")
+
+
+class ReplayDivergence(ValueError):
+    """Raised when replaying a log does not reproduce the model, names the record where the replay diverged."""
+
+    def __init__(self, sequence: Optional[int], detail: str):
+        super().__init__(f"replay diverged at record {sequence}: {detail}")
+        self.sequence = sequence
+        self.detail = detail
+
+
+class AuditRecord:
+    def __init__(
+        self,
+        sequence: int,
+        timestamp: float,
+        actor: str,
+        operation: Operation,
+        payload: Dict[str, Any],
+        result: str = "ok",
+    ):
+        """
+        Record of a single mutation
+        :param sequence: position in the log, starting at 1
+        :param actor: who applied the mutation (user name, 'gossip', 'snmp', ...)
+        :param payload: arguments of the operation, JSON compatible
+        :param result: 'ok', or the reason the mutation was rejected
+        """
+        self.sequence = sequence
+        self.timestamp = timestamp
+        self.actor = actor
+        self.operation = operation
+        self.payload = payload
+        self.result = result
+
+    def __repr__(self):
+        return f"<AuditRecord {self.sequence} {self.actor} {self.operation.name} {self.result}>"
+
+    @property
+    def succeeded(self) -> bool:
+        return self.result == "ok"
+
+    def serials(self) -> List[str]:
+        return [self.payload[k] for k in SERIAL_KEYS if k in self.payload]
+
+    def to_dict(self) -> Dict[str, Any]:
+        return {
+            "sequence": self.sequence,
+            "timestamp": self.timestamp,
+            "actor": self.actor,
+            "operation": self.operation.name,
+            "payload": self.payload,
+            "result": self.result,
+        }
+
+    def to_json(self) -> str:
+        return json.dumps(self.to_dict(), sort_keys=True, separators=(",", ":"))
+
+    @staticmethod
+    def from_dict(data: Dict[str, Any]) -> "AuditRecord":
+        """
+        Record from its dict representation
+        :param data: dict as produced by to_dict
+        :return: AuditRecord
+        :raises: ValueError if the record is malformed
+        """
+        try:
+            return AuditRecord(
+                int(data["sequence"]),
+                float(data["timestamp"]),
+                str(data["actor"]),
+                Operation[data["operation"]],
+                dict(data["payload"]),
+                str(data["result"]),
+            )
+        except (KeyError, TypeError, ValueError) as e:
+            raise ValueError(f"Malformed audit record ({e})")
+
+
+class AuditSink(ABC):
+    @abstractmethod
+    def append(self, record: AuditRecord) -> None:
+        pass
+
+    @abstractmethod
+    def records(self) -> Iterator[AuditRecord]:
+        """
+        Records in the sink, oldest first
+        :return: records
+        """
+        pass
+
+
+class MemorySink(AuditSink):
+    """Keeps the most recent records in memory, older records are dropped once the capacity is reached."""
+
+    def __init__(self, capacity: int = 10_000):
+        self._records: Deque[AuditRecord] = deque(maxlen=capacity)
+
+    def append(self, record: AuditRecord) -> None:
+        self._records.append(record)
+
+    def records(self) -> Iterator[AuditRecord]:
+        return iter(list(self._records))
+
+
+class JsonLinesSink(AuditSink):
+    """
+    Appends records as JSON lines to a file, the file is rotated (path.1, path.2, ...) when it exceeds max_bytes and
+    the oldest file is removed when there are more than backups rotated files.
+    """
+
+    def __init__(self, path: str, max_bytes: int = 10_000_000, backups: int = 5):
+        self.path = path
+        self.max_bytes = max_bytes
+        self.backups = backups
+
+    def _rotate(self) -> None:
+        oldest = f"{self.path}.{self.backups}"
+        if os.path.exists(oldest):
+            os.remove(oldest)
+        for i in range(self.backups - 1, 0, -1):
+            if os.path.exists(f"{self.path}.{i}"):
+                os.replace(f"{self.path}.{i}", f"{self.path}.{i + 1}")
+        if self.backups > 0:
+            os.replace(self.path, f"{self.path}.1")
+        else:
+            os.remove(self.path)
+
+    def append(self, record: AuditRecord) -> None:
+        line = record.to_json() + "\n"
+        if (
+            os.path.exists(self.path)
+            and os.path.getsize(self.path) + len(line) > self.max_bytes
+        ):
+            self._rotate()
+        with open(self.path, "a") as f:
+            f.write(line)
+
+    def files(self) -> List[str]:
+        """
+        Files of the log that exist, oldest first
+        :return: list of paths
+        """
+        paths = [f"{self.path}.{i}" for i in range(self.backups, 0, -1)]
+        return [p for p in paths + [self.path] if os.path.exists(p)]
+
+    def records(self) -> Iterator[AuditRecord]:
+        for path in self.files():
+            with open(path) as f:
+                for line in f:
+                    if line.strip():
+                        yield AuditRecord.from_dict(json.loads(line))
+
+
+class AuditLog:
+    def __init__(self, sink: Optional[AuditSink] = None):
+        """
+        Append-only log of the mutations of the model
+        :param sink: sink the records are written to, in memory if not given
+        """
+        self.sink = sink if sink else MemorySink()
+        self._lock = threading.Lock()
+        self._sequence = max((r.sequence for r in self.sink.records()), default=0)
+
+    def append(
+        self,
+        actor: str,
+        operation: Operation,
+        payload: Dict[str, Any],
+        result: str = "ok",
+        timestamp: Optional[float] = None,
+    ) -> AuditRecord:
+        """
+        Append a record to the log
+        :param actor: who applied the mutation
+        :param operation: operation that was applied
+        :param payload: arguments of the operation
+        :param result: 'ok' or the reason the mutation was rejected
+        :param timestamp: time of the mutation, defaults to now
+        :return: the record
+        """
+        with self._lock:
+            self._sequence += 1
+            record = AuditRecord(
+                self._sequence,
+                time.time() if timestamp is None else float(timestamp),
+                actor,
+                operation,
+                payload,
+                result,
+            )
+            self.sink.append(record)
+        return record
+
+    def records(self) -> List[AuditRecord]:
+        return list(self.sink.records())
+
+    def query(
+        self,
+        start: Optional[float] = None,
+        end: Optional[float] = None,
+        actor: Optional[str] = None,
+        serial_number: Optional[str] = None,
+    ) -> List[AuditRecord]:
+        """
+        Records matching all given criteria
+        :param start: earliest timestamp (inclusive)
+        :param end: latest timestamp (inclusive)
+        :param actor: actor of the mutation
+        :param serial_number: device involved in the mutation
+        :return: list of records, oldest first
+        """
+        return [
+            r
+            for r in self.sink.records()
+            if (start is None or r.timestamp >= start)
+            and (end is None or r.timestamp <= end)
+            and (actor is None or r.actor == actor)
+            and (serial_number is None or serial_number in r.serials())
+        ]
@@ -0,0 +1,156 @@
+# -*- coding: utf-8 -*-#
+"""Compact binary encoding
+
+Binary counterpart of the JSON encoding, meant for the wire (e.g. gossip between peers). The same JSON compatible
+structures are written as tagged values: integers as zigzag varints, floats as 8 byte doubles and strings as UTF-8
+with a varint length. Every distinct string is written once, repeated strings (dict keys, enum names) are written as
+a varint reference to their first occurrence. A document starts with a magic and a format version.
+"""
+import struct
+from typing import Any, Dict, List, Tuple
+
+MAGIC = b"HRB"
+FORMAT_VERSION = 1
+
+NONE, FALSE, TRUE, INT, FLOAT, STR, LIST, DICT, STR_REF = range(9)
+
+
+def _varint(value: int) -> bytes:
+    out = bytearray()
+    while True:
+        byte = value & 0x7F
+        value >>= 7
+        if value:
+            out.append(byte | 0x80)
+        else:
+            out.append(byte)
+            return bytes(out)
+
+
+def pack(value: Any) -> bytes:
+    """
+    Encode a JSON compatible structure
+    :param value: None, bool, int, float, str, list or dict with str keys
+    :return: bytes
+    :raises: ValueError if the value contains other types
+    """
+    out = bytearray(MAGIC)
+    out.append(FORMAT_VERSION)
+    strings: Dict[str, int] = {}
+
+    def write_str(text: str) -> None:
+        if text in strings:
+            out.append(STR_REF)
+            out.extend(_varint(strings[text]))
+            return
+        strings[text] = len(strings)
+        data = text.encode()
+        out.append(STR)
+        out.extend(_varint(len(data)))
+        out.extend(data)
+
+    def write(v: Any) -> None:
+        if v is None:
+            out.append(NONE)
+        elif isinstance(v, bool):
+            out.append(TRUE if v else FALSE)
+        elif isinstance(v, int):
+            out.append(INT)
+            out.extend(_varint(v << 1 if v >= 0 else (-v << 1) - 1))
+        elif isinstance(v, float):
+            out.append(FLOAT)
+            out.extend(struct.pack(">d", v))
+        elif isinstance(v, str):
+            write_str(v)
+        elif isinstance(v, (list, tuple)):
+            out.append(LIST)
+            out.extend(_varint(len(v)))
+            for item in v:
+                write(item)
+        elif isinstance(v, dict):
+            out.append(DICT)
+            out.extend(_varint(len(v)))
+            for key, item in v.items():
+                if not isinstance(key, str):
+                    raise ValueError(f"Keys should be strings, got {key!r}")
+                write_str(key)
+                write(item)
+        else:
+            raise ValueError(f"Cannot pack {type(v).__name__}")
+
+    write(value)
+    return bytes(out)
+
+
+def unpack(data: bytes) -> Any:
+    """
+    Decode a structure encoded with pack
+    :param data: bytes
+    :return: JSON compatible structure
+    :raises: ValueError if the data is not a (complete) document of a known format version
+    """
+    if data[: len(MAGIC)] != MAGIC:
+        raise ValueError("Not a binary model document")
+    if len(data) <= len(MAGIC) or data[len(MAGIC)] != FORMAT_VERSION:
+        raise ValueError("Unknown binary format version")
+    strings: List[str] = []
+
+    def varint(position: int) -> Tuple[int, int]:
+        value, shift = 0, 0
+        while True:
+            byte = data[position]
+            value |= (byte & 0x7F) << shift
+            position += 1
+            if not byte & 0x80:
+                return value, position
+            shift += 7
+
+    def read(position: int) -> Tuple[Any, int]:
+        tag = data[position]
+        position += 1
+        if tag == NONE:
+            return None, position
+        if tag in (FALSE, TRUE):
+            return tag == TRUE, position
+        if tag == INT:
+            value, position = varint(position)
+            return (value >> 1) if not value & 1 else -((value + 1) >> 1), position
+        if tag == FLOAT:
+            if position + 8 > len(data):
+                raise IndexError("truncated float")
+            return struct.unpack(">d", data[position : position + 8])[0], position + 8
+        if tag == STR:
+            length, position = varint(position)
+            if position + length > len(data):
+                raise IndexError("truncated string")
+            text = data[position : position + length].decode()
+            strings.append(text)
+            return text, position + length
+        if tag == STR_REF:
+            index, position = varint(position)
+            return strings[index], position
+        if tag == LIST:
+            count, position = varint(position)
+            items = []
+            for _ in range(count):
+                item, position = read(position)
+                items.append(item)
+            return items, position
+        if tag == DICT:
+            count, position = varint(position)
+            result = {}
+            for _ in range(count):
+                key, position = read(position)
+                if not isinstance(key, str):
+                    raise ValueError(f"Keys should be strings, got {key!r}")
+                result[key], position = read(position)
+            return result, position
+        raise ValueError(f"Unknown tag {tag}")
+
+    try:
+        value, position = read(len(MAGIC) + 1)
+    except (IndexError, UnicodeDecodeError) as e:
+        raise ValueError(f"Malformed binary model document ({e})")
+    if position != len(data):
+        raise ValueError("Trailing data after binary model document")
+    return value
@@ -0,0 +1,142 @@
+# -*- coding: utf-8 -*-#
+"""Execution budget of analytics
+
+Some analytics (path enumeration, all pairs metrics, simulations) explode on large models. An AnalysisBudget bounds
+the wall clock time, the number of visited nodes and the number of results of a single analysis. Algorithms track
+their progress with a meter and stop with a BudgetExceeded error, carrying the partial results where meaningful, as
+soon as one of the limits is hit.
+"""
+import time
+from typing import Any, Callable, Optional
+
+from horao.settings import Settings
+
+
+class BudgetExceeded(Exception):
+    """Raised when an analysis runs out of budget, partial holds the results found so far (None if not meaningful)."""
+
+    def __init__(self, reason: str, partial: Any = None):
+        super().__init__(f"analysis budget exceeded: {reason}")
+        self.reason = reason
+        self.partial = partial
+
+
+class AnalysisBudget:
+    def __init__(
+        self,
+        max_duration: Optional[float] = None,
+        max_nodes_visited: Optional[int] = None,
+        max_results: Optional[int] = None,
+    ):
+        """
+        Limits of a single analysis, limits that are None are not enforced
+        :param max_duration: seconds the analysis may take
+        :param max_nodes_visited: number of nodes (or steps) the analysis may visit
+        :param max_results: number of results the analysis may produce
+        """
+        self.max_duration = max_duration
+        self.max_nodes_visited = max_nodes_visited
+        self.max_results = max_results
+
+    def __repr__(self):
+        return (
+            f"<AnalysisBudget {self.max_duration}s, {self.max_nodes_visited} nodes, "
+            f"{self.max_results} results>"
+        )
+
+    @staticmethod
+    def from_settings(
+        settings: Optional[Settings] = None,
+        max_duration: Optional[float] = None,
+        max_nodes_visited: Optional[int] = None,
+        max_results: Optional[int] = None,
+    ) -> "AnalysisBudget":
+        """
+        Budget based on the configured defaults, requested overrides (e.g. of an HTTP request) are clamped to the
+        configured ceilings
+        :param settings: settings to take the defaults and ceilings from, defaults if not given
+        :param max_duration: requested duration in seconds
+        :param max_nodes_visited: requested number of visited nodes
+        :param max_results: requested number of results
+        :return: AnalysisBudget
+        :raises: ValueError if a requested limit is not positive
+        """
+        settings = settings if settings else Settings()
+
+        def limit(requested, default, ceiling):
+            if requested is None:
+                return min(default, ceiling)
+            if requested <= 0:
+                raise ValueError(f"Budget limits should be positive, got {requested}")
+            return min(requested, ceiling)
+
+        return AnalysisBudget(
+            limit(
+                max_duration,
+                settings.analysis_max_duration,
+                settings.analysis_ceiling_duration,
+            ),
+            limit(
+                max_nodes_visited,
+                settings.analysis_max_nodes_visited,
+                settings.analysis_ceiling_nodes_visited,
+            ),
+            limit(
+                max_results,
+                settings.analysis_max_results,
+                settings.analysis_ceiling_results,
+            ),
+        )
+
+    def meter(self, clock: Callable[[], float] = time.monotonic) -> "BudgetMeter":
+        """
+        Start tracking an analysis against this budget
+        :param clock: clock to measure the duration with
+        :return: BudgetMeter
+        """
+        return BudgetMeter(self, clock)
+
+
+class BudgetMeter:
+    """Progress of a single analysis, the deadline starts when the meter is created."""
+
+    def __init__(self, budget: AnalysisBudget, clock: Callable[[], float]):
+        self.budget = budget
+        self.clock = clock
+        self.deadline = (
+            clock() + budget.max_duration if budget.max_duration is not None else None
+        )
+        self.nodes_visited = 0
+        self.results = 0
+
+    def visit(self, count: int = 1, partial: Any = None) -> None:
+        """
+        Account for visited nodes and check the limits
+        :param count: number of nodes visited since the last call
+        :param partial: results so far, handed to the error if the budget is exceeded
+        :return: None
+        :raises: BudgetExceeded if the duration or node limit is exceeded
+        """
+        self.nodes_visited += count
+        limit = self.budget.max_nodes_visited
+        if limit is not None and self.nodes_visited > limit:
+            raise BudgetExceeded(f"more than {limit} nodes visited", partial)
+        if self.deadline is not None and self.clock() > self.deadline:
+            raise BudgetExceeded(
+                f"took longer than {self.budget.max_duration}s", partial
+            )
+
+    def result(self, partial: Any = None) -> None:
+        """
+        Account for a result that is about to be added
+        :param partial: results so far (without the new result)
+        :return: None
+        :raises: BudgetExceeded if the result limit would be exceeded
+        """
+        self.results += 1
+        limit = self.budget.max_results
+        if limit is not None and self.results > limit:
+            raise BudgetExceeded(f"more than {limit} results", partial)
+
+
+UNLIMITED = AnalysisBudget()
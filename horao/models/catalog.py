@@ -0,0 +1,189 @@
+# -*- coding: utf-8 -*-#
+"""Device catalog
+
+This module contains the catalog of hardware models. Facts that belong to a hardware model (ports, size, power rating,
+CPU and memory layout) are defined once per model string, devices can be stamped out of the catalog and existing
+inventories can be enriched with the facts they are missing.
+
+A catalog file (TOML or JSON) maps model strings to templates:
+
+    [dcs-7050]
+    kind = "switch"
+    switch_type = "Access"
+    size_ru = 1
+    rated_power_watts = 450
+    port_capacity = 54
+    max_mac_entries = 32768
+    max_vlans = 4094
+    ports = [{name = "eth1", speed_gb = 25}, {name = "eth49", speed_gb = 100, uplink = true}]
+
+    [r650]
+    kind = "server"
+    size_ru = 1
+    rated_power_watts = 800
+    cpu = [{model = "xeon", cores = 32, clock_speed = 2800, count = 2}]
+    ram = [{model = "ddr4", size_gb = 32, speed_mhz = 3200, count = 16}]
+    nic = [{model = "cx6", ports = [{name = "p0", speed_gb = 25}, {name = "p1", speed_gb = 25}]}]
+"""
+import json
+import os
+import tomllib
+from typing import Any, Dict, List, Union
+
+from horao.models.hardware import DataCenter, Server
+from horao.models.network import DataCenterNetwork, Switch, SwitchType
+from horao.models.osi_layers import Port
+from horao.models.status import DeviceStatus
+
+
+class CatalogError(ValueError):
+    """Raised when a catalog file cannot be loaded, names the file and the offending key."""
+
+    def __init__(self, source_file: str, key: str, detail: str):
+        super().__init__(f"{source_file}: {key}: {detail}")
+        self.source_file = source_file
+        self.key = key
+        self.detail = detail
+
+
+class DeviceCatalog:
+    def __init__(self, templates: Dict[str, Dict[str, Any]]):
+        self.templates = templates
+
+    def __contains__(self, model: str) -> bool:
+        return model in self.templates
+
+    def template(self, model: str) -> Dict[str, Any]:
+        """
+        Template for a hardware model
+        :param model: model string
+        :return: template
+        :raises: ValueError if the model is not in the catalog
+        """
+        if model not in self.templates:
+            raise ValueError(f"Model {model} is not in the catalog")
+        return self.templates[model]
+
+    @staticmethod
+    def ports(
+        serial_number: str, model: str, specs: List[Dict[str, Any]]
+    ) -> List[Port]:
+        """
+        Stamp out (down) ports from port specifications of a template
+        :param serial_number: serial number of the device the ports belong to
+        :param model: model of the device
+        :param specs: port specifications (name, speed_gb)
+        :return: list of ports
+        """
+        return [
+            Port(
+                f"{serial_number}-{spec['name']}",
+                spec["name"],
+                model,
+                number,
+                "",
+                DeviceStatus.Down,
+                spec["speed_gb"],
+            )
+            for number, spec in enumerate(specs, start=1)
+        ]
+
+    @staticmethod
+    def load(path: str) -> "DeviceCatalog":
+        """
+        Load a catalog from a TOML or JSON file (based on the extension)
+        :param path: path to the catalog file
+        :return: DeviceCatalog
+        :raises: CatalogError if the file cannot be parsed or a template is invalid
+        """
+        try:
+            with open(path, "rb") as f:
+                if os.path.splitext(path)[1].lower() == ".json":
+                    content = json.load(f)
+                else:
+                    content = tomllib.load(f)
+        except (OSError, ValueError) as e:
+            raise CatalogError(path, "", str(e))
+        if not isinstance(content, dict):
+            raise CatalogError(path, "", "catalog should map models to templates")
+        for model, template in content.items():
+            _validate(path, model, template)
+        return DeviceCatalog(content)
+
+
+def _validate(path: str, model: str, template: Any) -> None:
+    def require(key: str, value: Any, kind: type, optional: bool = False) -> None:
+        if value is None and optional:
+            return
+        if not isinstance(value, kind) or (kind is int and isinstance(value, bool)):
+            raise CatalogError(path, key, f"should be of type {kind.__name__}")
+
+    def require_ports(key: str, ports: Any) -> None:
+        require(key, ports, list)
+        for i, port in enumerate(ports):
+            require(f"{key}[{i}]", port, dict)
+            require(f"{key}[{i}].name", port.get("name"), str)
+            require(f"{key}[{i}].speed_gb", port.get("speed_gb"), int)
+
+    require(model, template, dict)
+    if template.get("kind") not in ("switch", "server"):
+        raise CatalogError(path, f"{model}.kind", "should be 'switch' or 'server'")
+    require(f"{model}.size_ru", template.get("size_ru"), int, optional=True)
+    require(
+        f"{model}.rated_power_watts",
+        template.get("rated_power_watts"),
+        int,
+        optional=True,
+    )
+    if template["kind"] == "switch":
+        if template.get("switch_type") not in SwitchType.__members__:
+            raise CatalogError(path, f"{model}.switch_type", "unknown switch type")
+        for key in ["port_capacity", "max_mac_entries", "max_acl_entries", "max_vlans"]:
+            require(f"{model}.{key}", template.get(key), int, optional=True)
+        require_ports(f"{model}.ports", template.get("ports", []))
+        return
+    for key, fields in [
+        ("cpu", ["cores", "clock_speed", "count"]),
+        ("ram", ["size_gb", "count"]),
+    ]:
+        require(f"{model}.{key}", template.get(key, []), list)
+        for i, spec in enumerate(template.get(key, [])):
+            require(f"{model}.{key}[{i}]", spec, dict)
+            for field in fields:
+                require(f"{model}.{key}[{i}].{field}", spec.get(field), int)
+    require(f"{model}.nic", template.get("nic", []), list)
+    for i, nic in enumerate(template.get("nic", [])):
+        require(f"{model}.nic[{i}]", nic, dict)
+        require_ports(f"{model}.nic[{i}].ports", nic.get("ports", []))
+
+
+def enrich(
+    target: Union[DataCenter, DataCenterNetwork], catalog: DeviceCatalog
+) -> List[str]:
+    """
+    Fill in the size and power rating of devices (and the table capacities of switches) that do not have them set,
+    explicitly set values are never overwritten.
+    :param target: datacenter (servers and cabinet switches) or network (switches) to enrich
+    :param catalog: catalog to take the facts from
+    :return: list of devices (serial number and model) whose model is not in the catalog
+    """
+    if isinstance(target, DataCenter):
+        devices: List[Union[Server, Switch]] = list(target.servers())
+        devices += [s for r in target.rows for c in r.cabinets for s in c.switches]
+    else:
+        devices = [n for n in target.graph.nodes if isinstance(n, Switch)]
+    unknown = []
+    for device in devices:
+        if device.model not in catalog:
+            unknown.append(f"{device.serial_number} ({device.model})")
+            continue
+        template = catalog.template(device.model)
+        if device.size_ru is None:
+            device.size_ru = template.get("size_ru")
+        if device.rated_power_watts is None:
+            device.rated_power_watts = template.get("rated_power_watts")
+        if isinstance(device, Switch):
+            for key in ["max_mac_entries", "max_acl_entries", "max_vlans"]:
+                if getattr(device, key) is None:
+                    setattr(device, key, template.get(key))
+    return unknown
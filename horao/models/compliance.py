@@ -0,0 +1,276 @@
+# -*- coding: utf-8 -*-#
+"""Port configuration compliance
+
+Port profiles describe the intended configuration of switch ports ('ports 1-44 of access switches are access ports in
+VLAN 100 at 10G'). The compliance check compares the ports in the network against the profiles, the first profile
+that matches a port applies. Profiles are loaded from a TOML or JSON file:
+
+    [[profile]]
+    name = "access"
+    switch_type = "Access"
+    ports = "1-44"
+    speed_gb = 10
+    access_vlan = 100
+
+    [[profile]]
+    name = "uplink"
+    switch_type = "Access"
+    ports = "47-48"
+    trunk_vlans = [100, 200]
+"""
+import json
+import os
+import tomllib
+from enum import Enum, auto
+from typing import Any, Dict, List, Optional
+
+from horao.models.network import DataCenterNetwork, Switch, SwitchType
+from horao.models.osi_layers import Medium, Port
+from horao.models.resources import configured_vlans
+from horao.settings import ConfigError
+
+
+class ProfileMatcher:
+    def __init__(
+        self,
+        switch_type: Optional[SwitchType] = None,
+        model: Optional[str] = None,
+        first_port: Optional[int] = None,
+        last_port: Optional[int] = None,
+    ):
+        """
+        Selects the ports a profile applies to, unset criteria match everything
+        :param switch_type: type of the switch
+        :param model: model of the switch
+        :param first_port: lowest port number (inclusive)
+        :param last_port: highest port number (inclusive)
+        """
+        self.switch_type = switch_type
+        self.model = model
+        self.first_port = first_port
+        self.last_port = last_port
+
+    def matches(self, switch: Switch, port: Port) -> bool:
+        return (
+            (self.switch_type is None or switch.switch_type == self.switch_type)
+            and (self.model is None or switch.model == self.model)
+            and (self.first_port is None or port.number >= self.first_port)
+            and (self.last_port is None or port.number <= self.last_port)
+        )
+
+
+class PortProfile:
+    def __init__(
+        self,
+        name: str,
+        matcher: ProfileMatcher,
+        speed_gb: Optional[int] = None,
+        medium: Optional[Medium] = None,
+        access_vlan: Optional[int] = None,
+        trunk_vlans: Optional[List[int]] = None,
+        admin_up: Optional[bool] = True,
+    ):
+        """
+        Intended configuration of the matched ports, unset expectations are not checked
+        :param name: name of the profile
+        :param matcher: ports the profile applies to
+        :param speed_gb: expected speed
+        :param medium: expected medium
+        :param access_vlan: expected access VLAN
+        :param trunk_vlans: expected trunk VLANs
+        :param admin_up: expected administrative state
+        """
+        self.name = name
+        self.matcher = matcher
+        self.speed_gb = speed_gb
+        self.medium = medium
+        self.access_vlan = access_vlan
+        self.trunk_vlans = trunk_vlans
+        self.admin_up = admin_up
+
+
+class Deviation(Enum):
+    Speed = auto()
+    Medium = auto()
+    Vlan = auto()
+    AdminDown = auto()
+    # the port is not matched by any profile (strict coverage only)
+    Uncovered = auto()
+    # the switch has more VLANs configured than its hardware model supports
+    Capacity = auto()
+
+
+class Finding:
+    def __init__(
+        self, port: str, profile: Optional[str], deviation: Deviation, detail: str
+    ):
+        self.port = port
+        self.profile = profile
+        self.deviation = deviation
+        self.detail = detail
+
+    def to_dict(self) -> dict:
+        return {
+            "port": self.port,
+            "profile": self.profile,
+            "deviation": self.deviation.name,
+            "detail": self.detail,
+        }
+
+
+class ComplianceReport:
+    def __init__(self, findings: Dict[str, List[Finding]], ports_checked: int):
+        """
+        Outcome of a compliance check
+        :param findings: findings per switch (serial number), switches without findings are left out
+        :param ports_checked: number of ports checked
+        """
+        self.findings = findings
+        self.ports_checked = ports_checked
+
+    @property
+    def compliant(self) -> bool:
+        return not self.findings
+
+    def to_dict(self) -> dict:
+        return {
+            "compliant": self.compliant,
+            "ports_checked": self.ports_checked,
+            "findings": {
+                switch: [f.to_dict() for f in findings]
+                for switch, findings in self.findings.items()
+            },
+        }
+
+
+def _deviations(profile: PortProfile, port: Port) -> List[Finding]:
+    findings = []
+
+    def finding(deviation: Deviation, detail: str) -> None:
+        findings.append(Finding(port.name, profile.name, deviation, detail))
+
+    if profile.speed_gb is not None and port.speed_gb != profile.speed_gb:
+        finding(
+            Deviation.Speed, f"speed {port.speed_gb}G, expected {profile.speed_gb}G"
+        )
+    if profile.medium is not None and port.medium != profile.medium:
+        finding(
+            Deviation.Medium,
+            f"medium {port.medium.name if port.medium else 'unknown'}, expected {profile.medium.name}",
+        )
+    if profile.access_vlan is not None and port.access_vlan != profile.access_vlan:
+        finding(
+            Deviation.Vlan,
+            f"access VLAN {port.access_vlan}, expected {profile.access_vlan}",
+        )
+    if profile.trunk_vlans is not None and sorted(port.trunk_vlans) != sorted(
+        profile.trunk_vlans
+    ):
+        finding(
+            Deviation.Vlan,
+            f"trunk VLANs {sorted(port.trunk_vlans)}, expected {sorted(profile.trunk_vlans)}",
+        )
+    if profile.admin_up and not port.admin_up:
+        finding(Deviation.AdminDown, "administratively down")
+    return findings
+
+
+def check_compliance(
+    network: DataCenterNetwork, profiles: List[PortProfile], strict: bool = False
+) -> ComplianceReport:
+    """
+    Check the ports of all switches in the network against the profiles, switches with more VLANs configured than
+    they support are reported as well
+    :param network: network to check
+    :param profiles: profiles, the first matching profile applies to a port
+    :param strict: report ports that are not matched by any profile
+    :return: ComplianceReport
+    """
+    findings: Dict[str, List[Finding]] = {}
+    checked = 0
+    switches = [n for n in network.graph_view().nodes if isinstance(n, Switch)]
+    for switch in sorted(switches, key=lambda s: s.serial_number):
+        for port in switch.lan_ports + switch.uplink_ports:
+            checked += 1
+            profile = next(
+                (p for p in profiles if p.matcher.matches(switch, port)), None
+            )
+            if profile is None:
+                port_findings = (
+                    [Finding(port.name, None, Deviation.Uncovered, "no profile")]
+                    if strict
+                    else []
+                )
+            else:
+                port_findings = _deviations(profile, port)
+            if port_findings:
+                findings.setdefault(switch.serial_number, []).extend(port_findings)
+        vlans = configured_vlans(switch)
+        if switch.max_vlans is not None and len(vlans) > switch.max_vlans:
+            findings.setdefault(switch.serial_number, []).append(
+                Finding(
+                    switch.name,
+                    None,
+                    Deviation.Capacity,
+                    f"{len(vlans)} VLANs configured, at most {switch.max_vlans} supported",
+                )
+            )
+    return ComplianceReport(findings, checked)
+
+
+def _profile(path: str, index: int, spec: Any) -> PortProfile:
+    key = f"profile[{index}]"
+    if not isinstance(spec, dict) or not isinstance(spec.get("name"), str):
+        raise ConfigError(path, key, "should be a table with a name")
+    first_port = last_port = None
+    if "ports" in spec:
+        first, _, last = str(spec["ports"]).partition("-")
+        if not first.isdigit() or not (last or first).isdigit():
+            raise ConfigError(path, f"{key}.ports", "should be a number or a range")
+        first_port, last_port = int(first), int(last or first)
+    enums: Dict[str, Any] = {}
+    for field, enum in [("switch_type", SwitchType), ("medium", Medium)]:
+        if field in spec:
+            if spec[field] not in enum.__members__:
+                raise ConfigError(path, f"{key}.{field}", f"unknown {field}")
+            enums[field] = enum[spec[field]]
+    for field, kind in [
+        ("speed_gb", int),
+        ("access_vlan", int),
+        ("trunk_vlans", list),
+    ]:
+        if field in spec and not isinstance(spec[field], kind):
+            raise ConfigError(
+                path, f"{key}.{field}", f"should be of type {kind.__name__}"
+            )
+    return PortProfile(
+        spec["name"],
+        ProfileMatcher(
+            enums.get("switch_type"), spec.get("model"), first_port, last_port
+        ),
+        spec.get("speed_gb"),
+        enums.get("medium"),
+        spec.get("access_vlan"),
+        spec.get("trunk_vlans"),
+        spec.get("admin_up", True),
+    )
+
+
+def load_profiles(path: str) -> List[PortProfile]:
+    """
+    Load port profiles from a TOML or JSON file (based on the extension)
+    :param path: path to the profiles file
+    :return: list of profiles, in the order of the file
+    :raises: ConfigError if the file cannot be parsed or a profile is invalid
+    """
+    try:
+        with open(path, "rb") as f:
+            if os.path.splitext(path)[1].lower() == ".json":
+                content = json.load(f)
+            else:
+                content = tomllib.load(f)
+    except (OSError, ValueError) as e:
+        raise ConfigError(path, "", str(e))
+    if not isinstance(content, dict) or not isinstance(content.get("profile"), list):
+        raise ConfigError(path, "profile", "should be a list of profiles")
+    return [_profile(path, i, spec) for i, spec in enumerate(content["profile"])]
@@ -0,0 +1,129 @@
+# -*- coding: utf-8 -*-#
+"""Differences between serialized models
+
+A ChangeSet records how one serialized (JSON compatible) document turns into another, as a list of changes addressed by
+their path in the document: a key of an object is set or removed, or a range of a list is replaced. Lists are compared
+by trimming their common start and end, so appending, removing or changing a device only records that device.
+Change sets serialize to JSON themselves, which makes them suitable for delta snapshots and for exchanging the changes
+between peers.
+"""
+import copy
+import hashlib
+import json
+from typing import Any, Dict, List, Optional, Union
+
+Path = List[Union[str, int]]
+
+
+def fingerprint(document: Any) -> str:
+    """
+    Fingerprint of a document, equal documents have the same fingerprint
+    :param document: JSON compatible document
+    :return: sha256 hex digest of the (stably) serialized document
+    """
+    return hashlib.sha256(
+        json.dumps(document, sort_keys=True, separators=(",", ":")).encode()
+    ).hexdigest()
+
+
+class ChangeSet:
+    def __init__(self, changes: Optional[List[Dict[str, Any]]] = None):
+        """
+        Changes turning one document into another
+        :param changes: changes, each with a path and an op (set, remove or splice)
+        """
+        self.changes = changes if changes else []
+
+    def __len__(self) -> int:
+        return len(self.changes)
+
+    def __eq__(self, other):
+        return isinstance(other, ChangeSet) and self.changes == other.changes
+
+    @staticmethod
+    def between(base: Any, target: Any) -> "ChangeSet":
+        """
+        Compute the changes turning base into target
+        :param base: JSON compatible document
+        :param target: JSON compatible document
+        :return: ChangeSet
+        """
+        changes: List[Dict[str, Any]] = []
+
+        def compare(left: Any, right: Any, path: Path) -> None:
+            if type(left) is not type(right):
+                changes.append({"path": path, "op": "set", "value": right})
+            elif isinstance(left, dict):
+                for key in left:
+                    if key not in right:
+                        changes.append({"path": path + [key], "op": "remove"})
+                for key, value in right.items():
+                    if key not in left:
+                        changes.append(
+                            {"path": path + [key], "op": "set", "value": value}
+                        )
+                    else:
+                        compare(left[key], value, path + [key])
+            elif isinstance(left, list):
+                start = 0
+                common = min(len(left), len(right))
+                while start < common and left[start] == right[start]:
+                    start += 1
+                end_left, end_right = len(left), len(right)
+                while (
+                    end_left > start
+                    and end_right > start
+                    and left[end_left - 1] == right[end_right - 1]
+                ):
+                    end_left -= 1
+                    end_right -= 1
+                if end_left - start == end_right - start:
+                    # same number of items changed, compare them one by one
+                    for i in range(start, end_left):
+                        compare(left[i], right[i], path + [i])
+                elif end_left > start or end_right > start:
+                    changes.append(
+                        {
+                            "path": path,
+                            "op": "splice",
+                            "start": start,
+                            "end": end_left,
+                            "items": right[start:end_right],
+                        }
+                    )
+            elif left != right:
+                changes.append({"path": path, "op": "set", "value": right})
+
+        compare(base, target, [])
+        return ChangeSet(changes)
+
+    def apply(self, base: Any) -> Any:
+        """
+        Apply the changes to a document, the document itself is left untouched
+        :param base: JSON compatible document
+        :return: changed copy of the document
+        :raises: ValueError if a change does not fit the document
+        """
+        document = copy.deepcopy(base)
+        for change in self.changes:
+            path = change["path"]
+            if change["op"] == "set" and not path:
+                document = copy.deepcopy(change["value"])
+                continue
+            try:
+                parent = document
+                for key in path[:-1] if change["op"] != "splice" else path:
+                    parent = parent[key]
+                if change["op"] == "set":
+                    parent[path[-1]] = copy.deepcopy(change["value"])
+                elif change["op"] == "remove":
+                    del parent[path[-1]]
+                elif change["op"] == "splice":
+                    parent[change["start"] : change["end"]] = copy.deepcopy(
+                        change["items"]
+                    )
+                else:
+                    raise ValueError(f"unknown op {change['op']}")
+            except (KeyError, IndexError, TypeError) as e:
+                raise ValueError(f"Change at {path} does not fit the document ({e})")
+        return document
@@ -0,0 +1,216 @@
+# -*- coding: utf-8 -*-#
+"""Capacity forecasting
+
+This module projects the usage of resources recorded in the snapshot store into the future, to estimate when they will
+be exhausted. Projections are made with a linear regression over the time series, or with Holt's double exponential
+smoothing when recent samples should weigh more. Both come with a (95%) confidence interval.
+"""
+import math
+from enum import Enum, auto
+from typing import Dict, Iterable, List, Optional, Tuple
+
+from horao.models.snapshot import SnapshotStore
+
+# z-score of the 95% confidence interval
+Z_95 = 1.96
+
+
+class ForecastMethod(Enum):
+    LinearRegression = auto()
+    Holt = auto()
+
+
+class Forecast:
+    def __init__(
+        self,
+        timestamp: float,
+        value: float,
+        lower: float,
+        upper: float,
+        slope: float,
+        origin: Tuple[float, float],
+    ):
+        """
+        Projected value of a time series
+        :param timestamp: time the projection is made for
+        :param value: projected value
+        :param lower: lower bound of the confidence interval
+        :param upper: upper bound of the confidence interval
+        :param slope: trend of the series (per second)
+        :param origin: timestamp and (fitted) value the trend starts from
+        """
+        self.timestamp = timestamp
+        self.value = value
+        self.lower = lower
+        self.upper = upper
+        self.slope = slope
+        self.origin = origin
+
+    def crossing(self, limit: float) -> Optional[float]:
+        """
+        Time at which the trend reaches the limit
+        :param limit: limit to reach
+        :return: timestamp, None if the trend is not increasing
+        """
+        if self.slope <= 0:
+            return None
+        return self.origin[0] + (limit - self.origin[1]) / self.slope
+
+
+def _linear(series: List[Tuple[float, float]], timestamp: float) -> Forecast:
+    n = len(series)
+    mean_x = sum(t for t, _ in series) / n
+    mean_y = sum(v for _, v in series) / n
+    sxx = sum((t - mean_x) ** 2 for t, _ in series)
+    slope = (
+        sum((t - mean_x) * (v - mean_y) for t, v in series) / sxx if sxx else 0.0
+    )
+    intercept = mean_y - slope * mean_x
+    residuals = [v - (intercept + slope * t) for t, v in series]
+    error = math.sqrt(sum(r**2 for r in residuals) / (n - 2)) if n > 2 else 0.0
+    value = intercept + slope * timestamp
+    spread = (
+        Z_95
+        * error
+        * math.sqrt(1 + 1 / n + ((timestamp - mean_x) ** 2 / sxx if sxx else 0))
+    )
+    return Forecast(
+        timestamp, value, value - spread, value + spread, slope, (mean_x, mean_y)
+    )
+
+
+def _holt(
+    series: List[Tuple[float, float]], timestamp: float, alpha: float, beta: float
+) -> Forecast:
+    step = (series[-1][0] - series[0][0]) / (len(series) - 1)
+    level, trend = series[0][1], series[1][1] - series[0][1]
+    errors = []
+    for _, value in series[1:]:
+        errors.append(value - (level + trend))
+        previous = level
+        level = alpha * value + (1 - alpha) * (level + trend)
+        trend = beta * (level - previous) + (1 - beta) * trend
+    steps = (timestamp - series[-1][0]) / step if step else 0
+    value = level + trend * steps
+    error = math.sqrt(sum(e**2 for e in errors) / len(errors))
+    spread = Z_95 * error * math.sqrt(max(1.0, steps))
+    slope = trend / step if step else 0.0
+    return Forecast(
+        timestamp, value, value - spread, value + spread, slope, (series[-1][0], level)
+    )
+
+
+def forecast(
+    series: Iterable[Tuple[float, float]],
+    horizon: float,
+    method: ForecastMethod = ForecastMethod.LinearRegression,
+    min_points: int = 3,
+    alpha: float = 0.5,
+    beta: float = 0.3,
+) -> Optional[Forecast]:
+    """
+    Project a time series into the future
+    :param series: tuples of timestamp and value
+    :param horizon: seconds after the last sample to project to
+    :param method: projection method
+    :param min_points: minimal number of samples needed for a projection
+    :param alpha: smoothing factor of the level (Holt only)
+    :param beta: smoothing factor of the trend (Holt only)
+    :return: Forecast, None if there are too few samples
+    """
+    samples = sorted(series)
+    if len(samples) < max(2, min_points):
+        return None
+    timestamp = samples[-1][0] + horizon
+    if method == ForecastMethod.Holt:
+        return _holt(samples, timestamp, alpha, beta)
+    return _linear(samples, timestamp)
+
+
+class ExhaustionEstimate:
+    def __init__(
+        self,
+        serial_number: str,
+        kind: str,
+        limit: float,
+        current: float,
+        exhausted_at: float,
+        forecast: Forecast,
+    ):
+        self.serial_number = serial_number
+        self.kind = kind
+        self.limit = limit
+        self.current = current
+        self.exhausted_at = exhausted_at
+        self.forecast = forecast
+
+    def to_dict(self) -> dict:
+        return {
+            "serial_number": self.serial_number,
+            "kind": self.kind,
+            "limit": self.limit,
+            "current": self.current,
+            "exhausted_at": self.exhausted_at,
+            "forecast": {
+                "timestamp": self.forecast.timestamp,
+                "value": self.forecast.value,
+                "lower": self.forecast.lower,
+                "upper": self.forecast.upper,
+            },
+        }
+
+
+def exhaustion_estimates(
+    store: SnapshotStore,
+    horizon: float,
+    start: Optional[float] = None,
+    end: Optional[float] = None,
+    method: ForecastMethod = ForecastMethod.LinearRegression,
+    min_points: int = 3,
+) -> List[ExhaustionEstimate]:
+    """
+    Estimate when disks, and the local storage of servers as a whole, run full. Series with too few samples or without
+    an increasing trend are skipped, as are resources that are not projected to run full within the horizon.
+    :param store: snapshot store to take the usage history from
+    :param horizon: seconds after the last snapshot to look ahead
+    :param start: start of the history to use
+    :param end: end of the history to use
+    :param method: projection method
+    :param min_points: minimal number of samples needed for an estimate
+    :return: list of estimates, the soonest exhaustion first
+    """
+    series: Dict[Tuple[str, str], List[Tuple[float, float]]] = {}
+    limits: Dict[Tuple[str, str], float] = {}
+    for snapshot in store.snapshots(start, end):
+        for server in snapshot.datacenter.servers():
+            disks = [d for d in server.disk or [] if d.usage_gb is not None]
+            for disk in disks:
+                series.setdefault((disk.serial_number, "disk"), []).append(
+                    (snapshot.timestamp, disk.usage_gb)
+                )
+                limits[(disk.serial_number, "disk")] = disk.size_gb
+            if disks:
+                key = (server.serial_number, "server storage")
+                series.setdefault(key, []).append(
+                    (snapshot.timestamp, sum(d.usage_gb for d in disks))
+                )
+                limits[key] = sum(d.size_gb for d in disks)
+    estimates = []
+    for (serial_number, kind), samples in series.items():
+        projection = forecast(samples, horizon, method, min_points)
+        if projection is None:
+            continue
+        exhausted_at = projection.crossing(limits[(serial_number, kind)])
+        if exhausted_at is None or exhausted_at > projection.timestamp:
+            continue
+        estimates.append(
+            ExhaustionEstimate(
+                serial_number,
+                kind,
+                limits[(serial_number, kind)],
+                samples[-1][1],
+                exhausted_at,
+                projection,
+            )
+        )
+    return sorted(estimates, key=lambda e: e.exhausted_at)
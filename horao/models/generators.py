@@ -0,0 +1,180 @@
+# -*- coding: utf-8 -*-#
+"""Topology generators
+
+Builders of fully wired networks of well known topologies, for testing the topology detection, the metrics and the
+simulations without building networks by hand. All switches are up and of model 'generated', every link is made
+between explicit (distinct) ports that are set up. Ports that are meant for servers are left down. Serial numbers
+and names encode the position of a switch in the topology (e.g. 'pod1-agg0').
+"""
+from typing import List
+
+from horao.models.network import DataCenterNetwork, NetworkType, Switch, SwitchType
+from horao.models.osi_layers import Link, LinkLayer, Port
+from horao.models.status import DeviceStatus
+
+
+def _switch(
+    serial_number: str,
+    switch_type: SwitchType,
+    number: int,
+    lan_ports: int,
+    uplink_ports: int,
+    speed_gb: int,
+) -> Switch:
+    def ports(prefix: str, count: int) -> List[Port]:
+        return [
+            Port(
+                f"{serial_number}-{prefix}{i}",
+                f"{prefix}{i}",
+                "generated",
+                i,
+                f"{serial_number}-{prefix}{i}",
+                DeviceStatus.Down,
+                speed_gb,
+            )
+            for i in range(count)
+        ]
+
+    return Switch(
+        serial_number,
+        serial_number,
+        "generated",
+        number,
+        LinkLayer.Layer2,
+        switch_type,
+        DeviceStatus.Up,
+        True,
+        ports("lan", lan_ports),
+        ports("up", uplink_ports),
+    )
+
+
+def _cable(
+    network: DataCenterNetwork,
+    left: Switch,
+    left_port: Port,
+    right: Switch,
+    right_port: Port,
+) -> None:
+    link = Link(
+        network.ports.intern(left_port, left), network.ports.intern(right_port, right)
+    )
+    network.graph.add_edge(left, right, link=link)
+    left_port.status = DeviceStatus.Up
+    right_port.status = DeviceStatus.Up
+
+
+def fat_tree(k: int, speed_gb: int = 100) -> DataCenterNetwork:
+    """
+    k-ary fat tree (Al-Fares): k pods of k/2 access and k/2 distribution switches and (k/2)^2 core switches, every
+    access switch has k/2 ports for servers
+    :param k: arity, the number of ports per switch
+    :param speed_gb: speed of all ports
+    :return: DataCenterNetwork
+    :raises: ValueError if k is not a positive even number
+    """
+    if k < 2 or k % 2:
+        raise ValueError(
+            f"The arity of a fat tree should be a positive even number, not {k}"
+        )
+    half = k // 2
+    network = DataCenterNetwork(f"fat-tree-{k}", NetworkType.Data)
+    cores = [
+        _switch(f"core{i}", SwitchType.Core, i, k, 0, speed_gb)
+        for i in range(half * half)
+    ]
+    network.add_multiple(cores)
+    for pod in range(k):
+        aggregation = [
+            _switch(
+                f"pod{pod}-agg{i}", SwitchType.Distribution, i, half, half, speed_gb
+            )
+            for i in range(half)
+        ]
+        access = [
+            _switch(f"pod{pod}-acc{i}", SwitchType.Access, i, half, half, speed_gb)
+            for i in range(half)
+        ]
+        network.add_multiple(aggregation + access)
+        for i, edge in enumerate(access):
+            for j, agg in enumerate(aggregation):
+                _cable(network, edge, edge.uplink_ports[j], agg, agg.lan_ports[i])
+        for i, agg in enumerate(aggregation):
+            for j in range(half):
+                core = cores[i * half + j]
+                _cable(network, agg, agg.uplink_ports[j], core, core.lan_ports[pod])
+    return network
+
+
+def tree(depth: int, fanout: int, speed_gb: int = 100) -> DataCenterNetwork:
+    """
+    Tree of switches: a core switch at the root, distribution switches in between and access switches at the leaves,
+    every switch has fanout ports towards the next level (or servers for the access switches)
+    :param depth: number of levels of switches
+    :param fanout: number of children per switch
+    :param speed_gb: speed of all ports
+    :return: DataCenterNetwork
+    :raises: ValueError if the depth or fanout is not positive
+    """
+    if depth < 1 or fanout < 1:
+        raise ValueError("The depth and fanout of a tree should be positive")
+    network = DataCenterNetwork(f"tree-{depth}-{fanout}", NetworkType.Data)
+
+    def switch_type(level: int) -> SwitchType:
+        if level == 0:
+            return SwitchType.Core
+        return SwitchType.Access if level == depth - 1 else SwitchType.Distribution
+
+    root = _switch("l0-s0", switch_type(0), 0, fanout, 0, speed_gb)
+    network.add(root)
+    parents = [root]
+    for level in range(1, depth):
+        children = []
+        for i, parent in enumerate(parents):
+            for j in range(fanout):
+                number = i * fanout + j
+                child = _switch(
+                    f"l{level}-s{number}",
+                    switch_type(level),
+                    number,
+                    fanout,
+                    1,
+                    speed_gb,
+                )
+                network.add(child)
+                _cable(
+                    network, child, child.uplink_ports[0], parent, parent.lan_ports[j]
+                )
+                children.append(child)
+        parents = children
+    return network
+
+
+def clos(
+    spines: int, leaves: int, ports: int, speed_gb: int = 100
+) -> DataCenterNetwork:
+    """
+    Two tier (leaf-spine) Clos fabric, every leaf (access switch) is linked to every spine (core switch)
+    :param spines: number of spine switches
+    :param leaves: number of leaf switches
+    :param ports: number of ports for servers per leaf
+    :param speed_gb: speed of all ports
+    :return: DataCenterNetwork
+    :raises: ValueError if there are no spines or leaves
+    """
+    if spines < 1 or leaves < 1 or ports < 0:
+        raise ValueError("A Clos fabric needs spines, leaves and server ports")
+    network = DataCenterNetwork(f"clos-{spines}-{leaves}", NetworkType.Data)
+    spine_switches = [
+        _switch(f"spine{i}", SwitchType.Core, i, leaves, 0, speed_gb)
+        for i in range(spines)
+    ]
+    leaf_switches = [
+        _switch(f"leaf{i}", SwitchType.Access, i, ports, spines, speed_gb)
+        for i in range(leaves)
+    ]
+    network.add_multiple(spine_switches + leaf_switches)
+    for i, leaf in enumerate(leaf_switches):
+        for j, spine in enumerate(spine_switches):
+            _cable(network, leaf, leaf.uplink_ports[j], spine, spine.lan_ports[i])
+    return network
@@ -5,11 +5,39 @@ This module contains the definition of compute/storage equipment (hardware) and
 We assume that 'faulty' equipment state is either up or down, it should be handled in a state machine, not here.
 Also we assume that these data structures are not very prone to change, given that this implies a manual activity.
 """
-from typing import List, Optional
+import base64
+import binascii
+import heapq
+import ipaddress
+import json
+import random
+from enum import Enum, auto
+from typing import (
+    Any,
+    Callable,
+    Dict,
+    Iterator,
+    List,
+    Optional,
+    Set,
+    Tuple,
+    TYPE_CHECKING,
+    TypeVar,
+    Union,
+)
 
-from horao.models import Port, Switch
-from horao.models.network import NIC
-from horao.models.status import DeviceStatus
+import networkx as nx  # type: ignore
+
+from horao.models import IpAddress, Port, Switch
+from horao.models.network import NIC, Annotated, DataCenterNetwork, NetworkDevice
+from horao.models.status import DeviceStatus, LifecycleState, transition
+from horao.settings import CapacityUnits, ComputeUnit
+
+if TYPE_CHECKING:
+    from horao.models.catalog import DeviceCatalog
+    from horao.models.journal import Journal
+
+T = TypeVar("T")
 
 
 class RAM:
@@ -78,15 +106,50 @@ class Disk:
         model: str,
         number: int,
         size_gb: int,
+        usage_gb: Optional[int] = None,
     ):
         self.serial_number = serial_number
         self.name = name
         self.model = model
         self.number = number
         self.size_gb = size_gb
+        self.usage_gb = usage_gb
+
+
+class BondMode(Enum):
+    # one member carries the traffic, the others take over on failure
+    ActiveBackup = auto()
+    # all members carry traffic, requires a link aggregate on the switch side
+    Lacp8023ad = auto()
+
+
+class Bond:
+    """Bonded NICs (by serial number) of a server, acting as a single logical connection."""
+
+    def __init__(self, name: str, mode: BondMode, members: List[str]):
+        self.name = name
+        self.mode = mode
+        self.members = members
+
+
+class AffinityPolicy(Enum):
+    # members of the group should be placed in the same cabinet
+    Affinity = auto()
+    # members of the group must not share a cabinet
+    AntiAffinity = auto()
+    # members of the group provide the same service, at least one must stay active
+    HighAvailability = auto()
+
+
+class AffinityGroup:
+    """Placement group of servers, servers are tagged with the groups they are a member of."""
+
+    def __init__(self, name: str, policy: AffinityPolicy):
+        self.name = name
+        self.policy = policy
 
 
-class Server:
+class Server(Annotated):
     def __init__(
         self,
         serial_number: str,
@@ -99,7 +162,24 @@ class Server:
         disk: Optional[List[Disk]],
         accelerator: Optional[List[Accelerator]],
         status: DeviceStatus,
+        bonds: Optional[List[Bond]] = None,
+        lifecycle: LifecycleState = LifecycleState.Active,
+        size_ru: Optional[int] = None,
+        rated_power_watts: Optional[int] = None,
+        affinity_groups: Optional[List[AffinityGroup]] = None,
+        labels: Optional[Dict[str, str]] = None,
+        tenant: Optional[str] = None,
+        cost: Optional[float] = None,
+        metadata: Optional[Dict[str, str]] = None,
     ):
+        """
+        Server (or blade)
+        :param affinity_groups: placement groups the server is a member of
+        :param labels: free form labels (e.g. role=storage)
+        :param tenant: tenant owning the server
+        :param cost: purchase cost
+        :param metadata: free form attributes of the operator (e.g. asset_tag, owner)
+        """
         self.serial_number = serial_number
         self.name = name
         self.model = model
@@ -110,6 +190,128 @@ class Server:
         self.disk = disk
         self.accelerator = accelerator
         self.status = status
+        self.bonds = bonds if bonds else []
+        self.lifecycle = lifecycle
+        self.size_ru = size_ru
+        self.rated_power_watts = rated_power_watts
+        self.affinity_groups = affinity_groups if affinity_groups else []
+        self.labels = labels if labels else {}
+        self.tenant = tenant
+        self.cost = cost
+        self.metadata = metadata if metadata else {}
+
+    def ip_addresses(self) -> List[IpAddress]:
+        """
+        IP addresses assigned to the NICs of the server
+        :return: list of addresses, in the order of the NICs
+        """
+        return [a for nic in self.nic for a in nic.addresses]
+
+    @classmethod
+    def from_catalog(
+        cls,
+        catalog: "DeviceCatalog",
+        model: str,
+        serial_number: str,
+        name: str,
+        number: int = 1,
+    ) -> "Server":
+        """
+        Stamp out a (provisioning) server from the catalog template of its model
+        :param catalog: catalog containing the model
+        :param model: model of the server
+        :param serial_number: serial number
+        :param name: name
+        :param number: number
+        :return: Server
+        :raises: ValueError if the model is not in the catalog
+        """
+        template = catalog.template(model)
+        cpu_specs = [s for s in template.get("cpu", []) for _ in range(s["count"])]
+        ram_specs = [s for s in template.get("ram", []) for _ in range(s["count"])]
+        return cls(
+            serial_number,
+            name,
+            model,
+            number,
+            [
+                CPU(
+                    f"{serial_number}-cpu{i}",
+                    f"cpu{i}",
+                    spec.get("model", model),
+                    i,
+                    spec["clock_speed"],
+                    spec["cores"],
+                    spec.get("features"),
+                )
+                for i, spec in enumerate(cpu_specs)
+            ],
+            [
+                RAM(
+                    f"{serial_number}-ram{i}",
+                    f"ram{i}",
+                    spec.get("model", model),
+                    i,
+                    spec["size_gb"],
+                    spec.get("speed_mhz"),
+                )
+                for i, spec in enumerate(ram_specs)
+            ],
+            [
+                NIC(
+                    f"{serial_number}-nic{i}",
+                    f"nic{i}",
+                    spec.get("model", model),
+                    i,
+                    catalog.ports(
+                        f"{serial_number}-nic{i}", model, spec.get("ports", [])
+                    ),
+                )
+                for i, spec in enumerate(template.get("nic", []))
+            ],
+            [],
+            [],
+            DeviceStatus.Down,
+            lifecycle=LifecycleState.Provisioning,
+            size_ru=template.get("size_ru"),
+            rated_power_watts=template.get("rated_power_watts"),
+        )
+
+    def bond_members(self, bond: Bond) -> List[NIC]:
+        """
+        NICs of this server that are member of the bond
+        :param bond: bond to resolve
+        :return: list of NICs
+        :raises: ValueError if a member NIC is not part of this server
+        """
+        nics = {n.serial_number: n for n in self.nic}
+        missing = [m for m in bond.members if m not in nics]
+        if missing:
+            raise ValueError(f"NICs {', '.join(missing)} do not belong to {self.name}")
+        return [nics[m] for m in bond.members]
+
+
+class SharedResourceType(Enum):
+    Power = auto()
+    Cooling = auto()
+    # network midplane connecting the blades to the switch modules
+    Midplane = auto()
+
+
+class SharedResource:
+    """Resource of a chassis that all blades depend on, a failure affects all blades at once."""
+
+    def __init__(
+        self,
+        name: str,
+        resource_type: SharedResourceType,
+        status: DeviceStatus,
+        critical: bool = True,
+    ):
+        self.name = name
+        self.resource_type = resource_type
+        self.status = status
+        self.critical = critical
 
 
 class Chassis:
@@ -120,12 +322,32 @@ class Chassis:
         model: str,
         number: int,
         servers: List[Server],
+        shared_resources: Optional[List[SharedResource]] = None,
     ):
         self.serial_number = serial_number
         self.name = name
         self.model = model
         self.number = number
         self.servers = servers
+        self.shared_resources = shared_resources if shared_resources else []
+
+    def is_available(self) -> bool:
+        """
+        A chassis is available when none of its critical shared resources are down, regardless of the blades
+        :return: True if all critical shared resources are up
+        """
+        return all(
+            r.status == DeviceStatus.Up for r in self.shared_resources if r.critical
+        )
+
+    def available_servers(self) -> List[Server]:
+        """
+        Blades that can actually serve, these are up and in an available chassis
+        :return: list of servers
+        """
+        if not self.is_available():
+            return []
+        return [s for s in self.servers if s.status == DeviceStatus.Up]
 
 
 class Cabinet:
@@ -147,19 +369,876 @@ class Cabinet:
         self.chassis = chassis
         self.switches = switches
 
+    def power_watts(self) -> int:
+        """
+        Rated power of the servers (including blades) and switches in the cabinet, devices without a rating count as 0
+        :return: power in watts
+        """
+        devices: List[Union[Server, Switch]] = list(self.servers)
+        devices += [s for c in self.chassis for s in c.servers] + self.switches
+        return sum(d.rated_power_watts or 0 for d in devices)
+
+    def power_density_watts_per_u(self, total_u: int) -> float:
+        """
+        Rated power per rack unit of the cabinet
+        :param total_u: height of the cabinet in rack units
+        :return: watts per rack unit
+        :raises: ValueError if the height is not positive
+        """
+        if total_u <= 0:
+            raise ValueError(f"Cabinet height should be positive, not {total_u}")
+        return self.power_watts() / total_u
+
 
 class Row:
-    def __init__(self, name, number, cabinets):
+    def __init__(self, name: str, number: int, cabinets: List[Cabinet]):
         self.name = name
         self.number = number
         self.cabinets = cabinets
 
 
+class InventorySummary:
+    def __init__(
+        self,
+        servers: int,
+        cpus: int,
+        cores: int,
+        ram_gb: int,
+        disk_gb: int,
+        accelerators: int,
+        units: Optional[CapacityUnits] = None,
+    ):
+        """
+        Compute and storage capacity
+        :param ram_gb: memory, in the storage unit of units
+        :param disk_gb: disk capacity, in the storage unit of units
+        :param units: units the capacity is reported in, cores and GB by default
+        """
+        self.servers = servers
+        self.cpus = cpus
+        self.cores = cores
+        self.ram_gb = ram_gb
+        self.disk_gb = disk_gb
+        self.accelerators = accelerators
+        self.units = units if units else CapacityUnits()
+
+    @property
+    def compute(self) -> int:
+        """
+        Compute capacity in the compute unit
+        :return: number of cores or sockets
+        """
+        return self.cores if self.units.compute == ComputeUnit.Cores else self.cpus
+
+    @classmethod
+    def of(
+        cls, servers: List[Server], units: Optional[CapacityUnits] = None
+    ) -> "InventorySummary":
+        """
+        Summarize the compute and storage capacity of a set of servers
+        :param servers: servers to summarize
+        :param units: units to report the capacity in, cores and GB by default
+        :return: InventorySummary
+        """
+        units = units if units else CapacityUnits()
+        return cls(
+            len(servers),
+            sum(len(s.cpu) for s in servers),
+            sum(c.cores for s in servers for c in s.cpu),
+            units.convert_storage(sum(r.size_gb for s in servers for r in s.ram)),
+            units.convert_storage(
+                sum(d.size_gb for s in servers for d in s.disk or [])
+            ),
+            sum(len(s.accelerator or []) for s in servers),
+            units,
+        )
+
+
+class CapacityDelta:
+    def __init__(
+        self,
+        servers: int,
+        cpus: int,
+        cores: int,
+        ram_gb: int,
+        disk_gb: int,
+        accelerators: int,
+    ):
+        """
+        Change in compute and storage capacity, negative when capacity is removed
+        :param ram_gb: change in memory, in GB
+        :param disk_gb: change in disk capacity, in GB
+        """
+        self.servers = servers
+        self.cpus = cpus
+        self.cores = cores
+        self.ram_gb = ram_gb
+        self.disk_gb = disk_gb
+        self.accelerators = accelerators
+
+    def __repr__(self):
+        return (
+            f"<CapacityDelta {self.servers:+} servers, {self.cores:+} cores, "
+            f"{self.ram_gb:+} GB RAM, {self.disk_gb:+} GB disk>"
+        )
+
+    @classmethod
+    def between(cls, before: "DataCenter", after: "DataCenter") -> "CapacityDelta":
+        """
+        Capacity impact of a change of the inventory, decommissioned servers do not count
+        :param before: datacenter before the change
+        :param after: datacenter after the change
+        :return: CapacityDelta
+        """
+        old, new = before.summary(), after.summary()
+        return cls(
+            new.servers - old.servers,
+            new.cpus - old.cpus,
+            new.cores - old.cores,
+            new.ram_gb - old.ram_gb,
+            new.disk_gb - old.disk_gb,
+            new.accelerators - old.accelerators,
+        )
+
+    def describe(self) -> str:
+        """
+        Human readable summary of the impact, e.g. 'removes 1 server, removes 512 cores'
+        :return: description, 'no capacity change' if nothing changed
+        """
+        parts = []
+        for value, unit in [
+            (self.servers, "server"),
+            (self.cores, "core"),
+            (self.ram_gb, "GB RAM"),
+            (self.disk_gb, "GB disk"),
+            (self.accelerators, "accelerator"),
+        ]:
+            if value:
+                plural = "s" if abs(value) != 1 and not unit.startswith("GB") else ""
+                verb = "adds" if value > 0 else "removes"
+                parts.append(f"{verb} {abs(value)} {unit}{plural}")
+        return ", ".join(parts) if parts else "no capacity change"
+
+
+class StatusEvent:
+    """Status of a device as observed by a monitoring system."""
+
+    def __init__(self, serial_number: str, status: DeviceStatus, timestamp: float):
+        self.serial_number = serial_number
+        self.status = status
+        self.timestamp = timestamp
+
+
+class StatusApplyReport:
+    def __init__(self, applied: List[str], stale: List[str], unknown: List[str]):
+        """
+        Outcome of applying a status feed
+        :param applied: serial numbers of the devices whose status was set
+        :param stale: serial numbers of the events that were older than the last applied event of the device
+        :param unknown: serial numbers that are not part of the datacenter
+        """
+        self.applied = applied
+        self.stale = stale
+        self.unknown = unknown
+
+
+class InvalidCursor(ValueError):
+    """Raised when a page cursor cannot be decoded."""
+
+    pass
+
+
+class FaultDomainKind(Enum):
+    # servers in a cabinet share its power feed
+    Cabinet = auto()
+    # blades share the power, cooling and midplane of their chassis
+    Chassis = auto()
+    # servers linked to the same (top of rack) switch
+    Switch = auto()
+
+
+class FaultDomain:
+    def __init__(
+        self, kind: FaultDomainKind, serial_number: str, name: str, servers: List[str]
+    ):
+        """
+        Servers that fail together when a single component fails
+        :param kind: kind of component
+        :param serial_number: serial number of the component
+        :param name: name of the component
+        :param servers: serial numbers of the servers in the domain
+        """
+        self.kind = kind
+        self.serial_number = serial_number
+        self.name = name
+        self.servers = servers
+
+    def __repr__(self):
+        return f"<FaultDomain {self.kind.name} {self.name}: {', '.join(self.servers)}>"
+
+
+class TransactionAborted(ValueError):
+    def __init__(self, problems: List[str]):
+        """
+        A transaction left the datacenter with (new) problems and was rolled back
+        :param problems: problems introduced by the transaction
+        """
+        super().__init__(f"Transaction rolled back: {'; '.join(problems)}")
+        self.problems = problems
+
+
+def _capture(root: Any) -> List[Tuple[Any, Any]]:
+    # shallow copy of the state of every object reachable from the root, restoring the copies in place keeps the
+    # identity of the objects (e.g. the NICs that are also part of a network)
+    captured: List[Tuple[Any, Any]] = []
+    seen: Set[int] = set()
+    pending = [root]
+    while pending:
+        value = pending.pop()
+        if isinstance(value, (str, int, float, bool, Enum, type(None))):
+            continue
+        if id(value) in seen:
+            continue
+        seen.add(id(value))
+        if isinstance(value, list):
+            captured.append((value, list(value)))
+            pending.extend(value)
+        elif isinstance(value, dict):
+            captured.append((value, dict(value)))
+            pending.extend(value.values())
+        elif isinstance(value, tuple):
+            pending.extend(value)
+        elif hasattr(value, "__dict__"):
+            captured.append((value, dict(value.__dict__)))
+            pending.extend(value.__dict__.values())
+    return captured
+
+
+def _restore(captured: List[Tuple[Any, Any]]) -> None:
+    for value, state in captured:
+        if isinstance(value, list):
+            value[:] = state
+        elif isinstance(value, dict):
+            value.clear()
+            value.update(state)
+        else:
+            value.__dict__.clear()
+            value.__dict__.update(state)
+
+
+class Page:
+    def __init__(
+        self,
+        items: List[Server],
+        next_cursor: Optional[str],
+        total: Optional[int] = None,
+    ):
+        """
+        Page of the servers matching a query
+        :param items: servers of the page, in order of serial number
+        :param next_cursor: cursor of the next page, None on the last page
+        :param total: number of matching servers over all pages, None if it was not requested
+        """
+        self.items = items
+        self.next_cursor = next_cursor
+        self.total = total
+
+
+def _encode_cursor(serial_number: str) -> str:
+    return base64.urlsafe_b64encode(
+        json.dumps({"after": serial_number}).encode("utf-8")
+    ).decode("ascii")
+
+
+def _decode_cursor(cursor: str) -> str:
+    try:
+        after = json.loads(base64.urlsafe_b64decode(cursor.encode("ascii")))["after"]
+    except (binascii.Error, ValueError, TypeError, KeyError, UnicodeError):
+        raise InvalidCursor(f"Invalid cursor {cursor}")
+    if not isinstance(after, str):
+        raise InvalidCursor(f"Invalid cursor {cursor}")
+    return after
+
+
+class ServerQuery:
+    """
+    Filter over the servers of a datacenter, predicates are combined (all have to match):
+
+        dc.query().with_min_cores(32).with_tag("gpu").with_status(DeviceStatus.Up).collect()
+    """
+
+    def __init__(self, servers: Iterator[Server]):
+        self._servers = servers
+        self._predicates: List[Callable[[Server], bool]] = []
+
+    def where(self, predicate: Callable[[Server], bool]) -> "ServerQuery":
+        """
+        Only match servers for which the predicate holds
+        :param predicate: function of a server
+        :return: ServerQuery
+        """
+        self._predicates.append(predicate)
+        return self
+
+    def with_min_cores(self, cores: int) -> "ServerQuery":
+        """
+        Only match servers with at least the given number of cores (over all CPUs)
+        :param cores: minimum number of cores
+        :return: ServerQuery
+        """
+        return self.where(lambda s: sum(c.cores for c in s.cpu) >= cores)
+
+    def with_tag(self, tag: str) -> "ServerQuery":
+        """
+        Only match servers with the given tag, a tag is either the key of a label ('gpu') or a key and value
+        ('role=compute')
+        :param tag: label key or key=value
+        :return: ServerQuery
+        """
+        if "=" in tag:
+            key, value = tag.split("=", 1)
+            return self.where(lambda s: s.labels.get(key) == value)
+        return self.where(lambda s: tag in s.labels)
+
+    def with_status(self, status: DeviceStatus) -> "ServerQuery":
+        """
+        Only match servers with the given status
+        :param status: status
+        :return: ServerQuery
+        """
+        return self.where(lambda s: s.status == status)
+
+    def collect(self) -> List[Server]:
+        """
+        Servers matching all predicates
+        :return: list of servers
+        """
+        return [s for s in self._servers if all(p(s) for p in self._predicates)]
+
+    def page(
+        self, page_size: int, cursor: Optional[str] = None, total: bool = False
+    ) -> Page:
+        """
+        Page of the servers matching all predicates, in order of serial number. The cursor anchors on the serial
+        number of the last server of the previous page, so walking the pages while the model changes returns every
+        server at most once: removed servers are skipped, added servers show up on a later page if they sort after
+        the cursor.
+        :param page_size: maximum number of servers of the page
+        :param cursor: cursor of the page, the first page if not given
+        :param total: also count the matching servers over all pages, which evaluates the predicates on all servers
+        :return: Page
+        :raises: ValueError if the page size is not positive, InvalidCursor if the cursor cannot be decoded
+        """
+        if page_size < 1:
+            raise ValueError("The page size should be at least 1")
+        after = _decode_cursor(cursor) if cursor is not None else None
+        count = 0
+        candidates = []
+        for server in self._servers:
+            if after is not None and server.serial_number <= after and not total:
+                continue
+            if not all(p(server) for p in self._predicates):
+                continue
+            count += 1
+            if after is None or server.serial_number > after:
+                candidates.append(server)
+        selected = heapq.nsmallest(
+            page_size + 1, candidates, key=lambda s: s.serial_number
+        )
+        items = selected[:page_size]
+        next_cursor = (
+            _encode_cursor(items[-1].serial_number)
+            if len(selected) > page_size
+            else None
+        )
+        return Page(items, next_cursor, count if total else None)
+
+
 class DataCenter:
     def __init__(self, name: str, number: int, rows: List[Row]):
         self.name = name
         self.number = number
         self.rows = rows
+        # time of the last status event applied per device
+        self._status_timestamps: Dict[str, float] = {}
+
+    def servers(self, include_decommissioned: bool = False) -> Iterator[Server]:
+        """
+        Iterate over all servers in the datacenter, including blades in chassis
+        :param include_decommissioned: also include decommissioned servers
+        :return: servers
+        """
+        for row in self.rows:
+            for cabinet in row.cabinets:
+                for server in cabinet.servers + [
+                    s for c in cabinet.chassis for s in c.servers
+                ]:
+                    if (
+                        include_decommissioned
+                        or server.lifecycle != LifecycleState.Decommissioned
+                    ):
+                        yield server
+
+    def query(self, include_decommissioned: bool = False) -> ServerQuery:
+        """
+        Query the servers in the datacenter, including blades in chassis
+        :param include_decommissioned: also include decommissioned servers
+        :return: ServerQuery
+        """
+        return ServerQuery(self.servers(include_decommissioned))
+
+    def validate(self) -> List[str]:
+        """
+        Problems of the inventory: serial numbers used by more than one server or switch, and affinity violations
+        :return: list of problems, empty if there are none
+        """
+        devices: Dict[str, int] = {}
+        for server in self.servers(include_decommissioned=True):
+            devices[server.serial_number] = devices.get(server.serial_number, 0) + 1
+        for row in self.rows:
+            for cabinet in row.cabinets:
+                for switch in cabinet.switches:
+                    devices[switch.serial_number] = (
+                        devices.get(switch.serial_number, 0) + 1
+                    )
+        problems = [
+            f"serial number {s} is used by {n} devices"
+            for s, n in sorted(devices.items())
+            if n > 1
+        ]
+        return problems + self.affinity_violations()
+
+    def transaction(self, change: Callable[["DataCenter"], T]) -> T:
+        """
+        Apply a batch of changes all-or-nothing: the datacenter is rolled back to its state before the transaction if
+        the changes raise, or if they introduce problems (see validate)
+        :param change: function making the changes, taking the datacenter
+        :return: result of the function
+        :raises: the exception raised by the function, TransactionAborted if the changes introduced problems
+        """
+        captured = _capture(self)
+        before = set(self.validate())
+        try:
+            result = change(self)
+            problems = [p for p in self.validate() if p not in before]
+        except BaseException:
+            _restore(captured)
+            raise
+        if problems:
+            _restore(captured)
+            raise TransactionAborted(problems)
+        return result
+
+    def apply_journal(self, journal: "Journal") -> None:
+        """
+        Apply the changes of a journal in order
+        :param journal: journal recorded against the current state of the datacenter
+        :return: None
+        :raises: ValueError if a change does not apply, the changes before it have been applied
+        """
+        for entry in journal:
+            try:
+                entry.change.apply(self)
+            except ValueError as e:
+                raise ValueError(
+                    f"Journal entry {entry.sequence} cannot be applied ({e})"
+                )
+
+    def to_netbox_json(self, rack_height_u: int = 42) -> str:
+        """
+        Export the inventory in the NetBox import format (sites, racks, devices and interfaces), see netbox.py
+        :param rack_height_u: height of the racks in rack units
+        :return: JSON string
+        """
+        # imported here, the export depends on the hardware model
+        from horao.models.netbox import to_netbox_json
+
+        return to_netbox_json(self, rack_height_u)
+
+    def high_density_cabinets(
+        self, threshold: float, total_u: int = 42
+    ) -> List[Cabinet]:
+        """
+        Cabinets whose power density exceeds what the facility can cool
+        :param threshold: highest acceptable watts per rack unit
+        :param total_u: height of the cabinets in rack units
+        :return: list of cabinets
+        """
+        return [
+            c
+            for r in self.rows
+            for c in r.cabinets
+            if c.power_density_watts_per_u(total_u) > threshold
+        ]
+
+    def affinity_violations(self) -> List[str]:
+        """
+        Check the placement of servers against their affinity groups, blades are placed in the cabinet of their chassis
+        :return: list of violations, anti-affinity groups with members sharing a cabinet and affinity groups that are
+        spread across cabinets
+        """
+        groups: Dict[Tuple[str, AffinityPolicy], Dict[str, List[Server]]] = {}
+        for row in self.rows:
+            for cabinet in row.cabinets:
+                for server in cabinet.servers + [
+                    s for c in cabinet.chassis for s in c.servers
+                ]:
+                    if server.lifecycle == LifecycleState.Decommissioned:
+                        continue
+                    for group in server.affinity_groups:
+                        groups.setdefault((group.name, group.policy), {}).setdefault(
+                            cabinet.name, []
+                        ).append(server)
+        violations = []
+        for (name, policy), cabinets in groups.items():
+            if policy == AffinityPolicy.AntiAffinity:
+                for cabinet_name, members in cabinets.items():
+                    if len(members) > 1:
+                        violations.append(
+                            f"anti-affinity group {name}: {', '.join(m.name for m in members)} share cabinet "
+                            f"{cabinet_name}"
+                        )
+            elif policy == AffinityPolicy.Affinity and len(cabinets) > 1:
+                violations.append(
+                    f"affinity group {name} is spread across cabinets {', '.join(cabinets)}"
+                )
+        return violations
+
+    def fault_domains(
+        self, networks: Optional[List[DataCenterNetwork]] = None
+    ) -> List[FaultDomain]:
+        """
+        Group the servers by the components they depend on: the cabinet (power feed), the chassis of blades and the
+        switches their NICs are linked to. A server is in one domain of every kind it depends on, a server linked to
+        two switches is in the domains of both. Replicas should be spread over the domains.
+        :param networks: networks the servers are linked in
+        :return: list of fault domains, by kind, domains without servers are left out
+        """
+        domains: List[FaultDomain] = []
+        switches: Dict[int, FaultDomain] = {}
+        for row in self.rows:
+            for cabinet in row.cabinets:
+                servers = cabinet.servers + [
+                    s for c in cabinet.chassis for s in c.servers
+                ]
+                active = [
+                    s for s in servers if s.lifecycle != LifecycleState.Decommissioned
+                ]
+                if active:
+                    domains.append(
+                        FaultDomain(
+                            FaultDomainKind.Cabinet,
+                            cabinet.serial_number,
+                            cabinet.name,
+                            [s.serial_number for s in active],
+                        )
+                    )
+                for chassis in cabinet.chassis:
+                    blades = [s for s in chassis.servers if s in active]
+                    if blades:
+                        domains.append(
+                            FaultDomain(
+                                FaultDomainKind.Chassis,
+                                chassis.serial_number,
+                                chassis.name,
+                                [s.serial_number for s in blades],
+                            )
+                        )
+                for server in active:
+                    for _, _, peer in self._links(server, networks or []):
+                        if not isinstance(peer, Switch):
+                            continue
+                        domain = switches.setdefault(
+                            id(peer),
+                            FaultDomain(
+                                FaultDomainKind.Switch,
+                                peer.serial_number,
+                                peer.name,
+                                [],
+                            ),
+                        )
+                        if server.serial_number not in domain.servers:
+                            domain.servers.append(server.serial_number)
+        return sorted(domains, key=lambda d: d.kind.value) + list(switches.values())
+
+    def servers_sorted_by_health(self) -> List[Server]:
+        """
+        All servers, the unhealthy ones first
+        :return: list of servers
+        """
+        return sorted(self.servers(), key=lambda s: s.status)
+
+    def sample_servers(
+        self, n: int, weight: Callable[[Server], float], rng: random.Random
+    ) -> List[Server]:
+        """
+        Weighted random selection of servers without replacement (e.g. to place a synthetic workload), a server is
+        picked with a probability proportional to its weight. Servers with a weight of zero (or less) are never picked.
+        :param n: number of servers to pick, all servers with a weight are returned if there are fewer
+        :param weight: weight of a server (e.g. its free cores)
+        :param rng: random generator, seed it for a reproducible selection
+        :return: list of servers, in the order they were picked
+        """
+        # Efraimidis-Spirakis: the n largest keys u^(1/w) are a weighted sample without replacement
+        keys = []
+        for server in self.servers():
+            w = weight(server)
+            if w > 0:
+                keys.append((rng.random() ** (1 / w), server.serial_number, server))
+        return [server for _, _, server in heapq.nlargest(max(0, n), keys)]
+
+    def summary(
+        self,
+        include_decommissioned: bool = False,
+        units: Optional[CapacityUnits] = None,
+    ) -> InventorySummary:
+        """
+        Summarize the compute and storage capacity of the datacenter
+        :param include_decommissioned: also count decommissioned servers
+        :param units: units to report the capacity in (see Settings.capacity_units), cores and GB by default
+        :return: InventorySummary
+        """
+        return InventorySummary.of(list(self.servers(include_decommissioned)), units)
+
+    def summary_by_tenant(
+        self, units: Optional[CapacityUnits] = None
+    ) -> Dict[Optional[str], InventorySummary]:
+        """
+        Summarize the compute and storage capacity per tenant
+        :param units: units to report the capacity in, cores and GB by default
+        :return: summary per tenant, servers without a tenant are summarized under None
+        """
+        tenants: Dict[Optional[str], List[Server]] = {}
+        for server in self.servers():
+            tenants.setdefault(server.tenant, []).append(server)
+        return {t: InventorySummary.of(s, units) for t, s in tenants.items()}
+
+    def apply_status_feed(
+        self,
+        events: List[StatusEvent],
+        networks: Optional[List[DataCenterNetwork]] = None,
+    ) -> StatusApplyReport:
+        """
+        Apply status events of a monitoring feed (e.g. Nagios, Zabbix), the most recent event of a device wins, events
+        older than the last applied event of a device are ignored
+        :param events: events to apply, in any order
+        :param networks: networks containing devices that are not part of the cabinets
+        :return: StatusApplyReport
+        """
+        applied, stale, unknown = [], [], []
+        for event in events:
+            try:
+                device = self.find_device(event.serial_number, networks or [])
+            except ValueError:
+                unknown.append(event.serial_number)
+                continue
+            if event.timestamp < self._status_timestamps.get(
+                event.serial_number, float("-inf")
+            ):
+                stale.append(event.serial_number)
+                continue
+            device.status = event.status
+            self._status_timestamps[event.serial_number] = event.timestamp
+            if event.serial_number not in applied:
+                applied.append(event.serial_number)
+        return StatusApplyReport(applied, stale, unknown)
+
+    def find_device(self, serial_number: str, networks: List[DataCenterNetwork]):
+        """
+        Find a device (server, switch, router or firewall) by serial number
+        :param serial_number: serial number of the device
+        :param networks: networks the device might be part of
+        :return: device
+        :raises: ValueError if the device does not exist
+        """
+        for server in self.servers(include_decommissioned=True):
+            if server.serial_number == serial_number:
+                return server
+        for network in networks:
+            for device in network.graph.nodes:
+                if device.serial_number == serial_number and hasattr(
+                    device, "lifecycle"
+                ):
+                    return device
+        for row in self.rows:
+            for cabinet in row.cabinets:
+                for switch in cabinet.switches:
+                    if switch.serial_number == serial_number:
+                        return switch
+        raise ValueError(f"Device {serial_number} does not exist")
+
+    def find_server_by_ip(
+        self, address: str, include_decommissioned: bool = False
+    ) -> Optional[Server]:
+        """
+        Find the server an IP address is assigned to
+        :param address: IP address
+        :param include_decommissioned: also search decommissioned servers, their addresses may have been reused
+        :return: server, None if no server has the address
+        :raises: ValueError if the address is not a valid IP address
+        """
+        wanted = ipaddress.ip_address(address)
+        for server in self.servers(include_decommissioned):
+            for assigned in server.ip_addresses():
+                if ipaddress.ip_address(assigned.address) == wanted:
+                    return server
+        return None
+
+    def find_by_meta(
+        self,
+        key: str,
+        value: str,
+        networks: Optional[List[DataCenterNetwork]] = None,
+    ) -> List[Annotated]:
+        """
+        Find the devices (servers, switches, routers and firewalls) with a metadata attribute set to a value
+        :param key: metadata attribute
+        :param value: value of the attribute
+        :param networks: networks containing devices that are not part of the cabinets
+        :return: list of devices, the servers first
+        """
+        devices: Dict[int, Annotated] = {}
+        for server in self.servers(include_decommissioned=True):
+            devices[id(server)] = server
+        for row in self.rows:
+            for cabinet in row.cabinets:
+                for switch in cabinet.switches:
+                    devices[id(switch)] = switch
+        for network in networks or []:
+            for device in network.graph.nodes:
+                if isinstance(device, Annotated):
+                    devices[id(device)] = device
+        return [d for d in devices.values() if d.get_meta(key) == value]
+
+    @staticmethod
+    def _links(
+        device, networks: List[DataCenterNetwork]
+    ) -> List[Tuple[DataCenterNetwork, NetworkDevice, NetworkDevice]]:
+        endpoints = device.nic if isinstance(device, Server) else [device]
+        return [
+            (network, endpoint, peer)
+            for network in networks
+            for endpoint in endpoints
+            for peer, _ in network.attachments(endpoint)
+        ]
+
+    def can_decommission(
+        self, serial_number: str, networks: Optional[List[DataCenterNetwork]] = None
+    ) -> List[str]:
+        """
+        Check that nothing depends on a server before decommissioning it: the other active members of its affinity
+        groups rely on being placed with it, a high-availability group needs at least one active member and other
+        devices may only be reachable through the server
+        :param serial_number: serial number of the server
+        :param networks: networks the server might be linked in
+        :return: list of reasons the server cannot be decommissioned, empty if it can
+        :raises: ValueError if the server does not exist
+        """
+        server = self.find_device(serial_number, networks or [])
+        if not isinstance(server, Server):
+            raise ValueError(f"Device {serial_number} is not a server")
+        others = [
+            s
+            for s in self.servers()
+            if s is not server and s.lifecycle == LifecycleState.Active
+        ]
+        reasons = []
+        for group in server.affinity_groups:
+            members = sorted(
+                s.name
+                for s in others
+                if any(
+                    g.name == group.name and g.policy == group.policy
+                    for g in s.affinity_groups
+                )
+            )
+            if group.policy == AffinityPolicy.Affinity and members:
+                reasons.append(
+                    f"affinity group {group.name} still has active members {', '.join(members)}"
+                )
+            elif group.policy == AffinityPolicy.HighAvailability and not members:
+                reasons.append(
+                    f"{server.name} is the last active server of HA group {group.name}"
+                )
+        for network in networks or []:
+            graph = network.graph_view()
+            endpoints = [n for n in server.nic if n in graph]
+            if not any(graph.degree(n) for n in endpoints):
+                continue
+            remaining = graph.subgraph([n for n in graph.nodes if n not in endpoints])
+            for component in nx.connected_components(graph):
+                if not any(n in component for n in endpoints):
+                    continue
+                parts = sorted(
+                    nx.connected_components(
+                        remaining.subgraph(c for c in component if c in remaining)
+                    ),
+                    key=len,
+                    reverse=True,
+                )
+                isolated = sorted(d.name for part in parts[1:] for d in part)
+                if isolated:
+                    reasons.append(
+                        f"removing {server.name} isolates {', '.join(isolated)} in {network.name}"
+                    )
+        return reasons
+
+    def decommission(
+        self, serial_number: str, networks: List[DataCenterNetwork], force: bool = False
+    ) -> List[str]:
+        """
+        Start decommissioning a device (server, switch, router or firewall). A device can only be decommissioned if it
+        is no longer linked to other devices and (for servers) nothing depends on it (see can_decommission), unless
+        forced, in that case the links are detached.
+        :param serial_number: serial number of the device
+        :param networks: networks the device might be linked in
+        :param force: detach the device from all links
+        :return: list of links that were detached
+        :raises: ValueError if the device does not exist, is still linked, other devices depend on it or it cannot
+        be decommissioned
+        """
+        device = self.find_device(serial_number, networks)
+        links = self._links(device, networks)
+        if links and not force:
+            raise ValueError(
+                f"{device.name} ({serial_number}) still has {len(links)} active links"
+            )
+        if isinstance(device, Server) and not force:
+            reasons = self.can_decommission(serial_number, networks)
+            if reasons:
+                raise ValueError(
+                    f"{device.name} ({serial_number}) cannot be decommissioned: {'; '.join(reasons)}"
+                )
+        transition(device, LifecycleState.Decommissioning)
+        detached = []
+        for network, endpoint, peer in links:
+            network.unlink(endpoint, peer)
+            detached.append(f"{endpoint.name} - {peer.name} ({network.name})")
+        return detached
+
+    def complete_decommission(
+        self, serial_number: str, networks: List[DataCenterNetwork]
+    ) -> None:
+        """
+        Finish decommissioning a device, it can no longer be re-activated afterwards
+        :param serial_number: serial number of the device
+        :param networks: networks the device might be part of
+        :return: None
+        :raises: ValueError if the device does not exist or is not decommissioning
+        """
+        transition(
+            self.find_device(serial_number, networks), LifecycleState.Decommissioned
+        )
+
+    def reactivate(self, serial_number: str, networks: List[DataCenterNetwork]) -> None:
+        """
+        Re-activate a device that is being decommissioned
+        :param serial_number: serial number of the device
+        :param networks: networks the device might be part of
+        :return: None
+        :raises: ValueError if the device does not exist or is already decommissioned
+        """
+        transition(self.find_device(serial_number, networks), LifecycleState.Active)
 
     def move_server(
         self, server: Server, from_cabinet: Cabinet, to_cabinet: Cabinet
@@ -207,6 +1286,73 @@ class DataCenter:
                 raise ValueError("Cannot remove disks that are not installed.")
             server.disk.remove(old_disk)
 
+    def validate_bonds(self, network: DataCenterNetwork) -> List[str]:
+        """
+        Validate the switch side of all server bonds. LACP members need to terminate on ports of the same link
+        aggregate, active-backup members need to terminate on different switches to be redundant.
+        :param network: network the servers are linked to
+        :return: list of problems found, empty if all bonds are valid
+        """
+        problems = []
+        for server in self.servers():
+            for bond in server.bonds:
+                try:
+                    members = server.bond_members(bond)
+                except ValueError as e:
+                    problems.append(f"{server.name} bond {bond.name}: {e}")
+                    continue
+                attachments = [
+                    (peer, port)
+                    for nic in members
+                    for peer, port in network.attachments(nic)
+                    if isinstance(peer, Switch)
+                ]
+                if len(attachments) < len(members):
+                    problems.append(
+                        f"{server.name} bond {bond.name}: not all members are linked to a switch"
+                    )
+                    continue
+                if bond.mode == BondMode.Lacp8023ad:
+                    aggregates = [
+                        la for sw, _ in attachments for la in sw.link_aggregates
+                    ]
+                    if not any(
+                        all(p in la.ports for _, p in attachments) for la in aggregates
+                    ):
+                        problems.append(
+                            f"{server.name} bond {bond.name}: switch ports are not in a single link aggregate"
+                        )
+                elif len({id(sw) for sw, _ in attachments}) < 2:
+                    problems.append(
+                        f"{server.name} bond {bond.name}: all members terminate on the same switch"
+                    )
+        return problems
+
+    def bonds_without_redundancy(self, network: DataCenterNetwork) -> List[str]:
+        """
+        Servers with a bond that has less than two members with a live path to an (up) switch
+        :param network: network the servers are linked to
+        :return: serial numbers of the servers
+        """
+        degraded = []
+        for server in self.servers():
+            for bond in server.bonds:
+                live = [
+                    nic
+                    for nic in server.nic
+                    if nic.serial_number in bond.members
+                    and any(
+                        isinstance(peer, Switch)
+                        and peer.status == DeviceStatus.Up
+                        and port.status == DeviceStatus.Up
+                        for peer, port in network.attachments(nic)
+                    )
+                ]
+                if len(live) < 2:
+                    degraded.append(server.serial_number)
+                    break
+        return degraded
+
     def fetch_server_nic(
         self, row: int, cabinet: int, server: int, nic: int, chassis: Optional[int]
     ) -> NIC:
@@ -0,0 +1,99 @@
+# -*- coding: utf-8 -*-#
+"""Health of the datacenter
+
+This module contains the roll-up of the individual device states into an overall health state. Devices are either up
+or down, the roll-up determines how severe the combination of down devices is. A device with a status this version does
+not know (reported by a newer peer) is treated as down.
+"""
+from enum import Enum, auto
+from typing import List, Optional
+
+from horao.models.hardware import DataCenter
+from horao.models.network import (
+    DataCenterNetwork,
+    Router,
+    RouterType,
+    Switch,
+    SwitchType,
+)
+from horao.models.status import DeviceStatus
+
+
+class HealthState(Enum):
+    # all devices are up
+    Healthy = auto()
+    # some devices are down, but the core of the datacenter is functional
+    Degraded = auto()
+    # core devices are down or most servers are unavailable
+    Critical = auto()
+
+
+class HealthReport:
+    def __init__(
+        self,
+        state: HealthState,
+        servers_total: int,
+        servers_down: List[str],
+        network_devices_total: int,
+        network_devices_down: List[str],
+        issues: List[str],
+        bonds_degraded: Optional[List[str]] = None,
+    ):
+        self.state = state
+        self.servers_total = servers_total
+        self.servers_down = servers_down
+        self.network_devices_total = network_devices_total
+        self.network_devices_down = network_devices_down
+        self.issues = issues
+        self.bonds_degraded = bonds_degraded if bonds_degraded else []
+
+    def to_dict(self) -> dict:
+        return {
+            "status": self.state.name.lower(),
+            "servers": {"total": self.servers_total, "down": self.servers_down},
+            "network": {
+                "total": self.network_devices_total,
+                "down": self.network_devices_down,
+            },
+            "bonds_degraded": self.bonds_degraded,
+            "issues": self.issues,
+        }
+
+
+def health_report(dc: DataCenter, network: DataCenterNetwork) -> HealthReport:
+    """
+    Roll up the state of all devices into a single health report
+    :param dc: datacenter containing the servers
+    :param network: network containing the network devices
+    :return: HealthReport
+    """
+    servers = list(dc.servers())
+    servers_down = [s.serial_number for s in servers if s.status != DeviceStatus.Up]
+    network_devices = list(network.devices())
+    network_devices_down = [n for n in network_devices if n.status != DeviceStatus.Up]
+    issues = [
+        f"core device {d.name} ({d.serial_number}) is down"
+        for d in network_devices_down
+        if (isinstance(d, Switch) and d.switch_type == SwitchType.Core)
+        or (isinstance(d, Router) and d.router_type == RouterType.Core)
+    ]
+    critical = len(issues) > 0
+    if servers and len(servers_down) * 2 > len(servers):
+        critical = True
+        issues.append(f"{len(servers_down)} out of {len(servers)} servers are down")
+    bonds_degraded = dc.bonds_without_redundancy(network)
+    if critical:
+        state = HealthState.Critical
+    elif servers_down or network_devices_down or bonds_degraded:
+        state = HealthState.Degraded
+    else:
+        state = HealthState.Healthy
+    return HealthReport(
+        state,
+        len(servers),
+        servers_down,
+        len(network_devices),
+        [n.serial_number for n in network_devices_down],
+        issues,
+        bonds_degraded,
+    )
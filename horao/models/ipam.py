@@ -0,0 +1,156 @@
+# -*- coding: utf-8 -*-#
+"""IP address management
+
+Address space is managed in a hierarchy of pools: the datacenter owns a supernet, delegates a subnet to every row, a
+row delegates subnets to its cabinets and addresses are allocated from the cabinet pools. A pool keeps track of the
+subnets it delegated to child pools and the subnets allocated from it directly, together these are the used ranges
+of the pool. Utilization rolls up: the utilization of a pool is the share of its addresses that are allocated, in the
+pool itself or in any of its descendants. Delegated ranges that are not allocated count as free.
+
+Subnets are written in CIDR notation, host addresses are allocated as /32 (or /128) subnets.
+"""
+import ipaddress
+from typing import List, Optional, Tuple, Union
+
+IpNetwork = Union[ipaddress.IPv4Network, ipaddress.IPv6Network]
+
+
+class IpPool:
+    def __init__(
+        self,
+        name: str,
+        network: str,
+        allocations: Optional[List[str]] = None,
+        delegations: Optional[List["IpPool"]] = None,
+    ):
+        """
+        Pool of addresses
+        :param name: name of the pool (e.g. the row or cabinet it belongs to)
+        :param network: range of the pool in CIDR notation
+        :param allocations: subnets allocated from the pool directly
+        :param delegations: child pools the pool delegated a subnet to
+        :raises: ValueError if the network is not a valid CIDR
+        """
+        self.name = name
+        self.network = str(ipaddress.ip_network(network))
+        self.allocations = allocations if allocations else []
+        self.delegations = delegations if delegations else []
+        self.parent: Optional[IpPool] = None
+        for child in self.delegations:
+            child.parent = self
+
+    @property
+    def ip_network(self) -> IpNetwork:
+        return ipaddress.ip_network(self.network)
+
+    def _used(self) -> List[IpNetwork]:
+        return [ipaddress.ip_network(a) for a in self.allocations] + [
+            d.ip_network for d in self.delegations
+        ]
+
+    def _free_subnet(self, prefix_len: int) -> Optional[IpNetwork]:
+        if prefix_len < self.ip_network.prefixlen:
+            return None
+        if prefix_len > self.ip_network.max_prefixlen:
+            raise ValueError(f"/{prefix_len} is not a valid prefix length")
+        free = [self.ip_network]
+        for used in self._used():
+            remaining = []
+            for block in free:
+                if used.subnet_of(block):  # type: ignore
+                    remaining += list(block.address_exclude(used))  # type: ignore
+                elif not block.subnet_of(used):  # type: ignore
+                    remaining.append(block)
+            free = remaining
+        for block in sorted(free):
+            if block.prefixlen <= prefix_len:
+                return next(block.subnets(new_prefix=prefix_len))
+        return None
+
+    def allocate(self, prefix_len: int) -> str:
+        """
+        Allocate the first free subnet of the given size from this pool
+        :param prefix_len: prefix length of the subnet
+        :return: subnet in CIDR notation
+        :raises: ValueError if there is no free subnet of the size
+        """
+        subnet = self._free_subnet(prefix_len)
+        if subnet is None:
+            raise ValueError(f"No free /{prefix_len} in {self.name} ({self.network})")
+        self.allocations.append(str(subnet))
+        return str(subnet)
+
+    def release(self, subnet: str) -> None:
+        """
+        Release a subnet allocated from this pool
+        :param subnet: subnet in CIDR notation
+        :return: None
+        :raises: ValueError if the subnet is not allocated from this pool
+        """
+        normalized = str(ipaddress.ip_network(subnet))
+        if normalized not in self.allocations:
+            raise ValueError(f"{subnet} is not allocated from {self.name}")
+        self.allocations.remove(normalized)
+
+    def delegate_subnet(self, prefix_len: int, name: str) -> "IpPool":
+        """
+        Delegate the first free subnet of the given size to a new child pool
+        :param prefix_len: prefix length of the subnet
+        :param name: name of the child pool
+        :return: child pool
+        :raises: ValueError if there is no free subnet of the size
+        """
+        subnet = self._free_subnet(prefix_len)
+        if subnet is None:
+            raise ValueError(f"No free /{prefix_len} in {self.name} ({self.network})")
+        child = IpPool(name, str(subnet))
+        child.parent = self
+        self.delegations.append(child)
+        return child
+
+    def release_delegation(self, child: "IpPool") -> None:
+        """
+        Delete a child pool, its range returns to this pool. A child pool (or any of its descendants) with live
+        allocations cannot be deleted.
+        :param child: child pool to delete
+        :return: None
+        :raises: ValueError if the pool is not a child of this pool or has live allocations
+        """
+        if child not in self.delegations:
+            raise ValueError(f"{child.name} is not delegated from {self.name}")
+        if child.allocated_addresses() > 0:
+            raise ValueError(f"{child.name} ({child.network}) has live allocations")
+        self.delegations.remove(child)
+        child.parent = None
+
+    def allocated_addresses(self) -> int:
+        """
+        Number of addresses allocated from this pool and its descendants
+        :return: number of addresses
+        """
+        return sum(
+            ipaddress.ip_network(a).num_addresses for a in self.allocations
+        ) + sum(d.allocated_addresses() for d in self.delegations)
+
+    def utilization(self) -> float:
+        """
+        Share of the addresses of the pool that is allocated, in the pool itself or any of its descendants
+        :return: fraction between 0 and 1
+        """
+        return self.allocated_addresses() / self.ip_network.num_addresses
+
+    def find_free_subnet(self, prefix_len: int) -> Optional[Tuple["IpPool", str]]:
+        """
+        Search the tree for a free subnet, the most specific pools (deepest first) are searched before their parents
+        :param prefix_len: prefix length of the subnet
+        :return: tuple of the pool and the free subnet, None if there is no free subnet of the size
+        """
+        for child in self.delegations:
+            found = child.find_free_subnet(prefix_len)
+            if found is not None:
+                return found
+        subnet = self._free_subnet(prefix_len)
+        return (self, str(subnet)) if subnet is not None else None
+
+    def __repr__(self):
+        return f"<IpPool {self.name} {self.network}>"
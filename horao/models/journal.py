@@ -0,0 +1,160 @@
+# -*- coding: utf-8 -*-#
+"""Journal of inventory changes
+
+An append-only journal of the changes made to the inventory of a datacenter, for event-sourcing the inventory: the
+journal replayed onto a base snapshot of the datacenter reproduces the datacenter after the changes. Unlike the audit
+log of the model store (which records the operations on the networks as well), the journal records changes to the
+cabinets: the status of a device is set, a server or switch is added to a cabinet, or removed from it. A device is
+recorded in its serialized form when it is added, later changes to the object do not change the journal.
+"""
+import time
+from typing import Any, Dict, Iterator, List, Optional, Union
+
+from horao.models.hardware import Cabinet, CapacityDelta, DataCenter, Server
+from horao.models.network import Switch
+from horao.models.serialization import Decoder, Encoder
+from horao.models.status import DeviceStatus
+
+DEVICE_KINDS = {"server": Server, "switch": Switch}
+
+
+class Change:
+    """Base class of the changes recorded in a journal."""
+
+    def apply(self, datacenter: DataCenter) -> None:
+        """
+        Apply the change to a datacenter
+        :param datacenter: datacenter to change
+        :return: None
+        :raises: ValueError if the change does not apply to the datacenter
+        """
+        raise NotImplementedError
+
+
+def _cabinet(datacenter: DataCenter, serial_number: str) -> Cabinet:
+    for row in datacenter.rows:
+        for cabinet in row.cabinets:
+            if cabinet.serial_number == serial_number:
+                return cabinet
+    raise ValueError(f"Cabinet {serial_number} does not exist")
+
+
+class StatusSet(Change):
+    def __init__(self, serial_number: str, status: DeviceStatus):
+        self.serial_number = serial_number
+        self.status = status
+
+    def apply(self, datacenter: DataCenter) -> None:
+        datacenter.find_device(self.serial_number, []).status = self.status
+
+    def __repr__(self):
+        return f"<StatusSet {self.serial_number} {self.status.name}>"
+
+
+class DeviceAdded(Change):
+    def __init__(self, cabinet: str, device: Union[Server, Switch]):
+        """
+        A server or switch is added to a cabinet
+        :param cabinet: serial number of the cabinet
+        :param device: device, recorded as it is now
+        """
+        self.cabinet = cabinet
+        self.kind = next(k for k, c in DEVICE_KINDS.items() if isinstance(device, c))
+        self.serial_number = device.serial_number
+        self.document: Dict[str, Any] = Encoder().encode(device)
+
+    def apply(self, datacenter: DataCenter) -> None:
+        cabinet = _cabinet(datacenter, self.cabinet)
+        device = Decoder().decode(self.document, DEVICE_KINDS[self.kind])
+        if self.kind == "server":
+            cabinet.servers.append(device)
+        else:
+            cabinet.switches.append(device)
+
+    def __repr__(self):
+        return f"<DeviceAdded {self.kind} {self.serial_number} to {self.cabinet}>"
+
+
+class DeviceRemoved(Change):
+    def __init__(self, serial_number: str):
+        """
+        A server (or blade) or switch is removed from its cabinet
+        :param serial_number: serial number of the device
+        """
+        self.serial_number = serial_number
+
+    def apply(self, datacenter: DataCenter) -> None:
+        for row in datacenter.rows:
+            for cabinet in row.cabinets:
+                for devices in [cabinet.servers, cabinet.switches] + [
+                    c.servers for c in cabinet.chassis
+                ]:
+                    for device in devices:
+                        if device.serial_number == self.serial_number:
+                            devices.remove(device)
+                            return
+        raise ValueError(f"Device {self.serial_number} does not exist")
+
+    def __repr__(self):
+        return f"<DeviceRemoved {self.serial_number}>"
+
+
+class JournalEntry:
+    def __init__(self, sequence: int, timestamp: float, change: Change):
+        """
+        Change at its position in the journal
+        :param sequence: position in the journal, starting at 1
+        :param timestamp: time the change was recorded
+        :param change: change
+        """
+        self.sequence = sequence
+        self.timestamp = timestamp
+        self.change = change
+
+
+class Journal:
+    def __init__(self):
+        self._entries: List[JournalEntry] = []
+
+    def __len__(self) -> int:
+        return len(self._entries)
+
+    def __iter__(self) -> Iterator[JournalEntry]:
+        return iter(list(self._entries))
+
+    def record(
+        self, change: Change, timestamp: Optional[float] = None
+    ) -> JournalEntry:
+        """
+        Append a change to the journal
+        :param change: change
+        :param timestamp: time of the change, now if not given
+        :return: JournalEntry
+        """
+        entry = JournalEntry(
+            len(self._entries) + 1,
+            timestamp if timestamp is not None else time.time(),
+            change,
+        )
+        self._entries.append(entry)
+        return entry
+
+    def replay(self, base: DataCenter) -> DataCenter:
+        """
+        Apply the journal onto a copy of a base datacenter (e.g. loaded from a snapshot)
+        :param base: datacenter before the first change, it is left untouched
+        :return: datacenter after the last change
+        :raises: ValueError if a change does not apply
+        """
+        datacenter = Decoder().decode(Encoder().encode(base), DataCenter)
+        datacenter.apply_journal(self)
+        return datacenter
+
+    def capacity_delta(self, base: DataCenter) -> CapacityDelta:
+        """
+        Capacity impact of the journal: the change in servers, cores, memory and disk when it is replayed
+        :param base: datacenter before the first change, it is left untouched
+        :return: CapacityDelta
+        :raises: ValueError if a change does not apply
+        """
+        return CapacityDelta.between(base, self.replay(base))
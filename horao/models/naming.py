@@ -0,0 +1,248 @@
+# -*- coding: utf-8 -*-#
+"""Naming conventions
+
+Conventions define the names of the devices per kind (switch, server) as a regular expression. The named groups of the
+pattern capture attributes of the device, which are checked against the place of the device in the model:
+
+- row: number of the row of the cabinet the device is in
+- cabinet: number of the cabinet the device is in
+- site: name of the datacenter
+- number: number of the device
+- role: role label of the device
+
+A template (format string over the same attributes) generates the conforming name of a device. Conventions are loaded
+from a TOML file (see Settings.naming_conventions_path) with a table per kind:
+
+    [switch]
+    pattern = "^sw-(?P<row>\\d+)-(?P<cabinet>\\d+)-(?P<number>\\d+)$"
+    template = "sw-{row}-{cabinet}-{number}"
+
+    [server]
+    pattern = "^(?P<role>[a-z]+)(?P<number>\\d{3})\\.(?P<site>[a-z0-9-]+)$"
+    template = "{role}{number:03d}.{site}"
+"""
+import re
+import tomllib
+from typing import Any, Dict, List, Optional, Tuple, Union
+
+from horao.models.hardware import Cabinet, DataCenter, Row, Server
+from horao.models.network import Switch
+from horao.settings import Settings
+
+ATTRIBUTES = ["row", "cabinet", "site", "number", "role"]
+
+Device = Union[Server, Switch]
+
+
+class NamingError(ValueError):
+    """Raised when naming conventions cannot be loaded, names the file and the offending key."""
+
+    def __init__(self, source_file: str, key: str, detail: str):
+        super().__init__(f"{source_file}: {key}: {detail}")
+        self.source_file = source_file
+        self.key = key
+        self.detail = detail
+
+
+class NamingConvention:
+    def __init__(self, kind: str, pattern: str, template: Optional[str] = None):
+        """
+        Convention for the names of a kind of device
+        :param kind: kind of device ('switch' or 'server')
+        :param pattern: regular expression, named groups capture attributes of the device
+        :param template: format string generating the conforming name, names cannot be suggested if not given
+        :raises: re.error if the pattern is not a valid regular expression
+        """
+        self.kind = kind
+        self.pattern = pattern
+        self.template = template
+        self.regex = re.compile(pattern)
+
+
+class NamingFinding:
+    def __init__(
+        self,
+        serial_number: str,
+        name: str,
+        kind: str,
+        contradictions: Optional[Dict[str, Tuple[str, Any]]] = None,
+        suggestion: Optional[str] = None,
+    ):
+        """
+        Device that does not follow its naming convention
+        :param serial_number: serial number of the device
+        :param name: name of the device
+        :param kind: kind of the device
+        :param contradictions: captured and actual value per attribute, empty if the name does not match the pattern
+        :param suggestion: conforming name, None if it cannot be generated
+        """
+        self.serial_number = serial_number
+        self.name = name
+        self.kind = kind
+        self.contradictions = contradictions if contradictions else {}
+        self.suggestion = suggestion
+
+    @property
+    def reason(self) -> str:
+        if not self.contradictions:
+            return f"{self.name} does not match the {self.kind} naming convention"
+        return f"{self.name} contradicts the model: " + ", ".join(
+            f"{a} is {actual}, the name says {captured}"
+            for a, (captured, actual) in self.contradictions.items()
+        )
+
+    def __repr__(self):
+        return f"<NamingFinding {self.serial_number} {self.name}>"
+
+
+class NamingConventions:
+    def __init__(self, conventions: Optional[List[NamingConvention]] = None):
+        self.conventions = {c.kind: c for c in conventions or []}
+
+    @staticmethod
+    def load(path: str) -> "NamingConventions":
+        """
+        Load conventions from a TOML file
+        :param path: path to the file
+        :return: NamingConventions
+        :raises: NamingError if the file cannot be parsed or a convention is invalid
+        """
+        try:
+            with open(path, "rb") as f:
+                content = tomllib.load(f)
+        except (OSError, ValueError) as e:
+            raise NamingError(path, "", str(e))
+        conventions = []
+        for kind, table in content.items():
+            if not isinstance(table, dict) or not isinstance(table.get("pattern"), str):
+                raise NamingError(path, f"{kind}.pattern", "should be of type str")
+            if not isinstance(table.get("template", ""), str):
+                raise NamingError(path, f"{kind}.template", "should be of type str")
+            try:
+                convention = NamingConvention(
+                    kind, table["pattern"], table.get("template")
+                )
+            except re.error as e:
+                raise NamingError(path, f"{kind}.pattern", f"invalid pattern ({e})")
+            unknown = set(convention.regex.groupindex) - set(ATTRIBUTES)
+            if unknown:
+                raise NamingError(
+                    path,
+                    f"{kind}.pattern",
+                    f"unknown attributes {', '.join(sorted(unknown))}",
+                )
+            conventions.append(convention)
+        return NamingConventions(conventions)
+
+    @staticmethod
+    def from_settings(settings: Optional[Settings] = None) -> "NamingConventions":
+        """
+        Conventions of the file configured in the settings
+        :param settings: settings, read from the environment if not given
+        :return: NamingConventions, without conventions if no file is configured
+        :raises: NamingError if the file cannot be loaded
+        """
+        settings = settings if settings else Settings.from_env()
+        if not settings.naming_conventions_path:
+            return NamingConventions()
+        return NamingConventions.load(settings.naming_conventions_path)
+
+    def suggest_name(self, dc: DataCenter, device: Device) -> str:
+        """
+        Name of a device according to the convention of its kind
+        :param dc: datacenter the device is part of
+        :param device: device to name
+        :return: name
+        :raises: ValueError if there is no convention (with a template) for the kind or an attribute is unknown
+        """
+        kind = _kind(device)
+        convention = self.conventions.get(kind)
+        if convention is None or not convention.template:
+            raise ValueError(f"No naming template for {kind}")
+        try:
+            return convention.template.format(**_attributes(dc, device))
+        except (KeyError, ValueError) as e:
+            raise ValueError(f"Cannot name {device.serial_number} ({e})")
+
+
+def _kind(device: Device) -> str:
+    return type(device).__name__.lower()
+
+
+def _location(dc: DataCenter, device: Device) -> Optional[Tuple[Row, Cabinet]]:
+    for row in dc.rows:
+        for cabinet in row.cabinets:
+            devices = (
+                cabinet.servers
+                + [s for c in cabinet.chassis for s in c.servers]
+                + cabinet.switches
+            )
+            if any(d is device for d in devices):
+                return row, cabinet
+    return None
+
+
+def _attributes(dc: DataCenter, device: Device) -> Dict[str, Any]:
+    attributes: Dict[str, Any] = {"site": dc.name, "number": device.number}
+    location = _location(dc, device)
+    if location is not None:
+        row, cabinet = location
+        attributes["row"], attributes["cabinet"] = row.number, cabinet.number
+    if "role" in device.labels:
+        attributes["role"] = device.labels["role"]
+    return attributes
+
+
+def _devices(dc: DataCenter) -> List[Device]:
+    devices: List[Device] = []
+    for row in dc.rows:
+        for cabinet in row.cabinets:
+            devices += cabinet.servers + [s for c in cabinet.chassis for s in c.servers]
+            devices += cabinet.switches
+    return devices
+
+
+def check_naming(dc: DataCenter, conventions: NamingConventions) -> List[NamingFinding]:
+    """
+    Check the names of the devices of the datacenter against the conventions of their kind, a name should match the
+    pattern and the attributes it captures should agree with the place of the device in the model
+    :param dc: datacenter to check
+    :param conventions: conventions to check against, devices of a kind without a convention are not checked
+    :return: list of findings
+    """
+    findings = []
+    for device in _devices(dc):
+        kind = _kind(device)
+        convention = conventions.conventions.get(kind)
+        if convention is None:
+            continue
+        try:
+            suggestion: Optional[str] = conventions.suggest_name(dc, device)
+        except ValueError:
+            suggestion = None
+        match = convention.regex.match(device.name)
+        if match is None:
+            findings.append(
+                NamingFinding(device.serial_number, device.name, kind, None, suggestion)
+            )
+            continue
+        actual = _attributes(dc, device)
+        contradictions = {}
+        for attribute, captured in match.groupdict().items():
+            if captured is None or attribute not in actual:
+                continue
+            value = actual[attribute]
+            agrees = (
+                captured.isdigit() and int(captured) == value
+                if isinstance(value, int)
+                else captured == str(value)
+            )
+            if not agrees:
+                contradictions[attribute] = (captured, value)
+        if contradictions:
+            findings.append(
+                NamingFinding(
+                    device.serial_number, device.name, kind, contradictions, suggestion
+                )
+            )
+    return findings
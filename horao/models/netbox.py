@@ -0,0 +1,142 @@
+# -*- coding: utf-8 -*-#
+"""NetBox export
+
+Exports the inventory of a datacenter in the shape of the NetBox (DCIM) import format, for shops that keep NetBox as
+their source of truth: a site for the datacenter, a rack per cabinet, a device per server, chassis and switch, and an
+interface per port. Objects refer to each other by name, the way the NetBox bulk import resolves them, rows are kept
+as the location of their racks. Blades refer to their chassis as parent device.
+
+Statuses are mapped onto the device statuses of NetBox: active devices are 'active' (or 'failed' when they are down),
+the other lifecycle states become 'planned', 'decommissioning' and 'offline'.
+"""
+import json
+import re
+from typing import Any, Dict, List, Optional
+
+from horao.models.hardware import DataCenter
+from horao.models.osi_layers import Port
+from horao.models.status import DeviceStatus, LifecycleState
+
+LIFECYCLE_STATUSES = {
+    LifecycleState.Provisioning: "planned",
+    LifecycleState.Decommissioning: "decommissioning",
+    LifecycleState.Decommissioned: "offline",
+}
+
+# NetBox interface types by port speed (in Gb), other speeds are exported as 'other'
+INTERFACE_TYPES = {
+    1: "1000base-t",
+    10: "10gbase-x-sfpp",
+    25: "25gbase-x-sfp28",
+    40: "40gbase-x-qsfpp",
+    100: "100gbase-x-qsfp28",
+    400: "400gbase-x-qsfpdd",
+}
+
+
+def slug(name: str) -> str:
+    """
+    NetBox slug of a name: lower case, runs of other characters than letters and digits become a dash
+    :param name: name
+    :return: slug
+    """
+    return re.sub(r"[^a-z0-9]+", "-", name.lower()).strip("-")
+
+
+def _status(device: Any) -> str:
+    lifecycle = getattr(device, "lifecycle", LifecycleState.Active)
+    if lifecycle in LIFECYCLE_STATUSES:
+        return LIFECYCLE_STATUSES[lifecycle]
+    return "active" if device.status == DeviceStatus.Up else "failed"
+
+
+def _interface(device: str, port: Port) -> Dict[str, Any]:
+    return {
+        "device": device,
+        "name": port.name,
+        "type": INTERFACE_TYPES.get(port.speed_gb, "other"),
+        "enabled": port.admin_up,
+        "mac_address": port.mac,
+        "mtu": port.mtu,
+        "speed": port.speed_gb * 1_000_000,
+    }
+
+
+def to_netbox(datacenter: DataCenter, rack_height_u: int = 42) -> Dict[str, Any]:
+    """
+    NetBox import structure of a datacenter
+    :param datacenter: datacenter to export
+    :param rack_height_u: height of the racks in rack units
+    :return: dict with the sites, racks, devices and interfaces
+    """
+    site = datacenter.name
+    racks: List[Dict[str, Any]] = []
+    devices: List[Dict[str, Any]] = []
+    interfaces: List[Dict[str, Any]] = []
+
+    def device(
+        value: Any,
+        role: str,
+        rack: str,
+        parent: Optional[str] = None,
+        tenant: Optional[str] = None,
+    ) -> None:
+        entry = {
+            "name": value.name,
+            "device_type": value.model,
+            "role": role,
+            "site": site,
+            "rack": rack,
+            "serial": value.serial_number,
+            "status": _status(value) if hasattr(value, "status") else "active",
+        }
+        if parent:
+            entry["parent_device"] = parent
+        if tenant:
+            entry["tenant"] = tenant
+        devices.append(entry)
+
+    for row in datacenter.rows:
+        for cabinet in row.cabinets:
+            racks.append(
+                {
+                    "name": cabinet.name,
+                    "site": site,
+                    "location": row.name,
+                    "serial": cabinet.serial_number,
+                    "u_height": rack_height_u,
+                    "status": "active",
+                }
+            )
+            for server in cabinet.servers:
+                device(server, "server", cabinet.name, tenant=server.tenant)
+            for chassis in cabinet.chassis:
+                device(chassis, "chassis", cabinet.name)
+                for blade in chassis.servers:
+                    device(blade, "server", cabinet.name, chassis.name, blade.tenant)
+            for switch in cabinet.switches:
+                device(switch, "switch", cabinet.name)
+                for port in switch.lan_ports + switch.uplink_ports:
+                    interfaces.append(_interface(switch.name, port))
+            for server in cabinet.servers + [
+                s for c in cabinet.chassis for s in c.servers
+            ]:
+                for nic in server.nic:
+                    for port in nic.lan_ports:
+                        interfaces.append(_interface(server.name, port))
+    return {
+        "sites": [{"name": site, "slug": slug(site), "status": "active"}],
+        "racks": racks,
+        "devices": devices,
+        "interfaces": interfaces,
+    }
+
+
+def to_netbox_json(datacenter: DataCenter, rack_height_u: int = 42) -> str:
+    """
+    NetBox import structure of a datacenter as JSON
+    :param datacenter: datacenter to export
+    :param rack_height_u: height of the racks in rack units
+    :return: JSON string
+    """
+    return json.dumps(to_netbox(datacenter, rack_height_u), indent=2)
@@ -5,14 +5,38 @@ This module contains the definition of networking equipment (hardware) and their
 We assume that 'faulty' equipment state is either up or down, it should be handled in the state machine, not here.
 Also we assume that these data structures are not very prone to change, given that this implies a manual activity.
 """
+import ipaddress
+import math
 from enum import Enum, auto
-from typing import List, Optional
+from typing import Dict, Iterator, List, Optional, Tuple, TYPE_CHECKING, Union
 
 import networkx as nx  # type: ignore
 
 
-from horao.models.status import DeviceStatus
-from horao.models.osi_layers import Port, LinkLayer
+from horao.models.budget import UNLIMITED, AnalysisBudget, BudgetExceeded, BudgetMeter
+from horao.models.status import DeviceStatus, LifecycleState
+from horao.settings import RoutingProtocol, Settings
+from horao.models.osi_layers import (
+    Port,
+    LinkLayer,
+    FirewallRule,
+    Packet,
+    IpAddress,
+    Link,
+    PortTable,
+    Route,
+    Segment,
+)
+
+if TYPE_CHECKING:
+    from horao.models.catalog import DeviceCatalog
+    from horao.models.hardware import DataCenter
+    from horao.models.topology import (
+        Confidence,
+        DetectionReport,
+        DetectionResult,
+        DetectorRegistry,
+    )
 
 
 class NetworkTopology(Enum):
@@ -67,6 +91,22 @@ class NetworkTopology(Enum):
     # No specific topology has been resolved
     Undefined = auto()
 
+    @classmethod
+    def default(cls) -> "NetworkTopology":
+        """Nothing is known about the topology until it has been detected."""
+        return cls.Undefined
+
+
+# topologies with equal cost paths between the switches, the neighbors of a failed switch repair the routes locally
+MULTIPATH_TOPOLOGIES = {
+    NetworkTopology.FatTree,
+    NetworkTopology.VL2,
+    NetworkTopology.Portland,
+    NetworkTopology.Hedera,
+}
+# milliseconds a switch takes to process a routing update and pass it on
+UPDATE_PROCESSING_MS = 1.0
+
 
 class NetworkType(Enum):
     Management = (
@@ -79,6 +119,11 @@ class NetworkType(Enum):
         auto()
     )  # aka forwarding plane, policies, scaling and/or behavior triggers are generally executed here
 
+    @classmethod
+    def default(cls) -> "NetworkType":
+        """Most networks of a datacenter carry the traffic of the servers."""
+        return cls.Data
+
 
 class RouterType(Enum):
     Core = auto()
@@ -91,6 +136,30 @@ class SwitchType(Enum):
     Core = auto()
 
 
+class Annotated:
+    """Device carrying metadata of the operator (e.g. asset tag, owner, cost center), not used by the analyses."""
+
+    metadata: Dict[str, str]
+
+    def get_meta(self, key: str, default: Optional[str] = None) -> Optional[str]:
+        """
+        Value of a metadata attribute
+        :param key: attribute
+        :param default: value if the attribute is not set
+        :return: value
+        """
+        return self.metadata.get(key, default)
+
+    def set_meta(self, key: str, value: str) -> None:
+        """
+        Set a metadata attribute
+        :param key: attribute
+        :param value: value
+        :return: None
+        """
+        self.metadata[key] = value
+
+
 class NetworkDevice:
     def __init__(self, serial_number, name, model, number, lan_ports: List[Port]):
         self.serial_number = serial_number
@@ -102,12 +171,24 @@ class NetworkDevice:
 
 class NIC(NetworkDevice):
     def __init__(
-        self, serial_number: str, name: str, model: str, number: int, ports: List[Port]
+        self,
+        serial_number: str,
+        name: str,
+        model: str,
+        number: int,
+        ports: List[Port],
+        addresses: Optional[List[IpAddress]] = None,
     ):
+        """
+        Network interface card of a server
+        :param ports: ports of the card
+        :param addresses: IP addresses assigned to the card (e.g. by DHCP)
+        """
         super().__init__(serial_number, name, model, number, ports)
+        self.addresses = addresses if addresses else []
 
 
-class Firewall(NetworkDevice):
+class Firewall(NetworkDevice, Annotated):
     def __init__(
         self,
         serial_number: str,
@@ -117,14 +198,41 @@ class Firewall(NetworkDevice):
         status: DeviceStatus,
         lan_ports: List[Port],
         wan_ports: Optional[List[Port]],
+        rules: Optional[List[FirewallRule]] = None,
+        lifecycle: LifecycleState = LifecycleState.Active,
+        labels: Optional[Dict[str, str]] = None,
+        metadata: Optional[Dict[str, str]] = None,
     ):
 
         super().__init__(serial_number, name, model, number, lan_ports)
         self.status = status
         self.wan_ports = wan_ports
+        self.rules = rules if rules else []
+        self.lifecycle = lifecycle
+        self.labels = labels if labels else {}
+        self.metadata = metadata if metadata else {}
+
+    def evaluate(self, packet: Packet, count: bool = True) -> str:
+        """
+        Evaluate a packet against the rules of the firewall, the first matching rule wins
+        :param packet: packet to evaluate
+        :param count: increase the hit count of the matching rule, False for simulations
+        :return: action of the matching rule, 'deny' if no rule matches
+        """
+        for rule in self.rules:
+            if rule.matches(packet, count):
+                return rule.action
+        return "deny"
+
+    def unused_rules(self) -> List[int]:
+        """
+        Rules that have not been hit (since the firewall was loaded), candidates for pruning
+        :return: indexes of the rules that were never hit
+        """
+        return [i for i, rule in enumerate(self.rules) if rule.hit_count == 0]
 
 
-class Router(NetworkDevice):
+class Router(NetworkDevice, Annotated):
     def __init__(
         self,
         serial_number: str,
@@ -135,14 +243,30 @@ class Router(NetworkDevice):
         status: DeviceStatus,
         lan_ports: List[Port],
         wan_ports: Optional[List[Port]],
+        lifecycle: LifecycleState = LifecycleState.Active,
+        labels: Optional[Dict[str, str]] = None,
+        routes: Optional[List[Route]] = None,
+        metadata: Optional[Dict[str, str]] = None,
     ):
         super().__init__(serial_number, name, model, number, lan_ports)
         self.router_type = router_type
         self.status = status
         self.wan_ports = wan_ports
+        self.lifecycle = lifecycle
+        self.labels = labels if labels else {}
+        self.routes = routes if routes else []
+        self.metadata = metadata if metadata else {}
+
+
+class LinkAggregate:
+    """Group of ports that act as a single logical link (LAG), may span a pair of switches (MLAG)."""
+
+    def __init__(self, name: str, ports: List[Port]):
+        self.name = name
+        self.ports = ports
 
 
-class Switch(NetworkDevice):
+class Switch(NetworkDevice, Annotated):
     def __init__(
         self,
         serial_number: str,
@@ -155,13 +279,252 @@ class Switch(NetworkDevice):
         managed: bool,
         lan_ports: List[Port],
         uplink_ports: Optional[List[Port]],
+        link_aggregates: Optional[List[LinkAggregate]] = None,
+        lifecycle: LifecycleState = LifecycleState.Active,
+        size_ru: Optional[int] = None,
+        rated_power_watts: Optional[int] = None,
+        labels: Optional[Dict[str, str]] = None,
+        port_capacity: Optional[int] = None,
+        max_mac_entries: Optional[int] = None,
+        max_acl_entries: Optional[int] = None,
+        max_vlans: Optional[int] = None,
+        metadata: Optional[Dict[str, str]] = None,
     ):
+        """
+        Switch
+        :param port_capacity: number of ports of the hardware model (vendor specification), not checked if None
+        :param max_mac_entries: size of the MAC (forwarding) table of the hardware model, unknown if None
+        :param max_acl_entries: number of ACL (TCAM) entries of the hardware model, unknown if None
+        :param max_vlans: number of VLANs the hardware model supports, unknown if None
+        :param metadata: free form attributes of the operator (e.g. asset_tag, owner)
+        """
         super().__init__(serial_number, name, model, number, lan_ports)
         self.layer = layer
         self.switch_type = switch_type
         self.status = status
         self.managed = managed
         self.uplink_ports = uplink_ports
+        self.link_aggregates = link_aggregates if link_aggregates else []
+        self.lifecycle = lifecycle
+        self.size_ru = size_ru
+        self.rated_power_watts = rated_power_watts
+        self.labels = labels if labels else {}
+        self.port_capacity = port_capacity
+        self.max_mac_entries = max_mac_entries
+        self.max_acl_entries = max_acl_entries
+        self.max_vlans = max_vlans
+        self.metadata = metadata if metadata else {}
+
+    def is_overprovisioned(self) -> bool:
+        """
+        A switch is overprovisioned when it has more ports defined than its hardware model has (e.g. after a bad import)
+        :return: True if the lan and uplink ports exceed the port capacity
+        """
+        if self.port_capacity is None:
+            return False
+        ports = len(self.lan_ports) + len(self.uplink_ports or [])
+        return ports > self.port_capacity
+
+    @property
+    def downstream_capacity_gb(self) -> int:
+        return sum(p.speed_gb for p in self.lan_ports)
+
+    @property
+    def uplink_capacity_gb(self) -> int:
+        return sum(p.speed_gb for p in self.uplink_ports or [])
+
+    def uplink_undersized(self, demand_ratio: float) -> bool:
+        """
+        An access switch has undersized uplinks when they cannot carry the traffic of its hosts at the expected
+        utilization, a demand ratio of 3 means the hosts are expected to use a third of their capacity
+        :param demand_ratio: downstream capacity relative to the expected traffic (e.g. 3 for 3:1)
+        :return: True if the uplink capacity is below the downstream capacity divided by the demand ratio
+        """
+        if self.switch_type != SwitchType.Access:
+            return False
+        return self.uplink_capacity_gb < self.downstream_capacity_gb / demand_ratio
+
+    def lag_speed_mismatches(self) -> List[str]:
+        """
+        Link aggregates with member ports of differing speeds, a mixed speed LAG silently underperforms
+        :return: list of the names of the link aggregates
+        """
+        return [
+            lag.name
+            for lag in self.link_aggregates
+            if len({p.speed_gb for p in lag.ports}) > 1
+        ]
+
+    @classmethod
+    def from_catalog(
+        cls,
+        catalog: "DeviceCatalog",
+        model: str,
+        serial_number: str,
+        name: str,
+        number: int = 1,
+    ) -> "Switch":
+        """
+        Stamp out a (provisioning) switch from the catalog template of its model
+        :param catalog: catalog containing the model
+        :param model: model of the switch
+        :param serial_number: serial number
+        :param name: name
+        :param number: number
+        :return: Switch
+        :raises: ValueError if the model is not in the catalog
+        """
+        template = catalog.template(model)
+        lan = [p for p in template.get("ports", []) if not p.get("uplink")]
+        uplink = [p for p in template.get("ports", []) if p.get("uplink")]
+        return cls(
+            serial_number,
+            name,
+            model,
+            number,
+            LinkLayer[template.get("layer", LinkLayer.Layer2.name)],
+            SwitchType[template["switch_type"]],
+            DeviceStatus.Down,
+            template.get("managed", True),
+            catalog.ports(serial_number, model, lan),
+            catalog.ports(serial_number, model, uplink),
+            lifecycle=LifecycleState.Provisioning,
+            size_ru=template.get("size_ru"),
+            rated_power_watts=template.get("rated_power_watts"),
+            port_capacity=template.get("port_capacity"),
+            max_mac_entries=template.get("max_mac_entries"),
+            max_acl_entries=template.get("max_acl_entries"),
+            max_vlans=template.get("max_vlans"),
+        )
+
+
+class PodSubnet:
+    def __init__(self, number: int, devices: List[str], subnet: IpAddress):
+        self.number = number
+        self.devices = devices
+        self.subnet = subnet
+
+
+class AddressPlan:
+    def __init__(self, management: IpAddress, pods: List[PodSubnet]):
+        self.management = management
+        self.pods = pods
+
+
+class LldpNeighbor:
+    def __init__(
+        self,
+        local_device: str,
+        local_port: str,
+        remote_chassis_id: str,
+        remote_port: str,
+    ):
+        """
+        Entry of the LLDP neighbor table of a device
+        :param local_device: serial number of the device reporting the neighbor
+        :param local_port: name of the port the neighbor is seen on
+        :param remote_chassis_id: chassis id of the neighbor, its serial number, name or the MAC of one of its ports
+        :param remote_port: port id of the neighbor, the name or MAC of the port
+        """
+        self.local_device = local_device
+        self.local_port = local_port
+        self.remote_chassis_id = remote_chassis_id
+        self.remote_port = remote_port
+
+    def __repr__(self):
+        return (
+            f"<LldpNeighbor {self.local_device}:{self.local_port} -> "
+            f"{self.remote_chassis_id}:{self.remote_port}>"
+        )
+
+
+class TopologyChange:
+    def __init__(
+        self,
+        added_devices: List[str],
+        removed_devices: List[str],
+        added_links: List[Tuple[str, str]],
+        removed_links: List[Tuple[str, str]],
+        topology_before: NetworkTopology,
+        topology_after: NetworkTopology,
+    ):
+        """
+        Differences between two versions of a network
+        :param added_devices: serial numbers of the devices that were added
+        :param removed_devices: serial numbers of the devices that were removed
+        :param added_links: serial numbers of the ends of the links that were added, in order
+        :param removed_links: serial numbers of the ends of the links that were removed, in order
+        :param topology_before: detected topology of the earlier version
+        :param topology_after: detected topology of the later version
+        """
+        self.added_devices = added_devices
+        self.removed_devices = removed_devices
+        self.added_links = added_links
+        self.removed_links = removed_links
+        self.topology_before = topology_before
+        self.topology_after = topology_after
+
+    @property
+    def topology_changed(self) -> bool:
+        return self.topology_before != self.topology_after
+
+    def __bool__(self):
+        return bool(
+            self.added_devices
+            or self.removed_devices
+            or self.added_links
+            or self.removed_links
+            or self.topology_changed
+        )
+
+    def __repr__(self):
+        return (
+            f"<TopologyChange +{len(self.added_devices)}/-{len(self.removed_devices)} devices, "
+            f"+{len(self.added_links)}/-{len(self.removed_links)} links, "
+            f"{self.topology_before.name} -> {self.topology_after.name}>"
+        )
+
+
+class Inconsistency:
+    def __init__(self, device: str, port: str, detail: str):
+        """
+        Disagreement between the network and the hardware model about a port on a link
+        :param device: serial number of the device owning the port (the server for ports of a NIC)
+        :param port: name of the port
+        :param detail: what the models disagree about
+        """
+        self.device = device
+        self.port = port
+        self.detail = detail
+
+    def __repr__(self):
+        return f"<Inconsistency {self.device}/{self.port}: {self.detail}>"
+
+
+class ReachabilityMatrix:
+    def __init__(self, name: str, components: Dict[str, Optional[int]]):
+        """
+        Connectivity between all pairs of devices of a network over the links that are up, as it was when the matrix
+        was computed. Lookups take constant time.
+        :param name: name of the network
+        :param components: connected component of every device, None for devices that are down or decommissioned
+        """
+        self.name = name
+        self._components = components
+
+    def reachable(self, serial_a: str, serial_b: str) -> bool:
+        """
+        Whether there is a live path between two devices (the opposite of DataCenterNetwork.are_isolated)
+        :param serial_a: serial number of the first device
+        :param serial_b: serial number of the second device
+        :return: True if the devices are connected
+        :raises: ValueError if a device is not part of the network
+        """
+        for serial in (serial_a, serial_b):
+            if serial not in self._components:
+                raise ValueError(f"Device {serial} is not part of {self.name}")
+        component = self._components[serial_a]
+        return component is not None and component == self._components[serial_b]
 
 
 class DataCenterNetwork:
@@ -171,8 +534,10 @@ class DataCenterNetwork:
         network_type: NetworkType,
     ):
         self.graph = nx.Graph()
+        self.ports = PortTable()
         self.name = name
         self.network_type = network_type
+        self.segments: List[Segment] = []
 
     def add(self, network_device: NetworkDevice) -> None:
         self.graph.add_node(network_device)
@@ -181,17 +546,55 @@ class DataCenterNetwork:
         for network_device in network_devices:
             self.add(network_device)
 
+    def add_segment(self, segment: Segment) -> None:
+        """
+        Add a segment to the network
+        :param segment: segment to add
+        :return: None
+        :raises: ValueError if a segment with the same name exists or a VLAN already belongs to another segment
+        """
+        for existing in self.segments:
+            if existing.name == segment.name:
+                raise ValueError(f"Segment {segment.name} already exists")
+            shared = set(existing.vlans) & set(segment.vlans)
+            if shared:
+                raise ValueError(
+                    f"VLANs {sorted(shared)} already belong to segment {existing.name}"
+                )
+        self.segments.append(segment)
+
+    def assign_segment(self, port: Port, name: Optional[str]) -> None:
+        """
+        Explicitly assign a port (interface or attachment) to a segment, overriding its VLANs and address
+        :param port: port to assign
+        :param name: name of the segment, None to remove the assignment
+        :return: None
+        :raises: ValueError if the segment does not exist
+        """
+        if name is not None and name not in [s.name for s in self.segments]:
+            raise ValueError(f"Segment {name} does not exist")
+        for segment in self.segments:
+            if port.serial_number in segment.ports:
+                segment.ports.remove(port.serial_number)
+            if segment.name == name:
+                segment.ports.append(port.serial_number)
+
     def link(self, left: NetworkDevice, right: NetworkDevice) -> None:
         """
         Link two network devices, if they are switches, they are connected via uplink ports, if they are routers or
         firewalls, they are connected via lan ports. We use 'the first' lan port if no uplink ports are available. We
-        currently do not keep count of port usage. There is currently no explicit link that is tracked for the
-        connection, we 'simply' pick the first available port.
+        currently do not keep count of port usage. The link between the ports is tracked on the edge of the graph,
+        we 'simply' pick the first available port.
         :param left: device (if uplink ports exist, they are used to connect to other devices)
         :param right: device (lan ports are used to connect to other devices)
         :return: None
-        :raises: ValueError if no free ports are available on either device.
+        :raises: ValueError if no free ports are available on either device, or the devices are already linked
         """
+        # the graph holds one link per pair of devices, linking again would replace the cable and orphan its ports
+        if self.graph.has_edge(left, right):
+            raise ValueError(
+                f"{left.serial_number} and {right.serial_number} are already linked"
+            )
 
         def link_free_ports(lp: Port, rp: Port) -> None:
             if not lp:
@@ -203,10 +606,25 @@ class DataCenterNetwork:
                 raise ValueError(
                     f"No free ports available on {right.name} ({right.number}:{right.serial_number})"
                 )
-            self.graph.add_edge(left, right, port=lp)
+            link = Link(self.ports.intern(lp, left), self.ports.intern(rp, right))
+            self.graph.add_edge(left, right, link=link)
             lp.status = DeviceStatus.Up
             rp.status = DeviceStatus.Up
 
+        left_port, right_port = self.link_ports(left, right)
+        if left_port and right_port:
+            link_free_ports(left_port, right_port)
+
+    @staticmethod
+    def link_ports(
+        left: NetworkDevice, right: NetworkDevice
+    ) -> Tuple[Optional[Port], Optional[Port]]:
+        """
+        Ports link would use to connect two devices
+        :param left: device
+        :param right: device
+        :return: tuple of the port of the left and the right device, None if a device has no free port
+        """
         if isinstance(left, Switch) and left.uplink_ports and any(left.uplink_ports):
             left_port = next(iter(left.uplink_ports), None)
         else:
@@ -214,13 +632,827 @@ class DataCenterNetwork:
         right_port = next(
             iter([p for p in right.lan_ports if p.status == DeviceStatus.Down]), None
         )
-        if left_port and right_port:
-            link_free_ports(left_port, right_port)
+        return left_port, right_port
+
+    def apply_lldp(self, neighbors: List[LldpNeighbor]) -> List[str]:
+        """
+        Link the devices as reported by their LLDP neighbor tables, both ends usually report the same link, it is
+        linked once. Entries that cannot be resolved, or contradict a link that exists, are reported and skipped.
+        :param neighbors: neighbor table entries
+        :return: list of problems, empty if all entries were applied
+        """
+
+        def ports_of(device: NetworkDevice) -> List[Port]:
+            return (
+                device.lan_ports
+                + (getattr(device, "uplink_ports", None) or [])
+                + (getattr(device, "wan_ports", None) or [])
+            )
+
+        def chassis(chassis_id: str) -> Optional[NetworkDevice]:
+            for device in self.graph.nodes:
+                if chassis_id in (device.serial_number, device.name) or any(
+                    p.mac == chassis_id for p in ports_of(device)
+                ):
+                    return device
+            return None
+
+        def port(device: NetworkDevice, port_id: str) -> Optional[Port]:
+            return next(
+                (p for p in ports_of(device) if port_id in (p.name, p.mac)), None
+            )
+
+        problems = []
+        # port at the other end of every linked port
+        peers: Dict[int, Port] = {}
+        for _, _, link in self.links():
+            left_port, right_port = link.resolve(self.ports)
+            peers[id(left_port)], peers[id(right_port)] = right_port, left_port
+        for entry in neighbors:
+            local = next(
+                (n for n in self.graph.nodes if n.serial_number == entry.local_device),
+                None,
+            )
+            remote = chassis(entry.remote_chassis_id)
+            if local is None or remote is None:
+                unknown = (
+                    entry.local_device if local is None else entry.remote_chassis_id
+                )
+                problems.append(f"{entry}: device {unknown} is not part of {self.name}")
+                continue
+            local_port = port(local, entry.local_port)
+            remote_port = port(remote, entry.remote_port)
+            if local_port is None or remote_port is None:
+                problems.append(f"{entry}: port not found")
+                continue
+            if peers.get(id(local_port)) is remote_port:
+                continue
+            if (
+                id(local_port) in peers
+                or id(remote_port) in peers
+                or self.graph.has_edge(local, remote)
+            ):
+                problems.append(f"{entry}: conflicts with an existing link")
+                continue
+            link = Link(
+                self.ports.intern(local_port, local),
+                self.ports.intern(remote_port, remote),
+            )
+            self.graph.add_edge(local, remote, link=link)
+            local_port.status = DeviceStatus.Up
+            remote_port.status = DeviceStatus.Up
+            peers[id(local_port)], peers[id(remote_port)] = remote_port, local_port
+        return problems
+
+    def attachments(self, device: NetworkDevice) -> List[Tuple[NetworkDevice, Port]]:
+        """
+        Devices linked to the given device, together with the port used on the linked device
+        :param device: device to look up
+        :return: list of tuples of linked device and its port
+        """
+        if device not in self.graph:
+            return []
+        return [
+            (peer, self.peer_port(data["link"], peer))
+            for peer, data in self.graph.adj[device].items()
+        ]
+
+    def links(self) -> List[Tuple[NetworkDevice, NetworkDevice, Link]]:
+        """
+        All links in the network
+        :return: list of tuples of both devices and the link between them
+        """
+        return list(self.graph.edges.data("link"))
+
+    def dedup_links(self) -> int:
+        """
+        Remove links that connect the same pair of ports as an earlier link, regardless of their left/right order
+        (e.g. a cable recorded from both ends in merged or decoded documents), so a cable is only counted once
+        :return: number of links removed
+        """
+        seen = set()
+        duplicates = []
+        for left, right, link in self.links():
+            ports = frozenset((link.left, link.right))
+            if ports in seen:
+                duplicates.append((left, right))
+            seen.add(ports)
+        self.graph.remove_edges_from(duplicates)
+        return len(duplicates)
+
+    def devices(
+        self, include_decommissioned: bool = False
+    ) -> Iterator[Union[Switch, Router, Firewall]]:
+        """
+        Iterate over the switches, routers and firewalls of the network (leaving out attached devices like NICs), in
+        the order they were added
+        :param include_decommissioned: also include decommissioned devices
+        :return: devices
+        """
+        for device in self.graph_view(include_decommissioned).nodes:
+            if isinstance(device, (Switch, Router, Firewall)):
+                yield device
+
+    def overprovisioned_switches(self) -> List[Switch]:
+        """
+        Switches with more ports defined than their port capacity
+        :return: list of switches
+        """
+        return [
+            n for n in self.devices() if isinstance(n, Switch) and n.is_overprovisioned()
+        ]
+
+    def undersized_uplinks(self, demand_ratio: float) -> List[Switch]:
+        """
+        Access switches whose uplinks cannot carry the traffic of their hosts (see Switch.uplink_undersized)
+        :param demand_ratio: downstream capacity relative to the expected traffic (e.g. 3 for 3:1)
+        :return: list of switches
+        """
+        return [
+            n
+            for n in self.devices()
+            if isinstance(n, Switch) and n.uplink_undersized(demand_ratio)
+        ]
+
+    def flapping_ports(self, threshold: int) -> List[Tuple[str, str]]:
+        """
+        Ports of the devices in the network that are flapping
+        :param threshold: number of link flaps that is still acceptable
+        :return: list of tuples of the serial number of the device and the name of the port
+        """
+        flapping = []
+        for device in self.graph_view().nodes:
+            ports = list(device.lan_ports)
+            if isinstance(device, Switch):
+                ports += device.uplink_ports or []
+            else:
+                ports += getattr(device, "wan_ports", None) or []
+            flapping += [
+                (device.serial_number, p.name) for p in ports if p.is_flapping(threshold)
+            ]
+        return flapping
+
+    def asymmetric_links(self) -> List[Tuple[NetworkDevice, NetworkDevice, Link]]:
+        """
+        Links with one port up and the other down, unlike links that are fully down these point at a faulty port,
+        cable or transceiver
+        :return: list of tuples of both devices and the link between them
+        """
+        return [
+            (left, right, link)
+            for left, right, link in self.links()
+            if link.is_asymmetric(self.ports)
+        ]
+
+    def reconcile_with_hardware(
+        self, datacenter: "DataCenter"
+    ) -> List[Inconsistency]:
+        """
+        Cross-check the ports on the links with the devices owning them, after a partial update a port can still be
+        up while its device is down. The ports of a NIC are checked against the server the NIC is installed in, a NIC
+        that is not installed in a server of the datacenter is reported as well.
+        :param datacenter: datacenter holding the servers of the NICs
+        :return: list of inconsistencies, ordered by device and port
+        """
+        servers = {
+            id(nic): server
+            for server in datacenter.servers(include_decommissioned=True)
+            for nic in server.nic
+        }
+        inconsistencies = []
+        checked = set()
+        for _, _, link in self.links():
+            for port_id in (link.left, link.right):
+                port, owner = self.ports.resolve(port_id), self.ports.owner(port_id)
+                if id(port) in checked:
+                    continue
+                checked.add(id(port))
+                if isinstance(owner, NIC):
+                    if id(owner) not in servers:
+                        inconsistencies.append(
+                            Inconsistency(
+                                owner.serial_number,
+                                port.name,
+                                f"NIC is not installed in a server of {datacenter.name}",
+                            )
+                        )
+                        continue
+                    owner = servers[id(owner)]
+                status = getattr(owner, "status", DeviceStatus.Up)
+                if port.status == DeviceStatus.Up and status != DeviceStatus.Up:
+                    inconsistencies.append(
+                        Inconsistency(
+                            owner.serial_number,
+                            port.name,
+                            f"port is up but its device is {status.name.lower()}",
+                        )
+                    )
+        return sorted(inconsistencies, key=lambda i: (i.device, i.port))
+
+    def physical_loops(self) -> List[List[str]]:
+        """
+        Cycles in the physical cabling, including devices cabled back into themselves. In a tree every cycle is a
+        miscable, fabrics with redundant paths (fat tree, clos) have cycles by design.
+        :return: serial numbers of the devices per cycle, starting at the lowest serial number
+        """
+        loops = []
+        for cycle in nx.cycle_basis(self.graph):
+            serials = [d.serial_number for d in cycle]
+            start = serials.index(min(serials))
+            loops.append(serials[start:] + serials[:start])
+        return sorted(loops)
+
+    def te_weights(self, reference_gb: int = 100) -> List[Tuple[Link, int]]:
+        """
+        Traffic engineering weights of all links, inverse to their bandwidth (OSPF style) unless the cost of the link
+        is overridden, for export to an SDN controller
+        :param reference_gb: reference bandwidth, links at or above it get weight 1
+        :return: list of tuples of link and weight
+        """
+        return [
+            (link, link.cost(self.ports, reference_gb)) for _, _, link in self.links()
+        ]
+
+    def peer_port(self, link: Link, device: NetworkDevice) -> Port:
+        """
+        Port of a link on the side of the given device
+        :param link: link to resolve
+        :param device: device on one of the ends of the link
+        :return: Port
+        :raises: ValueError if the device is on neither end of the link
+        """
+        if self.ports.owner(link.left) is device:
+            return self.ports.resolve(link.left)
+        if self.ports.owner(link.right) is device:
+            return self.ports.resolve(link.right)
+        raise ValueError(f"{device.serial_number} is on neither end of {link}")
 
     def unlink(self, left: NetworkDevice, right: NetworkDevice) -> None:
+        """
+        Unlink two network devices, the ports used by the link are marked as down
+        :param left: device
+        :param right: device
+        :return: None
+        """
+        for port in self.graph.edges[left, right]["link"].resolve(self.ports):
+            port.status = DeviceStatus.Down
         self.graph.remove_edge(left, right)
 
-    def get_topology(self) -> NetworkTopology:
-        if nx.is_tree(self.graph):
-            return NetworkTopology.Tree
-        return NetworkTopology.Undefined
+    def graph_view(self, include_decommissioned: bool = False) -> nx.Graph:
+        """
+        View of the network graph used for analysis, decommissioned devices are left out by default
+        :param include_decommissioned: also include decommissioned devices
+        :return: read-only view of the graph
+        """
+        if include_decommissioned:
+            return self.graph.subgraph(self.graph.nodes)
+        return self.graph.subgraph(
+            [
+                n
+                for n in self.graph.nodes
+                if getattr(n, "lifecycle", None) != LifecycleState.Decommissioned
+            ]
+        )
+
+    def up_view(self) -> nx.Graph:
+        """
+        View of the network with only the devices and links that are up, decommissioned devices are left out
+        :return: read-only view of the graph
+        """
+        view = self.graph_view()
+
+        def device_up(device: NetworkDevice) -> bool:
+            return getattr(device, "status", DeviceStatus.Up) == DeviceStatus.Up
+
+        def link_up(left: NetworkDevice, right: NetworkDevice) -> bool:
+            return view.edges[left, right]["link"].is_up(self.ports)
+
+        return nx.subgraph_view(view, filter_node=device_up, filter_edge=link_up)
+
+    def are_isolated(self, serial_a: str, serial_b: str) -> bool:
+        """
+        Two devices are isolated (air-gapped) when there is no path between them over the links that are up, a device
+        that is down or decommissioned is isolated from all others
+        :param serial_a: serial number of the first device
+        :param serial_b: serial number of the second device
+        :return: True if there is no live path between the devices
+        :raises: ValueError if a device is not part of the network
+        """
+        left, right = self._member(serial_a), self._member(serial_b)
+        graph = self.up_view()
+        if left not in graph or right not in graph:
+            return True
+        return not nx.has_path(graph, left, right)
+
+    def reachability_matrix(self) -> ReachabilityMatrix:
+        """
+        Connectivity between all pairs of devices over the links that are up, computed once for repeated lookups
+        (e.g. dashboards), the matrix does not follow later changes of the network
+        :return: ReachabilityMatrix
+        """
+        components: Dict[str, Optional[int]] = {
+            n.serial_number: None for n in self.graph.nodes
+        }
+        for i, component in enumerate(nx.connected_components(self.up_view())):
+            for device in component:
+                components[device.serial_number] = i
+        return ReachabilityMatrix(self.name, components)
+
+    def articulation_points(self) -> List[str]:
+        """
+        Single points of failure: devices whose failure disconnects devices that are connected now, over the links
+        that are up. A switch that a single-homed server hangs from is one as well.
+        :return: sorted serial numbers of the devices
+        """
+        return sorted(d.serial_number for d in nx.articulation_points(self.up_view()))
+
+    def safe_maintenance_set(self, max_concurrent: int) -> List[List[str]]:
+        """
+        Plan rolling maintenance: partition the switches, routers and firewalls into batches of devices that can be
+        taken down together without disconnecting the fabric (the devices that remain up stay connected to each
+        other over the links that are up). Attached devices like NICs are left out, a single-homed server is cut off
+        while its switch is down regardless. A device that disconnects the fabric on its own (an articulation point)
+        cannot be taken down safely, it gets a batch of its own.
+        :param max_concurrent: largest number of devices in a batch
+        :return: batches of serial numbers, in order of the serial numbers of their first device
+        :raises: ValueError if max_concurrent is not positive
+        """
+        if max_concurrent < 1:
+            raise ValueError(f"max_concurrent should be positive, not {max_concurrent}")
+        view = self.up_view()
+        fabric = view.subgraph([d for d in self.devices() if d in view])
+        components = list(nx.connected_components(fabric))
+
+        def safe(batch: List[NetworkDevice]) -> bool:
+            for component in components:
+                remaining = component - set(batch)
+                if remaining and not nx.is_connected(fabric.subgraph(remaining)):
+                    return False
+            return True
+
+        batches: List[List[NetworkDevice]] = []
+        unsafe: List[List[NetworkDevice]] = []
+        for device in sorted(self.devices(), key=lambda d: d.serial_number):
+            if not safe([device]):
+                unsafe.append([device])
+                batches.append(unsafe[-1])
+                continue
+            for batch in batches:
+                if (
+                    len(batch) < max_concurrent
+                    and batch not in unsafe
+                    and safe(batch + [device])
+                ):
+                    batch.append(device)
+                    break
+            else:
+                batches.append([device])
+        return [[d.serial_number for d in batch] for batch in batches]
+
+    def path_mtu(self, from_serial: str, to_serial: str) -> Optional[int]:
+        """
+        Largest frame that passes every hop of the shortest path that is up between two devices, the MTU of a link is
+        the smallest MTU of its ports
+        :param from_serial: serial number of the first device
+        :param to_serial: serial number of the second device
+        :return: MTU in bytes, None if the devices are not connected (or the same device)
+        :raises: ValueError if a device is not part of the network
+        """
+        left, right = self._member(from_serial), self._member(to_serial)
+        graph = self.up_view()
+        if left not in graph or right not in graph or left is right:
+            return None
+        try:
+            path = nx.shortest_path(graph, left, right)
+        except nx.NetworkXNoPath:
+            return None
+        return min(
+            port.mtu
+            for a, b in zip(path, path[1:])
+            for port in graph.edges[a, b]["link"].resolve(self.ports)
+        )
+
+    def _member(self, serial_number: str) -> NetworkDevice:
+        device = next(
+            (n for n in self.graph.nodes if n.serial_number == serial_number), None
+        )
+        if device is None:
+            raise ValueError(f"Device {serial_number} is not part of {self.name}")
+        return device
+
+    def reachable_via(
+        self,
+        from_serial: str,
+        to_serial: str,
+        waypoint: str,
+        all_paths: bool = True,
+        budget: Optional[AnalysisBudget] = None,
+    ) -> bool:
+        """
+        Whether the traffic between two devices passes a waypoint (e.g. a firewall that should inspect it), over the
+        shortest paths that are up
+        :param from_serial: serial number of the device the traffic starts at
+        :param to_serial: serial number of the device the traffic is destined for
+        :param waypoint: serial number of the device the traffic should pass
+        :param all_paths: every shortest path should pass the waypoint, otherwise at least one
+        :param budget: budget of the path enumeration, unlimited if not given
+        :return: True if the paths pass the waypoint, False if they do not or there is no path
+        :raises: ValueError if a device is not part of the network
+        :raises: BudgetExceeded if the budget is exceeded
+        """
+        via = self._member(waypoint)
+        paths = self.equal_cost_paths(
+            self._member(from_serial), self._member(to_serial), budget
+        )
+        if not paths:
+            return False
+        passes = [via in path for path in paths]
+        return all(passes) if all_paths else any(passes)
+
+    def cheapest_path(
+        self, from_serial: str, to_serial: str, reference_gb: int = 100
+    ) -> List[str]:
+        """
+        Path with the lowest total link cost (see Link.cost) between two devices over the links that are up, unlike
+        equal_cost_paths this takes the speeds and cost overrides of the links into account
+        :param from_serial: serial number of the device the path starts at
+        :param to_serial: serial number of the device the path ends at
+        :param reference_gb: reference bandwidth of the link costs
+        :return: serial numbers of the devices on the path, empty if the devices are not connected
+        :raises: ValueError if a device is not part of the network
+        """
+        left, right = self._member(from_serial), self._member(to_serial)
+        graph = self.up_view()
+        if left not in graph or right not in graph:
+            return []
+        if not nx.has_path(graph, left, right):
+            return []
+        path = nx.shortest_path(
+            graph,
+            left,
+            right,
+            weight=lambda _, __, data: data["link"].cost(self.ports, reference_gb),
+        )
+        return [d.serial_number for d in path]
+
+    def equal_cost_paths(
+        self,
+        left: NetworkDevice,
+        right: NetworkDevice,
+        budget: Optional[AnalysisBudget] = None,
+    ) -> List[List[NetworkDevice]]:
+        """
+        All shortest paths (in hops) between two devices over the links that are up, the number of paths grows
+        exponentially with the width of the fabric so the enumeration is bounded by the budget
+        :param left: device the paths start at
+        :param right: device the paths end at
+        :param budget: budget of the enumeration, unlimited if not given
+        :return: list of paths, empty if the devices are not connected
+        :raises: BudgetExceeded carrying the paths found so far if the budget is exceeded
+        """
+        meter = (budget if budget else UNLIMITED).meter()
+        graph = self.up_view()
+        if left not in graph or right not in graph:
+            return []
+        # breadth first from the destination, so every step of the enumeration moves one hop closer
+        distance = {right: 0}
+        frontier = [right]
+        while frontier and left not in distance:
+            meter.visit(len(frontier))
+            following = []
+            for node in frontier:
+                for neighbor in graph.neighbors(node):
+                    if neighbor not in distance:
+                        distance[neighbor] = distance[node] + 1
+                        following.append(neighbor)
+            frontier = following
+        if left not in distance:
+            return []
+        paths: List[List[NetworkDevice]] = []
+        stack = [[left]]
+        while stack:
+            path = stack.pop()
+            meter.visit(partial=paths)
+            if path[-1] == right:
+                meter.result(paths)
+                paths.append(path)
+                continue
+            for neighbor in graph.neighbors(path[-1]):
+                if distance.get(neighbor) == distance[path[-1]] - 1:
+                    stack.append(path + [neighbor])
+        return paths
+
+    def _path_lengths(self, meter: BudgetMeter) -> Optional[List[Dict]]:
+        graph = self.up_view()
+        if graph.number_of_nodes() == 0 or not nx.is_connected(graph):
+            return None
+        lengths = []
+        for node in graph.nodes:
+            meter.visit(graph.number_of_nodes())
+            lengths.append(nx.single_source_shortest_path_length(graph, node))
+        return lengths
+
+    def diameter(self, budget: Optional[AnalysisBudget] = None) -> Optional[int]:
+        """
+        Longest shortest path (in hops) over the links that are up
+        :param budget: budget of the all pairs computation, unlimited if not given
+        :return: diameter, None if the network is empty or not connected
+        :raises: BudgetExceeded if the budget is exceeded
+        """
+        lengths = self._path_lengths((budget if budget else UNLIMITED).meter())
+        if lengths is None:
+            return None
+        return max(max(distances.values()) for distances in lengths)
+
+    def average_path_length(
+        self, budget: Optional[AnalysisBudget] = None
+    ) -> Optional[float]:
+        """
+        Average shortest path length (in hops) between all pairs of devices over the links that are up
+        :param budget: budget of the all pairs computation, unlimited if not given
+        :return: average path length, None if the network is empty or not connected
+        :raises: BudgetExceeded if the budget is exceeded
+        """
+        lengths = self._path_lengths((budget if budget else UNLIMITED).meter())
+        if lengths is None:
+            return None
+        if len(lengths) == 1:
+            return 0.0
+        total = sum(sum(distances.values()) for distances in lengths)
+        return total / (len(lengths) * (len(lengths) - 1))
+
+    def pods(self) -> List[List[NetworkDevice]]:
+        """
+        Pods of the network, groups of switches (and their attached devices) that are connected without passing a
+        core switch.
+        :return: list of pods, ordered by the lowest serial number in the pod
+        """
+        graph = self.graph_view()
+        graph = graph.subgraph(
+            [
+                n
+                for n in graph.nodes
+                if not (isinstance(n, Switch) and n.switch_type == SwitchType.Core)
+            ]
+        )
+        pods = [
+            sorted(c, key=lambda n: n.serial_number)
+            for c in nx.connected_components(graph)
+            if any(isinstance(n, Switch) for n in c)
+        ]
+        return sorted(pods, key=lambda p: p[0].serial_number)
+
+    def generate_address_plan(self, base: IpAddress) -> AddressPlan:
+        """
+        Carve a base network into a management subnet (for all switches) and one subnet per pod, each subnet is sized
+        to the number of devices it needs to address, plus a gateway.
+        :param base: network to carve up (address and netmask are used)
+        :return: AddressPlan
+        :raises: ValueError if the base network is too small
+        """
+        supernet = ipaddress.ip_network(f"{base.address}/{base.netmask}", strict=False)
+        switches = [n for n in self.graph_view().nodes if isinstance(n, Switch)]
+        requests = [(-1, [s.serial_number for s in switches])] + [
+            (i, [n.serial_number for n in pod]) for i, pod in enumerate(self.pods())
+        ]
+
+        def prefix(devices: List[str]) -> int:
+            # devices, the gateway, the network and the broadcast address
+            return supernet.max_prefixlen - (len(devices) + 2).bit_length()
+
+        # allocating the largest subnets first keeps every subnet aligned
+        subnets = {}
+        start = int(supernet.network_address)
+        for number, devices in sorted(requests, key=lambda r: prefix(r[1])):
+            subnet = ipaddress.ip_network(
+                f"{ipaddress.ip_address(start)}/{prefix(devices)}"
+            )
+            if not subnet.subnet_of(supernet):  # type: ignore
+                raise ValueError(f"{supernet} is too small for the address plan")
+            subnets[number] = IpAddress(
+                str(subnet.network_address),
+                str(subnet.netmask),
+                str(subnet.network_address + 1),
+            )
+            start = int(subnet.broadcast_address) + 1
+        return AddressPlan(
+            subnets[-1],
+            [PodSubnet(n, d, subnets[n]) for n, d in requests if n >= 0],
+        )
+
+    def get_topology_detailed(
+        self,
+        include_decommissioned: bool = False,
+        registry: Optional["DetectorRegistry"] = None,
+        budget: Optional[AnalysisBudget] = None,
+    ) -> "DetectionReport":
+        """
+        Detect the topology of the network, including the alternatives of the other detectors that matched
+        :param include_decommissioned: also take decommissioned devices into account
+        :param registry: detectors to run, the built-in detectors if not given
+        :param budget: budget of the detection, unlimited if not given
+        :return: DetectionReport, Undefined and timed_out if the budget is exceeded
+        """
+        # imported here, the detectors depend on the network model
+        from horao.models.topology import (
+            DetectionReport,
+            DetectionResult,
+            DetectorRegistry,
+            TopologyInput,
+        )
+
+        registry = registry if registry else DetectorRegistry.default()
+        meter = (budget if budget else UNLIMITED).meter()
+        try:
+            topology = TopologyInput.of(self.graph_view(include_decommissioned), meter)
+        except BudgetExceeded:
+            return DetectionReport(
+                DetectionResult(NetworkTopology.Undefined), None, [], True
+            )
+        return registry.detect(topology)
+
+    def get_topology_timed(
+        self,
+        max_duration: float,
+        include_decommissioned: bool = False,
+        registry: Optional["DetectorRegistry"] = None,
+    ) -> "DetectionReport":
+        """
+        Detect the topology of the network within a time limit (e.g. in a request handler)
+        :param max_duration: seconds the detection may take
+        :param include_decommissioned: also take decommissioned devices into account
+        :param registry: detectors to run, the built-in detectors if not given
+        :return: DetectionReport, Undefined and timed_out if the detection took too long
+        """
+        return self.get_topology_detailed(
+            include_decommissioned, registry, AnalysisBudget(max_duration)
+        )
+
+    def get_topology(
+        self,
+        include_decommissioned: bool = False,
+        registry: Optional["DetectorRegistry"] = None,
+    ) -> NetworkTopology:
+        """
+        Detect the topology of the network
+        :param include_decommissioned: also take decommissioned devices into account
+        :param registry: detectors to run, the built-in detectors if not given
+        :return: NetworkTopology, Undefined if no detector matched
+        """
+        return self.get_topology_detailed(
+            include_decommissioned, registry
+        ).result.topology
+
+    def get_topology_relaxed(
+        self, max_violations: int, include_decommissioned: bool = False
+    ) -> "DetectionResult":
+        """
+        Detect the topology of the network while tolerating missing or miswired links (e.g. a fat tree with a
+        broken cable), the tolerated violations are reported on the result
+        :param max_violations: number of violations to tolerate
+        :param include_decommissioned: also take decommissioned devices into account
+        :return: DetectionResult, Undefined if no detector matched
+        :raises: ValueError if max_violations is negative
+        """
+        # imported here, the detectors depend on the network model
+        from horao.models.topology import DetectorRegistry
+
+        return self.get_topology_detailed(
+            include_decommissioned,
+            DetectorRegistry.default(max_violations=max_violations),
+        ).result
+
+    def get_topology_with_fallback(
+        self,
+        include_decommissioned: bool = False,
+        registry: Optional["DetectorRegistry"] = None,
+    ) -> Tuple[NetworkTopology, "Confidence"]:
+        """
+        Detect the topology of the network, or guess it from the structure of the switches if no detector matched
+        :param include_decommissioned: also take decommissioned devices into account
+        :param registry: detectors to run, the built-in detectors if not given
+        :return: tuple of the topology and the confidence in it
+        """
+        # imported here, the detectors depend on the network model
+        from horao.models.topology import DetectorRegistry, TopologyInput
+
+        registry = registry if registry else DetectorRegistry.default()
+        return registry.detect_with_fallback(
+            TopologyInput.of(self.graph_view(include_decommissioned))
+        )
+
+    def topology_diff(self, other: "DataCenterNetwork") -> TopologyChange:
+        """
+        Changes of the fabric from this version of the network to another (e.g. a later snapshot), devices are
+        compared by serial number and links by the devices on their ends, decommissioned devices are left out
+        :param other: later version of the network
+        :return: TopologyChange
+        """
+
+        def inventory(network: DataCenterNetwork):
+            view = network.graph_view()
+            devices = {d.serial_number for d in view.nodes}
+            links = {
+                tuple(sorted((left.serial_number, right.serial_number)))
+                for left, right in view.edges
+            }
+            return devices, links
+
+        devices, links = inventory(self)
+        other_devices, other_links = inventory(other)
+        return TopologyChange(
+            sorted(other_devices - devices),
+            sorted(devices - other_devices),
+            sorted(other_links - links),
+            sorted(links - other_links),
+            self.get_topology(),
+            other.get_topology(),
+        )
+
+    def estimated_convergence_ms(
+        self, failed: str, settings: Optional[Settings] = None
+    ) -> Optional[int]:
+        """
+        Estimate the time the routing takes to converge after a switch or router fails: the neighbors detect the
+        failure when the hold time (BGP) or dead interval (OSPF) expires, the update travels as far as the routes
+        change and the new routes are programmed. In a multipath topology (e.g. a fat tree) the neighbors only drop
+        the failed next hop, otherwise the update travels across the fabric.
+        :param failed: serial number of the failed device
+        :param settings: settings with the routing protocol and its timers, defaults if not given
+        :return: estimate in milliseconds, None if the failure splits the remaining switches and routers (there is no
+        route around it)
+        :raises: ValueError if the device is not part of the network
+        """
+        settings = settings if settings else Settings()
+        device = self._member(failed)
+        routed = self.up_view().subgraph(
+            n for n in self.up_view().nodes if isinstance(n, (Switch, Router))
+        )
+        if device not in routed:
+            return None
+        neighbors = list(routed.adj[device])
+        remaining = routed.subgraph(n for n in routed.nodes if n is not device)
+        hops = 0
+        if neighbors:
+            component = nx.node_connected_component(remaining, neighbors[0])
+            if any(n not in component for n in neighbors):
+                return None
+            topology, _ = self.get_topology_with_fallback()
+            if topology in MULTIPATH_TOPOLOGIES:
+                hops = 1
+            else:
+                fabric = remaining.subgraph(component)
+                hops = max(nx.eccentricity(fabric, n) for n in neighbors)
+        if settings.routing_protocol == RoutingProtocol.OSPF.name:
+            detection = settings.routing_ospf_dead_interval * 1000
+            propagation = (
+                hops * UPDATE_PROCESSING_MS + settings.routing_ospf_spf_delay * 1000
+            )
+        else:
+            detection = settings.routing_bgp_hold_time * 1000
+            propagation = hops * (
+                UPDATE_PROCESSING_MS
+                + settings.routing_bgp_advertisement_interval * 1000
+            )
+        return math.ceil(detection + propagation + settings.routing_fib_update_ms)
+
+
+def validate_management_coverage(networks: List[DataCenterNetwork]) -> List[str]:
+    """
+    Managed switches that cannot be administered: they are not part of any management network
+    :param networks: all networks of the datacenter
+    :return: sorted serial numbers of the switches
+    """
+    managed = {
+        d.serial_number
+        for network in networks
+        for d in network.devices()
+        if isinstance(d, Switch) and d.managed
+    }
+    covered = {
+        d.serial_number
+        for network in networks
+        if network.network_type == NetworkType.Management
+        for d in network.devices()
+    }
+    return sorted(managed - covered)
+
+
+def evaluate_path(firewalls: List[Firewall], packet: Packet) -> str:
+    """
+    Evaluate a packet that traverses several firewalls (e.g. the firewalls on the path between two segments), the
+    firewalls are evaluated in the order of the path and a firewall on the path twice is evaluated once. The packet
+    is dropped by the first firewall that denies it, later firewalls do not see it (and their hit counters are left
+    untouched).
+    :param firewalls: firewalls in the order the packet traverses them
+    :param packet: packet to evaluate
+    :return: 'deny' if any firewall denies the packet, 'allow' otherwise
+    """
+    seen = set()
+    for firewall in firewalls:
+        if firewall.serial_number in seen:
+            continue
+        seen.add(firewall.serial_number)
+        if firewall.evaluate(packet) != "allow":
+            return "deny"
+    return "allow"
@@ -5,7 +5,10 @@ This module contains the definition of networking activities and their propertie
 We assume that these data structures are prone to change, given that these are configuration artifacts.
 OSI: https://en.wikipedia.org/wiki/OSI_model
 """
+import ipaddress
+import threading
 from enum import Enum, auto
+from typing import Any, Dict, List, NewType, Optional, Tuple, Union
 
 from horao.models.status import DeviceStatus
 
@@ -14,12 +17,28 @@ class LinkLayer(Enum):
     Layer2 = auto()
     Layer3 = auto()
 
+    @classmethod
+    def default(cls) -> "LinkLayer":
+        """Ports are switched unless they are configured as routed ports."""
+        return cls.Layer2
+
 
 class Protocol(Enum):
     TCP = auto()
     UDP = auto()
     ICMP = auto()
 
+    @classmethod
+    def default(cls) -> "Protocol":
+        """Most traffic (and most firewall rules) is TCP."""
+        return cls.TCP
+
+
+class Medium(Enum):
+    Copper = auto()
+    Fiber = auto()
+    DirectAttach = auto()
+
 
 class Port:
     def __init__(
@@ -31,7 +50,27 @@ class Port:
         mac: str,
         status: DeviceStatus,
         speed_gb: int,
+        medium: Optional[Medium] = None,
+        access_vlan: Optional[int] = None,
+        trunk_vlans: Optional[List[int]] = None,
+        admin_up: bool = True,
+        address: Optional["IpAddress"] = None,
+        crc_errors: int = 0,
+        link_flaps: int = 0,
+        mtu: int = 1500,
     ):
+        """
+        Port of a network device
+        :param status: operational status
+        :param medium: physical medium
+        :param access_vlan: untagged VLAN of an access port (native VLAN of a trunk port)
+        :param trunk_vlans: tagged VLANs of a trunk port
+        :param admin_up: administratively enabled
+        :param address: layer 3 address of a routed port
+        :param crc_errors: number of frames received with a CRC error
+        :param link_flaps: number of times the link went down and up again
+        :param mtu: maximum transmission unit in bytes
+        """
         self.serial_number = serial_number
         self.name = name
         self.model = model
@@ -39,6 +78,120 @@ class Port:
         self.mac = mac
         self.status = status
         self.speed_gb = speed_gb
+        self.medium = medium
+        self.access_vlan = access_vlan
+        self.trunk_vlans = trunk_vlans if trunk_vlans else []
+        self.admin_up = admin_up
+        self.address = address
+        self.crc_errors = crc_errors
+        self.link_flaps = link_flaps
+        self.mtu = mtu
+
+    def is_flapping(self, threshold: int) -> bool:
+        """
+        A port is flapping when its link went down and up again more often than the threshold
+        :param threshold: number of link flaps that is still acceptable
+        :return: True if the number of link flaps exceeds the threshold
+        """
+        return self.link_flaps > threshold
+
+
+PortId = NewType("PortId", int)
+
+
+class PortTable:
+    """Central table of ports, ports are interned so that links only need to keep a (small) reference."""
+
+    def __init__(self):
+        self._ports: List[Tuple[Port, Any]] = []
+        self._ids: Dict[int, PortId] = {}
+
+    def __len__(self):
+        return len(self._ports)
+
+    def intern(self, port: Port, owner: Any) -> PortId:
+        """
+        Add a port to the table, interning a port that is already known returns the existing id
+        :param port: port to intern
+        :param owner: device the port belongs to
+        :return: PortId
+        """
+        if id(port) not in self._ids:
+            self._ids[id(port)] = PortId(len(self._ports))
+            self._ports.append((port, owner))
+        return self._ids[id(port)]
+
+    def resolve(self, port_id: PortId) -> Port:
+        return self._ports[port_id][0]
+
+    def owner(self, port_id: PortId) -> Any:
+        return self._ports[port_id][1]
+
+
+class Link:
+    """Connection between two ports, the ports are resolved through the port table of the network."""
+
+    __slots__ = ("left", "right", "cost_override")
+
+    def __init__(
+        self, left: PortId, right: PortId, cost_override: Optional[int] = None
+    ):
+        """
+        Link between two ports
+        :param left: port on one end
+        :param right: port on the other end
+        :param cost_override: cost pinned by the operator (e.g. for policy reasons), instead of the speed-derived cost
+        :raises: ValueError if the cost override is not positive
+        """
+        if cost_override is not None and cost_override < 1:
+            raise ValueError(f"Link cost should be positive, not {cost_override}")
+        self.left = left
+        self.right = right
+        self.cost_override = cost_override
+
+    def __repr__(self):
+        return f"<Link {self.left}-{self.right}>"
+
+    def resolve(self, ports: PortTable) -> Tuple[Port, Port]:
+        return ports.resolve(self.left), ports.resolve(self.right)
+
+    def is_up(self, ports: PortTable) -> bool:
+        """
+        A link is up when the ports on both ends are up
+        :param ports: port table to resolve the ports with
+        :return: True if both ports are up
+        """
+        return all(p.status == DeviceStatus.Up for p in self.resolve(ports))
+
+    def is_asymmetric(self, ports: PortTable) -> bool:
+        """
+        A link is asymmetric when the port on one end is up and the port on the other end is down
+        :param ports: port table to resolve the ports with
+        :return: True if exactly one of the ports is up
+        """
+        return [p.status for p in self.resolve(ports)].count(DeviceStatus.Up) == 1
+
+    def health(self, ports: PortTable) -> DeviceStatus:
+        return DeviceStatus.Up if self.is_up(ports) else DeviceStatus.Down
+
+    def speed_gb(self, ports: PortTable) -> int:
+        """
+        Speed of the link, limited by the slowest port
+        :param ports: port table to resolve the ports with
+        :return: speed in Gb
+        """
+        return min(p.speed_gb for p in self.resolve(ports))
+
+    def cost(self, ports: PortTable, reference_gb: int = 100) -> int:
+        """
+        Routing cost of the link, the cost override if set, otherwise inverse to its bandwidth (OSPF style)
+        :param ports: port table to resolve the ports with
+        :param reference_gb: reference bandwidth, links at or above it cost 1
+        :return: cost
+        """
+        if self.cost_override is not None:
+            return self.cost_override
+        return max(1, reference_gb // max(1, self.speed_gb(ports)))
 
 
 class IpAddress:
@@ -48,6 +201,55 @@ class IpAddress:
         self.gateway = gateway
 
 
+class Segment:
+    """Isolated part of the data plane (e.g. a VRF), traffic only crosses between segments through its firewalls."""
+
+    def __init__(
+        self,
+        name: str,
+        vlans: Optional[List[int]] = None,
+        subnets: Optional[List[IpAddress]] = None,
+        firewalls: Optional[List[str]] = None,
+        ports: Optional[List[str]] = None,
+    ):
+        """
+        Segment of the network, ports are members through their VLANs, their address or an explicit assignment
+        :param name: name of the segment
+        :param vlans: VLANs of the segment
+        :param subnets: subnets of the segment
+        :param firewalls: serial numbers of the firewalls that mediate between this segment and others
+        :param ports: serial numbers of the ports explicitly assigned to the segment
+        """
+        self.name = name
+        self.vlans = vlans if vlans else []
+        self.subnets = subnets if subnets else []
+        self.firewalls = firewalls if firewalls else []
+        self.ports = ports if ports else []
+
+    def __repr__(self):
+        return f"<Segment {self.name}>"
+
+    def contains_address(self, address: str) -> bool:
+        """
+        Check if an address is part of one of the subnets of the segment
+        :param address: address to check
+        :return: True if the address is in one of the subnets
+        """
+        return any(
+            ipaddress.ip_address(address)
+            in ipaddress.ip_network(f"{s.address}/{s.netmask}", strict=False)
+            for s in self.subnets
+        )
+
+
+class Packet:
+    def __init__(self, source: str, destination: str, protocol: Protocol, port: int):
+        self.source = source
+        self.destination = destination
+        self.protocol = protocol
+        self.port = port
+
+
 class Route:
     def __init__(self, destination: IpAddress, gateway: IpAddress, metric: int):
         self.destination = destination
@@ -55,6 +257,89 @@ class Route:
         self.metric = metric
 
 
+class PortMatcher:
+    """
+    Ports matched by a firewall rule: a single port, a range or a list of both, written as '80,443,8000-8100' (ranges
+    include both ends).
+    """
+
+    def __init__(self, ranges: List[Tuple[int, int]]):
+        """
+        Ports to match, the ranges are sorted and overlapping or adjacent ranges are merged, so matchers of the same
+        ports are equal however they were written
+        :param ranges: inclusive ranges of ports, a single port is a range of one
+        :raises: ValueError if a port is out of range or a range is reversed
+        """
+        if not ranges:
+            raise ValueError("Port matcher should match at least one port")
+        for low, high in ranges:
+            if not 0 <= low <= high <= 65535:
+                raise ValueError(f"Invalid port range {low}-{high}")
+        self.ranges: List[Tuple[int, int]] = []
+        for low, high in sorted(ranges):
+            if self.ranges and low <= self.ranges[-1][1] + 1:
+                self.ranges[-1] = (self.ranges[-1][0], max(self.ranges[-1][1], high))
+            else:
+                self.ranges.append((low, high))
+
+    @classmethod
+    def single(cls, port: int) -> "PortMatcher":
+        return cls([(port, port)])
+
+    @classmethod
+    def range(cls, low: int, high: int) -> "PortMatcher":
+        return cls([(low, high)])
+
+    @classmethod
+    def parse(cls, text: str) -> "PortMatcher":
+        """
+        Parse the written form of a matcher
+        :param text: comma separated ports and ranges, e.g. '80,443,8000-8100'
+        :return: PortMatcher
+        :raises: ValueError if the text is not a list of ports and ranges
+        """
+        ranges = []
+        for part in text.split(","):
+            bounds = part.strip().split("-")
+            if len(bounds) > 2 or not all(b.isdigit() for b in bounds):
+                raise ValueError(f"Invalid port or port range '{part.strip()}'")
+            ranges.append((int(bounds[0]), int(bounds[-1])))
+        return cls(ranges)
+
+    @classmethod
+    def of(
+        cls, value: Union["PortMatcher", int, str, None]
+    ) -> Optional["PortMatcher"]:
+        """
+        Matcher of a port given as a matcher, a single port or the written form
+        :param value: value to convert
+        :return: PortMatcher, None for None
+        :raises: ValueError if the value is not valid
+        """
+        if value is None or isinstance(value, PortMatcher):
+            return value
+        if isinstance(value, int):
+            return cls.single(value)
+        return cls.parse(value)
+
+    def matches(self, port: int) -> bool:
+        return any(low <= port <= high for low, high in self.ranges)
+
+    def __eq__(self, other):
+        return isinstance(other, PortMatcher) and self.ranges == other.ranges
+
+    def __hash__(self):
+        return hash(tuple(self.ranges))
+
+    def __str__(self):
+        return ",".join(
+            str(low) if low == high else f"{low}-{high}" for low, high in self.ranges
+        )
+
+    def __repr__(self):
+        return f"<PortMatcher {self}>"
+
+
 class FirewallRule:
     def __init__(
         self,
@@ -62,12 +347,53 @@ class FirewallRule:
         action: str,
         source: IpAddress,
         destination: IpAddress,
-        protocol: Protocol,
-        port: int,
+        protocol: Optional[Protocol],
+        port: Union[PortMatcher, int, str, None],
     ):
+        """
+        Rule of a firewall
+        :param action: action to take on a match (e.g. 'allow' or 'deny')
+        :param protocol: protocol to match, None matches any protocol
+        :param port: destination ports to match, a single port or the written form of a PortMatcher (e.g.
+        '80,443,8000-8100'), None matches any port
+        :raises: ValueError if the port is not valid
+        """
         self.name = name
         self.action = action
         self.source = source
         self.destination = destination
         self.protocol = protocol
-        self.port = port
+        self.port = PortMatcher.of(port)
+        # counter and its lock are runtime state, not part of the rule (nor of its serialized form)
+        self.hit_count = 0
+        self._hit_lock = threading.Lock()
+
+    @staticmethod
+    def _contains(network: IpAddress, address: str) -> bool:
+        return ipaddress.ip_address(address) in ipaddress.ip_network(
+            f"{network.address}/{network.netmask}", strict=False
+        )
+
+    def matches(self, packet: Packet, count: bool = True) -> bool:
+        """
+        Check if a packet matches this rule, the hit count is increased on a match
+        :param packet: packet to check
+        :param count: increase the hit count, False for simulations (e.g. a trace)
+        :return: True if source, destination, protocol and port (not for ICMP) match
+        """
+        if self.protocol is not None and self.protocol != packet.protocol:
+            return False
+        if (
+            self.protocol != Protocol.ICMP
+            and self.port is not None
+            and not self.port.matches(packet.port)
+        ):
+            return False
+        if not self._contains(self.source, packet.source) or not self._contains(
+            self.destination, packet.destination
+        ):
+            return False
+        if count:
+            with self._hit_lock:
+                self.hit_count += 1
+        return True
@@ -0,0 +1,470 @@
+# -*- coding: utf-8 -*-#
+"""Capacity planning
+
+This module evaluates whether a service can be deployed: instances are placed on servers (first fit, honouring the
+affinity policy of the profile), the placement implies a traffic matrix between the servers (and an external gateway)
+and the traffic is simulated on the fabric (split evenly over the equal cost paths) to find the link utilization.
+
+Traffic is symmetric, the demand between two endpoints loads the links on the path in both directions equally, the
+utilization of a link is its load relative to its speed. Servers are assumed to be empty, existing workloads are not
+modelled.
+
+For greenfield fabrics recommend_clos sizes a leaf-spine (two tier Clos) design for a number of servers.
+
+VLAN plans are checked against the table resources of the access switches (see resources.py), every access switch is
+assumed to carry all VLANs of the proposed segments.
+"""
+import logging
+from enum import Enum, auto
+from typing import Dict, List, Optional, Tuple
+
+from horao.models.budget import AnalysisBudget
+from horao.models.hardware import AffinityPolicy, DataCenter, Server
+from horao.models.network import (
+    DataCenterNetwork,
+    NetworkDevice,
+    Switch,
+    SwitchType,
+)
+from horao.models.osi_layers import Segment
+from horao.models.quotas import Demand, Quotas
+from horao.models.resources import configured_vlans
+from horao.models.status import DeviceStatus, LifecycleState
+
+
+class ResourceRequest:
+    def __init__(self, cores: int, ram_gb: int):
+        """
+        Resources needed by a single instance
+        :param cores: number of cores
+        :param ram_gb: memory in GB
+        :raises: ValueError if nothing is requested
+        """
+        if cores <= 0 and ram_gb <= 0:
+            raise ValueError("A resource request needs cores or memory")
+        self.cores = cores
+        self.ram_gb = ram_gb
+
+
+class PeerTraffic:
+    def __init__(
+        self,
+        gbps_per_pair: float = 0.0,
+        gateway: Optional[str] = None,
+        gateway_gbps: float = 0.0,
+    ):
+        """
+        East-West and North-South traffic of a service
+        :param gbps_per_pair: traffic between every pair of instances
+        :param gateway: serial number of the network device connecting to the outside world
+        :param gateway_gbps: traffic between every instance and the gateway
+        """
+        self.gbps_per_pair = gbps_per_pair
+        self.gateway = gateway
+        self.gateway_gbps = gateway_gbps
+
+    @property
+    def empty(self) -> bool:
+        return self.gbps_per_pair == 0 and (
+            self.gateway is None or self.gateway_gbps == 0
+        )
+
+
+class WorkloadProfile:
+    def __init__(
+        self,
+        name: str,
+        instances: int,
+        request: ResourceRequest,
+        policy: Optional[AffinityPolicy] = None,
+        traffic: Optional[PeerTraffic] = None,
+    ):
+        """
+        Service to deploy
+        :param instances: number of instances
+        :param request: resources per instance
+        :param policy: Affinity places all instances in one cabinet, AntiAffinity at most one instance per cabinet
+        :param traffic: traffic of the instances, no traffic if not given
+        """
+        self.name = name
+        self.instances = instances
+        self.request = request
+        self.policy = policy
+        self.traffic = traffic if traffic else PeerTraffic()
+
+
+class TrafficMatrix:
+    """Demand in Gbps between pairs of endpoints (serial numbers of servers or network devices)."""
+
+    def __init__(self, demands: Optional[Dict[Tuple[str, str], float]] = None):
+        self.demands = demands if demands else {}
+
+    def __len__(self):
+        return len(self.demands)
+
+    def add(self, left: str, right: str, gbps: float) -> None:
+        if left == right or gbps == 0:
+            return
+        key = (min(left, right), max(left, right))
+        self.demands[key] = self.demands.get(key, 0.0) + gbps
+
+    def demand(self, left: str, right: str) -> float:
+        return self.demands.get((min(left, right), max(left, right)), 0.0)
+
+
+class Binding(Enum):
+    # not enough servers with free cores or memory (given the affinity policy)
+    Compute = auto()
+    # the traffic exceeds the capacity of the fabric
+    Network = auto()
+
+
+class DeploymentVerdict:
+    def __init__(
+        self,
+        feasible: bool,
+        placement: List[str],
+        matrix: TrafficMatrix,
+        max_utilization: float,
+        binding: Optional[Binding],
+        headroom: int,
+        scale_limit: Optional[Binding],
+    ):
+        """
+        Outcome of planning a deployment
+        :param feasible: True if the profile fits on compute and network
+        :param placement: serial number of the server per instance, empty if the instances could not be placed
+        :param matrix: traffic matrix implied by the placement
+        :param max_utilization: highest link utilization (1.0 is a fully loaded link)
+        :param binding: constraint that makes the profile infeasible
+        :param headroom: number of instances that can be added while the profile stays feasible
+        :param scale_limit: constraint that is violated first when the profile is scaled up
+        """
+        self.feasible = feasible
+        self.placement = placement
+        self.matrix = matrix
+        self.max_utilization = max_utilization
+        self.binding = binding
+        self.headroom = headroom
+        self.scale_limit = scale_limit
+
+
+def place(
+    datacenter: DataCenter,
+    profile: WorkloadProfile,
+    instances: Optional[int] = None,
+    network: Optional[DataCenterNetwork] = None,
+    tenant: Optional[str] = None,
+    quotas: Optional[Quotas] = None,
+) -> Optional[List[Server]]:
+    """
+    Place the instances of a profile on the servers that are up, first fit in the order of the datacenter
+    :param datacenter: datacenter to place on
+    :param profile: profile to place
+    :param instances: number of instances, the instances of the profile if not given
+    :param network: if given, only servers with a NIC in this network are used
+    :param tenant: if given, only servers of the tenant or without a tenant are used, the servers without a tenant the
+    placement uses are claimed by the tenant (subject to its quotas)
+    :param quotas: quotas of the tenants
+    :return: server per instance, None if the instances do not fit
+    :raises: QuotaExceeded if the claimed servers exceed a hard quota of the tenant
+    """
+    count = profile.instances if instances is None else instances
+    request = profile.request
+    cabinets: List[List[Tuple[Server, List[int]]]] = []
+    for row in datacenter.rows:
+        for cabinet in row.cabinets:
+            free = []
+            for server in cabinet.servers + [
+                s for c in cabinet.chassis for s in c.servers
+            ]:
+                if (
+                    server.status != DeviceStatus.Up
+                    or server.lifecycle == LifecycleState.Decommissioned
+                ):
+                    continue
+                if network is not None and not any(
+                    n in network.graph for n in server.nic
+                ):
+                    continue
+                if tenant is not None and server.tenant not in (None, tenant):
+                    continue
+                free.append(
+                    (
+                        server,
+                        [
+                            sum(c.cores for c in server.cpu),
+                            sum(r.size_gb for r in server.ram),
+                        ],
+                    )
+                )
+            cabinets.append(free)
+
+    def fits(capacity: List[int]) -> bool:
+        return capacity[0] >= request.cores and capacity[1] >= request.ram_gb
+
+    def first_fit(candidates: List[Tuple[Server, List[int]]], n: int):
+        capacity = {id(s): list(c) for s, c in candidates}
+        placement = []
+        for server, _ in candidates:
+            while len(placement) < n and fits(capacity[id(server)]):
+                capacity[id(server)][0] -= request.cores
+                capacity[id(server)][1] -= request.ram_gb
+                placement.append(server)
+        return placement if len(placement) == n else None
+
+    placement: Optional[List[Server]]
+    if profile.policy == AffinityPolicy.Affinity:
+        placement = next(
+            (p for p in (first_fit(c, count) for c in cabinets) if p is not None),
+            None,
+        )
+    elif profile.policy == AffinityPolicy.AntiAffinity:
+        placed = [
+            s for s in (first_fit(c, 1) for c in cabinets) if s is not None
+        ][:count]
+        placement = [p[0] for p in placed] if len(placed) == count else None
+    else:
+        placement = first_fit([c for cabinet in cabinets for c in cabinet], count)
+    if placement is not None and tenant is not None and quotas is not None:
+        claimed = Demand()
+        networks = [network] if network is not None else []
+        for server in {id(s): s for s in placement if s.tenant is None}.values():
+            claimed += Demand.of(server, networks)
+        for warning in quotas.check(tenant, claimed, datacenter, networks):
+            logging.warning(warning)
+    return placement
+
+
+def traffic_matrix(placement: List[Server], traffic: PeerTraffic) -> TrafficMatrix:
+    """
+    Traffic matrix implied by a placement, traffic between instances on the same server does not reach the fabric
+    :param placement: server per instance
+    :param traffic: traffic of the instances
+    :return: TrafficMatrix
+    """
+    matrix = TrafficMatrix()
+    for i, left in enumerate(placement):
+        for right in placement[i + 1 :]:
+            matrix.add(left.serial_number, right.serial_number, traffic.gbps_per_pair)
+        if traffic.gateway is not None:
+            matrix.add(left.serial_number, traffic.gateway, traffic.gateway_gbps)
+    return matrix
+
+
+def simulate_traffic(
+    datacenter: DataCenter,
+    network: DataCenterNetwork,
+    matrix: TrafficMatrix,
+    budget: Optional[AnalysisBudget] = None,
+) -> Dict[Tuple[str, str], float]:
+    """
+    Load the demands of a traffic matrix on the links that are up, split evenly over the equal cost paths
+    :param datacenter: datacenter containing the servers of the matrix
+    :param network: network to simulate on
+    :param matrix: demands between servers and/or network devices
+    :param budget: budget of the path enumeration per demand
+    :return: utilization per link (serial numbers of the devices it connects), endpoints that are not connected
+    get an infinite utilization under their own serial numbers
+    :raises: ValueError if an endpoint is not part of the network
+    :raises: BudgetExceeded if the path enumeration exceeds the budget
+    """
+    endpoints: Dict[str, NetworkDevice] = {
+        n.serial_number: n for n in network.graph_view().nodes
+    }
+    for server in datacenter.servers():
+        nic = next((n for n in server.nic if n in network.graph), None)
+        if nic is not None:
+            endpoints[server.serial_number] = nic
+    load: Dict[Tuple[NetworkDevice, NetworkDevice], float] = {}
+    utilization: Dict[Tuple[str, str], float] = {}
+    for (left, right), gbps in matrix.demands.items():
+        if left not in endpoints or right not in endpoints:
+            raise ValueError(f"{left} or {right} is not part of {network.name}")
+        paths = network.equal_cost_paths(endpoints[left], endpoints[right], budget)
+        if not paths:
+            utilization[(left, right)] = float("inf")
+        for path in paths:
+            for u, v in zip(path, path[1:]):
+                edge = (v, u) if (v, u) in load else (u, v)
+                load[edge] = load.get(edge, 0.0) + gbps / len(paths)
+    for (u, v), gbps in load.items():
+        speed = network.graph.edges[u, v]["link"].speed_gb(network.ports)
+        utilization[(u.serial_number, v.serial_number)] = gbps / speed
+    return utilization
+
+
+def _evaluate(
+    datacenter: DataCenter,
+    network: DataCenterNetwork,
+    profile: WorkloadProfile,
+    instances: int,
+    max_utilization: float,
+    budget: Optional[AnalysisBudget],
+) -> Tuple[Optional[List[Server]], TrafficMatrix, float, Optional[Binding]]:
+    placement = place(
+        datacenter,
+        profile,
+        instances,
+        None if profile.traffic.empty else network,
+    )
+    if placement is None:
+        return None, TrafficMatrix(), 0.0, Binding.Compute
+    matrix = traffic_matrix(placement, profile.traffic)
+    utilization = simulate_traffic(datacenter, network, matrix, budget)
+    peak = max(utilization.values(), default=0.0)
+    return (
+        placement,
+        matrix,
+        peak,
+        Binding.Network if peak > max_utilization else None,
+    )
+
+
+def deploy_profile(
+    datacenter: DataCenter,
+    network: DataCenterNetwork,
+    profile: WorkloadProfile,
+    max_utilization: float = 1.0,
+    budget: Optional[AnalysisBudget] = None,
+) -> DeploymentVerdict:
+    """
+    Evaluate the deployment of a profile: place the instances, derive the traffic matrix and simulate the traffic,
+    the profile is scaled up one instance at a time to find the headroom
+    :param datacenter: datacenter to place the instances in
+    :param network: network carrying the traffic of the instances
+    :param profile: profile to deploy
+    :param max_utilization: highest acceptable link utilization
+    :param budget: budget of the path enumeration per demand
+    :return: DeploymentVerdict
+    :raises: ValueError if an endpoint of the traffic is not part of the network
+    """
+    placement, matrix, peak, binding = _evaluate(
+        datacenter, network, profile, profile.instances, max_utilization, budget
+    )
+    headroom, scale_limit = 0, binding
+    while scale_limit is None:
+        scale_limit = _evaluate(
+            datacenter,
+            network,
+            profile,
+            profile.instances + headroom + 1,
+            max_utilization,
+            budget,
+        )[3]
+        if scale_limit is None:
+            headroom += 1
+    return DeploymentVerdict(
+        binding is None,
+        [s.serial_number for s in placement] if placement else [],
+        matrix,
+        peak,
+        binding,
+        headroom,
+        scale_limit,
+    )
+
+
+def check_vlan_plan(network: DataCenterNetwork, segments: List[Segment]) -> List[str]:
+    """
+    Check a proposed VLAN plan against the VLANs the access switches support, switches without a known VLAN capacity
+    are not checked
+    :param network: network the segments are planned on
+    :param segments: proposed segments
+    :return: warnings for the access switches that would exceed their VLAN capacity, empty if the plan fits
+    """
+    planned = {v for s in segments for v in s.vlans}
+    warnings = []
+    switches = [
+        n
+        for n in network.graph_view().nodes
+        if isinstance(n, Switch)
+        and n.switch_type == SwitchType.Access
+        and n.max_vlans is not None
+    ]
+    for switch in sorted(switches, key=lambda s: s.serial_number):
+        vlans = configured_vlans(switch) | planned
+        if len(vlans) > switch.max_vlans:
+            warnings.append(
+                f"{switch.name} ({switch.serial_number}) would carry {len(vlans)} VLANs, "
+                f"at most {switch.max_vlans} supported"
+            )
+    return warnings
+
+
+class ClosDesign:
+    def __init__(
+        self,
+        spines: int,
+        leaves: int,
+        server_ports_per_leaf: int,
+        uplinks_per_leaf: int,
+        oversubscription: float,
+    ):
+        """
+        Two tier (leaf-spine) Clos fabric, every leaf has one uplink to every spine (as built by generators.clos)
+        :param spines: number of spine switches
+        :param leaves: number of leaf switches
+        :param server_ports_per_leaf: ports per leaf for servers
+        :param uplinks_per_leaf: ports per leaf to the spines
+        :param oversubscription: server bandwidth of a leaf relative to its uplink bandwidth
+        """
+        self.spines = spines
+        self.leaves = leaves
+        self.server_ports_per_leaf = server_ports_per_leaf
+        self.uplinks_per_leaf = uplinks_per_leaf
+        self.oversubscription = oversubscription
+
+    def __repr__(self):
+        return (
+            f"<ClosDesign {self.spines} spines, {self.leaves} leaves, "
+            f"{self.oversubscription:.2f}:1>"
+        )
+
+
+def recommend_clos(
+    servers: int,
+    per_server_gbps: int,
+    switch_port_count: int,
+    uplink_gbps: Optional[int] = None,
+    max_oversubscription: float = 3.0,
+) -> ClosDesign:
+    """
+    Recommend a leaf-spine fabric for a number of servers with one port each. All switches have the same number of
+    ports, a leaf uses as few ports as possible for uplinks (but at least two, for redundancy) while staying within
+    the oversubscription, and every spine needs a port per leaf.
+    :param servers: number of servers
+    :param per_server_gbps: bandwidth of a server port
+    :param switch_port_count: number of ports of a switch
+    :param uplink_gbps: bandwidth of an uplink, 4 times the server bandwidth (e.g. 25G servers, 100G uplinks) if not
+    given
+    :param max_oversubscription: highest acceptable oversubscription of a leaf
+    :return: ClosDesign
+    :raises: ValueError if the servers do not fit in a two tier fabric of these switches
+    """
+    if servers < 1 or per_server_gbps < 1 or max_oversubscription <= 0:
+        raise ValueError("A Clos design needs servers, bandwidth and oversubscription")
+    uplink_gbps = uplink_gbps if uplink_gbps else 4 * per_server_gbps
+    uplinks = 2
+    while (
+        uplinks < switch_port_count
+        and (switch_port_count - uplinks) * per_server_gbps
+        > max_oversubscription * uplinks * uplink_gbps
+    ):
+        uplinks += 1
+    server_ports = switch_port_count - uplinks
+    if server_ports < 1:
+        raise ValueError(f"A switch with {switch_port_count} ports cannot be a leaf")
+    leaves = -(-servers // server_ports)
+    if leaves > switch_port_count:
+        raise ValueError(
+            f"{servers} servers need {leaves} leaves, a spine has {switch_port_count} ports "
+            f"(more than two tiers are needed)"
+        )
+    return ClosDesign(
+        uplinks,
+        leaves,
+        server_ports,
+        uplinks,
+        server_ports * per_server_gbps / (uplinks * uplink_gbps),
+    )
@@ -0,0 +1,275 @@
+# -*- coding: utf-8 -*-#
+"""Tenant quotas
+
+A quota bounds what a tenant may own of a kind of resource: servers, cores (of the owned servers) or switch ports (the
+ports the NICs of the owned servers are attached to, optionally of a single speed). A hard quota refuses the changes
+that would exceed it, a soft quota allows them with a warning.
+
+The quotas are consulted by the mutations that assign ownership: ModelStore.set_tenant, ModelStore.link of a NIC of an
+owned server and planning.place with a tenant. A quota can always be changed, also below the current usage of the
+tenant, the resulting violations are reported right away.
+"""
+from enum import Enum, auto
+from typing import Dict, List, Optional
+
+from horao.models.hardware import DataCenter, Server
+from horao.models.network import DataCenterNetwork, Switch
+
+
+class ResourceKind(Enum):
+    Servers = auto()
+    Cores = auto()
+    Ports = auto()
+
+
+class QuotaExceeded(ValueError):
+    """Raised when a change would exceed a hard quota, names the tenant and the resource."""
+
+    def __init__(self, tenant: str, kind: ResourceKind, detail: str):
+        super().__init__(
+            f"quota of {tenant} for {kind.name.lower()} exceeded: {detail}"
+        )
+        self.tenant = tenant
+        self.kind = kind
+        self.detail = detail
+
+
+class Demand:
+    def __init__(
+        self,
+        servers: int = 0,
+        cores: int = 0,
+        ports: Optional[Dict[int, int]] = None,
+    ):
+        """
+        Resources owned by, or requested for, a tenant
+        :param servers: number of servers
+        :param cores: number of cores
+        :param ports: number of switch ports per speed in Gb
+        """
+        self.servers = servers
+        self.cores = cores
+        self.ports = ports if ports else {}
+
+    def __add__(self, other: "Demand") -> "Demand":
+        ports = dict(self.ports)
+        for speed, count in other.ports.items():
+            ports[speed] = ports.get(speed, 0) + count
+        return Demand(self.servers + other.servers, self.cores + other.cores, ports)
+
+    @classmethod
+    def of(cls, server: Server, networks: List[DataCenterNetwork]) -> "Demand":
+        """
+        Resources of a server
+        :param server: server
+        :param networks: networks the NICs of the server are attached in
+        :return: Demand
+        """
+        ports: Dict[int, int] = {}
+        for network in networks:
+            for nic in server.nic:
+                for peer, port in network.attachments(nic):
+                    if isinstance(peer, Switch):
+                        ports[port.speed_gb] = ports.get(port.speed_gb, 0) + 1
+        return cls(1, sum(c.cores for c in server.cpu), ports)
+
+
+class Quota:
+    def __init__(
+        self,
+        tenant: str,
+        kind: ResourceKind,
+        limit: int,
+        hard: bool = True,
+        speed_gb: Optional[int] = None,
+    ):
+        """
+        Quota of a tenant for a kind of resource
+        :param tenant: tenant
+        :param kind: kind of resource
+        :param limit: maximum amount the tenant may own
+        :param hard: refuse changes exceeding the quota, warn only if False
+        :param speed_gb: only count switch ports of this speed, all ports if not given
+        :raises: ValueError if the limit is negative or a speed is given for another kind than ports
+        """
+        if limit < 0:
+            raise ValueError(f"Quota of {tenant} for {kind.name} cannot be negative")
+        if speed_gb is not None and kind != ResourceKind.Ports:
+            raise ValueError("Only port quotas have a speed")
+        self.tenant = tenant
+        self.kind = kind
+        self.limit = limit
+        self.hard = hard
+        self.speed_gb = speed_gb
+
+    @property
+    def resource(self) -> str:
+        name = self.kind.name.lower()
+        return name if self.speed_gb is None else f"{name} of {self.speed_gb}G"
+
+    def amount(self, demand: Demand) -> int:
+        """
+        Amount of the resource of the quota in a demand
+        :param demand: demand
+        :return: amount
+        """
+        if self.kind == ResourceKind.Servers:
+            return demand.servers
+        if self.kind == ResourceKind.Cores:
+            return demand.cores
+        return sum(
+            count
+            for speed, count in demand.ports.items()
+            if self.speed_gb is None or speed == self.speed_gb
+        )
+
+
+class QuotaUsage:
+    def __init__(self, quota: Quota, used: int):
+        """
+        Usage of a tenant against a quota
+        :param quota: quota
+        :param used: amount the tenant owns
+        """
+        self.quota = quota
+        self.used = used
+
+    @property
+    def remaining(self) -> int:
+        return self.quota.limit - self.used
+
+    @property
+    def violated(self) -> bool:
+        return self.used > self.quota.limit
+
+    def to_dict(self) -> dict:
+        return {
+            "resource": self.quota.resource,
+            "used": self.used,
+            "quota": self.quota.limit,
+            "remaining": self.remaining,
+            "hard": self.quota.hard,
+        }
+
+
+class QuotaReport:
+    def __init__(self, usages: Dict[str, List[QuotaUsage]]):
+        """
+        Usage against the quotas per tenant
+        :param usages: usage per quota per tenant
+        """
+        self.usages = usages
+
+    @property
+    def violations(self) -> List[QuotaUsage]:
+        return [u for t in sorted(self.usages) for u in self.usages[t] if u.violated]
+
+    def to_dict(self) -> dict:
+        return {
+            tenant: {
+                "usage": [u.to_dict() for u in usages],
+                "violations": [u.quota.resource for u in usages if u.violated],
+            }
+            for tenant, usages in sorted(self.usages.items())
+        }
+
+
+class Quotas:
+    def __init__(self, quotas: Optional[List[Quota]] = None):
+        """
+        Quotas of the tenants, a tenant without quotas is not limited
+        :param quotas: initial quotas
+        """
+        self.quotas: Dict[str, List[Quota]] = {}
+        for quota in quotas or []:
+            self._store(quota)
+
+    def _store(self, quota: Quota) -> None:
+        quotas = [
+            q
+            for q in self.quotas.get(quota.tenant, [])
+            if (q.kind, q.speed_gb) != (quota.kind, quota.speed_gb)
+        ]
+        self.quotas[quota.tenant] = quotas + [quota]
+
+    def set(
+        self, quota: Quota, dc: DataCenter, networks: List[DataCenterNetwork]
+    ) -> List[QuotaUsage]:
+        """
+        Set (or replace) a quota, a quota below the current usage is allowed
+        :param quota: quota
+        :param dc: datacenter containing the servers
+        :param networks: networks the servers are attached in
+        :return: violated quotas of the tenant
+        """
+        self._store(quota)
+        return [
+            u
+            for u in usage_vs_quota(dc, networks, self).usages[quota.tenant]
+            if u.violated
+        ]
+
+    def check(
+        self,
+        tenant: Optional[str],
+        demand: Demand,
+        dc: DataCenter,
+        networks: List[DataCenterNetwork],
+    ) -> List[str]:
+        """
+        Check whether a tenant may own additional resources
+        :param tenant: tenant, None is never limited
+        :param demand: additional resources
+        :param dc: datacenter containing the servers
+        :param networks: networks the servers are attached in
+        :return: warnings of the soft quotas that would be exceeded
+        :raises: QuotaExceeded if a hard quota would be exceeded
+        """
+        if tenant is None or tenant not in self.quotas:
+            return []
+        current = usage(dc, networks, tenant)
+        warnings = []
+        for quota in self.quotas[tenant]:
+            requested = quota.amount(demand)
+            used = quota.amount(current)
+            if requested == 0 or used + requested <= quota.limit:
+                continue
+            detail = f"{used} + {requested} {quota.resource} > {quota.limit}"
+            if quota.hard:
+                raise QuotaExceeded(tenant, quota.kind, detail)
+            warnings.append(
+                f"soft quota of {tenant} for {quota.resource} exceeded: {detail}"
+            )
+        return warnings
+
+
+def usage(dc: DataCenter, networks: List[DataCenterNetwork], tenant: str) -> Demand:
+    """
+    Resources owned by a tenant, decommissioned servers are not counted
+    :param dc: datacenter containing the servers
+    :param networks: networks the servers are attached in
+    :param tenant: tenant
+    :return: Demand
+    """
+    owned = Demand()
+    for server in dc.servers():
+        if server.tenant == tenant:
+            owned += Demand.of(server, networks)
+    return owned
+
+
+def usage_vs_quota(
+    dc: DataCenter, networks: List[DataCenterNetwork], quotas: Quotas
+) -> QuotaReport:
+    """
+    Usage of the tenants against their quotas
+    :param dc: datacenter containing the servers
+    :param networks: networks the servers are attached in
+    :param quotas: quotas of the tenants
+    :return: QuotaReport
+    """
+    usages = {}
+    for tenant, tenant_quotas in quotas.quotas.items():
+        owned = usage(dc, networks, tenant)
+        usages[tenant] = [QuotaUsage(q, q.amount(owned)) for q in tenant_quotas]
+    return QuotaReport(usages)
@@ -0,0 +1,173 @@
+# -*- coding: utf-8 -*-#
+"""Switch table resources
+
+Besides ports and bandwidth switches run out of table space: MAC (forwarding) table entries, ACL (TCAM) entries and
+VLANs. The capacities come from the hardware model (see the device catalog), the consumption is derived from the
+model:
+
+- MAC entries: the model has no learned forwarding tables, a switch is assumed to learn the MAC addresses of all
+  endpoints (NICs, routers, firewalls) attached to its layer 2 domain, the domain ends at layer 3 switches
+- VLANs: the distinct access and trunk VLANs configured on the ports of the switch
+- ACL entries: the model has no per-switch ACLs (rules are only pushed to firewalls), the capacity is kept but no
+  consumption is reported
+
+Switches without any known capacity end up in the unknown bucket of the report.
+"""
+from enum import Enum, auto
+from typing import Dict, List, Optional, Set
+
+from horao.models.network import DataCenterNetwork, NetworkDevice, Switch
+from horao.models.osi_layers import LinkLayer
+
+
+class TableResource(Enum):
+    MacEntries = auto()
+    Vlans = auto()
+
+
+class ResourceUsage:
+    def __init__(self, resource: TableResource, used: int, capacity: int):
+        self.resource = resource
+        self.used = used
+        self.capacity = capacity
+
+    @property
+    def utilization(self) -> float:
+        """
+        Consumption relative to the capacity
+        :return: fraction of the capacity in use, may exceed 1
+        """
+        return self.used / self.capacity if self.capacity else float("inf")
+
+    def __repr__(self):
+        return f"<ResourceUsage {self.resource.name} {self.used}/{self.capacity}>"
+
+
+class ResourceReport:
+    def __init__(
+        self,
+        usage: Dict[str, List[ResourceUsage]],
+        unknown: List[str],
+        warnings: List[str],
+    ):
+        """
+        Table resources of the switches in a network
+        :param usage: usage per switch (serial number), only resources with a known capacity
+        :param unknown: serial numbers of the switches without any known capacity
+        :param warnings: resources with a utilization above the threshold
+        """
+        self.usage = usage
+        self.unknown = unknown
+        self.warnings = warnings
+
+    def utilization(self, serial_number: str, resource: TableResource) -> float:
+        """
+        Utilization of a resource of a switch
+        :param serial_number: serial number of the switch
+        :param resource: resource
+        :return: fraction of the capacity in use
+        :raises: ValueError if the capacity of the resource is not known
+        """
+        for usage in self.usage.get(serial_number, []):
+            if usage.resource == resource:
+                return usage.utilization
+        raise ValueError(f"Capacity of {resource.name} on {serial_number} is unknown")
+
+
+def capacity(switch: Switch, resource: TableResource) -> Optional[int]:
+    """
+    Capacity of a resource of a switch
+    :param switch: switch
+    :param resource: resource
+    :return: capacity, None if unknown
+    """
+    if resource == TableResource.MacEntries:
+        return switch.max_mac_entries
+    return switch.max_vlans
+
+
+def configured_vlans(switch: Switch) -> Set[int]:
+    """
+    VLANs configured on the ports of a switch
+    :param switch: switch
+    :return: distinct access and trunk VLANs
+    """
+    vlans = set()
+    for port in switch.lan_ports + (switch.uplink_ports or []):
+        if port.access_vlan is not None:
+            vlans.add(port.access_vlan)
+        vlans.update(port.trunk_vlans)
+    return vlans
+
+
+def learned_macs(network: DataCenterNetwork, switch: Switch) -> Set[str]:
+    """
+    MAC addresses a switch learns, those of the endpoints attached to its layer 2 domain
+    :param network: network the switch is part of
+    :param switch: switch
+    :return: distinct MAC addresses
+    """
+    graph = network.graph_view()
+    macs: Set[str] = set()
+    visited: Set[NetworkDevice] = {switch}
+    pending = [switch]
+    while pending:
+        for neighbour in graph.neighbors(pending.pop()):
+            if neighbour in visited:
+                continue
+            visited.add(neighbour)
+            if not isinstance(neighbour, Switch):
+                macs.update(p.mac for p in neighbour.lan_ports if p.mac)
+            elif neighbour.layer == LinkLayer.Layer2:
+                pending.append(neighbour)
+    return macs
+
+
+def usage(network: DataCenterNetwork, switch: Switch) -> List[ResourceUsage]:
+    """
+    Consumption of the resources of a switch that have a known capacity
+    :param network: network the switch is part of
+    :param switch: switch
+    :return: usage per resource
+    """
+    result = []
+    for resource in TableResource:
+        limit = capacity(switch, resource)
+        if limit is None:
+            continue
+        used = (
+            len(learned_macs(network, switch))
+            if resource == TableResource.MacEntries
+            else len(configured_vlans(switch))
+        )
+        result.append(ResourceUsage(resource, used, limit))
+    return result
+
+
+def resources(network: DataCenterNetwork, threshold: float = 0.8) -> ResourceReport:
+    """
+    Report the table resources of all switches in the network
+    :param network: network to report on
+    :param threshold: utilization above which a warning is raised
+    :return: ResourceReport
+    """
+    report: Dict[str, List[ResourceUsage]] = {}
+    unknown = []
+    warnings = []
+    switches = [n for n in network.graph_view().nodes if isinstance(n, Switch)]
+    for switch in sorted(switches, key=lambda s: s.serial_number):
+        if (
+            switch.max_mac_entries is None
+            and switch.max_vlans is None
+            and switch.max_acl_entries is None
+        ):
+            unknown.append(switch.serial_number)
+            continue
+        report[switch.serial_number] = usage(network, switch)
+        for entry in report[switch.serial_number]:
+            if entry.utilization > threshold:
+                warnings.append(
+                    f"{switch.name} ({switch.serial_number}) uses {entry.used} of {entry.capacity} "
+                    f"{entry.resource.name} ({entry.utilization:.0%})"
+                )
+    return ResourceReport(report, unknown, warnings)
@@ -0,0 +1,332 @@
+# -*- coding: utf-8 -*-#
+"""Segmentation of the data plane
+
+Segments (e.g. VRFs) share the physical network, traffic is separated by VLAN tags and addressing. The analyses in this
+module run on a segment graph: every device appears once per segment it carries, links connect the segments they
+carry on both ends, only firewalls declared by both segments connect two segments within a device.
+
+A port is a member of a segment (untagged) by explicit assignment, by its access (native) VLAN or by its address, its
+trunk VLANs carry the segments of those VLANs tagged. A port without any configuration carries all segments, each
+separately. A link between untagged ports of different segments (e.g. a native VLAN mismatch) leaks traffic between
+the segments, any path between segments that does not traverse a declared firewall is reported as a bypass.
+"""
+from typing import Dict, List, Optional, Set, Tuple
+
+import networkx as nx  # type: ignore
+
+from horao.models.network import (
+    DataCenterNetwork,
+    Firewall,
+    NetworkDevice,
+    Switch,
+)
+from horao.models.osi_layers import Packet, Port
+from horao.models.status import DeviceStatus
+
+Node = Tuple[NetworkDevice, str]
+
+
+class SegmentReachability:
+    def __init__(
+        self,
+        left: str,
+        right: str,
+        reachable: bool,
+        firewalls: List[str],
+        bypass: Optional[List[str]] = None,
+    ):
+        """
+        Reachability between two segments
+        :param reachable: True if there is any path between the segments
+        :param firewalls: serial numbers of the firewalls on the shortest path between the segments
+        :param bypass: names of the devices on a path that does not traverse a declared firewall
+        """
+        self.left = left
+        self.right = right
+        self.reachable = reachable
+        self.firewalls = firewalls
+        self.bypass = bypass if bypass else []
+
+    @property
+    def compliant(self) -> bool:
+        return not self.bypass
+
+    def __repr__(self):
+        state = "compliant" if self.compliant else "bypass"
+        return f"<SegmentReachability {self.left}-{self.right} {state}>"
+
+
+class SegmentReport:
+    def __init__(
+        self,
+        name: str,
+        devices: List[str],
+        ports: int,
+        ports_up: int,
+        capacity_gb: int,
+    ):
+        """
+        Ports and capacity of a segment, ports that carry the segment tagged are included
+        :param devices: serial numbers of the devices with ports in the segment
+        :param capacity_gb: combined speed of the ports that are up
+        """
+        self.name = name
+        self.devices = devices
+        self.ports = ports
+        self.ports_up = ports_up
+        self.capacity_gb = capacity_gb
+
+
+class TraceResult:
+    def __init__(
+        self,
+        delivered: bool,
+        path: List[str],
+        denied_by: Optional[List[str]] = None,
+    ):
+        """
+        Outcome of tracing a packet through the segments
+        :param delivered: True if the packet reaches its destination
+        :param path: names of the devices the packet traverses (or would traverse if allowed)
+        :param denied_by: serial numbers of the firewalls that deny the packet
+        """
+        self.delivered = delivered
+        self.path = path
+        self.denied_by = denied_by if denied_by else []
+
+
+def device_ports(device: NetworkDevice) -> List[Port]:
+    ports = list(device.lan_ports)
+    if isinstance(device, Switch):
+        return ports + (device.uplink_ports if device.uplink_ports else [])
+    return ports + (getattr(device, "wan_ports", None) or [])
+
+
+def untagged_segment(network: DataCenterNetwork, port: Port) -> Optional[str]:
+    """
+    Segment a port is an (untagged) member of
+    :param network: network defining the segments
+    :param port: port to check
+    :return: name of the segment, None if the port is not a member of a segment
+    """
+    for segment in network.segments:
+        if port.serial_number in segment.ports:
+            return segment.name
+    for segment in network.segments:
+        if port.access_vlan is not None and port.access_vlan in segment.vlans:
+            return segment.name
+    if port.address is not None:
+        for segment in network.segments:
+            if segment.contains_address(port.address.address):
+                return segment.name
+    return None
+
+
+def _transparent(network: DataCenterNetwork, port: Port) -> bool:
+    return (
+        port.access_vlan is None
+        and not port.trunk_vlans
+        and port.address is None
+        and untagged_segment(network, port) is None
+    )
+
+
+def tagged_segments(network: DataCenterNetwork, port: Port) -> Set[str]:
+    """
+    Segments a port carries tagged, a port without any configuration carries all segments
+    :param network: network defining the segments
+    :param port: port to check
+    :return: names of the segments
+    """
+    if _transparent(network, port):
+        return {s.name for s in network.segments}
+    return {
+        s.name for s in network.segments if set(s.vlans) & set(port.trunk_vlans)
+    }
+
+
+def segment_graph(network: DataCenterNetwork) -> nx.Graph:
+    """
+    Graph of (device, segment) nodes, edges are marked as 'mediated' when they cross segments within a declared
+    firewall and as 'leak' when a link connects different segments
+    :param network: network to build the graph for
+    :return: graph
+    """
+    graph = nx.Graph()
+    for left, right, link in network.links():
+        left_port, right_port = network.peer_port(link, left), network.peer_port(
+            link, right
+        )
+        for name in tagged_segments(network, left_port) & tagged_segments(
+            network, right_port
+        ):
+            graph.add_edge((left, name), (right, name), mediated=False, leak=False)
+        left_untagged = untagged_segment(network, left_port)
+        right_untagged = untagged_segment(network, right_port)
+        if left_untagged is None and _transparent(network, left_port):
+            left_untagged = right_untagged
+        if right_untagged is None and _transparent(network, right_port):
+            right_untagged = left_untagged
+        if left_untagged is not None and right_untagged is not None:
+            graph.add_edge(
+                (left, left_untagged),
+                (right, right_untagged),
+                mediated=False,
+                leak=left_untagged != right_untagged,
+            )
+    for firewall in [n for n in network.graph_view().nodes if isinstance(n, Firewall)]:
+        names = [
+            s.name for s in network.segments if firewall.serial_number in s.firewalls
+        ]
+        for i, left_name in enumerate(names):
+            for right_name in names[i + 1 :]:
+                graph.add_edge(
+                    (firewall, left_name),
+                    (firewall, right_name),
+                    mediated=True,
+                    leak=False,
+                )
+    return graph
+
+
+def _path(graph: nx.Graph, sources: List[Node], targets: List[Node]) -> List[Node]:
+    if not sources or not targets:
+        return []
+    source, target = ("source", None), ("target", None)
+    graph = graph.copy()
+    graph.add_edges_from([(source, s) for s in sources])
+    graph.add_edges_from([(t, target) for t in targets])
+    try:
+        return nx.shortest_path(graph, source, target)[1:-1]
+    except nx.NetworkXNoPath:
+        return []
+
+
+def _names(path: List[Node]) -> List[str]:
+    names: List[str] = []
+    for device, _ in path:
+        if not names or names[-1] != device.name:
+            names.append(device.name)
+    return names
+
+
+def _firewalls(path: List[Node]) -> List[str]:
+    return [
+        left[0].serial_number
+        for left, right in zip(path, path[1:])
+        if left[0] is right[0] and isinstance(left[0], Firewall)
+    ]
+
+
+def segment_reachability(network: DataCenterNetwork) -> List[SegmentReachability]:
+    """
+    Check for every pair of segments that all paths between them traverse one of the declared firewalls
+    :param network: network to check
+    :return: reachability per pair of segments, non-compliant pairs carry a bypass path
+    """
+    graph = segment_graph(network)
+    direct = graph.edge_subgraph(
+        [(u, v) for u, v, mediated in graph.edges(data="mediated") if not mediated]
+    )
+    results = []
+    names = [s.name for s in network.segments]
+    for i, left in enumerate(names):
+        for right in names[i + 1 :]:
+            sources = [n for n in graph.nodes if n[1] == left]
+            targets = [n for n in graph.nodes if n[1] == right]
+            path = _path(graph, sources, targets)
+            bypass = _path(
+                direct,
+                [n for n in sources if n in direct],
+                [n for n in targets if n in direct],
+            )
+            results.append(
+                SegmentReachability(
+                    left, right, bool(path), _firewalls(path), _names(bypass)
+                )
+            )
+    return results
+
+
+def segment_report(network: DataCenterNetwork) -> Dict[str, SegmentReport]:
+    """
+    Ports and capacity per segment, ports without any configuration (that carry all segments) are not counted
+    :param network: network to report on
+    :return: report per segment name
+    """
+    members: Dict[str, List[Tuple[NetworkDevice, Port]]] = {
+        s.name: [] for s in network.segments
+    }
+    for device in network.graph_view().nodes:
+        for port in device_ports(device):
+            untagged = untagged_segment(network, port)
+            if untagged is None and not port.trunk_vlans:
+                continue
+            names = tagged_segments(network, port)
+            if untagged is not None:
+                names.add(untagged)
+            for name in names:
+                members[name].append((device, port))
+    report = {}
+    for name, ports in members.items():
+        up = [p for _, p in ports if p.status == DeviceStatus.Up]
+        devices: List[str] = []
+        for device, _ in ports:
+            if device.serial_number not in devices:
+                devices.append(device.serial_number)
+        report[name] = SegmentReport(
+            name, devices, len(ports), len(up), sum(p.speed_gb for p in up)
+        )
+    return report
+
+
+def _endpoint(network: DataCenterNetwork, address: str) -> Optional[Node]:
+    for device in network.graph_view().nodes:
+        for port in device_ports(device):
+            if port.address is not None and port.address.address == address:
+                segment = untagged_segment(network, port)
+                return (device, segment) if segment is not None else None
+    return None
+
+
+def _allows(firewall: Firewall, packet: Packet) -> bool:
+    # a trace is a simulation, the hit counters of the rules are left untouched
+    return firewall.evaluate(packet, count=False) == "allow"
+
+
+def trace(network: DataCenterNetwork, packet: Packet) -> TraceResult:
+    """
+    Trace a packet between the ports holding its source and destination address, the packet stays within its
+    segment unless it crosses a declared firewall, where the rules of the firewall apply.
+    :param network: network to trace on
+    :param packet: packet to trace
+    :return: TraceResult
+    :raises: ValueError if the source or destination address is not assigned to a port in a segment
+    """
+    source, destination = _endpoint(network, packet.source), _endpoint(
+        network, packet.destination
+    )
+    if source is None or destination is None:
+        raise ValueError(
+            f"{packet.source} and {packet.destination} should be assigned to ports in a segment"
+        )
+    graph = segment_graph(network)
+    for node in (source, destination):
+        graph.add_node(node)
+    denied = [
+        left[0].serial_number
+        for left, right, mediated in graph.edges(data="mediated")
+        if mediated and not _allows(left[0], packet)
+    ]
+    allowed = nx.subgraph_view(
+        graph,
+        filter_edge=lambda u, v: not graph.edges[u, v]["mediated"]
+        or u[0].serial_number not in denied,
+    )
+    path = _path(allowed, [source], [destination])
+    if path:
+        return TraceResult(True, _names(path))
+    path = _path(graph, [source], [destination])
+    return TraceResult(
+        False, _names(path), [s for s in _firewalls(path) if s in denied]
+    )
@@ -0,0 +1,452 @@
+# -*- coding: utf-8 -*-#
+"""Serialization of the model
+
+The model is serialized to plain JSON compatible structures: objects become dicts keyed by their constructor
+parameters, enums are written by name and port matchers in their written form ('80,443,8000-8100'). Objects that
+occur more than once in a document (e.g. a NIC that is part of a server and of a network, or a port that is part of a
+link aggregate) are written once with an '@id' and referred to with '@ref' afterwards, so decoding restores the
+shared objects.
+
+Decoding is forward tolerant: enum values that this version does not know (written by a newer peer) are decoded as
+an UnknownVariant that keeps the original value, and are counted on the Decoder. Unknown values are never up.
+Required enum parameters that are missing from a document take the default of their enum (e.g. DeviceStatus.Up, see
+the default classmethod of the enums), so documents written by hand can leave them out.
+
+The structures are written as JSON (to_json) or in a compact binary form for the wire (to_binary, see binary.py),
+both decode to the same model.
+
+Enums can also be written adjacently tagged, {"type": "NetworkType", "content": "Data"}, for strongly typed clients
+that need to tell the enum values apart from plain strings. The decoder reads both forms.
+
+For compact storage the status, topology and protocol enums can be written as integer codes instead (int_enums). The
+codes are part of the stored data: they are fixed per value in ENUM_CODES, never reused or renumbered, new values get
+the next free code. The decoder reads names and codes.
+"""
+import inspect
+import json
+import logging
+import typing
+from collections import Counter
+from enum import Enum
+from functools import lru_cache
+from typing import Any, Dict, Iterator, List, Optional, Tuple, Type
+
+from horao.models.binary import pack, unpack
+from horao.models.hardware import DataCenter
+from horao.models.network import (
+    NIC,
+    DataCenterNetwork,
+    Firewall,
+    NetworkDevice,
+    NetworkTopology,
+    NetworkType,
+    Router,
+    Switch,
+)
+from horao.models.osi_layers import Link, Port, PortMatcher, Protocol, Segment
+from horao.models.status import DeviceStatus
+from horao.models.store import StatusChangeContext
+
+PRIMITIVES = (str, int, float, bool, type(None))
+
+# constructor parameters that are stored under a different attribute name
+ALIASES: Dict[type, Dict[str, str]] = {NIC: {"ports": "lan_ports"}}
+
+# stable integer codes of enum values, do not change or reuse a code, append new values with the next code
+ENUM_CODES: Dict[Type[Enum], Dict[str, int]] = {
+    DeviceStatus: {"Up": 1, "Down": 2},
+    NetworkTopology: {
+        "Tree": 1,
+        "VL2": 2,
+        "FatTree": 3,
+        "Portland": 4,
+        "Hedera": 5,
+        "DCell": 6,
+        "BCube": 7,
+        "MDCube": 8,
+        "FiConn": 9,
+        "OSA": 10,
+        "CThrough": 11,
+        "Helios": 12,
+        "DragonFly": 13,
+        "DragonFlyPlus": 14,
+        "Slingshot": 15,
+        "Undefined": 16,
+    },
+    Protocol: {"TCP": 1, "UDP": 2, "ICMP": 3},
+}
+
+NODE_KINDS: Dict[str, type] = {
+    "switch": Switch,
+    "router": Router,
+    "firewall": Firewall,
+    "nic": NIC,
+}
+
+
+class UnknownVariant:
+    """Value of an enum that is not known to this version, the original value is kept so it is written back as is."""
+
+    def __init__(self, enum_type: Type[Enum], value: Any):
+        self.enum_type = enum_type
+        self.value = value
+
+    @property
+    def name(self) -> str:
+        return str(self.value)
+
+    def __eq__(self, other):
+        return (
+            isinstance(other, UnknownVariant)
+            and other.enum_type is self.enum_type
+            and other.value == self.value
+        )
+
+    def __hash__(self):
+        return hash((self.enum_type, self.value))
+
+    def __lt__(self, other):
+        # unknown values sort before all known values, so they are listed with the broken devices
+        if isinstance(other, self.enum_type):
+            return True
+        if isinstance(other, UnknownVariant) and other.enum_type is self.enum_type:
+            return str(self.value) < str(other.value)
+        return NotImplemented
+
+    def __gt__(self, other):
+        if isinstance(other, self.enum_type):
+            return False
+        if isinstance(other, UnknownVariant) and other.enum_type is self.enum_type:
+            return str(self.value) > str(other.value)
+        return NotImplemented
+
+    def __repr__(self):
+        return f"<{self.enum_type.__name__}.Unknown({self.value!r})>"
+
+
+@lru_cache(maxsize=None)
+def parameters(cls: type) -> List[Tuple[str, Any]]:
+    """
+    Constructor parameters of a model class and their type hints
+    :param cls: model class
+    :return: list of tuples of parameter name and type hint (None if not annotated)
+    """
+    hints = typing.get_type_hints(cls.__init__)
+    return [
+        (name, hints.get(name))
+        for name in inspect.signature(cls.__init__).parameters
+        if name != "self"
+    ]
+
+
+@lru_cache(maxsize=None)
+def required(cls: type) -> List[Tuple[str, Any]]:
+    """
+    Constructor parameters of a model class that have no default value
+    :param cls: model class
+    :return: list of tuples of parameter name and type hint (None if not annotated)
+    """
+    signature = inspect.signature(cls.__init__).parameters
+    return [
+        (name, hint)
+        for name, hint in parameters(cls)
+        if signature[name].default is inspect.Parameter.empty
+    ]
+
+
+def _defaulted_enum(hint: Any) -> bool:
+    return (
+        isinstance(hint, type) and issubclass(hint, Enum) and hasattr(hint, "default")
+    )
+
+
+def _kind(node: NetworkDevice) -> str:
+    return next(k for k, c in NODE_KINDS.items() if type(node) is c)
+
+
+class Encoder:
+    def __init__(self, tagged_enums: bool = False, int_enums: bool = False):
+        """
+        Encoder of model documents
+        :param tagged_enums: write enum values adjacently tagged ({"type": ..., "content": ...}) instead of by name
+        :param int_enums: write the values of the enums in ENUM_CODES as their integer code instead of by name
+        """
+        self.tagged_enums = tagged_enums
+        self.int_enums = int_enums
+        self._counts: Counter = Counter()
+        self._ids: Dict[int, int] = {}
+
+    def encode(self, value: Any) -> Any:
+        """
+        Encode a model object (or a list or dict of them) as a single document
+        :param value: value to encode
+        :return: JSON compatible structure
+        """
+        self._counts = Counter()
+        self._ids = {}
+        self._count(value)
+        return self._encode(value)
+
+    @staticmethod
+    def _children(value: Any) -> Iterator[Any]:
+        if isinstance(value, DataCenterNetwork):
+            yield from value.graph.nodes
+            for _, _, link in value.links():
+                yield from link.resolve(value.ports)
+            return
+        aliases = ALIASES.get(type(value), {})
+        for name, _ in parameters(type(value)):
+            yield getattr(value, aliases.get(name, name))
+
+    def _count(self, value: Any) -> None:
+        if isinstance(value, PRIMITIVES + (Enum, UnknownVariant, PortMatcher)):
+            return
+        if isinstance(value, (list, tuple)):
+            for v in value:
+                self._count(v)
+            return
+        if isinstance(value, dict):
+            for v in value.values():
+                self._count(v)
+            return
+        self._counts[id(value)] += 1
+        if self._counts[id(value)] == 1:
+            for child in self._children(value):
+                self._count(child)
+
+    def _tagged(self, enum_type: Type[Enum], value: Any) -> Any:
+        if self.tagged_enums:
+            return {"type": enum_type.__name__, "content": value}
+        return value
+
+    def _encode(self, value: Any) -> Any:
+        if isinstance(value, PRIMITIVES):
+            return value
+        if isinstance(value, Enum):
+            if self.int_enums and type(value) in ENUM_CODES:
+                return self._tagged(type(value), ENUM_CODES[type(value)][value.name])
+            return self._tagged(type(value), value.name)
+        if isinstance(value, UnknownVariant):
+            return self._tagged(value.enum_type, value.value)
+        if isinstance(value, PortMatcher):
+            return str(value)
+        if isinstance(value, (list, tuple)):
+            return [self._encode(v) for v in value]
+        if isinstance(value, dict):
+            return {k: self._encode(v) for k, v in value.items()}
+        if id(value) in self._ids:
+            return {"@ref": self._ids[id(value)]}
+        data: Dict[str, Any] = {}
+        if self._counts[id(value)] > 1:
+            self._ids[id(value)] = len(self._ids)
+            data["@id"] = self._ids[id(value)]
+        if isinstance(value, DataCenterNetwork):
+            nodes = list(value.graph.nodes)
+            index = {id(n): i for i, n in enumerate(nodes)}
+            data["name"] = value.name
+            data["network_type"] = self._encode(value.network_type)
+            data["nodes"] = [dict(kind=_kind(n), **self._encode(n)) for n in nodes]
+            data["links"] = [
+                {
+                    "left": index[id(left)],
+                    "right": index[id(right)],
+                    "ports": [
+                        self._encode(value.peer_port(link, left)),
+                        self._encode(value.peer_port(link, right)),
+                    ],
+                    "cost_override": link.cost_override,
+                }
+                for left, right, link in value.links()
+            ]
+            data["segments"] = self._encode(value.segments)
+            return data
+        aliases = ALIASES.get(type(value), {})
+        for name, _ in parameters(type(value)):
+            data[name] = self._encode(getattr(value, aliases.get(name, name)))
+        return data
+
+
+class Decoder:
+    def __init__(self):
+        """
+        Decoder of model documents, keeps track of the unknown enum values it encountered
+        """
+        self.unknown_variants: Counter = Counter()
+        self._objects: Dict[int, Any] = {}
+
+    def enum(self, enum_type: Type[Enum], value: Any):
+        """
+        Decode an enum value by name or integer code, or adjacently tagged
+        :param enum_type: enum to decode
+        :param value: name or code of the value, or dict with the name of the enum (type) and the value (content)
+        :return: enum value, UnknownVariant if the name or code is not known
+        :raises: ValueError if a tagged value is of another enum
+        """
+        if isinstance(value, dict) and "type" in value and "content" in value:
+            if value["type"] != enum_type.__name__:
+                raise ValueError(
+                    f"Expected a {enum_type.__name__} value, got a {value['type']} value"
+                )
+            value = value["content"]
+        if isinstance(value, str) and value in enum_type.__members__:
+            return enum_type[value]
+        if isinstance(value, int) and not isinstance(value, bool):
+            names = {c: n for n, c in ENUM_CODES.get(enum_type, {}).items()}
+            if value in names:
+                return enum_type[names[value]]
+        logging.warning(f"Unknown {enum_type.__name__} value {value!r}")
+        self.unknown_variants[enum_type.__name__] += 1
+        return UnknownVariant(enum_type, value)
+
+    def decode(self, data: Any, hint: Any) -> Any:
+        """
+        Decode a (part of a) document, objects shared within the document are shared between calls on the same decoder
+        :param data: JSON compatible structure
+        :param hint: type to decode to (e.g. DataCenter or List[DataCenterNetwork])
+        :return: decoded value
+        :raises: ValueError if the data does not match the type
+        """
+        try:
+            return self._decode(data, hint)
+        except (TypeError, KeyError, IndexError, AttributeError) as e:
+            raise ValueError(f"Cannot decode {getattr(hint, '__name__', hint)} ({e})")
+
+    def _decode(self, data: Any, hint: Any) -> Any:
+        if data is None:
+            return None
+        origin = typing.get_origin(hint)
+        if origin is typing.Union:
+            hint = next(a for a in typing.get_args(hint) if a is not type(None))
+            return self._decode(data, hint)
+        if origin is list:
+            (argument,) = typing.get_args(hint)
+            return [self._decode(v, argument) for v in data]
+        if not isinstance(hint, type) or hint in PRIMITIVES:
+            return data
+        if issubclass(hint, Enum):
+            return self.enum(hint, data)
+        if hint is PortMatcher:
+            # single ports were written as a number before there were matchers
+            return PortMatcher.of(data)
+        if "@ref" in data:
+            return self._objects[data["@ref"]]
+        if hint is DataCenterNetwork:
+            value: Any = self._network(data)
+        else:
+            arguments = {
+                name: self._decode(data[name], h)
+                for name, h in parameters(hint)
+                if name in data
+            }
+            for name, h in required(hint):
+                if name not in data and _defaulted_enum(h):
+                    arguments[name] = h.default()
+            value = hint(**arguments)
+        if "@id" in data:
+            self._objects[data["@id"]] = value
+        return value
+
+    def _network(self, data: Dict[str, Any]) -> DataCenterNetwork:
+        network_type = (
+            self.enum(NetworkType, data["network_type"])
+            if "network_type" in data
+            else NetworkType.default()
+        )
+        network = DataCenterNetwork(data["name"], network_type)
+        nodes: List[Optional[NetworkDevice]] = []
+        for node in data["nodes"]:
+            if node.get("kind") not in NODE_KINDS:
+                logging.warning(
+                    f"Skipping network device of unknown kind {node.get('kind')!r}"
+                )
+                self.unknown_variants["kind"] += 1
+                nodes.append(None)
+                continue
+            nodes.append(self._decode(node, NODE_KINDS[node["kind"]]))
+        network.add_multiple([n for n in nodes if n is not None])
+        for link in data["links"]:
+            left, right = nodes[link["left"]], nodes[link["right"]]
+            if left is None or right is None:
+                continue
+            left_port, right_port = [self._decode(p, Port) for p in link["ports"]]
+            network.graph.add_edge(
+                left,
+                right,
+                link=Link(
+                    network.ports.intern(left_port, left),
+                    network.ports.intern(right_port, right),
+                    link.get("cost_override"),
+                ),
+            )
+        network.segments = self._decode(data.get("segments", []), List[Segment])
+        return network
+
+    def status_batch(
+        self, entries: List[Dict[str, Any]]
+    ) -> List[Tuple[str, DeviceStatus, StatusChangeContext]]:
+        """
+        Decode a batch of status updates (as sent by peers), entries with an unknown status are skipped
+        :param entries: dicts with serial_number, status, source and timestamp
+        :return: changes that can be applied with ModelStore.apply_status_batch
+        """
+        changes = []
+        for entry in entries:
+            status = self.enum(DeviceStatus, entry.get("status"))
+            if isinstance(status, UnknownVariant) or "serial_number" not in entry:
+                logging.warning(f"Skipping status update {entry}")
+                continue
+            changes.append(
+                (
+                    entry["serial_number"],
+                    status,
+                    StatusChangeContext(
+                        entry.get("source", "peer"), timestamp=entry.get("timestamp")
+                    ),
+                )
+            )
+        return changes
+
+
+def to_json(value: Any, tagged_enums: bool = False, int_enums: bool = False) -> str:
+    """
+    Serialize a model object (or a list or dict of them) to JSON
+    :param value: value to serialize
+    :param tagged_enums: write enum values adjacently tagged ({"type": ..., "content": ...}) instead of by name
+    :param int_enums: write the values of the enums in ENUM_CODES as their integer code
+    :return: JSON string
+    """
+    return json.dumps(Encoder(tagged_enums, int_enums).encode(value))
+
+
+def from_json(text: str, hint: Any, decoder: Optional[Decoder] = None) -> Any:
+    """
+    Deserialize a model object from JSON
+    :param text: JSON string
+    :param hint: type to decode to
+    :param decoder: decoder to use (e.g. to inspect the unknown values afterwards)
+    :return: decoded value
+    :raises: ValueError if the JSON is malformed or does not match the type
+    """
+    return (decoder if decoder else Decoder()).decode(json.loads(text), hint)
+
+
+def to_binary(value: Any, int_enums: bool = False) -> bytes:
+    """
+    Serialize a model object (or a list or dict of them) to the compact binary encoding
+    :param value: value to serialize
+    :param int_enums: write the values of the enums in ENUM_CODES as their integer code
+    :return: bytes
+    """
+    return pack(Encoder(int_enums=int_enums).encode(value))
+
+
+def from_binary(data: bytes, hint: Any, decoder: Optional[Decoder] = None) -> Any:
+    """
+    Deserialize a model object from the compact binary encoding
+    :param data: bytes
+    :param hint: type to decode to
+    :param decoder: decoder to use (e.g. to inspect the unknown values afterwards)
+    :return: decoded value
+    :raises: ValueError if the data is malformed or does not match the type
+    """
+    return (decoder if decoder else Decoder()).decode(unpack(data), hint)
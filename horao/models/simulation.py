@@ -0,0 +1,199 @@
+# -*- coding: utf-8 -*-#
+"""Simulations on the model
+
+This module contains 'what-if' simulations of operational activities. Simulations are evaluated on an overlay of the
+network, the model itself is never changed by a simulation.
+"""
+from enum import Enum, auto
+from typing import List, Optional, Set
+
+import networkx as nx  # type: ignore
+
+from horao.models.budget import UNLIMITED, AnalysisBudget
+from horao.models.hardware import DataCenter, Server
+from horao.models.network import DataCenterNetwork, Switch, SwitchType
+
+
+class InvariantType(Enum):
+    """Built-in checks that can be evaluated against the overlay."""
+
+    # every access switch keeps at least 'value' live uplinks towards a distribution or core switch
+    MinUplinksPerAccess = auto()
+    # the bisection bandwidth stays at or above 'value' (fraction) of the bisection bandwidth without maintenance
+    MinBisectionFraction = auto()
+    # the live part of the network stays connected
+    NoPartition = auto()
+    # at most 'value' servers lose their connection to the fabric
+    MaxUnreachableServers = auto()
+
+
+class Invariant:
+    def __init__(self, invariant_type: InvariantType, value: float = 0):
+        self.invariant_type = invariant_type
+        self.value = value
+
+    def __repr__(self):
+        return f"<Invariant {self.invariant_type.name}({self.value})>"
+
+
+class WaveResult:
+    def __init__(self, number: int, serials: List[str], violations: List[str]):
+        self.number = number
+        self.serials = serials
+        self.violations = violations
+
+    @property
+    def passed(self) -> bool:
+        return not self.violations
+
+    def __repr__(self):
+        return f"<WaveResult {self.number} {'passed' if self.passed else 'failed'}>"
+
+
+def overlay(network: DataCenterNetwork, down: Set[str]) -> nx.Graph:
+    """
+    Live view of the network (see up_view), devices and links that are down, decommissioned devices and the devices
+    of the given set are left out
+    :param network: network to create the overlay for
+    :param down: serial numbers of devices that should be considered down
+    :return: copy of the network graph containing only live devices and links
+    """
+    graph = network.up_view().copy()
+    graph.remove_nodes_from([d for d in graph.nodes if d.serial_number in down])
+    return graph
+
+
+def bisection_bandwidth(
+    network: DataCenterNetwork,
+    graph: nx.Graph,
+    budget: Optional[AnalysisBudget] = None,
+) -> int:
+    """
+    Bisection bandwidth of the switching fabric, approximated by the capacity of the minimum cut between switches.
+    The minimum cut visits every switch once per phase, the budget is checked up front.
+    :param network: network the graph belongs to
+    :param graph: graph to evaluate
+    :param budget: budget of the computation, unlimited if not given
+    :return: bandwidth in Gb, 0 if the fabric is partitioned
+    :raises: BudgetExceeded if the computation would exceed the node budget
+    """
+    switches = graph.subgraph([n for n in graph.nodes if isinstance(n, Switch)])
+    if switches.number_of_nodes() < 2 or not nx.is_connected(switches):
+        return 0
+    (budget if budget else UNLIMITED).meter().visit(switches.number_of_nodes() ** 2)
+    weighted = nx.Graph()
+    for left, right, data in switches.edges(data=True):
+        weighted.add_edge(left, right, weight=data["link"].speed_gb(network.ports))
+    cut, _ = nx.stoer_wagner(weighted)
+    return cut
+
+
+def _unreachable_servers(
+    graph: nx.Graph, datacenter: DataCenter, down: Set[str]
+) -> List[Server]:
+    switch_components = [
+        c
+        for c in nx.connected_components(graph)
+        if any(isinstance(n, Switch) for n in c)
+    ]
+    fabric = max(
+        switch_components,
+        key=lambda c: len([n for n in c if isinstance(n, Switch)]),
+        default=set(),
+    )
+    return [
+        s
+        for s in datacenter.servers()
+        if s.serial_number not in down and not any(n in fabric for n in s.nic)
+    ]
+
+
+def _violations(
+    network: DataCenterNetwork,
+    graph: nx.Graph,
+    baseline: int,
+    invariants: List[Invariant],
+    datacenter: Optional[DataCenter],
+    down: Set[str],
+) -> List[str]:
+    violations = []
+    for invariant in invariants:
+        if invariant.invariant_type == InvariantType.MinUplinksPerAccess:
+            for switch in [
+                n
+                for n in graph.nodes
+                if isinstance(n, Switch) and n.switch_type == SwitchType.Access
+            ]:
+                uplinks = [
+                    n
+                    for n in graph.neighbors(switch)
+                    if isinstance(n, Switch) and n.switch_type != SwitchType.Access
+                ]
+                if len(uplinks) < invariant.value:
+                    violations.append(
+                        f"{switch.name} ({switch.serial_number}) has {len(uplinks)} live uplinks, "
+                        f"at least {int(invariant.value)} required"
+                    )
+        elif invariant.invariant_type == InvariantType.MinBisectionFraction:
+            bisection = bisection_bandwidth(network, graph)
+            fraction = bisection / baseline if baseline else 1.0
+            if fraction < invariant.value:
+                violations.append(
+                    f"bisection bandwidth at {fraction:.0%}, at least {invariant.value:.0%} required"
+                )
+        elif invariant.invariant_type == InvariantType.NoPartition:
+            # devices without any cable (e.g. spare NICs) are not a part of their own
+            cabled = graph.subgraph(
+                [n for n in graph.nodes if network.graph.degree(n) > 0]
+            )
+            if cabled.number_of_nodes() > 0 and not nx.is_connected(cabled):
+                violations.append(
+                    f"{network.name} is partitioned into {nx.number_connected_components(cabled)} parts"
+                )
+        elif invariant.invariant_type == InvariantType.MaxUnreachableServers:
+            if datacenter is None:
+                raise ValueError("MaxUnreachableServers requires a datacenter")
+            unreachable = _unreachable_servers(graph, datacenter, down)
+            if len(unreachable) > invariant.value:
+                violations.append(
+                    f"{len(unreachable)} servers unreachable ({', '.join(s.name for s in unreachable)}), "
+                    f"at most {int(invariant.value)} allowed"
+                )
+    return violations
+
+
+def rolling_maintenance(
+    network: DataCenterNetwork,
+    waves: List[List[str]],
+    invariants: List[Invariant],
+    datacenter: Optional[DataCenter] = None,
+    budget: Optional[AnalysisBudget] = None,
+) -> List[WaveResult]:
+    """
+    Simulate taking devices down one wave at a time, evaluating the invariants for every wave. Devices of a wave are
+    restored before the next wave is evaluated.
+    :param network: network to simulate on
+    :param waves: serial numbers of the devices per wave
+    :param invariants: invariants that need to hold during every wave
+    :param datacenter: datacenter containing the servers (only needed for MaxUnreachableServers)
+    :param budget: budget of the simulation, the devices of the network are visited once per wave
+    :return: result per wave, including the violations found
+    :raises: ValueError if MaxUnreachableServers is requested without a datacenter
+    :raises: BudgetExceeded carrying the results of the completed waves if the budget is exceeded
+    """
+    meter = (budget if budget else UNLIMITED).meter()
+    baseline = bisection_bandwidth(network, overlay(network, set()), budget)
+    results: List[WaveResult] = []
+    for number, wave in enumerate(waves):
+        meter.visit(network.graph.number_of_nodes(), partial=results)
+        meter.result(results)
+        down = set(wave)
+        graph = overlay(network, down)
+        results.append(
+            WaveResult(
+                number,
+                wave,
+                _violations(network, graph, baseline, invariants, datacenter, down),
+            )
+        )
+    return results
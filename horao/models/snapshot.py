@@ -0,0 +1,497 @@
+# -*- coding: utf-8 -*-#
+"""Snapshots of the model
+
+The snapshot store keeps timestamped copies of the datacenter and its networks in a directory, one file per snapshot.
+Historical questions ('what was the status of this switch last Tuesday', 'when did the disks of this server fill up
+beyond 80%') are answered by loading the snapshots one at a time. Snapshots that are missing or unreadable are skipped.
+
+A snapshot is either full or a delta: the changes (see ChangeSet) since an earlier snapshot, which is how the store
+stays small when only a handful of devices change between snapshots. A delta is loaded by applying the chain of deltas
+onto the nearest full snapshot; every delta carries a checksum of its changes and the fingerprint of the model it
+applies to, so a missing or corrupted delta breaks the chain from that delta on, and is reported as such. The store
+writes a full snapshot instead of a delta every snapshot_compaction_deltas deltas, or when the chain would outgrow a
+full snapshot. Peers exchange the changes since a snapshot they both have with delta_since and apply_delta.
+"""
+import json
+import logging
+import os
+from functools import reduce
+from typing import Any, Callable, Dict, Iterator, List, Optional, Tuple, Union
+
+from horao.models.diff import ChangeSet, fingerprint
+from horao.models.hardware import DataCenter
+from horao.models.ipam import IpPool
+from horao.models.network import DataCenterNetwork
+from horao.models.serialization import Decoder, Encoder
+from horao.models.versioning import MODEL_SCHEMA_VERSION, can_read
+from horao.settings import Settings
+
+FieldSelector = Union[str, Callable[[Any], Any]]
+
+FULL = ".snapshot"
+DELTA = ".delta"
+
+
+class Snapshot:
+    def __init__(
+        self,
+        timestamp: float,
+        datacenter: DataCenter,
+        networks: Optional[List[DataCenterNetwork]] = None,
+        schema_version: str = MODEL_SCHEMA_VERSION,
+        pools: Optional[List[IpPool]] = None,
+    ):
+        """
+        Snapshot of the model
+        :param pools: top level address pools (with their delegations)
+        """
+        self.timestamp = timestamp
+        self.datacenter = datacenter
+        self.networks = networks if networks else []
+        self.schema_version = schema_version
+        self.pools = pools if pools else []
+
+    def device(self, serial_number: str):
+        """
+        Device with the given serial number as it was at the time of the snapshot
+        :param serial_number: serial number of the device
+        :return: device, None if the device was not part of the snapshot
+        """
+        try:
+            return self.datacenter.find_device(serial_number, self.networks)
+        except ValueError:
+            return None
+
+
+def select(device, field: FieldSelector) -> Any:
+    """
+    Extract a field from a device
+    :param device: device to extract from
+    :param field: (dotted) attribute name or function taking the device
+    :return: value of the field
+    """
+    if callable(field):
+        return field(device)
+    return reduce(getattr, field.split("."), device)
+
+
+class BrokenDeltaChain(ValueError):
+    def __init__(self, timestamp: float, reason: str, loadable: List[float]):
+        """
+        A delta snapshot cannot be reconstructed
+        :param timestamp: time of the delta (or base) snapshot that is missing or corrupted
+        :param reason: what is wrong with it
+        :param loadable: timestamps of the snapshots that can still be loaded
+        """
+        super().__init__(f"Snapshot {timestamp} cannot be reconstructed ({reason})")
+        self.timestamp = timestamp
+        self.reason = reason
+        self.loadable = loadable
+
+
+class SnapshotStore:
+    def __init__(self, path: str, settings: Optional[Settings] = None):
+        """
+        Snapshot store backed by a directory
+        :param path: directory to keep the snapshots in, created if it does not exist
+        :param settings: settings for the compaction of delta snapshots, defaults if not given
+        """
+        self.path = path
+        self.compaction_deltas = (
+            settings if settings else Settings()
+        ).snapshot_compaction_deltas
+        os.makedirs(path, exist_ok=True)
+
+    def _file(self, timestamp: float, extension: str = FULL) -> str:
+        return os.path.join(self.path, f"{timestamp:.6f}{extension}")
+
+    @staticmethod
+    def _model(
+        datacenter: DataCenter,
+        networks: Optional[List[DataCenterNetwork]],
+        pools: Optional[List[IpPool]],
+    ) -> Dict[str, Any]:
+        # round trip through JSON, so the model compares equal to a model read back from a file
+        return json.loads(
+            json.dumps(
+                Encoder().encode(
+                    {
+                        "datacenter": datacenter,
+                        "networks": networks if networks else [],
+                        "pools": pools if pools else [],
+                    }
+                )
+            )
+        )
+
+    def put(
+        self,
+        timestamp: float,
+        datacenter: DataCenter,
+        networks: Optional[List[DataCenterNetwork]] = None,
+        pools: Optional[List[IpPool]] = None,
+    ) -> Snapshot:
+        """
+        Store a snapshot of the datacenter and its networks
+        :param timestamp: time of the snapshot (seconds since the epoch)
+        :param datacenter: datacenter to store
+        :param networks: networks to store
+        :param pools: top level address pools to store, including their delegations
+        :return: Snapshot
+        """
+        snapshot = Snapshot(timestamp, datacenter, networks, pools=pools)
+        self._write_full(timestamp, self._model(datacenter, networks, pools))
+        return snapshot
+
+    def _write_full(self, timestamp: float, model: Dict[str, Any]) -> None:
+        with open(self._file(timestamp), "w") as f:
+            json.dump(
+                {
+                    "schema_version": MODEL_SCHEMA_VERSION,
+                    "timestamp": timestamp,
+                    **model,
+                },
+                f,
+            )
+        if os.path.exists(self._file(timestamp, DELTA)):
+            os.remove(self._file(timestamp, DELTA))
+
+    def put_delta(
+        self,
+        base_timestamp: float,
+        timestamp: float,
+        datacenter: DataCenter,
+        networks: Optional[List[DataCenterNetwork]] = None,
+        pools: Optional[List[IpPool]] = None,
+    ) -> Snapshot:
+        """
+        Store a snapshot as the changes since an earlier snapshot. A full snapshot is written instead when the chain of
+        deltas since the last full snapshot reaches snapshot_compaction_deltas, or when the deltas of the chain
+        together would be larger than a full snapshot.
+        :param base_timestamp: time of the snapshot the changes are computed against
+        :param timestamp: time of the snapshot (seconds since the epoch)
+        :param datacenter: datacenter to store
+        :param networks: networks to store
+        :param pools: top level address pools to store, including their delegations
+        :return: Snapshot
+        :raises: ValueError if the base is not older, BrokenDeltaChain if the base snapshot cannot be loaded
+        """
+        if base_timestamp >= timestamp:
+            raise ValueError(
+                f"Base snapshot {base_timestamp} should be older than {timestamp}"
+            )
+        snapshot = Snapshot(timestamp, datacenter, networks, pools=pools)
+        base = self._loadable_or_broken(base_timestamp, {})
+        model = self._model(datacenter, networks, pools)
+        self._write_delta(base_timestamp, base, timestamp, model)
+        return snapshot
+
+    @staticmethod
+    def _delta(
+        base_timestamp: float,
+        base: Dict[str, Any],
+        timestamp: float,
+        model: Dict[str, Any],
+    ) -> Dict[str, Any]:
+        changes = ChangeSet.between(base, model).changes
+        return {
+            "schema_version": MODEL_SCHEMA_VERSION,
+            "timestamp": timestamp,
+            "base": base_timestamp,
+            "base_fingerprint": fingerprint(base),
+            "fingerprint": fingerprint(model),
+            "checksum": fingerprint(changes),
+            "changes": changes,
+        }
+
+    def _write_delta(
+        self,
+        base_timestamp: float,
+        base: Dict[str, Any],
+        timestamp: float,
+        model: Dict[str, Any],
+    ) -> None:
+        content = json.dumps(self._delta(base_timestamp, base, timestamp, model))
+        chain = self._chain(base_timestamp)
+        chain_size = sum(os.path.getsize(self._file(t, DELTA)) for t in chain)
+        full_size = len(json.dumps(model))
+        if (
+            len(chain) >= self.compaction_deltas
+            or chain_size + len(content) > full_size
+        ):
+            self._write_full(timestamp, model)
+            return
+        with open(self._file(timestamp, DELTA), "w") as f:
+            f.write(content)
+
+    def _chain(self, timestamp: float) -> List[float]:
+        # deltas from the given snapshot back to the nearest full snapshot
+        chain: List[float] = []
+        while not os.path.exists(self._file(timestamp)):
+            document = self._read(timestamp, DELTA)
+            if document is None:
+                break
+            chain.append(timestamp)
+            timestamp = document.get("base")
+        return chain
+
+    def _read(self, timestamp: float, extension: str) -> Optional[Dict[str, Any]]:
+        try:
+            with open(self._file(timestamp, extension), "r") as f:
+                document = json.load(f)
+        except (OSError, ValueError):
+            return None
+        return document if isinstance(document, dict) else None
+
+    def _reconstruct(
+        self, timestamp: float, cache: Dict[float, Dict[str, Any]]
+    ) -> Dict[str, Any]:
+        """
+        Serialized model of a snapshot, applying its delta chain onto the nearest full snapshot
+        :param timestamp: time of the snapshot
+        :param cache: models already reconstructed, by timestamp
+        :return: serialized model
+        :raises: BrokenDeltaChain (without the loadable snapshots) if a delta or the full snapshot it starts from is
+        missing or corrupted
+        """
+        if timestamp in cache:
+            return cache[timestamp]
+        deltas = []
+        current = timestamp
+        while current not in cache:
+            if os.path.exists(self._file(current)):
+                full = self._read(current, FULL)
+                if full is None or "datacenter" not in full:
+                    raise BrokenDeltaChain(current, "unreadable full snapshot", [])
+                if not can_read(str(full.get("schema_version"))):
+                    raise BrokenDeltaChain(current, "incompatible format", [])
+                cache[current] = {
+                    k: full.get(k, []) for k in ("datacenter", "networks", "pools")
+                }
+                break
+            delta = self._read(current, DELTA)
+            if delta is None:
+                raise BrokenDeltaChain(current, "missing or unreadable", [])
+            if not can_read(str(delta.get("schema_version"))):
+                raise BrokenDeltaChain(current, "incompatible format", [])
+            if fingerprint(delta.get("changes")) != delta.get("checksum"):
+                raise BrokenDeltaChain(current, "checksum mismatch", [])
+            if not delta.get("base", current) < current:
+                raise BrokenDeltaChain(current, "base is not older", [])
+            deltas.append((current, delta))
+            current = delta["base"]
+        model = cache[current]
+        for current, delta in reversed(deltas):
+            if fingerprint(model) != delta["base_fingerprint"]:
+                raise BrokenDeltaChain(current, "base fingerprint mismatch", [])
+            try:
+                model = ChangeSet(delta["changes"]).apply(model)
+            except ValueError as e:
+                raise BrokenDeltaChain(current, str(e), [])
+            cache[current] = model
+        return model
+
+    def loadable(self) -> List[float]:
+        """
+        Timestamps of the snapshots that can be loaded, oldest first
+        :return: list of timestamps
+        """
+        cache: Dict[float, Dict[str, Any]] = {}
+        loadable = []
+        for timestamp in self.timestamps():
+            try:
+                self._reconstruct(timestamp, cache)
+                loadable.append(timestamp)
+            except BrokenDeltaChain:
+                continue
+        return loadable
+
+    def _loadable_or_broken(
+        self, timestamp: float, cache: Dict[float, Dict[str, Any]]
+    ) -> Dict[str, Any]:
+        try:
+            return self._reconstruct(timestamp, cache)
+        except BrokenDeltaChain as e:
+            raise BrokenDeltaChain(e.timestamp, e.reason, self.loadable())
+
+    def delta_since(self, timestamp: float) -> Dict[str, Any]:
+        """
+        Changes between a snapshot and the latest snapshot, for a peer that has the older snapshot to catch up (e.g.
+        during the anti-entropy reconciliation), to be applied with apply_delta on the store of the peer
+        :param timestamp: time of the snapshot the peer has
+        :return: delta, as stored for delta snapshots
+        :raises: ValueError if the store has no newer snapshot, BrokenDeltaChain if a snapshot cannot be loaded
+        """
+        latest = max(self.timestamps(), default=None)
+        if latest is None or latest <= timestamp:
+            raise ValueError(f"No snapshot newer than {timestamp}")
+        cache: Dict[float, Dict[str, Any]] = {}
+        base = self._loadable_or_broken(timestamp, cache)
+        return self._delta(
+            timestamp, base, latest, self._loadable_or_broken(latest, cache)
+        )
+
+    def apply_delta(self, delta: Dict[str, Any]) -> Snapshot:
+        """
+        Store the snapshot described by a delta of a peer (see delta_since), the delta is verified against the local
+        copy of its base snapshot
+        :param delta: delta
+        :return: Snapshot
+        :raises: ValueError if the delta is corrupted or does not apply to the local base snapshot
+        """
+        try:
+            base_timestamp, timestamp = float(delta["base"]), float(delta["timestamp"])
+            changes = delta["changes"]
+        except (KeyError, TypeError, ValueError):
+            raise ValueError("Delta is malformed")
+        if not can_read(str(delta.get("schema_version"))):
+            raise ValueError("Delta has an incompatible format")
+        if fingerprint(changes) != delta.get("checksum"):
+            raise ValueError("Delta is corrupted (checksum mismatch)")
+        base = self._loadable_or_broken(base_timestamp, {})
+        if fingerprint(base) != delta.get("base_fingerprint"):
+            raise ValueError(f"Delta does not apply to the local snapshot {base_timestamp}")
+        model = ChangeSet(changes).apply(base)
+        if fingerprint(model) != delta.get("fingerprint"):
+            raise ValueError("Delta does not reproduce the snapshot of the peer")
+        self._write_delta(base_timestamp, base, timestamp, model)
+        return self._snapshot(timestamp, MODEL_SCHEMA_VERSION, model)
+
+    def timestamps(
+        self, start: Optional[float] = None, end: Optional[float] = None
+    ) -> List[float]:
+        """
+        Timestamps of the stored (full and delta) snapshots, oldest first
+        :param start: only timestamps at or after start
+        :param end: only timestamps at or before end
+        :return: list of timestamps
+        """
+        timestamps = set()
+        for name in os.listdir(self.path):
+            stem, extension = os.path.splitext(name)
+            if extension not in (FULL, DELTA):
+                continue
+            try:
+                timestamp = float(stem)
+            except ValueError:
+                continue
+            if (start is None or timestamp >= start) and (
+                end is None or timestamp <= end
+            ):
+                timestamps.add(timestamp)
+        return sorted(timestamps)
+
+    @staticmethod
+    def _snapshot(
+        timestamp: float, schema_version: str, model: Dict[str, Any]
+    ) -> Snapshot:
+        decoder = Decoder()
+        return Snapshot(
+            timestamp,
+            decoder.decode(model["datacenter"], DataCenter),
+            decoder.decode(model["networks"], List[DataCenterNetwork]),
+            schema_version,
+            decoder.decode(model.get("pools", []), List[IpPool]),
+        )
+
+    def load(self, timestamp: float) -> Snapshot:
+        """
+        Load the snapshot taken at the given time, a delta snapshot is reconstructed from its chain of deltas
+        :param timestamp: time of the snapshot
+        :return: Snapshot
+        :raises: ValueError if the snapshot does not exist, cannot be read or has an incompatible schema version,
+        BrokenDeltaChain if a delta on the way to it is missing or corrupted
+        """
+        if not os.path.exists(self._file(timestamp)) and os.path.exists(
+            self._file(timestamp, DELTA)
+        ):
+            return self._snapshot(
+                timestamp,
+                MODEL_SCHEMA_VERSION,
+                self._loadable_or_broken(timestamp, {}),
+            )
+        try:
+            with open(self._file(timestamp), "r") as f:
+                document = json.load(f)
+        except (OSError, ValueError) as e:
+            raise ValueError(f"Snapshot {timestamp} cannot be read ({e})")
+        if not isinstance(document, dict) or not can_read(
+            str(document.get("schema_version"))
+        ):
+            raise ValueError(f"Snapshot {timestamp} has an incompatible format")
+        if "datacenter" not in document:
+            raise ValueError(f"Snapshot {timestamp} has no datacenter")
+        return self._snapshot(
+            document.get("timestamp", timestamp), document["schema_version"], document
+        )
+
+    def snapshots(
+        self, start: Optional[float] = None, end: Optional[float] = None
+    ) -> Iterator[Snapshot]:
+        """
+        Lazily load the snapshots in a time range, oldest first, skipping snapshots that cannot be read
+        :param start: only snapshots at or after start
+        :param end: only snapshots at or before end
+        :return: snapshots
+        """
+        for timestamp in self.timestamps(start, end):
+            try:
+                yield self.load(timestamp)
+            except ValueError as e:
+                logging.warning(f"Skipping snapshot: {e}")
+
+    def device_at(self, serial_number: str, timestamp: float):
+        """
+        State of a device at a point in time, taken from the nearest snapshot at or before the timestamp
+        :param serial_number: serial number of the device
+        :param timestamp: point in time
+        :return: device, None if the device was not part of that snapshot
+        :raises: ValueError if there is no readable snapshot at or before the timestamp
+        """
+        for candidate in reversed(self.timestamps(end=timestamp)):
+            try:
+                return self.load(candidate).device(serial_number)
+            except ValueError as e:
+                logging.warning(f"Skipping snapshot: {e}")
+        raise ValueError(f"No snapshot at or before {timestamp}")
+
+    def history_of(
+        self,
+        serial_number: str,
+        field: FieldSelector,
+        start: Optional[float] = None,
+        end: Optional[float] = None,
+    ) -> Iterator[Tuple[float, Any]]:
+        """
+        Time series of a field of a device, snapshots in which the device does not exist are left out
+        :param serial_number: serial number of the device
+        :param field: (dotted) attribute name or function taking the device, e.g. 'status'
+        :param start: start of the range
+        :param end: end of the range
+        :return: tuples of timestamp and value, oldest first
+        """
+        for snapshot in self.snapshots(start, end):
+            device = snapshot.device(serial_number)
+            if device is not None:
+                yield snapshot.timestamp, select(device, field)
+
+    def first_time_when(
+        self,
+        serial_number: str,
+        predicate: Callable[[Any], bool],
+        start: Optional[float] = None,
+        end: Optional[float] = None,
+    ) -> Optional[float]:
+        """
+        First snapshot in which a device satisfied a predicate
+        :param serial_number: serial number of the device
+        :param predicate: function taking the device
+        :param start: start of the range
+        :param end: end of the range
+        :return: timestamp of the snapshot, None if the predicate never held
+        """
+        for snapshot in self.snapshots(start, end):
+            device = snapshot.device(serial_number)
+            if device is not None and predicate(device):
+                return snapshot.timestamp
+        return None
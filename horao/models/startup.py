@@ -0,0 +1,146 @@
+# -*- coding: utf-8 -*-#
+"""Startup planning
+
+After a full power loss the datacenter has to come back in order: the core switches before the distribution switches,
+the network before the servers, the storage before the compute. The startup plan derives the dependencies between the
+devices from the model and orders the devices in stages, every device only depends on devices in earlier stages.
+Dependencies come from:
+
+- the topology tiers: a switch depends on the switches of a higher tier it is linked to (Core, Distribution, Access)
+- the attachments: a server depends on the network devices its NICs are linked to
+- the storage pools: servers labelled role=compute depend on the servers labelled role=storage of the same pool (the
+  storage_pool label)
+- explicit dependencies supplied by the operator
+
+Before starting the devices of a stage, verify_ready checks that all devices of the earlier stages are up.
+"""
+from typing import Dict, List, Optional, Tuple
+
+import networkx as nx  # type: ignore
+
+from horao.models.hardware import DataCenter
+from horao.models.network import DataCenterNetwork, Firewall, Router, Switch, SwitchType
+from horao.models.status import DeviceStatus
+
+TIERS = {SwitchType.Core: 0, SwitchType.Distribution: 1, SwitchType.Access: 2}
+
+
+class StartupPlan:
+    def __init__(self, stages: List[List[str]]):
+        """
+        Ordered stages of the startup
+        :param stages: serial numbers of the devices per stage, in order of startup
+        """
+        self.stages = stages
+
+    def stage_of(self, serial_number: str) -> int:
+        """
+        Stage a device is started in
+        :param serial_number: serial number of the device
+        :return: index of the stage
+        :raises: ValueError if the device is not part of the plan
+        """
+        for i, stage in enumerate(self.stages):
+            if serial_number in stage:
+                return i
+        raise ValueError(f"Device {serial_number} is not part of the plan")
+
+    def to_dict(self) -> dict:
+        return {"stages": self.stages}
+
+    def render(self) -> str:
+        """
+        Plan as text, for runbooks
+        :return: text with a line per stage
+        """
+        return "\n".join(
+            f"stage {i + 1}: {', '.join(stage)}" for i, stage in enumerate(self.stages)
+        )
+
+
+def _dependencies(
+    dc: DataCenter, networks: List[DataCenterNetwork]
+) -> List[Tuple[str, str]]:
+    dependencies = []
+    for network in networks:
+        for left, right, _ in network.links():
+            if isinstance(left, Switch) and isinstance(right, Switch):
+                upper, lower = sorted([left, right], key=lambda s: TIERS[s.switch_type])
+                if TIERS[upper.switch_type] < TIERS[lower.switch_type]:
+                    dependencies.append((lower.serial_number, upper.serial_number))
+    pools: Dict[str, List[str]] = {}
+    for server in dc.servers():
+        if server.labels.get("role") == "storage" and "storage_pool" in server.labels:
+            pools.setdefault(server.labels["storage_pool"], []).append(
+                server.serial_number
+            )
+    for server in dc.servers():
+        for network in networks:
+            for nic in server.nic:
+                if nic in network.graph:
+                    dependencies += [
+                        (server.serial_number, peer.serial_number)
+                        for peer, _ in network.attachments(nic)
+                    ]
+        if server.labels.get("role") == "compute":
+            dependencies += [
+                (server.serial_number, storage)
+                for storage in pools.get(server.labels.get("storage_pool", ""), [])
+            ]
+    return dependencies
+
+
+def startup_plan(
+    dc: DataCenter,
+    networks: List[DataCenterNetwork],
+    dependencies: Optional[List[Tuple[str, str]]] = None,
+) -> StartupPlan:
+    """
+    Derive the startup plan of the datacenter
+    :param dc: datacenter containing the servers
+    :param networks: networks containing the network devices
+    :param dependencies: explicit dependencies, tuples of a serial number and the serial number it depends on
+    :return: StartupPlan
+    :raises: ValueError if the dependencies contain a cycle
+    """
+    graph = nx.DiGraph()
+    graph.add_nodes_from(s.serial_number for s in dc.servers())
+    for network in networks:
+        graph.add_nodes_from(
+            d.serial_number
+            for d in network.graph_view().nodes
+            if isinstance(d, (Switch, Router, Firewall))
+        )
+    for device, depends_on in _dependencies(dc, networks) + (dependencies or []):
+        graph.add_edge(depends_on, device)
+    try:
+        stages = [sorted(stage) for stage in nx.topological_generations(graph)]
+    except nx.NetworkXUnfeasible:
+        cycle = [edge[0] for edge in nx.find_cycle(graph)]
+        raise ValueError(f"Dependency cycle: {' -> '.join(cycle + cycle[:1])}")
+    return StartupPlan(stages)
+
+
+def verify_ready(
+    plan: StartupPlan,
+    stage: int,
+    dc: DataCenter,
+    networks: List[DataCenterNetwork],
+) -> List[str]:
+    """
+    Check that all devices of the stages before the given stage are up
+    :param plan: startup plan
+    :param stage: index of the stage that is about to be started
+    :param dc: datacenter containing the servers
+    :param networks: networks containing the network devices
+    :return: serial numbers of the devices of earlier stages that are not up, empty if the stage can be started
+    :raises: ValueError if the stage is not part of the plan
+    """
+    if not 0 <= stage < len(plan.stages):
+        raise ValueError(f"Stage {stage} is not part of the plan")
+    return [
+        serial_number
+        for earlier in plan.stages[:stage]
+        for serial_number in earlier
+        if dc.find_device(serial_number, networks).status != DeviceStatus.Up
+    ]
@@ -1,8 +1,63 @@
 # -*- coding: utf-8 -*-#
 """States that we are able to manage"""
 from enum import Enum, auto
+from functools import total_ordering
 
 
+@total_ordering
 class DeviceStatus(Enum):
+    """Ordered by health, Down < Up, so sorting in ascending order lists broken devices first."""
+
     Up = auto()
     Down = auto()
+
+    @classmethod
+    def default(cls) -> "DeviceStatus":
+        """Devices are assumed up until they are reported down, like devices without a status in the analyses."""
+        return cls.Up
+
+    def __lt__(self, other):
+        if self.__class__ is not other.__class__:
+            return NotImplemented
+        health = [DeviceStatus.Down, DeviceStatus.Up]
+        return health.index(self) < health.index(other)
+
+
+class LifecycleState(Enum):
+    """Administrative state of a device, independent of it being up or down."""
+
+    Provisioning = auto()
+    Active = auto()
+    Decommissioning = auto()
+    # kept in the model for history, but excluded from capacity, health and topology by default
+    Decommissioned = auto()
+
+
+LIFECYCLE_TRANSITIONS = {
+    LifecycleState.Provisioning: {
+        LifecycleState.Active,
+        LifecycleState.Decommissioning,
+    },
+    LifecycleState.Active: {LifecycleState.Decommissioning},
+    LifecycleState.Decommissioning: {
+        LifecycleState.Active,
+        LifecycleState.Decommissioned,
+    },
+    LifecycleState.Decommissioned: set(),
+}
+
+
+def transition(device, target: LifecycleState) -> None:
+    """
+    Transition the lifecycle state of a device
+    :param device: device with a lifecycle state
+    :param target: state to transition to
+    :return: None
+    :raises: ValueError if the transition is not allowed
+    """
+    if target not in LIFECYCLE_TRANSITIONS[device.lifecycle]:
+        raise ValueError(
+            f"{device.name} ({device.serial_number}) cannot transition from "
+            f"{device.lifecycle.name} to {target.name}"
+        )
+    device.lifecycle = target
@@ -0,0 +1,528 @@
+# -*- coding: utf-8 -*-#
+"""Model store
+
+The model store owns the datacenter and its networks. Mutations go through the store, they are applied under the
+store's lock, recorded in the history of the device (and the audit log, if the store has one) and published to
+subscribers as events. Readers that need a consistent view of the model should read it within store.read().
+"""
+import hashlib
+import json
+import logging
+import threading
+import time
+from contextlib import contextmanager
+from typing import Any, Callable, Dict, Iterator, List, Optional, Tuple
+
+from horao.models.audit import AuditLog, AuditRecord, Operation, ReplayDivergence
+from horao.models.hardware import DataCenter, Server
+from horao.models.network import DataCenterNetwork, Firewall, NetworkDevice, Switch
+from horao.models.osi_layers import FirewallRule
+from horao.models.quotas import Demand, Quotas
+from horao.models.status import DeviceStatus, LifecycleState
+
+
+class StatusChangeContext:
+    def __init__(
+        self, source: str, reason: str = "", timestamp: Optional[float] = None
+    ):
+        """
+        Context of a status change
+        :param source: origin of the change (e.g. 'snmp', 'gossip', a user name)
+        :param reason: free text reason
+        :param timestamp: time of the change, defaults to now
+        """
+        self.source = source
+        self.reason = reason
+        self.timestamp = time.time() if timestamp is None else timestamp
+
+
+class ModelEvent:
+    """Base class of the events published by the store."""
+
+    pass
+
+
+class StatusChanged(ModelEvent):
+    def __init__(
+        self,
+        serial_number: str,
+        previous: DeviceStatus,
+        status: DeviceStatus,
+        context: StatusChangeContext,
+    ):
+        self.serial_number = serial_number
+        self.previous = previous
+        self.status = status
+        self.context = context
+
+    def __repr__(self):
+        return f"<StatusChanged {self.serial_number} {self.previous.name}->{self.status.name}>"
+
+
+class BatchStatusChanged(ModelEvent):
+    def __init__(self, changes: List[StatusChanged]):
+        self.changes = changes
+
+
+class ModelChanged(ModelEvent):
+    """Change of the model other than a status change (links, firewall rules, tenants)."""
+
+    def __init__(self, operation: Operation, payload: Dict[str, Any]):
+        self.operation = operation
+        self.payload = payload
+
+    def __repr__(self):
+        return f"<ModelChanged {self.operation.name}>"
+
+
+class QuotaWarning(ModelEvent):
+    """A change exceeded a soft quota of a tenant, the change was applied."""
+
+    def __init__(self, operation: Operation, payload: Dict[str, Any], message: str):
+        self.operation = operation
+        self.payload = payload
+        self.message = message
+
+    def __repr__(self):
+        return f"<QuotaWarning {self.message}>"
+
+
+class BatchResult:
+    def __init__(
+        self, applied: List[StatusChanged], failures: List[Tuple[str, str]]
+    ):
+        """
+        Outcome of a batch of status changes
+        :param applied: changes that were applied
+        :param failures: serial number and reason of the changes that were rejected
+        """
+        self.applied = applied
+        self.failures = failures
+
+
+class ModelStore:
+    def __init__(
+        self,
+        datacenter: DataCenter,
+        networks: Optional[List[DataCenterNetwork]] = None,
+        audit: Optional[AuditLog] = None,
+        quotas: Optional[Quotas] = None,
+    ):
+        """
+        Store of the model
+        :param datacenter: datacenter
+        :param networks: networks of the datacenter
+        :param audit: log every mutation is recorded in, mutations are not audited if not given
+        :param quotas: quotas of the tenants consulted by the mutations assigning ownership, unlimited if not given
+        """
+        self.datacenter = datacenter
+        self.networks = networks if networks else []
+        self.audit = audit
+        self.quotas = quotas
+        self._lock = threading.RLock()
+        self._history: Dict[str, List[StatusChanged]] = {}
+        self._subscribers: List[Tuple[Callable[[ModelEvent], None], bool]] = []
+
+    @contextmanager
+    def read(self) -> Iterator["ModelStore"]:
+        """
+        Consistent view of the model, no mutations are applied while reading
+        :return: the store
+        """
+        with self._lock:
+            yield self
+
+    def subscribe(
+        self, callback: Callable[[ModelEvent], None], individual: bool = False
+    ) -> None:
+        """
+        Subscribe to the events of the store
+        :param callback: called with every event
+        :param individual: also receive the individual changes of a batch
+        :return: None
+        """
+        self._subscribers.append((callback, individual))
+
+    def _publish(self, event: ModelEvent) -> None:
+        for callback, individual in self._subscribers:
+            events = [event]
+            if individual and isinstance(event, BatchStatusChanged):
+                events += event.changes
+            for e in events:
+                try:
+                    callback(e)
+                except Exception as ex:
+                    logging.error(f"Subscriber failed on {e} ({ex})")
+
+    def history(self, serial_number: str) -> List[StatusChanged]:
+        """
+        Status changes of a device, oldest first
+        :param serial_number: serial number of the device
+        :return: list of changes
+        """
+        return list(self._history.get(serial_number, []))
+
+    def fingerprint(self) -> str:
+        """
+        Fingerprint of the model, equal models (regardless of how they came to be) have the same fingerprint
+        :return: sha256 hex digest of the (stably) serialized model
+        """
+        # imported here, serialization depends on the store for the status change context
+        from horao.models.serialization import Encoder
+
+        with self._lock:
+            document = Encoder().encode([self.datacenter, self.networks])
+        return hashlib.sha256(
+            json.dumps(document, sort_keys=True, separators=(",", ":")).encode()
+        ).hexdigest()
+
+    def _audit(
+        self,
+        actor: str,
+        operation: Operation,
+        payload: Dict[str, Any],
+        result: str = "ok",
+        timestamp: Optional[float] = None,
+    ) -> None:
+        if self.audit is not None:
+            self.audit.append(actor, operation, payload, result, timestamp)
+
+    @staticmethod
+    def _status_payload(
+        serial_number: str, status: Any, context: StatusChangeContext
+    ) -> Dict[str, Any]:
+        return {
+            "serial_number": serial_number,
+            "status": getattr(status, "name", str(status)),
+            "source": context.source,
+            "reason": context.reason,
+            "timestamp": context.timestamp,
+        }
+
+    def _validate(self, serial_number: str, status: DeviceStatus):
+        if not isinstance(status, DeviceStatus):
+            raise ValueError(f"{status} is not a device status")
+        device = self.datacenter.find_device(serial_number, self.networks)
+        if getattr(device, "lifecycle", None) == LifecycleState.Decommissioned:
+            raise ValueError(f"Device {serial_number} is decommissioned")
+        return device
+
+    def _apply(
+        self, device, status: DeviceStatus, context: StatusChangeContext
+    ) -> StatusChanged:
+        change = StatusChanged(device.serial_number, device.status, status, context)
+        device.status = status
+        self._history.setdefault(device.serial_number, []).append(change)
+        return change
+
+    def set_status(
+        self,
+        serial_number: str,
+        status: DeviceStatus,
+        context: StatusChangeContext,
+    ) -> StatusChanged:
+        """
+        Change the status of a device
+        :param serial_number: serial number of the device
+        :param status: new status
+        :param context: context of the change
+        :return: the change
+        :raises: ValueError if the device does not exist or its status cannot be changed
+        """
+        payload = self._status_payload(serial_number, status, context)
+        with self._lock:
+            try:
+                device = self._validate(serial_number, status)
+            except ValueError as e:
+                self._audit(context.source, Operation.SetStatus, payload, str(e))
+                raise
+            change = self._apply(device, status, context)
+            self._audit(context.source, Operation.SetStatus, payload)
+        self._publish(change)
+        return change
+
+    def apply_status_batch(
+        self,
+        changes: List[Tuple[str, DeviceStatus, StatusChangeContext]],
+        strict: bool = False,
+    ) -> BatchResult:
+        """
+        Apply a batch of status changes at once. All changes are validated first and applied under a single lock, a
+        single BatchStatusChanged event is published for the batch. Invalid changes are reported, by default the
+        valid changes are still applied; in strict mode nothing is applied if any change is invalid.
+        :param changes: tuples of serial number, new status and context
+        :param strict: all or nothing
+        :return: BatchResult
+        """
+        with self._lock:
+            valid = []
+            failures = []
+            rejected = []
+            for serial_number, status, context in changes:
+                try:
+                    device = self._validate(serial_number, status)
+                    valid.append((device, status, context))
+                except ValueError as e:
+                    failures.append((serial_number, str(e)))
+                    rejected.append((serial_number, status, context, str(e)))
+            if strict and failures:
+                rejected += [
+                    (d.serial_number, s, c, "batch rejected") for d, s, c in valid
+                ]
+                valid = []
+            for serial_number, status, context, reason in rejected:
+                self._audit(
+                    context.source,
+                    Operation.SetStatus,
+                    self._status_payload(serial_number, status, context),
+                    reason,
+                )
+            applied = []
+            for device, status, context in valid:
+                applied.append(self._apply(device, status, context))
+                self._audit(
+                    context.source,
+                    Operation.SetStatus,
+                    self._status_payload(device.serial_number, status, context),
+                )
+            if not applied:
+                return BatchResult([], failures)
+        if applied:
+            self._publish(BatchStatusChanged(applied))
+        return BatchResult(applied, failures)
+
+    def _network(self, name: str) -> DataCenterNetwork:
+        for network in self.networks:
+            if network.name == name:
+                return network
+        raise ValueError(f"Network {name} does not exist")
+
+    @staticmethod
+    def _network_device(
+        network: DataCenterNetwork, serial_number: str
+    ) -> NetworkDevice:
+        for device in network.graph.nodes:
+            if device.serial_number == serial_number:
+                return device
+        raise ValueError(f"Device {serial_number} is not part of {network.name}")
+
+    def _firewall(self, serial_number: str) -> Firewall:
+        for network in self.networks:
+            for device in network.graph.nodes:
+                if device.serial_number == serial_number and isinstance(
+                    device, Firewall
+                ):
+                    return device
+        raise ValueError(f"Firewall {serial_number} does not exist")
+
+    def _server(self, serial_number: str) -> Server:
+        device = self.datacenter.find_device(serial_number, self.networks)
+        if not isinstance(device, Server):
+            raise ValueError(f"Device {serial_number} is not a server")
+        return device
+
+    def _check_quotas(self, operation: Operation, payload: Dict[str, Any]) -> List[str]:
+        if self.quotas is None:
+            return []
+        if operation == Operation.SetTenant:
+            server = self._server(payload["serial_number"])
+            if server.tenant == payload["tenant"]:
+                return []
+            return self.quotas.check(
+                payload["tenant"],
+                Demand.of(server, self.networks),
+                self.datacenter,
+                self.networks,
+            )
+        if operation == Operation.Link:
+            network = self._network(payload["network"])
+            left = self._network_device(network, payload["left"])
+            right = self._network_device(network, payload["right"])
+            ports = network.link_ports(left, right)
+            for nic, peer, port in [(left, right, ports[1]), (right, left, ports[0])]:
+                owner = next(
+                    (
+                        s
+                        for s in self.datacenter.servers()
+                        if any(n is nic for n in s.nic)
+                    ),
+                    None,
+                )
+                if owner is not None and isinstance(peer, Switch) and port:
+                    return self.quotas.check(
+                        owner.tenant,
+                        Demand(ports={port.speed_gb: 1}),
+                        self.datacenter,
+                        self.networks,
+                    )
+        return []
+
+    def _mutate(
+        self, actor: str, operation: Operation, payload: Dict[str, Any]
+    ) -> None:
+        with self._lock:
+            try:
+                warnings = self._check_quotas(operation, payload)
+                self._execute(operation, payload)
+            except ValueError as e:
+                self._audit(actor, operation, payload, str(e))
+                raise
+            self._audit(actor, operation, payload)
+        self._publish(ModelChanged(operation, payload))
+        for warning in warnings:
+            logging.warning(warning)
+            self._publish(QuotaWarning(operation, payload, warning))
+
+    def _execute(self, operation: Operation, payload: Dict[str, Any]) -> None:
+        # imported here, serialization depends on the store for the status change context
+        from horao.models.serialization import Decoder
+
+        if operation == Operation.SetStatus:
+            status = DeviceStatus[payload["status"]]
+            self._apply(
+                self._validate(payload["serial_number"], status),
+                status,
+                StatusChangeContext(
+                    payload["source"], payload["reason"], payload["timestamp"]
+                ),
+            )
+        elif operation in (Operation.Link, Operation.Unlink):
+            network = self._network(payload["network"])
+            left = self._network_device(network, payload["left"])
+            right = self._network_device(network, payload["right"])
+            if operation == Operation.Link:
+                network.link(left, right)
+            elif network.graph.has_edge(left, right):
+                network.unlink(left, right)
+            else:
+                raise ValueError(f"{left.name} and {right.name} are not linked")
+        elif operation == Operation.AddRule:
+            firewall = self._firewall(payload["firewall"])
+            firewall.rules.append(Decoder().decode(payload["rule"], FirewallRule))
+        elif operation == Operation.RemoveRule:
+            firewall = self._firewall(payload["firewall"])
+            names = [r.name for r in firewall.rules]
+            if payload["name"] not in names:
+                raise ValueError(f"{firewall.name} has no rule {payload['name']}")
+            firewall.rules.pop(names.index(payload["name"]))
+        elif operation == Operation.SetTenant:
+            self._server(payload["serial_number"]).tenant = payload["tenant"]
+
+    def link(self, network: str, left: str, right: str, actor: str) -> None:
+        """
+        Link two devices of a network (see DataCenterNetwork.link)
+        :param network: name of the network
+        :param left: serial number of the left device
+        :param right: serial number of the right device
+        :param actor: who applies the change
+        :return: None
+        :raises: ValueError if the network or devices do not exist or have no free ports
+        """
+        self._mutate(
+            actor, Operation.Link, {"network": network, "left": left, "right": right}
+        )
+
+    def unlink(self, network: str, left: str, right: str, actor: str) -> None:
+        """
+        Unlink two devices of a network
+        :param network: name of the network
+        :param left: serial number of the left device
+        :param right: serial number of the right device
+        :param actor: who applies the change
+        :return: None
+        :raises: ValueError if the network or devices do not exist or are not linked
+        """
+        self._mutate(
+            actor, Operation.Unlink, {"network": network, "left": left, "right": right}
+        )
+
+    def add_firewall_rule(self, firewall: str, rule: FirewallRule, actor: str) -> None:
+        """
+        Append a rule to a firewall
+        :param firewall: serial number of the firewall
+        :param rule: rule to append
+        :param actor: who applies the change
+        :return: None
+        :raises: ValueError if the firewall does not exist
+        """
+        from horao.models.serialization import Encoder
+
+        self._mutate(
+            actor,
+            Operation.AddRule,
+            {"firewall": firewall, "rule": Encoder().encode(rule)},
+        )
+
+    def remove_firewall_rule(self, firewall: str, name: str, actor: str) -> None:
+        """
+        Remove a rule from a firewall
+        :param firewall: serial number of the firewall
+        :param name: name of the rule
+        :param actor: who applies the change
+        :return: None
+        :raises: ValueError if the firewall or the rule does not exist
+        """
+        self._mutate(
+            actor, Operation.RemoveRule, {"firewall": firewall, "name": name}
+        )
+
+    def set_tenant(self, serial_number: str, tenant: Optional[str], actor: str) -> None:
+        """
+        Assign a server to a tenant, subject to the quotas of the tenant
+        :param serial_number: serial number of the server
+        :param tenant: tenant owning the server, None to release the server
+        :param actor: who applies the change
+        :return: None
+        :raises: ValueError if the server does not exist, QuotaExceeded if a hard quota of the tenant would be exceeded
+        """
+        with self._lock:
+            previous = self._server(serial_number).tenant
+            self._mutate(
+                actor,
+                Operation.SetTenant,
+                {
+                    "serial_number": serial_number,
+                    "tenant": tenant,
+                    "previous": previous,
+                },
+            )
+
+    @classmethod
+    def replay(
+        cls,
+        records: List[AuditRecord],
+        datacenter: DataCenter,
+        networks: Optional[List[DataCenterNetwork]] = None,
+        fingerprint: Optional[str] = None,
+    ) -> "ModelStore":
+        """
+        Reapply the successful operations of an audit log onto a base model (e.g. a snapshot), which reproduces the
+        model at the end of the log. Gives a recovery path and, given the fingerprint of the current model, a
+        correctness check of the log.
+        :param records: consecutive records of the log, starting after the base model was taken
+        :param datacenter: datacenter of the base model, it is changed in place
+        :param networks: networks of the base model, they are changed in place
+        :param fingerprint: expected fingerprint of the model after the replay
+        :return: store holding the replayed model
+        :raises: ReplayDivergence if records are missing, an operation cannot be applied or the fingerprint differs
+        """
+        store = cls(datacenter, networks)
+        previous: Optional[int] = None
+        for record in sorted(records, key=lambda r: r.sequence):
+            if previous is not None and record.sequence != previous + 1:
+                raise ReplayDivergence(
+                    record.sequence,
+                    f"records {previous + 1} to {record.sequence - 1} are missing",
+                )
+            previous = record.sequence
+            if not record.succeeded:
+                continue
+            try:
+                store._execute(record.operation, record.payload)
+            except (KeyError, TypeError, ValueError) as e:
+                raise ReplayDivergence(record.sequence, f"cannot be applied ({e})")
+        if fingerprint is not None and store.fingerprint() != fingerprint:
+            raise ReplayDivergence(
+                previous, "fingerprint of the replayed model differs"
+            )
+        return store
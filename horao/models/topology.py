@@ -0,0 +1,376 @@
+# -*- coding: utf-8 -*-#
+"""Topology detection
+
+Every topology is recognized by a TopologyDetector, the DetectorRegistry runs the detectors on the network and
+resolves the outcome by priority: the matching detector with the lowest priority value wins, the other matching
+detectors are reported as alternatives. The built-in detectors, in order of priority:
+
+- tree (100): the network, including the attached devices, is a tree
+- fat-tree (200): the switches form a k-ary fat tree (see FatTreeDetector)
+
+Detectors of in-house topologies can be registered on a registry, built-in detectors can be disabled by name.
+
+The fat-tree detector can tolerate a number of missing or miswired links (max_violations), the tolerated violations are
+reported on the result.
+
+Detection can be bounded by an AnalysisBudget: the budget is checked between the detectors and while a detector walks
+the switches (see TopologyInput.checkpoint), a detection that runs out of budget reports Undefined and timed_out.
+
+When no detector matches, detect_with_fallback guesses the topology from the dominant structural features of the
+switches, together with the confidence of the guess:
+
+- Exact: a detector matched
+- Likely: the switches (leaving out the attached devices) form a tree
+- Guess: most switches below the core have a single uplink (tree) or multiple uplinks (fat tree) to a higher tier
+- Unknown: there are no features to go by
+"""
+from abc import ABC, abstractmethod
+from collections import Counter
+from enum import Enum, auto
+from typing import Dict, Iterable, List, Optional, Set, Tuple
+
+import networkx as nx  # type: ignore
+
+from horao.models.budget import BudgetExceeded, BudgetMeter
+from horao.models.network import NetworkDevice, NetworkTopology, Switch, SwitchType
+from horao.models.osi_layers import Link
+
+
+class TopologyInput:
+    def __init__(
+        self,
+        graph: nx.Graph,
+        switches: List[Switch],
+        links: List[Tuple[NetworkDevice, NetworkDevice, Link]],
+        tiers: Dict[Switch, SwitchType],
+        meter: Optional[BudgetMeter] = None,
+    ):
+        """
+        Input of the detectors
+        :param graph: graph of the network, including the attached devices
+        :param switches: switches of the network
+        :param links: links between the switches
+        :param tiers: tier of every switch
+        :param meter: budget of the detection, unlimited if not given
+        """
+        self.graph = graph
+        self.switches = switches
+        self.links = links
+        self.tiers = tiers
+        self.meter = meter
+
+    @classmethod
+    def of(
+        cls, graph: nx.Graph, meter: Optional[BudgetMeter] = None
+    ) -> "TopologyInput":
+        """
+        Input of the detectors for a network graph, the tier of a switch is inferred from its type
+        :param graph: graph of the network
+        :param meter: budget of the detection, unlimited if not given
+        :return: TopologyInput
+        :raises: BudgetExceeded if the budget is exceeded
+        """
+        switches = [n for n in graph.nodes if isinstance(n, Switch)]
+        topology = cls(
+            graph,
+            switches,
+            list(graph.subgraph(switches).edges.data("link")),
+            {s: s.switch_type for s in switches},
+            meter,
+        )
+        topology.checkpoint(graph.number_of_nodes())
+        return topology
+
+    def checkpoint(self, count: int = 1) -> None:
+        """
+        Account for visited nodes, detectors call this while traversing the graph
+        :param count: number of nodes visited since the last call
+        :return: None
+        :raises: BudgetExceeded if the budget is exceeded
+        """
+        if self.meter is not None:
+            self.meter.visit(count)
+
+
+class Confidence(Enum):
+    Exact = auto()
+    Likely = auto()
+    Guess = auto()
+    Unknown = auto()
+
+
+# rank of the tiers, a lower rank is a higher tier
+TIER_RANKS = {SwitchType.Core: 0, SwitchType.Distribution: 1, SwitchType.Access: 2}
+
+
+class DetectionResult:
+    def __init__(
+        self,
+        topology: NetworkTopology,
+        detail: str = "",
+        violations: Optional[List[str]] = None,
+    ):
+        """
+        Topology recognized by a detector
+        :param topology: detected topology
+        :param detail: parameters of the topology (e.g. 'k=4')
+        :param violations: deviations from the topology that were tolerated
+        """
+        self.topology = topology
+        self.detail = detail
+        self.violations = violations if violations else []
+
+    def __repr__(self):
+        return f"<DetectionResult {self.topology.name} {self.detail}>"
+
+
+class TopologyDetector(ABC):
+    @property
+    @abstractmethod
+    def name(self) -> str:
+        pass
+
+    @abstractmethod
+    def detect(self, topology: TopologyInput) -> Optional[DetectionResult]:
+        """
+        Recognize the topology
+        :param topology: input of the detection
+        :return: DetectionResult, None if the topology is not recognized
+        """
+        pass
+
+
+class TreeDetector(TopologyDetector):
+    name = "tree"
+
+    def detect(self, topology: TopologyInput) -> Optional[DetectionResult]:
+        if topology.graph.number_of_nodes() == 0 or not nx.is_tree(topology.graph):
+            return None
+        return DetectionResult(NetworkTopology.Tree)
+
+
+class FatTreeDetector(TopologyDetector):
+    """
+    A (k-ary) fat tree consists of k pods of k/2 access and k/2 distribution switches, with every access switch linked
+    to all distribution switches of its pod, and (k/2)^2 core switches that each link to a single distribution switch
+    in every pod. Devices attached to the switches (e.g. NICs) are not taken into account.
+
+    Real fabrics are not always wired perfectly, a detector with max_violations tolerates up to that many missing or
+    miswired links and reports them on the result. The number of switches per tier and pod is never relaxed.
+    """
+
+    name = "fat-tree"
+
+    def __init__(self, max_violations: int = 0):
+        """
+        Fat tree detector
+        :param max_violations: number of missing or miswired links to tolerate
+        :raises: ValueError if max_violations is negative
+        """
+        if max_violations < 0:
+            raise ValueError("max_violations cannot be negative")
+        self.max_violations = max_violations
+
+    def detect(self, topology: TopologyInput) -> Optional[DetectionResult]:
+        graph = nx.Graph()
+        graph.add_nodes_from(topology.switches)
+        graph.add_edges_from((left, right) for left, right, _ in topology.links)
+        tiers: Dict[SwitchType, List[Switch]] = {t: [] for t in SwitchType}
+        for switch in topology.switches:
+            tiers[topology.tiers[switch]].append(switch)
+        k = round(len(tiers[SwitchType.Core]) ** 0.5) * 2
+        if (
+            k < 2
+            or len(tiers[SwitchType.Core]) != (k // 2) ** 2
+            or len(tiers[SwitchType.Distribution]) != k * k // 2
+            or len(tiers[SwitchType.Access]) != k * k // 2
+        ):
+            return None
+        pod_switches = tiers[SwitchType.Access] + tiers[SwitchType.Distribution]
+        pods = list(nx.connected_components(graph.subgraph(pod_switches)))
+        if len(pods) != k or any(
+            Counter(topology.tiers[s] for s in pod)
+            != Counter({SwitchType.Access: k // 2, SwitchType.Distribution: k // 2})
+            for pod in pods
+        ):
+            return None
+        pod_of = {s: i for i, pod in enumerate(pods) for s in pod}
+        # pods are named after their first switch
+        pod_names = [min(s.serial_number for s in pod) for pod in pods]
+        allowed = {
+            frozenset((SwitchType.Access, SwitchType.Distribution)),
+            frozenset((SwitchType.Distribution, SwitchType.Core)),
+        }
+        violations = [
+            f"unexpected link {left.serial_number} - {right.serial_number}"
+            for left, right in graph.edges
+            if frozenset((topology.tiers[left], topology.tiers[right])) not in allowed
+        ]
+        for switch in topology.switches:
+            topology.checkpoint()
+            neighbors = list(graph.neighbors(switch))
+            if topology.tiers[switch] == SwitchType.Access:
+                pod = sorted(pods[pod_of[switch]], key=lambda s: s.serial_number)
+                violations.extend(
+                    f"missing link {switch.serial_number} - {peer.serial_number}"
+                    for peer in pod
+                    if topology.tiers[peer] == SwitchType.Distribution
+                    and peer not in neighbors
+                )
+            elif topology.tiers[switch] == SwitchType.Distribution:
+                # missing core links are reported by the core switch
+                cores = [n for n in neighbors if topology.tiers[n] == SwitchType.Core]
+                if len(cores) > k // 2:
+                    violations.append(
+                        f"{switch.serial_number} has {len(cores)} core links instead of {k // 2}"
+                    )
+            else:
+                linked = Counter(
+                    pod_of[n]
+                    for n in neighbors
+                    if topology.tiers[n] == SwitchType.Distribution
+                )
+                violations.extend(
+                    f"{switch.serial_number} has {linked[pod]} links to the pod of {pod_names[pod]}"
+                    for pod in range(k)
+                    if linked[pod] != 1
+                )
+            if len(violations) > self.max_violations:
+                return None
+        return DetectionResult(NetworkTopology.FatTree, f"k={k}", violations)
+
+
+class DetectionReport:
+    def __init__(
+        self,
+        result: DetectionResult,
+        detector: Optional[str],
+        alternatives: List[Tuple[str, DetectionResult]],
+        timed_out: bool = False,
+    ):
+        """
+        Outcome of running the detectors
+        :param result: winning result, Undefined if no detector matched
+        :param detector: name of the winning detector
+        :param alternatives: names and results of the other detectors that matched, in order of priority
+        :param timed_out: the detection ran out of budget before all detectors ran, the result is Undefined
+        """
+        self.result = result
+        self.detector = detector
+        self.alternatives = alternatives
+        self.timed_out = timed_out
+
+
+class DetectorRegistry:
+    def __init__(self):
+        self._detectors: List[Tuple[int, TopologyDetector]] = []
+        self._disabled: Set[str] = set()
+
+    @classmethod
+    def default(
+        cls, disabled: Optional[Iterable[str]] = None, max_violations: int = 0
+    ) -> "DetectorRegistry":
+        """
+        Registry with the built-in detectors
+        :param disabled: names of the built-in detectors to leave out
+        :param max_violations: number of missing or miswired links the fat-tree detector tolerates
+        :return: DetectorRegistry
+        """
+        registry = cls()
+        registry.register(TreeDetector(), 100)
+        registry.register(FatTreeDetector(max_violations), 200)
+        for name in disabled or []:
+            registry.disable(name)
+        return registry
+
+    def register(self, detector: TopologyDetector, priority: int = 0) -> None:
+        """
+        Add a detector, detectors with a lower priority value win
+        :param detector: detector to add
+        :param priority: priority of the detector, ahead of the built-in detectors by default
+        :return: None
+        :raises: ValueError if a detector with the same name is already registered
+        """
+        if any(d.name == detector.name for _, d in self._detectors):
+            raise ValueError(f"Detector {detector.name} is already registered")
+        self._detectors.append((priority, detector))
+        self._detectors.sort(key=lambda d: d[0])
+
+    def disable(self, name: str) -> None:
+        """
+        Disable a detector
+        :param name: name of the detector
+        :return: None
+        :raises: ValueError if there is no detector with the name
+        """
+        if not any(d.name == name for _, d in self._detectors):
+            raise ValueError(f"Detector {name} is not registered")
+        self._disabled.add(name)
+
+    @property
+    def detectors(self) -> List[str]:
+        """
+        Names of the enabled detectors in order of priority
+        :return: list of names
+        """
+        return [d.name for _, d in self._detectors if d.name not in self._disabled]
+
+    def detect(self, topology: TopologyInput) -> DetectionReport:
+        """
+        Run all enabled detectors and resolve the matches by priority
+        :param topology: input of the detection
+        :return: DetectionReport, Undefined and timed_out if the budget of the input is exceeded
+        """
+        matches = []
+        for _, detector in self._detectors:
+            if detector.name in self._disabled:
+                continue
+            try:
+                topology.checkpoint()
+                result = detector.detect(topology)
+            except BudgetExceeded:
+                return DetectionReport(
+                    DetectionResult(NetworkTopology.Undefined), None, [], True
+                )
+            if result is not None:
+                matches.append((detector.name, result))
+        if not matches:
+            return DetectionReport(DetectionResult(NetworkTopology.Undefined), None, [])
+        (name, result), alternatives = matches[0], matches[1:]
+        return DetectionReport(result, name, alternatives)
+
+    def detect_with_fallback(
+        self, topology: TopologyInput
+    ) -> Tuple[NetworkTopology, Confidence]:
+        """
+        Run all enabled detectors, guess the topology from the structure of the switches if none matched
+        :param topology: input of the detection
+        :return: tuple of the topology and the confidence in it
+        """
+        report = self.detect(topology)
+        if report.result.topology != NetworkTopology.Undefined:
+            return report.result.topology, Confidence.Exact
+        return _guess(topology)
+
+
+def _guess(topology: TopologyInput) -> Tuple[NetworkTopology, Confidence]:
+    graph = nx.Graph()
+    graph.add_nodes_from(topology.switches)
+    graph.add_edges_from((left, right) for left, right, _ in topology.links)
+    if graph.number_of_nodes() > 1 and nx.is_tree(graph):
+        return NetworkTopology.Tree, Confidence.Likely
+    uplinks: Counter = Counter()
+    for switch in topology.switches:
+        rank = TIER_RANKS[topology.tiers[switch]]
+        if rank == 0:
+            continue
+        count = sum(
+            1 for n in graph.neighbors(switch) if TIER_RANKS[topology.tiers[n]] < rank
+        )
+        if count:
+            uplinks["multiple" if count > 1 else "single"] += 1
+    if not uplinks or uplinks["multiple"] == uplinks["single"]:
+        return NetworkTopology.Undefined, Confidence.Unknown
+    if uplinks["multiple"] > uplinks["single"]:
+        return NetworkTopology.FatTree, Confidence.Guess
+    return NetworkTopology.Tree, Confidence.Guess
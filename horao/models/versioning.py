@@ -0,0 +1,106 @@
+# -*- coding: utf-8 -*-#
+"""Model schema versioning
+
+The model schema version is expressed as 'major.minor'. Data written with the same major version can always be read,
+a newer minor version only adds optional information that older readers can ignore. A change of major version means
+that the data is no longer readable by older (or newer) tools.
+
+Every gossip message carries the schema version of its sender, messages of peers with a schema version that cannot be
+read are dropped (and logged) instead of being merged into the state.
+
+Peers exchange their VersionInfo in the gossip handshake: the version of the package (kept in sync with setup.py),
+the git commit it was built from and the version of the gossip protocol. The commit is written to build_info.py when
+the package is built, a checkout falls back to asking git.
+"""
+import os
+import subprocess
+from typing import Optional, Tuple
+
+MODEL_SCHEMA_VERSION = "1.0"
+PACKAGE_VERSION = "1.0.0"
+GOSSIP_PROTOCOL_VERSION = 1
+
+
+class VersionInfo:
+    def __init__(
+        self,
+        package_version: str,
+        git_hash: Optional[str],
+        gossip_protocol_version: int,
+        schema_version: str = MODEL_SCHEMA_VERSION,
+    ):
+        """
+        Versions of this peer
+        :param package_version: version of the package
+        :param git_hash: commit the package was built from, None if unknown
+        :param gossip_protocol_version: version of the gossip protocol
+        :param schema_version: model schema version
+        """
+        self.package_version = package_version
+        self.git_hash = git_hash
+        self.gossip_protocol_version = gossip_protocol_version
+        self.schema_version = schema_version
+
+    def to_dict(self) -> dict:
+        return {
+            "package_version": self.package_version,
+            "git_hash": self.git_hash,
+            "gossip_protocol_version": self.gossip_protocol_version,
+            "schema_version": self.schema_version,
+        }
+
+
+def _git_hash() -> Optional[str]:
+    try:
+        from horao.build_info import GIT_HASH  # type: ignore
+
+        return GIT_HASH
+    except ImportError:
+        pass
+    try:
+        return (
+            subprocess.run(
+                ["git", "rev-parse", "HEAD"],
+                cwd=os.path.dirname(__file__),
+                capture_output=True,
+                check=True,
+                text=True,
+            ).stdout.strip()
+            or None
+        )
+    except (OSError, subprocess.CalledProcessError):
+        return None
+
+
+def version_info() -> VersionInfo:
+    """
+    Versions of this peer, for the gossip handshake and debugging
+    :return: VersionInfo
+    """
+    return VersionInfo(PACKAGE_VERSION, _git_hash(), GOSSIP_PROTOCOL_VERSION)
+
+
+def parse_version(version: str) -> Optional[Tuple[int, int]]:
+    """
+    Parse a 'major.minor' version string
+    :param version: version to parse
+    :return: tuple of major and minor, None if the version is malformed
+    """
+    major, _, minor = version.partition(".")
+    if not major.isdigit() or not minor.isdigit():
+        return None
+    return int(major), int(minor)
+
+
+def can_read(peer_version: str, schema_version: str = MODEL_SCHEMA_VERSION) -> bool:
+    """
+    Check if data from a peer (or a snapshot) with the given schema version can be read
+    :param peer_version: schema version of the peer
+    :param schema_version: schema version of the reader, this version by default
+    :return: True if the major versions match, False otherwise (or if the version is malformed)
+    """
+    ours = parse_version(schema_version)
+    theirs = parse_version(peer_version)
+    if ours is None or theirs is None:
+        return False
+    return ours[0] == theirs[0]
@@ -0,0 +1,192 @@
+# -*- coding: utf-8 -*-#
+"""Scoped views of the model
+
+A scoped view is a read-only facade over the model store that only exposes a slice of the model, e.g. the servers of
+a single tenant or all network devices without cost data. References that cross the boundary of the scope (a server
+attached to a switch the scope cannot see) are represented as opaque stubs.
+"""
+import hashlib
+import secrets
+from enum import Enum, auto
+from typing import Any, Dict, List, Optional
+
+from horao.models.hardware import InventorySummary, Server
+from horao.models.network import Firewall, NetworkDevice, Router, Switch
+from horao.models.serialization import Encoder
+from horao.models.store import ModelStore
+from horao.settings import CapacityUnits
+
+
+class EntityKind(Enum):
+    Server = auto()
+    Switch = auto()
+    Router = auto()
+    Firewall = auto()
+
+
+def _kind(entity) -> Optional[EntityKind]:
+    for kind, cls in [
+        (EntityKind.Server, Server),
+        (EntityKind.Switch, Switch),
+        (EntityKind.Router, Router),
+        (EntityKind.Firewall, Firewall),
+    ]:
+        if isinstance(entity, cls):
+            return kind
+    return None
+
+
+class Scope:
+    def __init__(
+        self,
+        tenants: Optional[List[str]] = None,
+        labels: Optional[Dict[str, str]] = None,
+        kinds: Optional[List[EntityKind]] = None,
+        redact: Optional[List[str]] = None,
+    ):
+        """
+        Slice of the model, an entity is in scope if it matches all given criteria. A scope without any criteria
+        sees nothing.
+        :param tenants: tenants owning the entity
+        :param labels: labels the entity should have
+        :param kinds: kinds of entities
+        :param redact: fields that are left out of the output (e.g. cost, serial_number)
+        """
+        self.tenants = tenants
+        self.labels = labels
+        self.kinds = kinds
+        self.redact = redact if redact else []
+
+    def is_empty(self) -> bool:
+        return self.tenants is None and self.labels is None and self.kinds is None
+
+    def contains(self, entity) -> bool:
+        """
+        Check if an entity is in scope
+        :param entity: server or network device
+        :return: True if in scope
+        """
+        if self.is_empty():
+            return False
+        kind = _kind(entity)
+        if kind is None or (self.kinds is not None and kind not in self.kinds):
+            return False
+        tenant = getattr(entity, "tenant", None)
+        if self.tenants is not None and tenant not in self.tenants:
+            return False
+        labels = getattr(entity, "labels", {})
+        return all(labels.get(k) == v for k, v in (self.labels or {}).items())
+
+    def to_dict(self) -> dict:
+        return {
+            "tenants": self.tenants,
+            "labels": self.labels,
+            "kinds": [k.name for k in self.kinds] if self.kinds is not None else None,
+            "redact": self.redact,
+        }
+
+    @staticmethod
+    def from_dict(data: Dict[str, Any]) -> "Scope":
+        """
+        Scope from its dict representation (e.g. configured per API token)
+        :param data: dict as produced by to_dict
+        :return: Scope
+        :raises: ValueError on unknown entity kinds
+        """
+        kinds = data.get("kinds")
+        if kinds is not None and any(k not in EntityKind.__members__ for k in kinds):
+            raise ValueError(f"Unknown entity kind in {kinds}")
+        return Scope(
+            data.get("tenants"),
+            data.get("labels"),
+            [EntityKind[k] for k in kinds] if kinds is not None else None,
+            data.get("redact"),
+        )
+
+
+class ScopedView:
+    def __init__(self, store: ModelStore, scope: Scope):
+        self.store = store
+        self.scope = scope
+        self._salt = secrets.token_bytes(16)
+
+    def stub(self, entity) -> Dict[str, str]:
+        """
+        Opaque representation of an entity outside of the scope, stable within the view
+        :param entity: entity to represent
+        :return: stub
+        """
+        digest = hashlib.sha256(self._salt + entity.serial_number.encode("utf-8"))
+        return {"opaque": digest.hexdigest()[:16]}
+
+    def servers(self) -> List[Server]:
+        with self.store.read():
+            servers = self.store.datacenter.servers()
+            return [s for s in servers if self.scope.contains(s)]
+
+    def network_devices(self) -> List[NetworkDevice]:
+        with self.store.read():
+            devices: Dict[int, NetworkDevice] = {}
+            for network in self.store.networks:
+                for device in network.graph_view().nodes:
+                    if self.scope.contains(device):
+                        devices[id(device)] = device
+            return list(devices.values())
+
+    def summary(self, units: Optional[CapacityUnits] = None) -> InventorySummary:
+        """
+        Compute and storage capacity of the servers in scope
+        :param units: units to report the capacity in, cores and GB by default
+        :return: InventorySummary
+        """
+        return InventorySummary.of(self.servers(), units)
+
+    def attachments(self, server: Server) -> List[Dict[str, Any]]:
+        """
+        Ports a server in scope is attached to, peers outside of the scope are represented as stubs
+        :param server: server in scope
+        :return: list of dicts with nic, peer and port (left out for stubs)
+        :raises: ValueError if the server is not in scope
+        """
+        if not self.scope.contains(server):
+            raise ValueError("Server is not in scope")
+        attachments = []
+        with self.store.read():
+            for network in self.store.networks:
+                for nic in server.nic:
+                    if nic not in network.graph:
+                        continue
+                    for peer, port in network.attachments(nic):
+                        if self.scope.contains(peer):
+                            entry = {"peer": peer.name, "port": port.name}
+                        else:
+                            entry = {"peer": self.stub(peer)}
+                        attachments.append(
+                            dict(self._redacted({"nic": nic.name}), **entry)
+                        )
+        return attachments
+
+    def _redacted(self, data: Any) -> Any:
+        if isinstance(data, list):
+            return [self._redacted(v) for v in data]
+        if isinstance(data, dict):
+            return {
+                k: self._redacted(v)
+                for k, v in data.items()
+                if k not in self.scope.redact
+            }
+        return data
+
+    def export(self) -> Dict[str, Any]:
+        """
+        Export of the entities in scope with the redacted fields left out
+        :return: JSON compatible dict with servers, network devices and server attachments
+        """
+        servers = self.servers()
+        return {
+            "servers": [self._redacted(Encoder().encode(s)) for s in servers],
+            "network_devices": [
+                self._redacted(Encoder().encode(d)) for d in self.network_devices()
+            ],
+            "attachments": {s.name: self.attachments(s) for s in servers},
+        }
@@ -0,0 +1,212 @@
+# -*- coding: utf-8 -*-#
+"""Security zones
+
+Classification of the servers by the firewalls that mediate the traffic from outside the datacenter. Traffic enters
+the fabric through the edge routers (RouterType.Edge with WAN ports), a firewall mediates the traffic that enters it
+on one of its WAN ports and leaves it on one of its LAN ports (or vice versa). Traffic that passes a firewall from LAN
+port to LAN port is not mediated, the firewall merely forwards it. A server is:
+
+- Protected: every path from an edge router to the server is mediated by a firewall
+- Exposed: at least one path from an edge router reaches the server without being mediated
+- Isolated: there is no path from an edge router to the server at all
+
+For protected servers the firewalls that every path has to traverse are reported, for exposed servers an example of
+an unmediated path. Servers are part of the network through their NICs.
+"""
+from enum import Enum, auto
+from typing import Dict, List, Optional, Set, Tuple, Union
+
+import networkx as nx  # type: ignore
+
+from horao.models.hardware import DataCenter, Server
+from horao.models.network import (
+    DataCenterNetwork,
+    Firewall,
+    NetworkDevice,
+    Router,
+    RouterType,
+)
+
+# (firewall, side) node of the analysis graph
+_Side = Tuple[Firewall, str]
+_Node = Union[NetworkDevice, _Side]
+
+
+class Zone(Enum):
+    Protected = auto()
+    Exposed = auto()
+    Isolated = auto()
+
+
+class ServerZone:
+    def __init__(
+        self,
+        serial_number: str,
+        name: str,
+        zone: Zone,
+        firewalls: Optional[List[str]] = None,
+        example_path: Optional[List[str]] = None,
+    ):
+        """
+        Zone of a server
+        :param serial_number: serial number of the server
+        :param name: name of the server
+        :param zone: zone of the server
+        :param firewalls: serial numbers of the firewalls every path to a protected server traverses
+        :param example_path: serial numbers of the devices on an unmediated path to an exposed server
+        """
+        self.serial_number = serial_number
+        self.name = name
+        self.zone = zone
+        self.firewalls = firewalls if firewalls else []
+        self.example_path = example_path if example_path else []
+
+    def to_dict(self) -> dict:
+        return {
+            "serial_number": self.serial_number,
+            "name": self.name,
+            "zone": self.zone.name.lower(),
+            "firewalls": self.firewalls,
+            "example_path": self.example_path,
+        }
+
+    def __repr__(self):
+        return f"<ServerZone {self.serial_number} {self.zone.name}>"
+
+
+class ZoneReport:
+    def __init__(
+        self, network: str, edge_routers: List[str], servers: List[ServerZone]
+    ):
+        """
+        Zones of the servers of a network
+        :param network: name of the network
+        :param edge_routers: serial numbers of the edge routers the paths start at
+        :param servers: zone of every server
+        """
+        self.network = network
+        self.edge_routers = edge_routers
+        self.servers = servers
+
+    def of(self, zone: Zone) -> List[ServerZone]:
+        """
+        Servers in a zone
+        :param zone: zone to select
+        :return: list of server zones
+        """
+        return [s for s in self.servers if s.zone == zone]
+
+    def to_dict(self) -> dict:
+        return {
+            "network": self.network,
+            "edge_routers": self.edge_routers,
+            "servers": [s.to_dict() for s in self.servers],
+        }
+
+
+def _side(network: DataCenterNetwork, device: NetworkDevice, link) -> _Node:
+    if not isinstance(device, Firewall):
+        return device
+    port = network.peer_port(link, device)
+    return device, "wan" if port in (device.wan_ports or []) else "lan"
+
+
+def _analysis_graph(
+    network: DataCenterNetwork, view: nx.Graph
+) -> Tuple[nx.Graph, List[Tuple[_Side, _Side]]]:
+    """
+    Graph of the network with every firewall split in a WAN and a LAN side, a path through a firewall is mediated if
+    it crosses from one side to the other
+    :return: graph and the edges between the sides of the firewalls
+    """
+    graph = nx.Graph()
+    crossings = []
+    for device in view.nodes:
+        if isinstance(device, Firewall):
+            crossing = ((device, "wan"), (device, "lan"))
+            graph.add_edge(*crossing)
+            crossings.append(crossing)
+        else:
+            graph.add_node(device)
+    for left, right, link in view.edges.data("link"):
+        graph.add_edge(_side(network, left, link), _side(network, right, link))
+    return graph, crossings
+
+
+def _serial(node: _Node) -> str:
+    return node[0].serial_number if isinstance(node, tuple) else node.serial_number
+
+
+def _reachable(graph: nx.Graph, sources: List[_Node], targets: Set[_Node]) -> bool:
+    return any(
+        s in graph and t in graph and nx.has_path(graph, s, t)
+        for s in sources
+        for t in targets
+    )
+
+
+def _classify(
+    server: Server,
+    nics: Set[_Node],
+    edge_routers: List[_Node],
+    graph: nx.Graph,
+    unmediated: nx.Graph,
+    firewalls: List[Firewall],
+) -> ServerZone:
+    paths: Dict[int, List[_Node]] = {}
+    for router in edge_routers:
+        for nic in nics:
+            if nx.has_path(unmediated, router, nic):
+                path = nx.shortest_path(unmediated, router, nic)
+                paths.setdefault(len(path), path)
+    if paths:
+        example: List[str] = []
+        for node in paths[min(paths)]:
+            if not example or example[-1] != _serial(node):
+                example.append(_serial(node))
+        return ServerZone(
+            server.serial_number, server.name, Zone.Exposed, example_path=example
+        )
+    if not _reachable(graph, edge_routers, nics):
+        return ServerZone(server.serial_number, server.name, Zone.Isolated)
+    required = []
+    for firewall in firewalls:
+        without = graph.copy()
+        without.remove_nodes_from([(firewall, "wan"), (firewall, "lan")])
+        if not _reachable(without, edge_routers, nics):
+            required.append(firewall.serial_number)
+    return ServerZone(
+        server.serial_number, server.name, Zone.Protected, firewalls=sorted(required)
+    )
+
+
+def server_zones(
+    dc: DataCenter, network: DataCenterNetwork, ignore_status: bool = False
+) -> ZoneReport:
+    """
+    Classify the servers of the datacenter that are attached to the network into security zones
+    :param dc: datacenter containing the servers
+    :param network: network containing the routers, firewalls and links
+    :param ignore_status: treat devices and links that are down as up, so an outage does not change the zones
+    :return: ZoneReport
+    """
+    view = network.graph_view() if ignore_status else network.up_view()
+    graph, crossings = _analysis_graph(network, view)
+    edge_routers: List[_Node] = [
+        n
+        for n in view.nodes
+        if isinstance(n, Router) and n.router_type == RouterType.Edge and n.wan_ports
+    ]
+    unmediated = graph.copy()
+    unmediated.remove_edges_from(crossings)
+    firewalls = [n for n in view.nodes if isinstance(n, Firewall)]
+    servers: List[ServerZone] = []
+    for server in dc.servers():
+        nics: Set[_Node] = {n for n in server.nic if n in graph}
+        if nics:
+            servers.append(
+                _classify(server, nics, edge_routers, graph, unmediated, firewalls)
+            )
+    return ZoneReport(
+        network.name, sorted(r.serial_number for r in edge_routers), servers
+    )
@@ -0,0 +1,2 @@
+# -*- coding: utf-8 -*-#
+"""Parsers for configuration formats of third parties."""
@@ -0,0 +1,315 @@
+# -*- coding: utf-8 -*-#
+"""Vendor configuration parser
+
+This module imports the relevant parts of Cisco/JunOS-style (IOS-like) text configurations into the model. Only a
+documented subset is supported:
+
+    interface Ethernet1
+     description uplink to core
+     speed 10000                                  (Mb/s, or with a G suffix: 10G)
+     switchport access vlan 100
+     switchport trunk allowed vlan 100,200-210
+     ip address 10.0.0.1 255.255.255.0            (or 10.0.0.1/24)
+     shutdown | no shutdown
+    !
+    ip route 10.1.0.0 255.255.0.0 10.0.0.254 [metric]
+    access-list 101 permit tcp 10.0.0.0 0.0.0.255 any eq 80
+    access-list 101 permit tcp any any range 8000 8100
+    ip access-list extended WEB
+     10 permit tcp any host 10.1.0.5 eq 443
+     deny ip any any
+
+Addresses in ACL entries are 'any', 'host <address>' or '<address> <wildcard mask>', the wildcard mask is converted
+to a netmask. The parser is line oriented, lines that are not understood are collected with their line number instead
+of aborting the import.
+"""
+import ipaddress
+from typing import Dict, List, Optional, Tuple, Union
+
+from horao.models.network import Firewall, Router, Switch
+from horao.models.osi_layers import (
+    FirewallRule,
+    IpAddress,
+    Port,
+    PortMatcher,
+    Protocol,
+    Route,
+)
+
+PROTOCOLS = {"tcp": Protocol.TCP, "udp": Protocol.UDP, "icmp": Protocol.ICMP}
+ACTIONS = {"permit": "allow", "deny": "deny"}
+IGNORED = ("!", "end", "remark", "switchport mode", "speed auto")
+
+
+class InterfaceConfig:
+    def __init__(
+        self,
+        name: str,
+        description: str = "",
+        speed_gb: Optional[int] = None,
+        access_vlan: Optional[int] = None,
+        trunk_vlans: Optional[List[int]] = None,
+        address: Optional[IpAddress] = None,
+        shutdown: Optional[bool] = None,
+    ):
+        """
+        Interface block of a configuration, attributes that are not configured are None
+        :param name: name of the interface, matches the name of a port
+        :param shutdown: True if administratively down, False if explicitly enabled
+        """
+        self.name = name
+        self.description = description
+        self.speed_gb = speed_gb
+        self.access_vlan = access_vlan
+        self.trunk_vlans = trunk_vlans
+        self.address = address
+        self.shutdown = shutdown
+
+
+class UnknownLine:
+    def __init__(self, number: int, text: str, reason: str):
+        self.number = number
+        self.text = text
+        self.reason = reason
+
+    def __repr__(self):
+        return f"<UnknownLine {self.number}: {self.text} ({self.reason})>"
+
+
+class VendorConfig:
+    def __init__(
+        self,
+        interfaces: Optional[List[InterfaceConfig]] = None,
+        routes: Optional[List[Route]] = None,
+        rules: Optional[List[FirewallRule]] = None,
+        unknown: Optional[List[UnknownLine]] = None,
+    ):
+        self.interfaces = interfaces if interfaces else []
+        self.routes = routes if routes else []
+        self.rules = rules if rules else []
+        self.unknown = unknown if unknown else []
+
+
+def wildcard_to_netmask(wildcard: str) -> str:
+    """
+    Convert an ACL wildcard mask to a netmask (0.0.0.255 becomes 255.255.255.0)
+    :param wildcard: wildcard mask
+    :return: netmask
+    :raises: ValueError if the wildcard is not a valid, contiguous mask
+    """
+    inverted = int(ipaddress.IPv4Address(wildcard)) ^ 0xFFFFFFFF
+    netmask = str(ipaddress.IPv4Address(inverted))
+    # raises when the mask is not contiguous
+    ipaddress.IPv4Network(f"0.0.0.0/{netmask}")
+    return netmask
+
+
+def _address(tokens: List[str]) -> Tuple[IpAddress, List[str]]:
+    if not tokens:
+        raise ValueError("missing address")
+    if tokens[0] == "any":
+        return IpAddress("0.0.0.0", "0.0.0.0", ""), tokens[1:]
+    if tokens[0] == "host" and len(tokens) > 1:
+        ipaddress.IPv4Address(tokens[1])
+        return IpAddress(tokens[1], "255.255.255.255", ""), tokens[2:]
+    if len(tokens) < 2:
+        raise ValueError(f"missing wildcard mask for {tokens[0]}")
+    ipaddress.IPv4Address(tokens[0])
+    return IpAddress(tokens[0], wildcard_to_netmask(tokens[1]), ""), tokens[2:]
+
+
+def _port(tokens: List[str]) -> Tuple[Optional[PortMatcher], List[str]]:
+    if not tokens or tokens[0] not in ("eq", "range"):
+        return None, tokens
+    if tokens[0] == "range":
+        if len(tokens) < 3 or not tokens[1].isdigit() or not tokens[2].isdigit():
+            raise ValueError("port range should be two numeric ports")
+        return PortMatcher.range(int(tokens[1]), int(tokens[2])), tokens[3:]
+    if len(tokens) < 2 or not tokens[1].isdigit():
+        raise ValueError("port should be numeric")
+    return PortMatcher.single(int(tokens[1])), tokens[2:]
+
+
+def _rule(name: str, tokens: List[str]) -> FirewallRule:
+    if not tokens or tokens[0] not in ACTIONS:
+        raise ValueError("expected permit or deny")
+    action, tokens = ACTIONS[tokens[0]], tokens[1:]
+    protocol: Optional[Protocol] = None
+    if tokens and (tokens[0] in PROTOCOLS or tokens[0] == "ip"):
+        protocol, tokens = PROTOCOLS.get(tokens[0]), tokens[1:]
+        source, tokens = _address(tokens)
+        if tokens and tokens[0] in ("eq", "range"):
+            raise ValueError("source ports are not supported")
+        destination, tokens = _address(tokens)
+        port, tokens = _port(tokens)
+    else:
+        # standard access list, only the source is given
+        source, tokens = _address(tokens)
+        destination, port = IpAddress("0.0.0.0", "0.0.0.0", ""), None
+    if tokens:
+        raise ValueError(f"unexpected {' '.join(tokens)}")
+    if port is not None and protocol not in (Protocol.TCP, Protocol.UDP):
+        raise ValueError("ports are only supported for tcp and udp")
+    return FirewallRule(name, action, source, destination, protocol, port)
+
+
+def _vlans(text: str) -> List[int]:
+    vlans: List[int] = []
+    for part in text.split(","):
+        first, _, last = part.partition("-")
+        vlans += range(int(first), int(last if last else first) + 1)
+    return vlans
+
+
+def _interface_line(interface: InterfaceConfig, tokens: List[str]) -> None:
+    line = " ".join(tokens)
+    if tokens[0] == "description":
+        interface.description = line[len("description") :].strip()
+    elif tokens[0] == "speed" and len(tokens) == 2:
+        speed = tokens[1].lower()
+        if speed.endswith("g"):
+            interface.speed_gb = int(speed[:-1])
+        else:
+            interface.speed_gb = int(speed) // 1000
+    elif line.startswith("switchport access vlan ") and len(tokens) == 4:
+        interface.access_vlan = int(tokens[3])
+    elif line.startswith("switchport trunk allowed vlan ") and len(tokens) == 5:
+        interface.trunk_vlans = _vlans(tokens[4])
+    elif tokens[:2] == ["ip", "address"] and len(tokens) in (3, 4):
+        if len(tokens) == 3:
+            network = ipaddress.IPv4Interface(tokens[2])
+        else:
+            network = ipaddress.IPv4Interface(f"{tokens[2]}/{tokens[3]}")
+        interface.address = IpAddress(str(network.ip), str(network.netmask), "")
+    elif tokens == ["shutdown"]:
+        interface.shutdown = True
+    elif tokens == ["no", "shutdown"]:
+        interface.shutdown = False
+    else:
+        raise KeyError(line)
+
+
+def parse(text: str) -> VendorConfig:
+    """
+    Parse a vendor configuration, lines that cannot be parsed are reported and skipped
+    :param text: configuration text
+    :return: VendorConfig containing the parsed artifacts and the unknown lines
+    """
+    config = VendorConfig()
+    interface: Optional[InterfaceConfig] = None
+    acl: Optional[str] = None
+    sequence = 0
+    numbered: Dict[str, int] = {}
+    for number, raw in enumerate(text.splitlines(), start=1):
+        line = raw.strip()
+        if not line or line.startswith(IGNORED):
+            if line.startswith("!"):
+                interface, acl = None, None
+            continue
+        tokens = line.split()
+        nested = raw[0].isspace()
+        if not nested:
+            interface, acl = None, None
+        try:
+            if interface is not None:
+                _interface_line(interface, tokens)
+            elif acl is not None:
+                if tokens[0].isdigit():
+                    sequence, tokens = int(tokens[0]), tokens[1:]
+                else:
+                    sequence += 10
+                config.rules.append(_rule(f"{acl} {sequence}", tokens))
+            elif tokens[0] == "interface" and len(tokens) == 2:
+                interface = InterfaceConfig(tokens[1])
+                config.interfaces.append(interface)
+            elif tokens[:3] in (
+                ["ip", "access-list", "extended"],
+                ["ip", "access-list", "standard"],
+            ):
+                if len(tokens) != 4:
+                    raise ValueError("expected a single access list name")
+                acl, sequence = tokens[3], 0
+            elif tokens[0] == "access-list" and len(tokens) > 2:
+                # numbered entries have no sequence, they are numbered in order of appearance
+                numbered[tokens[1]] = numbered.get(tokens[1], 0) + 10
+                config.rules.append(
+                    _rule(f"{tokens[1]} {numbered[tokens[1]]}", tokens[2:])
+                )
+            elif tokens[:2] == ["ip", "route"] and len(tokens) in (5, 6):
+                destination = ipaddress.IPv4Network(
+                    f"{tokens[2]}/{tokens[3]}", strict=True
+                )
+                ipaddress.IPv4Address(tokens[4])
+                config.routes.append(
+                    Route(
+                        IpAddress(
+                            str(destination.network_address),
+                            str(destination.netmask),
+                            "",
+                        ),
+                        IpAddress(tokens[4], "", ""),
+                        int(tokens[5]) if len(tokens) == 6 else 1,
+                    )
+                )
+            else:
+                raise KeyError(line)
+        except KeyError:
+            config.unknown.append(UnknownLine(number, line, "unknown statement"))
+        except ValueError as e:
+            config.unknown.append(UnknownLine(number, line, str(e)))
+    return config
+
+
+def apply(device: Union[Router, Firewall, Switch], config: VendorConfig) -> List[str]:
+    """
+    Merge a parsed configuration onto a device. Interfaces are matched to ports by name and only the configured
+    attributes are changed, routes (routers) replace routes to the same destination and rules (firewalls) replace
+    rules with the same name, others are appended.
+    :param device: device to apply the configuration to
+    :param config: parsed configuration
+    :return: names of the interfaces that do not match a port of the device
+    :raises: ValueError if routes or rules are given for a device that does not support them
+    """
+    ports: List[Port] = list(device.lan_ports)
+    if isinstance(device, Switch):
+        ports += device.uplink_ports if device.uplink_ports else []
+    else:
+        ports += device.wan_ports if device.wan_ports else []
+    by_name = {p.name: p for p in ports}
+    unmatched = []
+    for interface in config.interfaces:
+        port = by_name.get(interface.name)
+        if port is None:
+            unmatched.append(interface.name)
+            continue
+        if interface.speed_gb is not None:
+            port.speed_gb = interface.speed_gb
+        if interface.access_vlan is not None:
+            port.access_vlan = interface.access_vlan
+        if interface.trunk_vlans is not None:
+            port.trunk_vlans = interface.trunk_vlans
+        if interface.address is not None:
+            port.address = interface.address
+        if interface.shutdown is not None:
+            port.admin_up = not interface.shutdown
+    if config.routes:
+        if not isinstance(device, Router):
+            raise ValueError(f"{device.name} does not support static routes")
+        for route in config.routes:
+            key = (route.destination.address, route.destination.netmask)
+            device.routes = [
+                r
+                for r in device.routes
+                if (r.destination.address, r.destination.netmask) != key
+            ] + [route]
+    if config.rules:
+        if not isinstance(device, Firewall):
+            raise ValueError(f"{device.name} does not support firewall rules")
+        current = {r.name: i for i, r in enumerate(device.rules)}
+        for rule in config.rules:
+            if rule.name in current:
+                device.rules[current[rule.name]] = rule
+            else:
+                current[rule.name] = len(device.rules)
+                device.rules.append(rule)
+    return unmatched
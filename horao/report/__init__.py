@@ -0,0 +1,2 @@
+# -*- coding: utf-8 -*-#
+"""Reports of the model for stakeholders that do not use the API."""
@@ -0,0 +1,282 @@
+# -*- coding: utf-8 -*-#
+"""HTML report
+
+A single self-contained HTML file (inline CSS and JS, no external requests) that can be browsed without installing
+anything: a collapsible tree of the datacenter, the space and power used per cabinet, a searchable table of all devices
+and a drawing (SVG) of every network. The output only depends on the model, rendering the same model twice gives the
+same file. All values taken from the inventory are escaped.
+
+The networks are drawn in layers: routers and firewalls on top, followed by the core, distribution and access
+switches, the attached devices (e.g. NICs) at the bottom. Links that are down are drawn dashed.
+"""
+from html import escape
+from typing import Any, Dict, List, Optional, Tuple
+
+from horao.models.hardware import Cabinet, DataCenter, Server
+from horao.models.network import (
+    DataCenterNetwork,
+    Firewall,
+    NetworkDevice,
+    Router,
+    Switch,
+    SwitchType,
+)
+from horao.models.status import DeviceStatus
+
+STATUS_COLORS = {DeviceStatus.Up.name: "#2e7d32", DeviceStatus.Down.name: "#c62828"}
+# color of statuses this version does not know (written by a newer peer)
+UNKNOWN_COLOR = "#757575"
+
+STYLE = """
+body { font-family: sans-serif; margin: 2em; color: #212121; }
+details { margin-left: 1.5em; }
+summary { cursor: pointer; }
+.badge { border-radius: 3px; color: #fff; font-size: 0.8em; padding: 0 0.4em; }
+.bar { background: #e0e0e0; display: inline-block; height: 0.8em; width: 10em; }
+.bar span { background: #1565c0; display: block; height: 100%; }
+table { border-collapse: collapse; }
+td, th { border-bottom: 1px solid #e0e0e0; padding: 0.2em 0.8em; text-align: left; }
+"""
+
+SCRIPT = """
+document.getElementById("search").addEventListener("input", function (e) {
+  var query = e.target.value.toLowerCase();
+  document.querySelectorAll("#devices tbody tr").forEach(function (row) {
+    row.style.display = row.textContent.toLowerCase().indexOf(query) < 0 ? "none" : "";
+  });
+});
+"""
+
+
+class ReportOptions:
+    def __init__(
+        self,
+        tree: bool = True,
+        capacity: bool = True,
+        devices: bool = True,
+        networks: bool = True,
+        costs: bool = False,
+        cabinet_size_ru: int = 42,
+        cabinet_power_watts: int = 10_000,
+    ):
+        """
+        Sections of the report
+        :param tree: datacenter, row, cabinet and server tree
+        :param capacity: space and power used per cabinet
+        :param devices: searchable device table
+        :param networks: drawing of every network
+        :param costs: purchase costs of the servers (in the tree and the device table)
+        :param cabinet_size_ru: space of a cabinet, to scale the space bars
+        :param cabinet_power_watts: power budget of a cabinet, to scale the power bars
+        """
+        self.tree = tree
+        self.capacity = capacity
+        self.devices = devices
+        self.networks = networks
+        self.costs = costs
+        self.cabinet_size_ru = cabinet_size_ru
+        self.cabinet_power_watts = cabinet_power_watts
+
+
+def _badge(status: Any) -> str:
+    name = getattr(status, "name", str(status))
+    color = STATUS_COLORS.get(name, UNKNOWN_COLOR)
+    return f'<span class="badge" style="background: {color}">{escape(name)}</span>'
+
+
+def _bar(used: int, total: int) -> str:
+    width = min(100, round(used * 100 / total)) if total else 100
+    return f'<span class="bar"><span style="width: {width}%"></span></span>'
+
+
+def _cost(server: Server) -> str:
+    return "" if server.cost is None else f"{server.cost:.2f}"
+
+
+def _cabinet_servers(cabinet: Cabinet) -> List[Server]:
+    return cabinet.servers + [s for c in cabinet.chassis for s in c.servers]
+
+
+def _tree(dc: DataCenter, options: ReportOptions) -> List[str]:
+    lines = [
+        "<h2>Datacenter</h2>",
+        f"<details open><summary>{escape(dc.name)}</summary>",
+    ]
+    for row in dc.rows:
+        lines.append(f"<details><summary>{escape(row.name)}</summary>")
+        for cabinet in row.cabinets:
+            lines.append(f"<details><summary>{escape(cabinet.name)}</summary><ul>")
+            for server in _cabinet_servers(cabinet):
+                cost = f" {_cost(server)}" if options.costs and _cost(server) else ""
+                lines.append(
+                    f"<li>{escape(server.name)} ({escape(server.serial_number)}) "
+                    f"{_badge(server.status)}{escape(cost)}</li>"
+                )
+            lines.append("</ul></details>")
+        lines.append("</details>")
+    lines.append("</details>")
+    return lines
+
+
+def _capacity(dc: DataCenter, options: ReportOptions) -> List[str]:
+    lines = [
+        "<h2>Cabinets</h2>",
+        "<table><thead><tr><th>Row</th><th>Cabinet</th><th>Space</th><th>Power</th></tr></thead><tbody>",
+    ]
+    for row in dc.rows:
+        for cabinet in row.cabinets:
+            # blades take the space of their chassis, which is not modelled
+            space = sum(d.size_ru or 0 for d in cabinet.servers + cabinet.switches)
+            power = cabinet.power_watts()
+            lines.append(
+                f"<tr><td>{escape(row.name)}</td><td>{escape(cabinet.name)}</td>"
+                f"<td>{_bar(space, options.cabinet_size_ru)} {space}/{options.cabinet_size_ru} RU</td>"
+                f"<td>{_bar(power, options.cabinet_power_watts)} {power}/{options.cabinet_power_watts} W</td></tr>"
+            )
+    lines.append("</tbody></table>")
+    return lines
+
+
+def _device_rows(
+    dc: DataCenter, networks: List[DataCenterNetwork]
+) -> List[Tuple[Any, str]]:
+    devices: List[Tuple[Any, str]] = []
+    seen = set()
+    for row in dc.rows:
+        for cabinet in row.cabinets:
+            for device in _cabinet_servers(cabinet) + cabinet.switches:
+                seen.add(device.serial_number)
+                devices.append((device, f"{row.name}/{cabinet.name}"))
+    for network in networks:
+        for device in network.graph_view().nodes:
+            if device.serial_number not in seen and hasattr(device, "status"):
+                seen.add(device.serial_number)
+                devices.append((device, network.name))
+    return devices
+
+
+def _devices(
+    dc: DataCenter, networks: List[DataCenterNetwork], options: ReportOptions
+) -> List[str]:
+    headers = ["Serial number", "Name", "Model", "Kind", "Location", "Status"]
+    if options.costs:
+        headers.append("Cost")
+    lines = [
+        "<h2>Devices</h2>",
+        '<input id="search" type="search" placeholder="Search devices">',
+        '<table id="devices"><thead><tr>'
+        + "".join(f"<th>{h}</th>" for h in headers)
+        + "</tr></thead><tbody>",
+    ]
+    for device, location in _device_rows(dc, networks):
+        cells = [
+            escape(device.serial_number),
+            escape(device.name),
+            escape(device.model),
+            type(device).__name__,
+            escape(location),
+            _badge(device.status),
+        ]
+        if options.costs:
+            cells.append(escape(_cost(device)) if isinstance(device, Server) else "")
+        lines.append("<tr>" + "".join(f"<td>{c}</td>" for c in cells) + "</tr>")
+    lines.append("</tbody></table>")
+    return lines
+
+
+def _layer(device: NetworkDevice) -> int:
+    if isinstance(device, (Router, Firewall)):
+        return 0
+    if isinstance(device, Switch):
+        return {SwitchType.Core: 1, SwitchType.Distribution: 2}.get(
+            device.switch_type, 3
+        )
+    return 4
+
+
+def svg(network: DataCenterNetwork) -> str:
+    """
+    Drawing of a network, devices are drawn in layers by their role
+    :param network: network to draw
+    :return: SVG element
+    """
+    layers: Dict[int, List[NetworkDevice]] = {}
+    for device in network.graph_view().nodes:
+        layers.setdefault(_layer(device), []).append(device)
+    width = max([len(d) for d in layers.values()], default=1) * 140
+    height = len(layers) * 90
+    positions = {}
+    for y, layer in enumerate(sorted(layers)):
+        devices = sorted(layers[layer], key=lambda d: d.serial_number)
+        for x, device in enumerate(devices):
+            positions[device] = (
+                (2 * x + 1) * width // (2 * len(devices)),
+                45 + y * 90,
+            )
+    lines = [
+        f'<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" '
+        f'viewBox="0 0 {width} {height}">'
+    ]
+    for left, right, link in network.links():
+        if left not in positions or right not in positions:
+            continue
+        (x1, y1), (x2, y2) = positions[left], positions[right]
+        dash = "" if link.is_up(network.ports) else ' stroke-dasharray="4"'
+        lines.append(
+            f'<line x1="{x1}" y1="{y1}" x2="{x2}" y2="{y2}" stroke="#9e9e9e"{dash}/>'
+        )
+    for device, (x, y) in positions.items():
+        status = getattr(device, "status", None)
+        color = (
+            STATUS_COLORS.get(getattr(status, "name", ""), UNKNOWN_COLOR)
+            if status
+            else "#1565c0"
+        )
+        lines.append(f'<circle cx="{x}" cy="{y}" r="8" fill="{color}"/>')
+        lines.append(
+            f'<text x="{x}" y="{y + 24}" text-anchor="middle" font-size="12">'
+            f"{escape(device.name)}</text>"
+        )
+    lines.append("</svg>")
+    return "\n".join(lines)
+
+
+def render_html(
+    dc: DataCenter,
+    networks: List[DataCenterNetwork],
+    options: Optional[ReportOptions] = None,
+) -> str:
+    """
+    Render the model as a self-contained HTML page
+    :param dc: datacenter to report on
+    :param networks: networks to report on
+    :param options: sections to include, all but the costs by default
+    :return: HTML document
+    """
+    options = options if options else ReportOptions()
+    lines = [
+        "<!DOCTYPE html>",
+        '<html lang="en">',
+        "<head>",
+        '<meta charset="utf-8">',
+        f"<title>{escape(dc.name)}</title>",
+        f"<style>{STYLE}</style>",
+        "</head>",
+        "<body>",
+        f"<h1>{escape(dc.name)}</h1>",
+    ]
+    if options.tree:
+        lines += _tree(dc, options)
+    if options.capacity:
+        lines += _capacity(dc, options)
+    if options.devices:
+        lines += _devices(dc, networks, options)
+    if options.networks:
+        lines.append("<h2>Networks</h2>")
+        for network in networks:
+            lines.append(f"<h3>{escape(network.name)}</h3>")
+            lines.append(svg(network))
+    if options.devices:
+        lines.append(f"<script>{SCRIPT}</script>")
+    lines += ["</body>", "</html>", ""]
+    return "\n".join(lines)
@@ -0,0 +1,441 @@
+# -*- coding: utf-8 -*-#
+"""Settings of the application.
+
+Settings are read from the environment (populated from horao/env/.env.<ENVIRONMENT> by the application) or from a TOML
+file with the same keys in lower case, every setting has a sensible default so the models and controllers can be used
+without any configuration.
+"""
+import inspect
+import os
+import tomllib
+from enum import Enum, auto
+from typing import Any, Dict, Union
+
+
+class ConfigError(ValueError):
+    """Raised when the settings cannot be loaded, names the source (file or environment) and the offending key."""
+
+    def __init__(self, source_file: str, key: str, detail: str):
+        super().__init__(
+            f"{source_file}: {key}: {detail}" if key else f"{source_file}: {detail}"
+        )
+        self.source_file = source_file
+        self.key = key
+        self.detail = detail
+
+
+class ComputeUnit(Enum):
+    Cores = auto()
+    # physical CPUs
+    Sockets = auto()
+
+
+class StorageUnit(Enum):
+    # decimal, 1 GB is 1000^3 bytes, the unit the model is recorded in
+    GB = auto()
+    # binary, 1 GiB is 1024^3 bytes
+    GiB = auto()
+
+
+class RoutingProtocol(Enum):
+    BGP = auto()
+    OSPF = auto()
+
+
+class GossipEncryptionMode(Enum):
+    # only encrypted datagrams are accepted
+    Required = auto()
+    # plaintext datagrams are accepted as well, while peers are being switched to encryption
+    Grace = auto()
+
+
+class CapacityUnits:
+    """Units capacity is reported in, memory and disk sizes of the model are recorded in (decimal) GB."""
+
+    def __init__(
+        self,
+        compute: ComputeUnit = ComputeUnit.Cores,
+        storage: StorageUnit = StorageUnit.GB,
+    ):
+        self.compute = compute
+        self.storage = storage
+
+    def __eq__(self, other):
+        return (
+            isinstance(other, CapacityUnits)
+            and self.compute == other.compute
+            and self.storage == other.storage
+        )
+
+    def __repr__(self):
+        return f"<CapacityUnits {self.compute.name}, {self.storage.name}>"
+
+    def convert_storage(self, size_gb: int) -> Union[int, float]:
+        """
+        Convert a size recorded in the model to the storage unit
+        :param size_gb: size in GB
+        :return: size in the storage unit, rounded to 2 decimals for GiB
+        """
+        if self.storage == StorageUnit.GB:
+            return size_gb
+        return round(size_gb * 1000**3 / 1024**3, 2)
+
+
+class Secret:
+    """Value that should not end up in logs or reprs (e.g. an authorization header), revealed on request."""
+
+    def __init__(self, value: str):
+        self._value = value
+
+    def reveal(self) -> str:
+        return self._value
+
+    def __bool__(self):
+        return bool(self._value)
+
+    def __eq__(self, other):
+        return isinstance(other, Secret) and self._value == other._value
+
+    def __repr__(self):
+        return "Secret('****')" if self._value else "Secret('')"
+
+    def __str__(self):
+        return repr(self)
+
+
+class Settings:
+    def __init__(
+        self,
+        retry_max_attempts: int = 3,
+        retry_base_delay: float = 0.5,
+        retry_max_delay: float = 30.0,
+        retry_jitter: float = 0.1,
+        snapshot_path: str = "snapshots",
+        analysis_max_duration: float = 10.0,
+        analysis_max_nodes_visited: int = 1_000_000,
+        analysis_max_results: int = 10_000,
+        analysis_ceiling_duration: float = 60.0,
+        analysis_ceiling_nodes_visited: int = 10_000_000,
+        analysis_ceiling_results: int = 100_000,
+        capacity_compute_unit: str = ComputeUnit.Cores.name,
+        capacity_storage_unit: str = StorageUnit.GB.name,
+        gossip_max_retransmissions: int = 8,
+        gossip_aging_rounds: int = 10,
+        gossip_probe_window: int = 100,
+        gossip_hop_latency_ms: float = 0.05,
+        gossip_rtt_excess_factor: float = 3.0,
+        gossip_loss_threshold: float = 0.05,
+        cmdb_endpoint: str = "",
+        cmdb_auth_header: Secret = Secret(""),
+        cmdb_batch_window: float = 5.0,
+        cmdb_batch_size: int = 100,
+        cmdb_cursor_path: str = "cmdb.cursor",
+        cmdb_dead_letter_path: str = "cmdb.dead-letter.jsonl",
+        naming_conventions_path: str = "",
+        snapshot_compaction_deltas: int = 10,
+        routing_protocol: str = RoutingProtocol.BGP.name,
+        routing_bgp_hold_time: float = 9.0,
+        routing_bgp_advertisement_interval: float = 0.0,
+        routing_ospf_dead_interval: float = 40.0,
+        routing_ospf_spf_delay: float = 0.05,
+        routing_fib_update_ms: float = 10.0,
+        gossip_encryption_key: Secret = Secret(""),
+        gossip_encryption_mode: str = GossipEncryptionMode.Required.name,
+    ):
+        """
+        Settings of the application
+        :param retry_max_attempts: number of attempts before giving up on a transient failure
+        :param retry_base_delay: seconds to wait after the first failed attempt, doubled on every attempt
+        :param retry_max_delay: upper bound of the wait between attempts in seconds
+        :param retry_jitter: fraction of the delay that is randomly added to spread retries
+        :param snapshot_path: directory of the snapshot store
+        :param analysis_max_duration: default seconds an analysis may take
+        :param analysis_max_nodes_visited: default number of nodes an analysis may visit
+        :param analysis_max_results: default number of results an analysis may produce
+        :param analysis_ceiling_duration: upper bound of a requested analysis duration
+        :param analysis_ceiling_nodes_visited: upper bound of a requested number of visited nodes
+        :param analysis_ceiling_results: upper bound of a requested number of results
+        :param capacity_compute_unit: unit compute capacity is reported in (Cores or Sockets)
+        :param capacity_storage_unit: unit memory and disk capacity is reported in (GB or GiB)
+        :param gossip_max_retransmissions: number of datagrams an update is piggybacked on before it is dropped
+        :param gossip_aging_rounds: rounds an update waits before its priority is raised by one level
+        :param gossip_probe_window: number of most recent probes per peer the statistics are computed over
+        :param gossip_hop_latency_ms: modelled one way latency of a device on the path between two peers
+        :param gossip_rtt_excess_factor: factor by which the median RTT may exceed the modelled RTT before it is flagged
+        :param gossip_loss_threshold: fraction of lost probes above which the loss of a peer is flagged
+        :param cmdb_endpoint: url the change feed is posted to, changes are not synchronized to a CMDB if empty
+        :param cmdb_auth_header: value of the Authorization header of the change feed posts
+        :param cmdb_batch_window: seconds changes are collected before they are posted
+        :param cmdb_batch_size: maximum number of changes per post
+        :param cmdb_cursor_path: file keeping the sequence number of the last change the CMDB acknowledged, the highest
+        posted sequence number is kept next to it (with an '.issued' suffix)
+        :param cmdb_dead_letter_path: file (JSON lines) the batches that exhausted their retries are written to
+        :param naming_conventions_path: TOML file with the naming conventions of the devices, names are not checked if empty
+        :param snapshot_compaction_deltas: number of consecutive delta snapshots after which a full snapshot is written
+        :param routing_protocol: routing protocol of the fabric (BGP or OSPF), for the convergence estimates
+        :param routing_bgp_hold_time: seconds without keepalives before a BGP session is declared down
+        :param routing_bgp_advertisement_interval: seconds between BGP updates to a neighbor (MRAI)
+        :param routing_ospf_dead_interval: seconds without hellos before an OSPF neighbor is declared down
+        :param routing_ospf_spf_delay: seconds between receiving a topology change and running SPF
+        :param routing_fib_update_ms: milliseconds to program the new routes in the forwarding table
+        :param gossip_encryption_key: base64 encoded 32 byte key shared by the peers, gossip is not encrypted if empty
+        :param gossip_encryption_mode: Required, or Grace to also accept plaintext datagrams (for rolling upgrades)
+        """
+        self.retry_max_attempts = retry_max_attempts
+        self.retry_base_delay = retry_base_delay
+        self.retry_max_delay = retry_max_delay
+        self.retry_jitter = retry_jitter
+        self.snapshot_path = snapshot_path
+        self.analysis_max_duration = analysis_max_duration
+        self.analysis_max_nodes_visited = analysis_max_nodes_visited
+        self.analysis_max_results = analysis_max_results
+        self.analysis_ceiling_duration = analysis_ceiling_duration
+        self.analysis_ceiling_nodes_visited = analysis_ceiling_nodes_visited
+        self.analysis_ceiling_results = analysis_ceiling_results
+        self.capacity_compute_unit = capacity_compute_unit
+        self.capacity_storage_unit = capacity_storage_unit
+        self.gossip_max_retransmissions = gossip_max_retransmissions
+        self.gossip_aging_rounds = gossip_aging_rounds
+        self.gossip_probe_window = gossip_probe_window
+        self.gossip_hop_latency_ms = gossip_hop_latency_ms
+        self.gossip_rtt_excess_factor = gossip_rtt_excess_factor
+        self.gossip_loss_threshold = gossip_loss_threshold
+        self.cmdb_endpoint = cmdb_endpoint
+        self.cmdb_auth_header = cmdb_auth_header
+        self.cmdb_batch_window = cmdb_batch_window
+        self.cmdb_batch_size = cmdb_batch_size
+        self.cmdb_cursor_path = cmdb_cursor_path
+        self.cmdb_dead_letter_path = cmdb_dead_letter_path
+        self.naming_conventions_path = naming_conventions_path
+        self.snapshot_compaction_deltas = snapshot_compaction_deltas
+        self.routing_protocol = routing_protocol
+        self.routing_bgp_hold_time = routing_bgp_hold_time
+        self.routing_bgp_advertisement_interval = routing_bgp_advertisement_interval
+        self.routing_ospf_dead_interval = routing_ospf_dead_interval
+        self.routing_ospf_spf_delay = routing_ospf_spf_delay
+        self.routing_fib_update_ms = routing_fib_update_ms
+        self.gossip_encryption_key = gossip_encryption_key
+        self.gossip_encryption_mode = gossip_encryption_mode
+
+    @property
+    def capacity_units(self) -> CapacityUnits:
+        return CapacityUnits(
+            ComputeUnit[self.capacity_compute_unit],
+            StorageUnit[self.capacity_storage_unit],
+        )
+
+    def _validate(self, source: str, key=lambda name: name) -> "Settings":
+        for name, enum_type in [
+            ("capacity_compute_unit", ComputeUnit),
+            ("capacity_storage_unit", StorageUnit),
+            ("routing_protocol", RoutingProtocol),
+            ("gossip_encryption_mode", GossipEncryptionMode),
+        ]:
+            if getattr(self, name) not in enum_type.__members__:
+                raise ConfigError(
+                    source,
+                    key(name),
+                    f"should be one of {', '.join(enum_type.__members__)}",
+                )
+        return self
+
+    @staticmethod
+    def _defaults() -> Dict[str, Any]:
+        return {
+            name: parameter.default
+            for name, parameter in inspect.signature(Settings).parameters.items()
+        }
+
+    @staticmethod
+    def from_env() -> "Settings":
+        """
+        Settings from the environment, unset variables fall back to the defaults
+        :return: Settings
+        :raises: ConfigError if a variable cannot be converted to the type of the setting
+        """
+        values = {}
+        for name, default in Settings._defaults().items():
+            variable = name.upper()
+            if variable not in os.environ:
+                continue
+            try:
+                values[name] = type(default)(os.environ[variable])
+            except ValueError:
+                raise ConfigError(
+                    "environment",
+                    variable,
+                    f"should be of type {type(default).__name__}, got '{os.environ[variable]}'",
+                )
+        return Settings(**values)._validate("environment", str.upper)
+
+    @staticmethod
+    def from_file(path: str) -> "Settings":
+        """
+        Settings from a TOML file, missing keys fall back to the defaults
+        :param path: path to the settings file
+        :return: Settings
+        :raises: ConfigError if the file cannot be parsed or contains unknown or mistyped keys
+        """
+        try:
+            with open(path, "rb") as f:
+                content = tomllib.load(f)
+        except (OSError, ValueError) as e:
+            raise ConfigError(path, "", str(e))
+        return Settings._typed(path, content)
+
+    @staticmethod
+    def _typed(source: str, values: Dict[str, Any]) -> "Settings":
+        defaults = Settings._defaults()
+        typed = dict(values)
+        for key, value in values.items():
+            if key not in defaults:
+                raise ConfigError(source, key, "unknown setting")
+            expected = type(defaults[key])
+            if expected is Secret and isinstance(value, str):
+                typed[key] = Secret(value)
+                continue
+            if isinstance(value, bool) or not (
+                isinstance(value, expected)
+                or (expected is float and isinstance(value, int))
+            ):
+                raise ConfigError(
+                    source, key, f"should be of type {expected.__name__}"
+                )
+        return Settings(**typed)._validate(source)
+
+    @staticmethod
+    def builder() -> "SettingsBuilder":
+        """
+        Builder of settings in code (e.g. when embedding the models in an application or a test), without files or
+        environment variables
+        :return: SettingsBuilder
+        """
+        return SettingsBuilder()
+
+
+class SettingsBuilder:
+    """
+    Builds settings one setting at a time, every setting has a method of the same name and type:
+
+        Settings.builder().retry_max_attempts(5).snapshot_path("/tmp").build()
+    """
+
+    def __init__(self):
+        self._values: Dict[str, Any] = {}
+
+    def _set(self, name: str, value: Any) -> "SettingsBuilder":
+        self._values[name] = value
+        return self
+
+    def retry_max_attempts(self, value: int) -> "SettingsBuilder":
+        return self._set("retry_max_attempts", value)
+
+    def retry_base_delay(self, value: float) -> "SettingsBuilder":
+        return self._set("retry_base_delay", value)
+
+    def retry_max_delay(self, value: float) -> "SettingsBuilder":
+        return self._set("retry_max_delay", value)
+
+    def retry_jitter(self, value: float) -> "SettingsBuilder":
+        return self._set("retry_jitter", value)
+
+    def snapshot_path(self, value: str) -> "SettingsBuilder":
+        return self._set("snapshot_path", value)
+
+    def analysis_max_duration(self, value: float) -> "SettingsBuilder":
+        return self._set("analysis_max_duration", value)
+
+    def analysis_max_nodes_visited(self, value: int) -> "SettingsBuilder":
+        return self._set("analysis_max_nodes_visited", value)
+
+    def analysis_max_results(self, value: int) -> "SettingsBuilder":
+        return self._set("analysis_max_results", value)
+
+    def analysis_ceiling_duration(self, value: float) -> "SettingsBuilder":
+        return self._set("analysis_ceiling_duration", value)
+
+    def analysis_ceiling_nodes_visited(self, value: int) -> "SettingsBuilder":
+        return self._set("analysis_ceiling_nodes_visited", value)
+
+    def analysis_ceiling_results(self, value: int) -> "SettingsBuilder":
+        return self._set("analysis_ceiling_results", value)
+
+    def capacity_compute_unit(self, value: str) -> "SettingsBuilder":
+        return self._set("capacity_compute_unit", value)
+
+    def capacity_storage_unit(self, value: str) -> "SettingsBuilder":
+        return self._set("capacity_storage_unit", value)
+
+    def gossip_max_retransmissions(self, value: int) -> "SettingsBuilder":
+        return self._set("gossip_max_retransmissions", value)
+
+    def gossip_aging_rounds(self, value: int) -> "SettingsBuilder":
+        return self._set("gossip_aging_rounds", value)
+
+    def gossip_probe_window(self, value: int) -> "SettingsBuilder":
+        return self._set("gossip_probe_window", value)
+
+    def gossip_hop_latency_ms(self, value: float) -> "SettingsBuilder":
+        return self._set("gossip_hop_latency_ms", value)
+
+    def gossip_rtt_excess_factor(self, value: float) -> "SettingsBuilder":
+        return self._set("gossip_rtt_excess_factor", value)
+
+    def gossip_loss_threshold(self, value: float) -> "SettingsBuilder":
+        return self._set("gossip_loss_threshold", value)
+
+    def cmdb_endpoint(self, value: str) -> "SettingsBuilder":
+        return self._set("cmdb_endpoint", value)
+
+    def cmdb_auth_header(self, value: Union[str, Secret]) -> "SettingsBuilder":
+        return self._set("cmdb_auth_header", value)
+
+    def cmdb_batch_window(self, value: float) -> "SettingsBuilder":
+        return self._set("cmdb_batch_window", value)
+
+    def cmdb_batch_size(self, value: int) -> "SettingsBuilder":
+        return self._set("cmdb_batch_size", value)
+
+    def cmdb_cursor_path(self, value: str) -> "SettingsBuilder":
+        return self._set("cmdb_cursor_path", value)
+
+    def cmdb_dead_letter_path(self, value: str) -> "SettingsBuilder":
+        return self._set("cmdb_dead_letter_path", value)
+
+    def naming_conventions_path(self, value: str) -> "SettingsBuilder":
+        return self._set("naming_conventions_path", value)
+
+    def snapshot_compaction_deltas(self, value: int) -> "SettingsBuilder":
+        return self._set("snapshot_compaction_deltas", value)
+
+    def routing_protocol(self, value: str) -> "SettingsBuilder":
+        return self._set("routing_protocol", value)
+
+    def routing_bgp_hold_time(self, value: float) -> "SettingsBuilder":
+        return self._set("routing_bgp_hold_time", value)
+
+    def routing_bgp_advertisement_interval(self, value: float) -> "SettingsBuilder":
+        return self._set("routing_bgp_advertisement_interval", value)
+
+    def routing_ospf_dead_interval(self, value: float) -> "SettingsBuilder":
+        return self._set("routing_ospf_dead_interval", value)
+
+    def routing_ospf_spf_delay(self, value: float) -> "SettingsBuilder":
+        return self._set("routing_ospf_spf_delay", value)
+
+    def routing_fib_update_ms(self, value: float) -> "SettingsBuilder":
+        return self._set("routing_fib_update_ms", value)
+
+    def gossip_encryption_key(self, value: Union[str, Secret]) -> "SettingsBuilder":
+        return self._set("gossip_encryption_key", value)
+
+    def gossip_encryption_mode(self, value: str) -> "SettingsBuilder":
+        return self._set("gossip_encryption_mode", value)
+
+    def build(self) -> Settings:
+        """
+        Validate the settings, unset settings fall back to the defaults
+        :return: Settings
+        :raises: ConfigError if a setting is mistyped or has an invalid value
+        """
+        return Settings._typed("builder", self._values)
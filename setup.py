@@ -1,8 +1,13 @@
 # coding: utf-8
 
+import os
+import subprocess
+
 from setuptools import setup, find_packages
+from setuptools.command.build_py import build_py
 
 NAME = "horao"
+# keep in sync with PACKAGE_VERSION in horao/models/versioning.py
 VERSION = "1.0.0"
 
 # To install the library, run the following
@@ -14,6 +19,22 @@ VERSION = "1.0.0"
 
 REQUIRES = ["Starlette"]
 
+
+class BuildWithGitHash(build_py):
+    """Record the commit the package is built from in horao/build_info.py"""
+
+    def run(self):
+        try:
+            git_hash = subprocess.run(
+                ["git", "rev-parse", "HEAD"], capture_output=True, check=True, text=True
+            ).stdout.strip()
+        except (OSError, subprocess.CalledProcessError):
+            git_hash = None
+        with open(os.path.join("horao", "build_info.py"), "w") as f:
+            f.write(f"GIT_HASH = {git_hash!r}\n")
+        super().run()
+
+
 setup(
     name=NAME,
     version=VERSION,
@@ -22,7 +43,11 @@ setup(
     url="",
     keywords=["OpenAPI", "Starlette"],
     install_requires=REQUIRES,
+    # gossip encryption (see horao/gossip/encryption.py)
+    extras_require={"crypto": ["cryptography"]},
     packages=find_packages(),
+    cmdclass={"build_py": BuildWithGitHash},
+    entry_points={"console_scripts": ["horao=horao.cli:main"]},
     long_description="""\
     Management engine for hybrid multi-cloud environments
     """,
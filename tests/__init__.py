@@ -1,8 +1,11 @@
 # -*- coding: utf-8 -*-#
 import os
 from base64 import b64encode
+from typing import List, Optional
 
 from horao.models import (
+    CPU,
+    RAM,
     Switch,
     LinkLayer,
     DeviceStatus,
@@ -12,12 +15,14 @@ from horao.models import (
     DataCenter,
     Row,
     Cabinet,
+    IpAddress,
 )
 from horao.models.network import (
     SwitchType,
     DataCenterNetwork,
     NetworkType,
 )
+from horao.models.osi_layers import Link
 
 
 os.environ["ENVIRONMENT"] = "development"
@@ -34,6 +39,174 @@ def basic_auth(username, password) -> str:
     return f"Basic {token}"
 
 
+def create_switch(
+    serial_number: str,
+    switch_type: SwitchType,
+    lan_ports: int,
+    uplink_ports: int,
+    speed_gb: int = 100,
+) -> Switch:
+    """
+    This function returns a switch with the given amount of (down) ports for testing purposes
+    :param serial_number: serial number, also used as the name of the switch
+    :param switch_type: type of switch
+    :param lan_ports: number of lan ports
+    :param uplink_ports: number of uplink ports
+    :param speed_gb: speed of all ports
+    :return: Switch
+    """
+    return Switch(
+        serial_number,
+        serial_number,
+        "sw",
+        1,
+        LinkLayer.Layer2,
+        switch_type,
+        DeviceStatus.Up,
+        True,
+        [
+            Port(
+                f"{serial_number}-lp{i}",
+                f"lp{i}",
+                "swp",
+                i,
+                f"{serial_number}-m{i}",
+                DeviceStatus.Down,
+                speed_gb,
+            )
+            for i in range(lan_ports)
+        ],
+        [
+            Port(
+                f"{serial_number}-up{i}",
+                f"up{i}",
+                "swp",
+                i,
+                f"{serial_number}-um{i}",
+                DeviceStatus.Down,
+                speed_gb,
+            )
+            for i in range(uplink_ports)
+        ],
+    )
+
+
+def create_server(
+    serial_number: str,
+    cpu: Optional[List[CPU]] = None,
+    ram: Optional[List[RAM]] = None,
+    nic_speed_gb: Optional[int] = None,
+    status: DeviceStatus = DeviceStatus.Up,
+    **kwargs,
+) -> Server:
+    """
+    This function returns a server for testing purposes
+    :param serial_number: serial number, also used as the name of the server
+    :param cpu: CPUs of the server
+    :param ram: memory of the server
+    :param nic_speed_gb: speed of the (down) port of its NIC, the server has no NIC if not given
+    :param status: status of the server
+    :param kwargs: other arguments of the server (e.g. labels)
+    :return: Server
+    """
+    nic = []
+    if nic_speed_gb is not None:
+        port = Port(
+            f"{serial_number}-p",
+            "p",
+            "nic",
+            1,
+            f"{serial_number}-m",
+            DeviceStatus.Down,
+            nic_speed_gb,
+        )
+        nic = [NIC(f"{serial_number}-nic", "nic", "nic", 1, [port])]
+    return Server(
+        serial_number,
+        serial_number,
+        "srv",
+        1,
+        cpu if cpu else [],
+        ram if ram else [],
+        nic,
+        [],
+        [],
+        status,
+        **kwargs,
+    )
+
+
+def port(
+    serial_number: str, speed_gb: int = 100, address: Optional[str] = None
+) -> Port:
+    """
+    This function returns a (down) port for testing purposes
+    :param serial_number: serial number, also used as the name of the port
+    :param speed_gb: speed of the port
+    :param address: address of the port in a /24, the port has no address if not given
+    :return: Port
+    """
+    return Port(
+        serial_number,
+        serial_number,
+        "port",
+        1,
+        "",
+        DeviceStatus.Down,
+        speed_gb,
+        address=IpAddress(address, "255.255.255.0", "") if address else None,
+    )
+
+
+def cable(network: DataCenterNetwork, left, left_port, right, right_port) -> None:
+    """
+    This function links two devices through the given ports (bypassing the port selection of link) for testing
+    purposes, both ports are brought up
+    :param network: network the devices are part of
+    :param left: device on one end
+    :param left_port: port of the left device
+    :param right: device on the other end
+    :param right_port: port of the right device
+    :return: None
+    """
+    network.graph.add_edge(
+        left,
+        right,
+        link=Link(
+            network.ports.intern(left_port, left),
+            network.ports.intern(right_port, right),
+        ),
+    )
+    left_port.status = right_port.status = DeviceStatus.Up
+
+
+def two_pod_network() -> DataCenterNetwork:
+    """
+    This function returns a network of two pods, each with two aggregation switches and an access switch
+    :return: DataCenterNetwork
+    """
+    #                 core
+    #        /      /      \      \
+    #   agg_a1  agg_a2    agg_b1  agg_b2
+    #        \  /              \  /
+    #       acc_a              acc_b
+    dcn = DataCenterNetwork("dcn", NetworkType.Data)
+    core = create_switch("core", SwitchType.Core, 4, 0)
+    aggregation = {
+        s: create_switch(s, SwitchType.Distribution, 2, 1)
+        for s in ["agg_a1", "agg_a2", "agg_b1", "agg_b2"]
+    }
+    access = {s: create_switch(s, SwitchType.Access, 0, 1) for s in ["acc_a", "acc_b"]}
+    dcn.add_multiple([core] + list(aggregation.values()) + list(access.values()))
+    for agg in aggregation.values():
+        dcn.link(agg, core)
+    dcn.link(access["acc_a"], aggregation["agg_a1"])
+    dcn.link(access["acc_a"], aggregation["agg_a2"])
+    dcn.link(access["acc_b"], aggregation["agg_b1"])
+    dcn.link(access["acc_b"], aggregation["agg_b2"])
+    return dcn
+
+
 def basic_networking_configuration():
     """
     This function returns a basic networking configuration for testing purposes
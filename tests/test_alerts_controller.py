@@ -0,0 +1,37 @@
+# -*- coding: utf-8 -*-#
+from starlette.testclient import TestClient
+
+from horao import init_api
+from horao.controllers.alerting import AlertManager, AlertRule, AlertRuleType
+from horao.models import DeviceStatus
+from tests import basic_auth
+from tests.test_controller_alerting import alerting_configuration
+
+
+def test_get_alerts_without_manager():
+    ia = init_api()
+    with TestClient(ia) as client:
+        lg = client.get(
+            "/alerts", headers={"Authorization": basic_auth("netadm", "secret")}
+        )
+        assert 503 == lg.status_code
+
+
+def test_get_firing_alerts():
+    dc, dcn, _, access = alerting_configuration()
+    access.status = DeviceStatus.Down
+    manager = AlertManager([AlertRule("down", AlertRuleType.DeviceDown)])
+    manager.evaluate(dc, dcn, now=0)
+    ia = init_api()
+    ia.state.alert_manager = manager
+    with TestClient(ia) as client:
+        lg = client.get(
+            "/alerts?state=firing",
+            headers={"Authorization": basic_auth("netadm", "secret")},
+        )
+        assert 200 == lg.status_code
+        assert [a["serial_number"] for a in lg.json()] == ["acc"]
+        assert 400 == client.get(
+            "/alerts?state=bogus",
+            headers={"Authorization": basic_auth("netadm", "secret")},
+        ).status_code
@@ -0,0 +1,94 @@
+# -*- coding: utf-8 -*-#
+import json
+import threading
+from http.server import BaseHTTPRequestHandler, HTTPServer
+
+from horao.controllers.alerting import (
+    AlertManager,
+    AlertRule,
+    AlertRuleType,
+    AlertState,
+    WebhookSink,
+)
+from horao.models import DataCenter, DeviceStatus
+from horao.models.network import DataCenterNetwork, NetworkType, SwitchType
+from tests import create_switch
+
+
+def alerting_configuration():
+    core = create_switch("core", SwitchType.Core, 4, 0)
+    access = create_switch("acc", SwitchType.Access, 4, 1)
+    dcn = DataCenterNetwork("dcn", NetworkType.Data)
+    dcn.add_multiple([core, access])
+    dcn.link(access, core)
+    return DataCenter("dc", 1, []), dcn, core, access
+
+
+def test_alert_is_deduplicated_and_renotified():
+    dc, dcn, _, access = alerting_configuration()
+    events = []
+    manager = AlertManager(
+        [AlertRule("down", AlertRuleType.DeviceDown)],
+        renotify_interval=60,
+        sink=events.append,
+    )
+    assert manager.evaluate(dc, dcn, now=0) == []
+    access.status = DeviceStatus.Down
+    assert len(manager.evaluate(dc, dcn, now=10)) == 1
+    assert manager.evaluate(dc, dcn, now=20) == []
+    assert len(manager.evaluate(dc, dcn, now=70)) == 1
+    firing = manager.alerts(AlertState.Firing)
+    assert [a.serial_number for a in firing] == ["acc"]
+    assert firing[0].since == 10
+    access.status = DeviceStatus.Up
+    resolved = manager.evaluate(dc, dcn, now=80)
+    assert [e.state for e in resolved] == [AlertState.Resolved]
+    assert manager.alerts(AlertState.Firing) == []
+    assert len(events) == 3
+
+
+def test_port_alerts_are_grouped_under_switch_alert():
+    dc, dcn, core, access = alerting_configuration()
+    manager = AlertManager(
+        [
+            AlertRule("down", AlertRuleType.DeviceDown),
+            AlertRule("port", AlertRuleType.PortDown),
+        ]
+    )
+    core.lan_ports[0].status = DeviceStatus.Down
+    events = manager.evaluate(dc, dcn, now=0)
+    assert [e.alert.serial_number for e in events] == ["core-lp0"]
+    core.status = DeviceStatus.Down
+    manager.evaluate(dc, dcn, now=1)
+    firing = manager.alerts(AlertState.Firing)
+    assert [a.serial_number for a in firing] == ["core"]
+    assert firing[0].grouped == ["core-lp0"]
+
+
+def test_webhook_sink_retries_failed_posts():
+    received = []
+
+    class Handler(BaseHTTPRequestHandler):
+        def do_POST(self):
+            body = self.rfile.read(int(self.headers["Content-Length"]))
+            received.append(json.loads(body))
+            self.send_response(500 if len(received) == 1 else 200)
+            self.end_headers()
+
+        def log_message(self, *args):
+            pass
+
+    server = HTTPServer(("127.0.0.1", 0), Handler)
+    threading.Thread(target=server.serve_forever, daemon=True).start()
+    try:
+        dc, dcn, _, access = alerting_configuration()
+        access.status = DeviceStatus.Down
+        sink = WebhookSink(f"http://127.0.0.1:{server.server_port}", backoff=0)
+        AlertManager([AlertRule("down", AlertRuleType.DeviceDown)], sink=sink).evaluate(
+            dc, dcn
+        )
+    finally:
+        server.shutdown()
+    assert len(received) == 2
+    assert received[1]["serial_number"] == "acc"
+    assert received[1]["state"] == "firing"
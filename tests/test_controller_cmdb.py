@@ -0,0 +1,166 @@
+# -*- coding: utf-8 -*-#
+import json
+import threading
+from http.server import BaseHTTPRequestHandler, HTTPServer
+
+import pytest
+
+from horao.controllers.cmdb import CmdbSync
+from horao.models import DeviceStatus
+from horao.models.store import ModelStore, StatusChangeContext
+from horao.settings import Secret, Settings
+from tests import basic_networking_configuration
+
+
+class Cmdb:
+    """CMDB that fails the first given number of posts"""
+
+    def __init__(self, failures: int = 0):
+        self.received = []
+        self.headers = []
+        cmdb = self
+
+        class Handler(BaseHTTPRequestHandler):
+            def do_POST(self):
+                body = self.rfile.read(int(self.headers["Content-Length"]))
+                cmdb.received.append(json.loads(body))
+                cmdb.headers.append(self.headers["Authorization"])
+                self.send_response(500 if len(cmdb.received) <= failures else 200)
+                self.end_headers()
+
+            def log_message(self, *args):
+                pass
+
+        self.server = HTTPServer(("127.0.0.1", 0), Handler)
+        threading.Thread(target=self.server.serve_forever, daemon=True).start()
+
+    @property
+    def url(self) -> str:
+        return f"http://127.0.0.1:{self.server.server_port}"
+
+    def sequences(self, serial_number=None):
+        return [
+            c["sequence"]
+            for batch in self.received
+            for c in batch["changes"]
+            if serial_number is None or c["serial_number"] == serial_number
+        ]
+
+
+def synchronized_store(cmdb: Cmdb, tmp_path, clock=lambda: 0.0):
+    dc, dcn, _, _, _, _ = basic_networking_configuration()
+    store = ModelStore(dc, [dcn])
+    settings = (
+        Settings.builder()
+        .cmdb_endpoint(cmdb.url)
+        .cmdb_auth_header("Bearer token")
+        .cmdb_batch_size(2)
+        .cmdb_cursor_path(str(tmp_path / "cursor"))
+        .cmdb_dead_letter_path(str(tmp_path / "dead-letter.jsonl"))
+        .retry_max_attempts(3)
+        .retry_base_delay(0)
+        .build()
+    )
+    sync = CmdbSync(settings, clock=clock, sleep=lambda _: None)
+    sync.attach(store)
+    return store, sync
+
+
+def set_status(store: ModelStore, serial_number: str, status: DeviceStatus):
+    store.set_status(serial_number, status, StatusChangeContext("test", timestamp=1))
+
+
+def test_changes_are_batched_and_delivered(tmp_path):
+    cmdb = Cmdb()
+    now = [0.0]
+    try:
+        store, sync = synchronized_store(cmdb, tmp_path, lambda: now[0])
+        set_status(store, "ser3", DeviceStatus.Down)
+        assert not sync.due()
+        now[0] = 10.0
+        assert sync.tick() == 1
+        store.link("dcn", "ser5", "ser3", "netadm")
+        set_status(store, "srv", DeviceStatus.Down)
+        set_status(store, "ser7", DeviceStatus.Down)
+        assert sync.due()
+        assert sync.flush() == 3
+    finally:
+        cmdb.server.shutdown()
+    assert [len(b["changes"]) for b in cmdb.received] == [1, 2, 1]
+    assert cmdb.received[0]["changes"][0] == {
+        "sequence": 1,
+        "timestamp": 1,
+        "kind": "switch",
+        "serial_number": "ser3",
+        "operation": "update",
+        "changes": {"status": {"old": "Up", "new": "Down"}},
+    }
+    link = cmdb.received[1]["changes"][0]
+    assert (link["kind"], link["operation"]) == ("link", "create")
+    assert link["changes"]["left"] == {"old": None, "new": "ser5"}
+    assert cmdb.received[1]["changes"][1]["kind"] == "server"
+    assert cmdb.headers[0] == "Bearer token"
+    assert sync.cursor == 4
+
+
+def test_retry_then_success_advances_the_cursor(tmp_path):
+    cmdb = Cmdb(failures=2)
+    try:
+        store, sync = synchronized_store(cmdb, tmp_path)
+        set_status(store, "ser3", DeviceStatus.Down)
+        assert sync.flush() == 1
+    finally:
+        cmdb.server.shutdown()
+    assert cmdb.sequences() == [1, 1, 1]
+    assert (tmp_path / "cursor").read_text() == "1"
+    assert not (tmp_path / "dead-letter.jsonl").exists()
+    # a restarted feed continues after the acknowledged changes
+    assert CmdbSync(sync.settings).cursor == 1
+
+
+def test_exhausted_batches_are_dead_lettered(tmp_path):
+    cmdb = Cmdb(failures=3)
+    try:
+        store, sync = synchronized_store(cmdb, tmp_path)
+        set_status(store, "ser3", DeviceStatus.Down)
+        assert sync.flush() == 0
+    finally:
+        cmdb.server.shutdown()
+    assert len(cmdb.received) == 3
+    (line,) = (tmp_path / "dead-letter.jsonl").read_text().splitlines()
+    assert json.loads(line)["changes"][0]["serial_number"] == "ser3"
+    assert sync.cursor == 0
+    assert sync.pending == []
+    # a restarted feed does not hand out the numbers of the dead-lettered batch again
+    restarted = CmdbSync(sync.settings)
+    assert restarted.cursor == 0
+    restarted.attach(store)
+    set_status(store, "ser3", DeviceStatus.Up)
+    assert [c.sequence for c in restarted.pending] == [2]
+
+
+def test_entity_order_is_preserved_across_a_retried_batch(tmp_path):
+    cmdb = Cmdb(failures=1)
+    try:
+        store, sync = synchronized_store(cmdb, tmp_path)
+        for status in [DeviceStatus.Down, DeviceStatus.Up, DeviceStatus.Down]:
+            set_status(store, "ser3", status)
+        set_status(store, "srv", DeviceStatus.Down)
+        assert sync.flush() == 4
+    finally:
+        cmdb.server.shutdown()
+    # the first batch is sent twice, the changes of ser3 still arrive in order
+    assert cmdb.sequences() == [1, 2, 1, 2, 3, 4]
+    delivered = [c for b in cmdb.received[1:] for c in b["changes"]]
+    assert [c["changes"]["status"]["new"] for c in delivered[:3]] == [
+        "Down",
+        "Up",
+        "Down",
+    ]
+
+
+def test_sync_requires_an_endpoint():
+    assert repr(Settings().cmdb_auth_header) == "Secret('')"
+    assert "token" not in repr(Secret("token"))
+    with pytest.raises(ValueError):
+        CmdbSync(Settings())
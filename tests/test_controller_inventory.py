@@ -0,0 +1,48 @@
+# -*- coding: utf-8 -*-#
+import pytest
+
+from horao.controllers.inventory import (
+    InventoryProvider,
+    RetryingProvider,
+    TransientError,
+)
+from horao.models import DataCenter
+from horao.settings import Settings
+
+
+class FlakyProvider(InventoryProvider):
+    def __init__(self, failures: int, error: Exception):
+        self.failures = failures
+        self.error = error
+        self.calls = 0
+
+    def load(self) -> DataCenter:
+        self.calls += 1
+        if self.calls <= self.failures:
+            raise self.error
+        return DataCenter("dc", 1, [])
+
+
+def test_retrying_provider_succeeds_within_attempt_budget():
+    delays = []
+    provider = FlakyProvider(2, TransientError("cmdb unavailable"))
+    retrying = RetryingProvider(
+        provider, Settings(3, 1.0, 1.5, 0.0), sleep=delays.append
+    )
+    assert retrying.load().name == "dc"
+    assert provider.calls == 3
+    assert delays == [1.0, 1.5]
+
+
+def test_retrying_provider_gives_up_after_attempt_budget():
+    provider = FlakyProvider(3, TransientError("cmdb unavailable"))
+    with pytest.raises(TransientError):
+        RetryingProvider(provider, Settings(2), sleep=lambda _: None).load()
+    assert provider.calls == 2
+
+
+def test_retrying_provider_does_not_retry_validation_errors():
+    provider = FlakyProvider(1, ValueError("invalid inventory"))
+    with pytest.raises(ValueError):
+        RetryingProvider(provider, Settings(3), sleep=lambda _: None).load()
+    assert provider.calls == 1
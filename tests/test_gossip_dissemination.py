@@ -0,0 +1,61 @@
+# -*- coding: utf-8 -*-#
+from horao.gossip.dissemination import (
+    PRIORITY_CRITICAL,
+    PRIORITY_REFRESH,
+    PRIORITY_STATUS,
+    DefaultPriority,
+    Dissemination,
+)
+from horao.models.binary import pack
+from horao.settings import Settings
+from tests import basic_networking_configuration
+
+
+def refresh(i):
+    return {"serial_number": f"srv{i:03}", "field": "disk_used_gb", "value": i}
+
+
+def status(serial_number, value="Down"):
+    return {"serial_number": serial_number, "status": value, "source": "peer"}
+
+
+def test_core_down_preempts_low_priority_backlog():
+    _, dcn, core, leaf, _, _ = basic_networking_configuration()
+    leaf.switch_type = leaf.switch_type.Access
+    priority = DefaultPriority.of([dcn])
+    assert priority.priority(status(core.serial_number)) == PRIORITY_CRITICAL
+    assert priority.priority(status(core.serial_number, "Up")) == PRIORITY_STATUS
+    assert priority.priority(status(leaf.serial_number)) == PRIORITY_STATUS
+    assert priority.priority(refresh(1)) == PRIORITY_REFRESH
+    queue = Dissemination(priority)
+    for i in range(200):
+        queue.enqueue(refresh(i))
+    queue.enqueue(status(core.serial_number))
+    updates = Dissemination.updates(queue.assemble(64))
+    assert updates[0] == status(core.serial_number)
+    assert all("status" not in u for u in updates[1:])
+
+
+def test_low_priority_updates_are_not_starved():
+    queue = Dissemination(settings=Settings(gossip_aging_rounds=2))
+    queue.enqueue(refresh(1))
+    size = max(len(pack([status("sw000")])), len(pack([refresh(1)])))
+    sent = []
+    for i in range(10):
+        # a steady stream of status updates that fill every datagram on their own
+        queue.enqueue(status(f"sw{i:03}"))
+        sent += Dissemination.updates(queue.assemble(size))
+    assert refresh(1) in sent
+
+
+def test_updates_are_dropped_after_retransmissions():
+    queue = Dissemination(settings=Settings(gossip_max_retransmissions=3))
+    queue.enqueue(status("sw1"))
+    queue.enqueue(refresh(1))
+    # a newer update replaces the pending one
+    queue.enqueue(refresh(1) | {"value": 2})
+    assert len(queue) == 2
+    for _ in range(3):
+        assert len(Dissemination.updates(queue.assemble(1024))) == 2
+    assert len(queue) == 0 and queue.dropped_updates == 2
+    assert Dissemination.updates(queue.assemble(1024)) == []
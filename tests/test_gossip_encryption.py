@@ -0,0 +1,79 @@
+# -*- coding: utf-8 -*-#
+import base64
+
+import pytest
+
+from horao.gossip.encryption import (
+    DecryptionFailed,
+    EncryptedTransport,
+    GossipCipher,
+    generate_key,
+)
+from horao.gossip.testkit import InMemoryNetwork, SimulatedClock
+from horao.models.binary import pack
+from horao.settings import GossipEncryptionMode, Secret, Settings
+
+
+def cipher(key: str) -> GossipCipher:
+    return GossipCipher(base64.b64decode(key))
+
+
+def test_encrypted_datagram_round_trip():
+    key = generate_key()
+    datagram = pack({"kind": "gossip", "updates": [{"serial_number": "srv1"}]})
+    frame = cipher(key).encrypt(datagram)
+    assert datagram not in frame
+    assert cipher(key).decrypt(frame) == datagram
+    # a fresh nonce per datagram
+    assert cipher(key).encrypt(datagram) != frame
+    with pytest.raises(DecryptionFailed, match="wrong key"):
+        cipher(generate_key()).decrypt(frame)
+    tampered = frame[:-1] + bytes([frame[-1] ^ 1])
+    with pytest.raises(DecryptionFailed):
+        cipher(key).decrypt(tampered)
+    with pytest.raises(DecryptionFailed, match="not an encrypted"):
+        cipher(key).decrypt(datagram)
+    with pytest.raises(ValueError, match="32 bytes"):
+        GossipCipher(b"short")
+
+
+def test_encrypted_transport_modes():
+    clock = SimulatedClock()
+    network = InMemoryNetwork(clock)
+    key = generate_key()
+    settings = Settings.builder().gossip_encryption_key(key).build()
+    grace = (
+        Settings.builder()
+        .gossip_encryption_key(key)
+        .gossip_encryption_mode("Grace")
+        .build()
+    )
+    sender = EncryptedTransport.wrap(network.transport("a"), settings)
+    required = EncryptedTransport.wrap(network.transport("b"), settings)
+    lenient = EncryptedTransport.wrap(network.transport("c"), grace)
+    other_key = Settings.builder().gossip_encryption_key(generate_key()).build()
+    outsider = EncryptedTransport.wrap(network.transport("d"), other_key)
+    plaintext = network.transport("e")
+    assert isinstance(lenient, EncryptedTransport)
+    assert lenient.mode == GossipEncryptionMode.Grace
+    # without a key the transport is left as is
+    assert EncryptedTransport.wrap(plaintext, Settings()) is plaintext
+    received = {m: [] for m in "bcd"}
+    for member, transport in [("b", required), ("c", lenient), ("d", outsider)]:
+        transport.on_receive(lambda peer, d, m=member: received[m].append((peer, d)))
+    for peer in "bcd":
+        sender.send(peer, b"hello")
+        plaintext.send(peer, b"plain")
+    clock.advance(1)
+    assert received["b"] == [("a", b"hello")]
+    assert received["c"] == [("a", b"hello"), ("e", b"plain")]
+    assert received["d"] == []
+    assert (required.rejected, lenient.rejected, outsider.rejected) == (1, 0, 2)
+
+
+def test_invalid_key_is_rejected():
+    with pytest.raises(ValueError, match="base64"):
+        GossipCipher.from_settings(
+            Settings(gossip_encryption_key=Secret("not base64!"))
+        )
+    assert GossipCipher.from_settings(Settings()) is None
@@ -0,0 +1,53 @@
+# -*- coding: utf-8 -*-#
+import asyncio
+
+import pytest
+
+from horao.gossip.membership import (
+    Joined,
+    Left,
+    Membership,
+    PeerStatus,
+    StatusChanged,
+)
+
+
+def test_every_subscriber_observes_join_and_leave():
+    async def scenario():
+        membership = Membership()
+        first, second = membership.subscribe(), membership.subscribe()
+        membership.join("node-b", 1)
+        membership.set_status("node-b", PeerStatus.Suspect)
+        membership.leave("node-b")
+        return [
+            [await asyncio.wait_for(q.get(), timeout=1) for _ in range(3)]
+            for q in (first, second)
+        ]
+
+    for events in asyncio.run(scenario()):
+        assert [type(e) for e in events] == [Joined, StatusChanged, Left]
+        assert all(e.peer == "node-b" for e in events)
+        assert events[1].status == PeerStatus.Suspect
+
+
+def test_stale_incarnations_are_ignored():
+    async def scenario():
+        membership = Membership()
+        queue = membership.subscribe()
+        membership.join("node-b", 2)
+        membership.set_status("node-b", PeerStatus.Suspect, incarnation=1)
+        membership.set_status("node-b", PeerStatus.Suspect)
+        # the peer refutes the suspicion with a new incarnation
+        membership.join("node-b", 3)
+        membership.unsubscribe(queue)
+        membership.leave("node-b")
+        events = []
+        while not queue.empty():
+            events.append(queue.get_nowait())
+        with pytest.raises(ValueError):
+            membership.set_status("node-c", PeerStatus.Alive)
+        return events
+
+    events = asyncio.run(scenario())
+    assert [type(e) for e in events] == [Joined, StatusChanged, StatusChanged]
+    assert (events[2].status, events[2].incarnation) == (PeerStatus.Alive, 3)
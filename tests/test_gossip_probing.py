@@ -0,0 +1,93 @@
+# -*- coding: utf-8 -*-#
+from horao.gossip.probing import ProbeStatistics, correlate, percentile
+from horao.models import NIC, DeviceStatus, Port, Server
+from horao.settings import Settings
+from tests import basic_networking_configuration
+
+
+def probed(statistics: ProbeStatistics, peer: str, rtts_ms, incarnation=1):
+    for sequence, rtt in enumerate(rtts_ms):
+        statistics.probe_sent(peer, incarnation, sequence, 100.0)
+        if rtt is None:
+            statistics.probe_timed_out(peer, sequence)
+        else:
+            statistics.ack_received(peer, sequence, 100.0 + rtt / 1000)
+
+
+def two_node_site():
+    """
+    srv (node-a) and srv2 (node-b) attached to different leaves below the core: srv - ser5 - ser3 - ser7 - srv2
+    """
+    dc, dcn, core, leaf_left, leaf_right, server = basic_networking_configuration()
+    port = Port("srv2_nic_port", "p", "p", 1, "", DeviceStatus.Down, 25)
+    other = Server(
+        "srv2",
+        "srv2",
+        "srv",
+        2,
+        [],
+        [],
+        [NIC("srv2_nic", "srv2_nic", "srv2_nic", 1, [port])],
+        [],
+        [],
+        DeviceStatus.Up,
+    )
+    dc.rows[0].cabinets[0].servers.append(other)
+    dcn.add_multiple([server.nic[0], other.nic[0]])
+    dcn.link(leaf_left, core)
+    dcn.link(leaf_right, core)
+    dcn.link(server.nic[0], leaf_left)
+    dcn.link(other.nic[0], leaf_right)
+    return dc, dcn, core, {"node-a": "srv", "node-b": "srv2"}
+
+
+def test_rtt_percentiles_from_synthetic_timings():
+    statistics = ProbeStatistics(Settings(gossip_probe_window=10))
+    probed(statistics, "node-b", [float(ms) for ms in range(1, 11)])
+    peer = statistics.peers["node-b"]
+    assert round(peer.rtt_percentile_ms(50), 6) == 5.0
+    assert round(peer.rtt_percentile_ms(95), 6) == 10.0
+    assert peer.loss == 0.0
+    # the window rolls, the oldest probes make way for newer ones
+    probed(statistics, "node-b", [None] * 5)
+    assert peer.probes == 10
+    assert peer.loss == 0.5
+    assert round(peer.rtt_percentile_ms(50), 6) == 8.0
+    assert statistics.to_dict()["node-b"]["loss"] == 0.5
+    assert percentile([], 50) is None
+
+
+def test_rtt_exceeding_the_modelled_path_is_flagged():
+    dc, dcn, _, members = two_node_site()
+    statistics = ProbeStatistics()
+    # three switches on the path, modelled RTT 0.3 ms
+    probed(statistics, "node-b", [0.4, 0.5, 0.6])
+    assert correlate(statistics, members, "node-a", dc, [dcn]) == []
+    probed(statistics, "node-b", [2.0] * 10)
+    (finding,) = correlate(statistics, members, "node-a", dc, [dcn])
+    assert finding.reason == "latency"
+    assert round(finding.modelled_ms, 6) == 0.3
+    assert finding.suspect_hop is None
+
+
+def test_loss_is_attributed_to_a_degraded_mid_path_switch():
+    dc, dcn, core, members = two_node_site()
+    statistics = ProbeStatistics()
+    probed(statistics, "node-b", [0.4, None, 0.4, None])
+    assert correlate(statistics, members, "node-a", dc, [dcn]) == []
+    core.status = DeviceStatus.Down
+    (finding,) = correlate(statistics, members, "node-a", dc, [dcn])
+    assert finding.to_dict()["reason"] == "loss"
+    assert finding.suspect_hop == core.serial_number
+
+
+def test_statistics_reset_on_incarnation_change():
+    statistics = ProbeStatistics()
+    probed(statistics, "node-b", [1.0, None])
+    statistics.probe_sent("node-b", 1, 99, 100.0)
+    # the peer restarted, the outstanding probe of the old incarnation is forgotten
+    probed(statistics, "node-b", [3.0], incarnation=2)
+    statistics.ack_received("node-b", 99, 100.1)
+    peer = statistics.peers["node-b"]
+    assert (peer.incarnation, peer.probes, peer.loss) == (2, 1, 0.0)
+    assert round(peer.rtt_percentile_ms(50), 6) == 3.0
@@ -0,0 +1,71 @@
+# -*- coding: utf-8 -*-#
+from horao.gossip.testkit import FaultRules, Scenario
+
+MEMBERS = [f"node-{i}" for i in range(8)]
+
+
+def test_members_converge_under_loss():
+    scenario = Scenario(MEMBERS, FaultRules(loss=0.2), seed=7)
+    scenario.nodes["node-3"].publish("srv1", "Down")
+    scenario.run_until_converged(within=60)
+    assert scenario.network.dropped > 0
+    assert all(n.state["srv1"] == (1, "Down") for n in scenario.live())
+
+
+def test_partition_heals_through_anti_entropy():
+    scenario = Scenario(MEMBERS, seed=3)
+    scenario.run_until_converged(within=30)
+    left, right = set(MEMBERS[:4]), set(MEMBERS[4:])
+    scenario.rules.partition(left, right)
+    scenario.nodes["node-0"].publish("srv1", "Down")
+    scenario.nodes["node-5"].publish("srv2", "Down")
+    scenario.run(20)
+    assert scenario.nodes["node-0"].live_members == left
+    assert "srv1" not in scenario.nodes["node-5"].state
+    scenario.rules.heal()
+    scenario.run_until_converged(within=60)
+    assert set(scenario.nodes["node-5"].state) == {"srv1", "srv2"}
+
+
+def test_duplicate_delivery_is_idempotent():
+    scenario = Scenario(MEMBERS, FaultRules(duplication=1.0), seed=5)
+    scenario.nodes["node-1"].publish("srv1", "Down")
+    scenario.nodes["node-1"].publish("srv1", "Up")
+    scenario.run_until_converged(within=30)
+    assert scenario.network.duplicated == scenario.network.sent
+    assert all(n.state == {"srv1": (2, "Up")} for n in scenario.live())
+
+
+def test_restarted_member_recovers_state():
+    scenario = Scenario(MEMBERS, seed=11)
+    scenario.nodes["node-2"].publish("srv1", "Down")
+    scenario.run_until_converged(within=30)
+    crashed = scenario.nodes["node-6"]
+    crashed.crash()
+    scenario.nodes["node-2"].publish("srv2", "Down")
+    scenario.run_until_converged(within=30)
+    assert all("node-6" not in n.live_members for n in scenario.live())
+    crashed.restart()
+    assert crashed.state == {}
+    scenario.run_until_converged(within=60)
+    assert crashed.state == {"srv1": (1, "Down"), "srv2": (1, "Down")}
+    assert all("node-6" in n.live_members for n in scenario.live())
+
+
+def test_member_with_an_incompatible_schema_is_refused():
+    scenario = Scenario(MEMBERS[:4], seed=13)
+    newer = scenario.nodes["node-3"]
+    newer.schema_version = "2.0"
+    newer.publish("srv1", "Down")
+    # a newer minor version is still readable
+    scenario.nodes["node-2"].schema_version = "1.1"
+    scenario.run(30)
+    compatible = [scenario.nodes[m] for m in MEMBERS[:3]]
+    for node in compatible:
+        assert node.live_members == set(MEMBERS[:3])
+        assert "srv1" not in node.state
+        assert node.rejected > 0
+    scenario.nodes["node-0"].publish("srv2", "Down")
+    scenario.run(30)
+    assert all(n.state == {"srv2": (1, "Down")} for n in compatible)
+    assert newer.state == {"srv1": (1, "Down")}
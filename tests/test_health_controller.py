@@ -0,0 +1,27 @@
+# -*- coding: utf-8 -*-#
+import json
+
+from horao.api.health_controller import health_response
+from horao.models import DeviceStatus
+from tests import basic_networking_configuration
+
+
+def test_health_response_healthy():
+    dc, dcn, core, leaf_left, leaf_right, _ = basic_networking_configuration()
+    dcn.link(leaf_left, core)
+    dcn.link(leaf_right, core)
+    status_code, body = health_response(dc, dcn)
+    assert status_code == 200
+    assert json.loads(body)["status"] == "healthy"
+
+
+def test_health_response_critical():
+    dc, dcn, core, leaf_left, leaf_right, _ = basic_networking_configuration()
+    dcn.link(leaf_left, core)
+    dcn.link(leaf_right, core)
+    core.status = DeviceStatus.Down
+    status_code, body = health_response(dc, dcn)
+    assert status_code == 503
+    content = json.loads(body)
+    assert content["status"] == "critical"
+    assert content["network"]["down"] == [core.serial_number]
@@ -0,0 +1,59 @@
+# -*- coding: utf-8 -*-#
+from horao.models import DataCenter, Row, Cabinet
+from horao.models.hardware import AffinityGroup, AffinityPolicy
+from tests import create_server
+
+
+
+
+
+def create_datacenter(placement) -> DataCenter:
+    return DataCenter(
+        "dc",
+        1,
+        [
+            Row(
+                "row",
+                1,
+                [
+                    Cabinet(name, name, "cab", i, servers, [], [])
+                    for i, (name, servers) in enumerate(placement.items())
+                ],
+            )
+        ],
+    )
+
+
+def test_anti_affinity_members_sharing_cabinet():
+    db = AffinityGroup("db", AffinityPolicy.AntiAffinity)
+    dc = create_datacenter(
+        {
+            "cab1": [
+                create_server("db1", affinity_groups=[db]),
+                create_server("db2", affinity_groups=[db]),
+            ],
+            "cab2": [create_server("db3", affinity_groups=[db])],
+        }
+    )
+    assert dc.affinity_violations() == [
+        "anti-affinity group db: db1, db2 share cabinet cab1"
+    ]
+
+
+def test_affinity_constraints_satisfied():
+    db = AffinityGroup("db", AffinityPolicy.AntiAffinity)
+    web = AffinityGroup("web", AffinityPolicy.Affinity)
+    dc = create_datacenter(
+        {
+            "cab1": [
+                create_server("db1", affinity_groups=[db]),
+                create_server("web1", affinity_groups=[web]),
+            ],
+            "cab2": [create_server("db2", affinity_groups=[db])],
+        }
+    )
+    assert dc.affinity_violations() == []
+    dc.rows[0].cabinets[1].servers.append(create_server("web2", affinity_groups=[web]))
+    assert dc.affinity_violations() == [
+        "affinity group web is spread across cabinets cab1, cab2"
+    ]
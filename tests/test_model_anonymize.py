@@ -0,0 +1,103 @@
+# -*- coding: utf-8 -*-#
+import ipaddress
+
+from horao.models import IpAddress
+from horao.models.anonymize import Pseudonymizer, pseudonymize
+from horao.models.generators import fat_tree
+from horao.models.ipam import IpPool
+from horao.models.network import NetworkTopology
+from horao.models.osi_layers import Segment
+from horao.models.serialization import to_json
+from tests import basic_networking_configuration
+
+
+def test_references_stay_consistent_across_a_link():
+    dc, dcn, cs, lsl, _, server = basic_networking_configuration()
+    dcn.add(server.nic[0])
+    dcn.link(server.nic[0], lsl)
+    dcn.link(lsl, cs)
+    pseudonymizer = Pseudonymizer("secret")
+    shared_dc, shared_dcn = pseudonymizer.pseudonymize([dc, dcn])
+    (shared_server,) = list(shared_dc.servers())
+    (shared_nic,) = shared_server.nic
+    assert shared_nic.serial_number != "srv_nic"
+    linked = {
+        (left.serial_number, right.serial_number)
+        for left, right, _ in shared_dcn.links()
+    }
+    nic, leaf = shared_nic.serial_number, pseudonymizer.identifier("sn", "ser5")
+    assert (nic, leaf) in linked or (leaf, nic) in linked
+    (network_nic,) = [n for n in shared_dcn.graph.nodes if n.serial_number == nic]
+    assert network_nic.lan_ports[0].mac == shared_nic.lan_ports[0].mac
+    assert shared_nic.lan_ports[0].mac != "m5"
+    assert shared_nic.lan_ports[0].speed_gb == 100
+    assert pseudonymizer.export_mapping()[nic] == "srv_nic"
+    assert pseudonymizer.reveal(f"{nic} is down") == "srv_nic is down"
+
+
+def test_metadata_values_are_pseudonymized():
+    _, _, cs, _, _, server = basic_networking_configuration()
+    cs.metadata = {"owner": "alice@corp", "asset_tag": "AT-123", "name": "core-a"}
+    server.set_meta("owner", "alice@corp")
+    pseudonymizer = Pseudonymizer("secret")
+    shared_switch, shared_server = pseudonymizer.pseudonymize([cs, server])
+    assert sorted(shared_switch.metadata) == ["asset_tag", "name", "owner"]
+    assert "alice@corp" not in to_json(shared_switch)
+    assert "AT-123" not in to_json(shared_switch)
+    # metadata keys named like model fields are values of the operator all the same
+    assert shared_switch.metadata["name"] == pseudonymizer.identifier("meta", "core-a")
+    assert shared_switch.metadata["name"] != pseudonymizer.identifier("name", "core-a")
+    # equal values map to equal pseudonyms, and can be revealed
+    owner = shared_switch.metadata["owner"]
+    assert shared_server.metadata["owner"] == owner
+    assert pseudonymizer.reveal(owner) == "alice@corp"
+
+
+def test_subnets_keep_prefix_length_and_containment():
+    pool = IpPool("dc", "10.0.0.0/16")
+    row = pool.delegate_subnet(20, "row")
+    cabinet = row.delegate_subnet(24, "cabinet")
+    host = cabinet.allocate(32)
+    (shared,) = pseudonymize([pool], "secret")
+    shared_row = shared.delegations[0]
+    shared_cabinet = shared_row.delegations[0]
+    assert shared.network != pool.network
+    assert shared_row.ip_network.prefixlen == 20
+    assert shared_cabinet.ip_network.subnet_of(shared_row.ip_network)
+    assert shared_row.ip_network.subnet_of(shared.ip_network)
+    shared_host = ipaddress.ip_network(shared_cabinet.allocations[0])
+    assert shared_host.subnet_of(shared_cabinet.ip_network)
+    assert str(shared_host) != host
+    assert shared.utilization() == pool.utilization()
+
+
+def test_segment_subnets_and_firewalls_are_mapped():
+    _, dcn, _, _, _, _ = basic_networking_configuration()
+    dcn.add_segment(
+        Segment("prod", [10], [IpAddress("10.10.0.0", "255.255.255.0", "")], ["fw"])
+    )
+    pseudonymizer = Pseudonymizer("secret")
+    (segment,) = pseudonymizer.pseudonymize(dcn).segments
+    assert segment.vlans == [10]
+    assert segment.firewalls == [pseudonymizer.identifier("sn", "fw")]
+    assert segment.subnets[0].address.endswith(".0")
+    assert segment.subnets[0].netmask == "255.255.255.0"
+
+
+def test_pseudonyms_are_deterministic_per_key():
+    dc, dcn, cs, lsl, _, _ = basic_networking_configuration()
+    dcn.link(lsl, cs)
+    first = to_json(pseudonymize([dc, dcn], "secret"))
+    assert first == to_json(pseudonymize([dc, dcn], "secret"))
+    assert first != to_json(pseudonymize([dc, dcn], "other"))
+    assert "ser3" not in first and "core" not in first
+
+
+def test_topology_detection_is_unchanged():
+    network = fat_tree(4)
+    shared = pseudonymize(network, "secret")
+    assert network.get_topology() == NetworkTopology.FatTree
+    assert shared.get_topology() == NetworkTopology.FatTree
+    assert not {n.serial_number for n in shared.graph.nodes} & {
+        n.serial_number for n in network.graph.nodes
+    }
@@ -0,0 +1,143 @@
+# -*- coding: utf-8 -*-#
+import os
+from typing import List
+
+import pytest
+
+from horao.models import DeviceStatus, Firewall, FirewallRule, IpAddress, Protocol
+from horao.models.audit import (
+    AuditLog,
+    AuditRecord,
+    JsonLinesSink,
+    MemorySink,
+    Operation,
+    ReplayDivergence,
+)
+from horao.models.hardware import DataCenter
+from horao.models.network import DataCenterNetwork
+from horao.models.serialization import Decoder, Encoder
+from horao.models.store import ModelStore, StatusChangeContext
+from tests import basic_networking_configuration
+
+
+def audited_store():
+    dc, dcn, _, _, _, _ = basic_networking_configuration()
+    dcn.add(Firewall("fw1", "fw1", "fw", 1, DeviceStatus.Up, [], []))
+    return ModelStore(dc, [dcn], AuditLog(MemorySink()))
+
+
+def copy_model(store: ModelStore):
+    document = Encoder().encode([store.datacenter, store.networks])
+    decoder = Decoder()
+    return decoder.decode(document[0], DataCenter), decoder.decode(
+        document[1], List[DataCenterNetwork]
+    )
+
+
+def mixed_operations(store: ModelStore) -> None:
+    store.link("dcn", "ser5", "ser3", "alice")
+    store.link("dcn", "ser7", "ser3", "alice")
+    store.set_status("ser7", DeviceStatus.Down, StatusChangeContext("snmp", "timeout"))
+    rule = FirewallRule(
+        "ssh",
+        "allow",
+        IpAddress("10.0.0.0", "255.0.0.0", ""),
+        IpAddress("0.0.0.0", "0.0.0.0", ""),
+        Protocol.TCP,
+        22,
+    )
+    store.add_firewall_rule("fw1", rule, "bob")
+    with pytest.raises(ValueError):
+        store.remove_firewall_rule("fw1", "telnet", "bob")
+    store.unlink("dcn", "ser7", "ser3", "alice")
+    store.apply_status_batch(
+        [
+            ("srv", DeviceStatus.Down, StatusChangeContext("gossip", timestamp=5)),
+            ("unknown", DeviceStatus.Down, StatusChangeContext("gossip")),
+        ]
+    )
+
+
+def test_append_and_query():
+    log = AuditLog()
+    log.append("alice", Operation.Link, {"left": "a", "right": "b"}, timestamp=10)
+    log.append("bob", Operation.SetStatus, {"serial_number": "a"}, timestamp=20)
+    log.append("alice", Operation.RemoveRule, {"firewall": "fw"}, "no rule", 30)
+    assert [r.sequence for r in log.records()] == [1, 2, 3]
+    assert [r.sequence for r in log.query(actor="alice")] == [1, 3]
+    assert [r.sequence for r in log.query(serial_number="a")] == [1, 2]
+    assert [r.sequence for r in log.query(start=15, end=30)] == [2, 3]
+    assert not log.query(start=15, end=30)[1].succeeded
+    record = log.records()[0]
+    assert record.to_json() == (
+        '{"actor":"alice","operation":"Link","payload":{"left":"a","right":"b"},'
+        '"result":"ok","sequence":1,"timestamp":10.0}'
+    )
+    assert AuditRecord.from_dict(record.to_dict()).to_json() == record.to_json()
+
+
+def test_store_mutations_are_audited():
+    store = audited_store()
+    mixed_operations(store)
+    records = store.audit.records()
+    assert [r.operation for r in records] == [
+        Operation.Link,
+        Operation.Link,
+        Operation.SetStatus,
+        Operation.AddRule,
+        Operation.RemoveRule,
+        Operation.Unlink,
+        Operation.SetStatus,
+        Operation.SetStatus,
+    ]
+    assert [r.succeeded for r in records].count(False) == 2
+    assert [r.sequence for r in store.audit.query(actor="bob")] == [4, 5]
+    assert [r.sequence for r in store.audit.query(serial_number="ser7")] == [2, 3, 6]
+
+
+def test_file_rotation(tmp_path):
+    path = os.path.join(str(tmp_path), "audit.jsonl")
+    sink = JsonLinesSink(path, max_bytes=300, backups=2)
+    log = AuditLog(sink)
+    for i in range(20):
+        log.append("alice", Operation.SetStatus, {"serial_number": f"ser{i}"})
+    assert sink.files() == [f"{path}.2", f"{path}.1", path]
+    assert all(os.path.getsize(f) <= 300 for f in sink.files())
+    sequences = [r.sequence for r in sink.records()]
+    # the oldest records were rotated out, the remaining ones are consecutive
+    assert sequences[-1] == 20 and sequences[0] > 1
+    assert sequences == list(range(sequences[0], 21))
+    # a new log on the same file continues the sequence
+    assert AuditLog(sink).append("bob", Operation.Link, {}).sequence == 21
+
+
+def test_replay_reproduces_fingerprint():
+    store = audited_store()
+    datacenter, networks = copy_model(store)
+    assert ModelStore(datacenter, networks).fingerprint() == store.fingerprint()
+    mixed_operations(store)
+    assert ModelStore(datacenter, networks).fingerprint() != store.fingerprint()
+    replayed = ModelStore.replay(
+        store.audit.records(), datacenter, networks, store.fingerprint()
+    )
+    assert replayed.fingerprint() == store.fingerprint()
+    assert replayed.datacenter.find_device("ser7", networks).status == DeviceStatus.Down
+
+
+def test_replay_detects_tampered_records():
+    store = audited_store()
+    datacenter, networks = copy_model(store)
+    mixed_operations(store)
+    records = [AuditRecord.from_dict(r.to_dict()) for r in store.audit.records()]
+    records[2].payload["status"] = "Up"
+    with pytest.raises(ReplayDivergence) as e:
+        ModelStore.replay(records, datacenter, networks, store.fingerprint())
+    assert "fingerprint" in str(e.value)
+    datacenter, networks = copy_model(audited_store())
+    with pytest.raises(ReplayDivergence) as e:
+        ModelStore.replay(
+            store.audit.records()[:2] + store.audit.records()[3:],
+            datacenter,
+            networks,
+        )
+    assert e.value.sequence == 4
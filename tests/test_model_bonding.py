@@ -0,0 +1,102 @@
+# -*- coding: utf-8 -*-#
+from horao.models import NIC, DeviceStatus, Port, Server, DataCenter, Row, Cabinet
+from horao.models.hardware import Bond, BondMode
+from horao.models.health import HealthState, health_report
+from horao.models.network import (
+    DataCenterNetwork,
+    LinkAggregate,
+    NetworkType,
+    SwitchType,
+)
+from horao.models.simulation import Invariant, InvariantType, rolling_maintenance
+from tests import create_switch
+
+
+def bonded_configuration(mode: BondMode, same_switch: bool = False):
+    nics = [
+        NIC(
+            f"nic{i}",
+            f"nic{i}",
+            "nic",
+            i,
+            [Port(f"np{i}", f"np{i}", "np", 1, f"nm{i}", DeviceStatus.Down, 25)],
+        )
+        for i in range(2)
+    ]
+    server = Server(
+        "srv",
+        "srv",
+        "srv",
+        1,
+        [],
+        [],
+        nics,
+        [],
+        [],
+        DeviceStatus.Up,
+        [Bond("bond0", mode, ["nic0", "nic1"])],
+    )
+    left = create_switch("acc_l", SwitchType.Access, 4, 1)
+    right = create_switch("acc_r", SwitchType.Access, 4, 1)
+    dcn = DataCenterNetwork("dcn", NetworkType.Data)
+    dcn.add_multiple([left, right] + nics)
+    dcn.link(nics[0], left)
+    dcn.link(nics[1], left if same_switch else right)
+    dcn.link(left, right)
+    dc = DataCenter(
+        "dc", 1, [Row("row", 1, [Cabinet("cab", "cab", "cab", 1, [server], [], [])])]
+    )
+    return dc, dcn, server, left, right
+
+
+def test_lacp_bond_matches_switch_link_aggregate():
+    dc, dcn, _, left, right = bonded_configuration(BondMode.Lacp8023ad)
+    assert len(dc.validate_bonds(dcn)) == 1
+    # MLAG spanning both access switches with the ports the NICs terminate on
+    left.link_aggregates.append(
+        LinkAggregate("po1", [left.lan_ports[0], right.lan_ports[0]])
+    )
+    assert dc.validate_bonds(dcn) == []
+
+
+def test_active_backup_bond_requires_different_switches():
+    dc, dcn, _, _, _ = bonded_configuration(BondMode.ActiveBackup)
+    assert dc.validate_bonds(dcn) == []
+    dc, dcn, _, _, _ = bonded_configuration(BondMode.ActiveBackup, same_switch=True)
+    problems = dc.validate_bonds(dcn)
+    assert len(problems) == 1
+    assert "same switch" in problems[0]
+
+
+def test_bonded_server_stays_reachable_when_one_member_dies():
+    dc, dcn, _, _, _ = bonded_configuration(BondMode.ActiveBackup)
+    results = rolling_maintenance(
+        dcn,
+        [["acc_r"], ["acc_l", "acc_r"]],
+        [Invariant(InvariantType.MaxUnreachableServers, 0)],
+        dc,
+    )
+    assert results[0].passed
+    assert not results[1].passed
+
+
+def test_health_flags_bond_without_redundancy():
+    dc, dcn, server, _, right = bonded_configuration(BondMode.ActiveBackup)
+    assert health_report(dc, dcn).bonds_degraded == []
+    right.status = DeviceStatus.Down
+    report = health_report(dc, dcn)
+    assert report.bonds_degraded == [server.serial_number]
+    assert report.state == HealthState.Degraded
+
+
+def test_lag_speed_mismatches():
+    switch = create_switch("acc", SwitchType.Access, 4, 1)
+    switch.link_aggregates.append(
+        LinkAggregate("po1", [switch.lan_ports[0], switch.lan_ports[1]])
+    )
+    assert switch.lag_speed_mismatches() == []
+    switch.lan_ports[2].speed_gb, switch.lan_ports[3].speed_gb = 10, 25
+    switch.link_aggregates.append(
+        LinkAggregate("po2", [switch.lan_ports[2], switch.lan_ports[3]])
+    )
+    assert switch.lag_speed_mismatches() == ["po2"]
@@ -0,0 +1,113 @@
+# -*- coding: utf-8 -*-#
+import time
+
+import pytest
+
+from horao.models.budget import AnalysisBudget, BudgetExceeded
+from horao.models.network import DataCenterNetwork, NetworkType, SwitchType
+from horao.models.simulation import Invariant, InvariantType, rolling_maintenance
+from horao.settings import Settings
+from tests import create_switch, two_pod_network
+
+
+def layered_network(layers: int, width: int) -> DataCenterNetwork:
+    """
+    Network of fully meshed layers between two switches, the number of equal cost paths is width ** layers
+    """
+    network = DataCenterNetwork("layered", NetworkType.Data)
+    left = create_switch("left", SwitchType.Access, 0, width)
+    right = create_switch("right", SwitchType.Access, width, 0)
+    columns = [
+        [
+            create_switch(f"s{layer}-{i}", SwitchType.Distribution, width, 1)
+            for i in range(width)
+        ]
+        for layer in range(layers)
+    ]
+    network.add_multiple([left, right] + [s for column in columns for s in column])
+    for switch in columns[0]:
+        network.link(left, switch)
+    for upper, lower in zip(columns, columns[1:]):
+        for u in upper:
+            for v in lower:
+                network.link(u, v)
+    for switch in columns[-1]:
+        network.link(switch, right)
+    return network
+
+
+def test_equal_cost_paths_without_budget():
+    network = layered_network(2, 3)
+    left, right = list(network.graph_view().nodes)[:2]
+    paths = network.equal_cost_paths(left, right)
+    assert len(paths) == 9
+    assert all(len(p) == 4 and p[0] == left and p[-1] == right for p in paths)
+
+
+def test_explosive_equal_cost_paths_stop_at_node_budget():
+    network = layered_network(6, 8)
+    left, right = list(network.graph_view().nodes)[:2]
+    with pytest.raises(BudgetExceeded) as e:
+        network.equal_cost_paths(
+            left, right, AnalysisBudget(max_nodes_visited=10_000)
+        )
+    assert "nodes" in e.value.reason
+    # partial results are complete paths
+    assert 0 < len(e.value.partial) < 8**6
+    assert all(p[0] == left and p[-1] == right for p in e.value.partial)
+
+
+def test_equal_cost_paths_stop_at_result_budget():
+    network = layered_network(2, 3)
+    left, right = list(network.graph_view().nodes)[:2]
+    with pytest.raises(BudgetExceeded) as e:
+        network.equal_cost_paths(left, right, AnalysisBudget(max_results=4))
+    assert len(e.value.partial) == 4
+
+
+def test_duration_limit_is_honoured():
+    network = layered_network(8, 8)
+    left, right = list(network.graph_view().nodes)[:2]
+    started = time.monotonic()
+    with pytest.raises(BudgetExceeded) as e:
+        network.equal_cost_paths(left, right, AnalysisBudget(max_duration=0.1))
+    assert time.monotonic() - started < 0.2
+    assert "longer" in e.value.reason
+
+
+def test_metrics_and_simulation_respect_the_budget():
+    network = two_pod_network()
+    assert network.diameter(AnalysisBudget(max_nodes_visited=1000)) is not None
+    with pytest.raises(BudgetExceeded):
+        network.diameter(AnalysisBudget(max_nodes_visited=1))
+    with pytest.raises(BudgetExceeded):
+        network.average_path_length(AnalysisBudget(max_nodes_visited=1))
+    waves = [[d.serial_number] for d in network.graph_view().nodes]
+    with pytest.raises(BudgetExceeded) as e:
+        rolling_maintenance(
+            network,
+            waves,
+            [Invariant(InvariantType.NoPartition)],
+            budget=AnalysisBudget(max_results=2),
+        )
+    assert len(e.value.partial) == 2
+
+
+def test_requested_budget_is_clamped_to_ceiling():
+    settings = Settings(
+        analysis_max_duration=1.0,
+        analysis_max_nodes_visited=100,
+        analysis_ceiling_duration=5.0,
+        analysis_ceiling_nodes_visited=1000,
+        analysis_ceiling_results=10,
+    )
+    defaults = AnalysisBudget.from_settings(settings)
+    assert (defaults.max_duration, defaults.max_nodes_visited) == (1.0, 100)
+    # the configured default is above the ceiling
+    assert defaults.max_results == 10
+    requested = AnalysisBudget.from_settings(
+        settings, max_duration=60.0, max_nodes_visited=500
+    )
+    assert (requested.max_duration, requested.max_nodes_visited) == (5.0, 500)
+    with pytest.raises(ValueError):
+        AnalysisBudget.from_settings(settings, max_results=0)
@@ -0,0 +1,52 @@
+# -*- coding: utf-8 -*-#
+import os
+
+import pytest
+
+from horao.models import LifecycleState, Server, Switch, SwitchType
+from horao.models.catalog import CatalogError, DeviceCatalog, enrich
+from tests import basic_networking_configuration
+
+FIXTURES = os.path.join(os.path.dirname(__file__), "fixtures")
+
+
+def test_switch_and_server_from_catalog():
+    catalog = DeviceCatalog.load(os.path.join(FIXTURES, "catalog.toml"))
+    switch = Switch.from_catalog(catalog, "dcs-7050", "sw-ser1", "sw1")
+    assert switch.switch_type == SwitchType.Access
+    assert [p.name for p in switch.lan_ports] == ["eth1", "eth2"]
+    assert [p.speed_gb for p in switch.uplink_ports] == [100]
+    assert switch.size_ru == 1 and switch.rated_power_watts == 450
+    assert switch.port_capacity == 3 and not switch.is_overprovisioned()
+    assert switch.max_vlans == 64 and switch.max_mac_entries is None
+    assert switch.lifecycle == LifecycleState.Provisioning
+    server = Server.from_catalog(catalog, "r650", "srv-ser1", "srv1")
+    assert sum(c.cores for c in server.cpu) == 64
+    assert sum(r.size_gb for r in server.ram) == 512
+    assert [len(n.lan_ports) for n in server.nic] == [2]
+    with pytest.raises(ValueError):
+        Switch.from_catalog(catalog, "unknown", "sw-ser2", "sw2")
+
+
+def test_enrich_fills_only_missing_fields_and_reports_unknown_models():
+    catalog = DeviceCatalog.load(os.path.join(FIXTURES, "catalog.toml"))
+    dc, dcn, core, leaf_left, leaf_right, _ = basic_networking_configuration()
+    core.model = leaf_left.model = "dcs-7050"
+    leaf_left.rated_power_watts = 300
+    unknown = enrich(dcn, catalog)
+    assert unknown == [f"{leaf_right.serial_number} ({leaf_right.model})"]
+    assert core.size_ru == 1 and core.rated_power_watts == 450
+    assert leaf_left.size_ru == 1 and leaf_left.rated_power_watts == 300
+    assert leaf_right.size_ru is None
+    assert core.max_vlans == 64 and leaf_right.max_vlans is None
+    # servers are only known in the datacenter
+    assert "srv (srv)" in enrich(dc, catalog)
+
+
+def test_catalog_loading_error_names_offending_key():
+    with pytest.raises(CatalogError) as e:
+        DeviceCatalog.load(os.path.join(FIXTURES, "catalog_invalid.json"))
+    assert e.value.key == "dcs-7050.ports[1].speed_gb"
+    assert "catalog_invalid.json" in str(e.value)
+    with pytest.raises(CatalogError):
+        DeviceCatalog.load(os.path.join(FIXTURES, "does_not_exist.toml"))
@@ -0,0 +1,82 @@
+# -*- coding: utf-8 -*-#
+from horao.models import (
+    Cabinet,
+    Chassis,
+    DataCenter,
+    DeviceStatus,
+    Row,
+    Server,
+)
+from horao.models.hardware import (
+    FaultDomainKind,
+    SharedResource,
+    SharedResourceType,
+)
+from horao.models.network import DataCenterNetwork, NetworkType, SwitchType
+from tests import create_server, create_switch
+
+
+def create_blade(serial_number: str, status: DeviceStatus) -> Server:
+    return Server(serial_number, serial_number, "blade", 1, [], [], [], [], [], status)
+
+
+def create_chassis(midplane: DeviceStatus) -> Chassis:
+    return Chassis(
+        "ch1",
+        "ch1",
+        "chassis",
+        1,
+        [
+            create_blade("b1", DeviceStatus.Up),
+            create_blade("b2", DeviceStatus.Up),
+            create_blade("b3", DeviceStatus.Down),
+        ],
+        [
+            SharedResource("psu", SharedResourceType.Power, DeviceStatus.Up),
+            SharedResource("midplane", SharedResourceType.Midplane, midplane),
+            SharedResource(
+                "fan", SharedResourceType.Cooling, DeviceStatus.Down, critical=False
+            ),
+        ],
+    )
+
+
+def test_chassis_available_with_critical_resources_up():
+    chassis = create_chassis(DeviceStatus.Up)
+    # the non-critical fan does not affect availability
+    assert chassis.is_available()
+    assert [s.serial_number for s in chassis.available_servers()] == ["b1", "b2"]
+
+
+def test_down_midplane_makes_all_blades_unavailable():
+    chassis = create_chassis(DeviceStatus.Down)
+    assert all(s.status == DeviceStatus.Up for s in chassis.servers[:2])
+    assert not chassis.is_available()
+    assert chassis.available_servers() == []
+
+
+def test_chassis_without_shared_resources_is_available():
+    assert Chassis("ch2", "ch2", "chassis", 2, []).is_available()
+
+
+def test_fault_domains_of_tor_and_chassis():
+    rack = [create_server(s, nic_speed_gb=25) for s in ("s1", "s2", "s3")]
+    chassis = create_chassis(DeviceStatus.Up)
+    cabinet = Cabinet("cab1", "cab1", "cab", 1, rack, [chassis], [])
+    dc = DataCenter("dc", 1, [Row("row", 1, [cabinet])])
+    tor1 = create_switch("tor1", SwitchType.Access, 4, 1)
+    tor2 = create_switch("tor2", SwitchType.Access, 4, 1)
+    network = DataCenterNetwork("dcn", NetworkType.Data)
+    network.add_multiple([tor1, tor2] + [s.nic[0] for s in rack])
+    network.link(rack[0].nic[0], tor1)
+    network.link(rack[1].nic[0], tor1)
+    network.link(rack[2].nic[0], tor2)
+    domains = [
+        (d.kind, d.name, sorted(d.servers)) for d in dc.fault_domains([network])
+    ]
+    assert domains == [
+        (FaultDomainKind.Cabinet, "cab1", ["b1", "b2", "b3", "s1", "s2", "s3"]),
+        (FaultDomainKind.Chassis, "ch1", ["b1", "b2", "b3"]),
+        (FaultDomainKind.Switch, "tor1", ["s1", "s2"]),
+        (FaultDomainKind.Switch, "tor2", ["s3"]),
+    ]
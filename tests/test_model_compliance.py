@@ -0,0 +1,84 @@
+# -*- coding: utf-8 -*-#
+import os
+
+import pytest
+
+from horao.models import DeviceStatus, Port
+from horao.models.compliance import Deviation, check_compliance, load_profiles
+from horao.models.network import DataCenterNetwork, NetworkType, SwitchType
+from horao.models.osi_layers import Medium
+from horao.settings import ConfigError
+from tests import create_switch
+
+FIXTURES = os.path.join(os.path.dirname(__file__), "fixtures")
+
+
+def compliant_network():
+    switch = create_switch("acc", SwitchType.Access, 2, 0, speed_gb=10)
+    for port in switch.lan_ports:
+        port.medium = Medium.Copper
+        port.access_vlan = 100
+    switch.uplink_ports.append(
+        Port("acc-up", "up", "swp", 2, "acc-um", DeviceStatus.Down, 100)
+    )
+    switch.uplink_ports[0].trunk_vlans = [200, 100]
+    dcn = DataCenterNetwork("dcn", NetworkType.Data)
+    dcn.add(switch)
+    return dcn, switch
+
+
+def profiles():
+    return load_profiles(os.path.join(FIXTURES, "port_profiles.toml"))
+
+
+def test_compliant_switch():
+    dcn, _ = compliant_network()
+    report = check_compliance(dcn, profiles(), strict=True)
+    assert report.compliant
+    assert report.ports_checked == 3
+
+
+def test_deviations():
+    dcn, switch = compliant_network()
+    switch.lan_ports[0].speed_gb = 25
+    switch.lan_ports[0].medium = Medium.Fiber
+    switch.lan_ports[1].access_vlan = 101
+    switch.uplink_ports[0].trunk_vlans = [100]
+    switch.uplink_ports[0].admin_up = False
+    report = check_compliance(dcn, profiles())
+    assert [(f.port, f.deviation) for f in report.findings["acc"]] == [
+        ("lp0", Deviation.Speed),
+        ("lp0", Deviation.Medium),
+        ("lp1", Deviation.Vlan),
+        ("up", Deviation.Vlan),
+        ("up", Deviation.AdminDown),
+    ]
+    assert report.to_dict()["findings"]["acc"][0]["deviation"] == "Speed"
+
+
+def test_strict_reports_uncovered_ports():
+    dcn, switch = compliant_network()
+    switch.lan_ports.append(
+        Port("acc-x", "x", "swp", 9, "acc-xm", DeviceStatus.Down, 10)
+    )
+    assert check_compliance(dcn, profiles()).compliant
+    [finding] = check_compliance(dcn, profiles(), strict=True).findings["acc"]
+    assert (finding.port, finding.deviation) == ("x", Deviation.Uncovered)
+
+
+def test_load_profiles():
+    access, uplink = profiles()
+    assert access.matcher.switch_type == SwitchType.Access
+    assert (access.matcher.first_port, access.matcher.last_port) == (0, 1)
+    assert access.medium == Medium.Copper
+    assert (uplink.matcher.first_port, uplink.matcher.last_port) == (2, 2)
+    assert uplink.trunk_vlans == [100, 200]
+
+
+def test_load_invalid_profiles_names_the_key(tmp_path):
+    path = os.path.join(str(tmp_path), "profiles.toml")
+    with open(path, "w") as f:
+        f.write('[[profile]]\nname = "access"\nswitch_type = "Spine"\n')
+    with pytest.raises(ConfigError) as e:
+        load_profiles(path)
+    assert e.value.key == "profile[0].switch_type"
@@ -0,0 +1,142 @@
+# -*- coding: utf-8 -*-#
+import sys
+import threading
+
+import pytest
+
+from horao.models import (
+    DeviceStatus,
+    Firewall,
+    FirewallRule,
+    IpAddress,
+    Packet,
+    PortMatcher,
+    Protocol,
+)
+from horao.models.network import evaluate_path
+from horao.models.serialization import Decoder, Encoder
+
+
+def firewall_with_rules():
+    lan = IpAddress("10.0.0.0", "255.255.255.0", "10.0.0.1")
+    wan = IpAddress("0.0.0.0", "0.0.0.0", "192.168.1.1")
+    return Firewall(
+        "fw1",
+        "fw1",
+        "fw",
+        1,
+        DeviceStatus.Up,
+        [],
+        [],
+        [
+            FirewallRule("https", "allow", wan, lan, Protocol.TCP, 443),
+            FirewallRule("ssh", "allow", lan, lan, Protocol.TCP, 22),
+            FirewallRule("dns", "allow", lan, wan, Protocol.UDP, 53),
+        ],
+    )
+
+
+def test_firewall_rule_hit_counters():
+    fw = firewall_with_rules()
+    assert fw.unused_rules() == [0, 1, 2]
+    assert fw.evaluate(Packet("8.8.8.8", "10.0.0.5", Protocol.TCP, 443)) == "allow"
+    assert fw.evaluate(Packet("8.8.8.8", "10.0.0.6", Protocol.TCP, 443)) == "allow"
+    assert fw.evaluate(Packet("10.0.0.5", "1.1.1.1", Protocol.UDP, 53)) == "allow"
+    # does not match any rule, so no counters are touched
+    assert fw.evaluate(Packet("8.8.8.8", "10.0.0.5", Protocol.TCP, 22)) == "deny"
+    assert [r.hit_count for r in fw.rules] == [2, 0, 1]
+    assert fw.unused_rules() == [1]
+    # simulations leave the counters untouched
+    ssh = Packet("10.0.0.5", "10.0.0.6", Protocol.TCP, 22)
+    assert fw.evaluate(ssh, count=False) == "allow"
+    assert fw.rules[1].matches(ssh, count=False)
+    assert [r.hit_count for r in fw.rules] == [2, 0, 1]
+
+
+def test_firewall_rule_hit_counter_is_atomic():
+    rule = firewall_with_rules().rules[0]
+    packet = Packet("8.8.8.8", "10.0.0.5", Protocol.TCP, 443)
+
+    def hit():
+        for _ in range(2000):
+            rule.matches(packet)
+
+    interval = sys.getswitchinterval()
+    # switch threads as often as possible to provoke lost updates
+    sys.setswitchinterval(1e-6)
+    try:
+        threads = [threading.Thread(target=hit) for _ in range(8)]
+        for thread in threads:
+            thread.start()
+        for thread in threads:
+            thread.join()
+    finally:
+        sys.setswitchinterval(interval)
+    assert rule.hit_count == 8 * 2000
+    document = Encoder().encode(rule)
+    assert "hit_count" not in document and "_hit_lock" not in document
+
+
+def test_port_matchers():
+    single = PortMatcher.parse("443")
+    assert single == PortMatcher.single(443)
+    assert single.matches(443) and not single.matches(442) and not single.matches(444)
+    ranged = PortMatcher.parse("8000-8100")
+    assert ranged.matches(8000) and ranged.matches(8100)
+    assert not ranged.matches(7999) and not ranged.matches(8101)
+    listed = PortMatcher.parse("80, 443,8000-8100")
+    assert [p for p in [79, 80, 81, 443, 8050, 8101] if listed.matches(p)] == [
+        80,
+        443,
+        8050,
+    ]
+    assert str(listed) == "80,443,8000-8100"
+    for invalid in ["", "80-", "http", "1-2-3", "8100-8000", "70000"]:
+        with pytest.raises(ValueError):
+            PortMatcher.parse(invalid)
+    # normalized, the same ports are equal however they are written
+    assert PortMatcher.parse("443,80") == PortMatcher.parse("80,443")
+    assert str(PortMatcher.parse("8050-8100,80,8000-8060,79")) == "79-80,8000-8100"
+    assert len({PortMatcher.parse("443,80"), PortMatcher.parse("80,443")}) == 1
+
+
+def test_rule_with_port_range():
+    any_address = IpAddress("0.0.0.0", "0.0.0.0", "")
+    rule = FirewallRule(
+        "web", "allow", any_address, any_address, Protocol.TCP, "80,443,8000-8100"
+    )
+    assert rule.port == PortMatcher([(80, 80), (443, 443), (8000, 8100)])
+    for port, expected in [(80, True), (8100, True), (8101, False), (22, False)]:
+        packet = Packet("10.0.0.1", "10.0.0.2", Protocol.TCP, port)
+        assert rule.matches(packet) == expected
+    # written in the human-friendly form, numbers of older documents still decode
+    document = Encoder().encode(rule)
+    assert document["port"] == "80,443,8000-8100"
+    assert Decoder().decode(document, FirewallRule).port == rule.port
+    document["port"] = 22
+    assert Decoder().decode(document, FirewallRule).port == PortMatcher.single(22)
+
+
+def test_evaluate_path_deny_wins():
+    lan = IpAddress("10.0.0.0", "255.255.255.0", "10.0.0.1")
+    wan = IpAddress("0.0.0.0", "0.0.0.0", "192.168.1.1")
+    perimeter = firewall_with_rules()
+    inner = Firewall(
+        "fw2",
+        "fw2",
+        "fw",
+        1,
+        DeviceStatus.Up,
+        [],
+        [],
+        [FirewallRule("no-https", "deny", wan, lan, Protocol.TCP, 443)],
+    )
+    https = Packet("8.8.8.8", "10.0.0.5", Protocol.TCP, 443)
+    assert evaluate_path([], https) == "allow"
+    assert evaluate_path([perimeter, perimeter], https) == "allow"
+    assert perimeter.rules[0].hit_count == 1
+    assert evaluate_path([perimeter, inner], https) == "deny"
+    assert inner.rules[0].hit_count == 1
+    # dropped at the first firewall, the second never sees the packet
+    assert evaluate_path([inner, perimeter], https) == "deny"
+    assert perimeter.rules[0].hit_count == 2
@@ -0,0 +1,48 @@
+# -*- coding: utf-8 -*-#
+import json
+
+import pytest
+
+from horao.models.forecasting import ForecastMethod, exhaustion_estimates, forecast
+from horao.models.hardware import Disk
+from horao.models.snapshot import SnapshotStore
+from tests import basic_networking_configuration
+
+
+def test_linear_regression_hits_known_exhaustion_date():
+    # 10 GB per day on a 1000 GB disk, starting at 500 GB: full at day 50
+    series = [(day * 86400.0, 500.0 + 10 * day) for day in range(10)]
+    projection = forecast(series, 60 * 86400)
+    assert projection.crossing(1000) == pytest.approx(50 * 86400)
+    assert projection.lower == pytest.approx(projection.upper)
+    holt = forecast(series, 60 * 86400, ForecastMethod.Holt)
+    assert holt.crossing(1000) == pytest.approx(50 * 86400)
+
+
+def test_too_few_points_and_flat_trend():
+    assert forecast([(0.0, 1.0), (1.0, 2.0)], 10) is None
+    flat = forecast([(float(t), 100.0) for t in range(5)], 10)
+    assert flat.crossing(1000) is None
+
+
+def test_exhaustion_estimates_roll_up_server_storage(tmp_path):
+    store = SnapshotStore(str(tmp_path))
+    dc, dcn, _, _, _, server = basic_networking_configuration()
+    server.disk = [
+        Disk("d1", "d1", "ssd", 1, 1000, 0),
+        Disk("d2", "d2", "ssd", 2, 1000, 0),
+    ]
+    for day in range(5):
+        # d1 grows 100 GB per day, d2 stays flat
+        server.disk[0].usage_gb = 100 * day
+        server.disk[1].usage_gb = 500
+        store.put(day * 86400.0, dc, [dcn])
+    estimates = exhaustion_estimates(store, 30 * 86400)
+    assert [(e.kind, e.serial_number) for e in estimates] == [
+        ("disk", "d1"),
+        ("server storage", "srv"),
+    ]
+    assert estimates[0].exhausted_at == pytest.approx(10 * 86400)
+    assert estimates[1].exhausted_at == pytest.approx(15 * 86400)
+    assert json.loads(json.dumps(estimates[1].to_dict()))["limit"] == 2000
+    assert exhaustion_estimates(store, 5 * 86400) == []
@@ -0,0 +1,47 @@
+# -*- coding: utf-8 -*-#
+import pytest
+
+from horao.models import DeviceStatus
+from horao.models.generators import clos, fat_tree, tree
+from horao.models.network import NetworkTopology, Switch, SwitchType
+
+
+def test_fat_tree_is_detected():
+    network = fat_tree(4)
+    switches = [n for n in network.graph.nodes if isinstance(n, Switch)]
+    assert len(switches) == 20 and network.graph.number_of_edges() == 32
+    assert network.get_topology() == NetworkTopology.FatTree
+    # every access switch keeps k/2 ports for servers
+    assert all(
+        [p.status for p in s.lan_ports] == [DeviceStatus.Down] * 2
+        for s in switches
+        if s.switch_type == SwitchType.Access
+    )
+    assert network.diameter() == 4
+    # the smallest fat tree is a plain tree
+    assert fat_tree(2).get_topology() == NetworkTopology.Tree
+    with pytest.raises(ValueError):
+        fat_tree(3)
+
+
+def test_broken_fat_tree_is_not_detected():
+    network = fat_tree(4)
+    left, right = next(
+        (l, r)
+        for l, r in network.graph.edges
+        if l.switch_type == SwitchType.Distribution
+    )
+    network.graph.remove_edge(left, right)
+    assert network.get_topology() == NetworkTopology.Undefined
+
+
+def test_tree_and_clos():
+    network = tree(3, 2)
+    assert network.graph.number_of_nodes() == 7
+    assert network.get_topology() == NetworkTopology.Tree
+    assert network.diameter() == 4
+    network = clos(2, 4, 8)
+    assert network.graph.number_of_edges() == 8
+    assert network.get_topology() == NetworkTopology.Undefined
+    assert network.diameter() == 2
+    assert network.are_isolated("leaf0", "leaf3") is False
@@ -0,0 +1,79 @@
+# -*- coding: utf-8 -*-#
+import pytest
+
+from horao.models.ipam import IpPool
+from horao.models.snapshot import SnapshotStore
+from tests import basic_networking_configuration
+
+
+def datacenter_pools():
+    dc = IpPool("dc", "10.0.0.0/8")
+    row = dc.delegate_subnet(16, "row1")
+    cabinet = row.delegate_subnet(24, "cab1")
+    return dc, row, cabinet
+
+
+def test_delegation_chain():
+    dc, row, cabinet = datacenter_pools()
+    assert row.network == "10.0.0.0/16" and row.parent is dc
+    assert cabinet.network == "10.0.0.0/24" and cabinet.parent is row
+    assert dc.delegate_subnet(16, "row2").network == "10.1.0.0/16"
+    assert cabinet.allocate(32) == "10.0.0.0/32"
+    assert cabinet.allocate(32) == "10.0.0.1/32"
+    # the delegated ranges are not available to the parent
+    assert row.allocate(24) == "10.0.1.0/24"
+    with pytest.raises(ValueError):
+        cabinet.delegate_subnet(16, "too large")
+
+
+def test_utilization_rolls_up():
+    dc, row, cabinet = datacenter_pools()
+    assert dc.utilization() == 0
+    for _ in range(64):
+        cabinet.allocate(32)
+    row.allocate(24)
+    assert cabinet.utilization() == 0.25
+    assert row.utilization() == (64 + 256) / 65536
+    assert dc.allocated_addresses() == 64 + 256
+
+
+def test_find_free_subnet_searches_the_tree():
+    dc, row, cabinet = datacenter_pools()
+    assert dc.find_free_subnet(28) == (cabinet, "10.0.0.0/28")
+    assert dc.find_free_subnet(20) == (row, "10.0.16.0/20")
+    assert cabinet.find_free_subnet(16) is None
+
+
+def test_deletion_with_live_allocations_is_refused():
+    dc, row, cabinet = datacenter_pools()
+    address = cabinet.allocate(32)
+    with pytest.raises(ValueError):
+        dc.release_delegation(row)
+    with pytest.raises(ValueError):
+        row.release_delegation(cabinet)
+    cabinet.release(address)
+    row.release_delegation(cabinet)
+    assert row.delegations == [] and cabinet.parent is None
+
+
+def test_released_delegation_is_reused():
+    _, row, cabinet = datacenter_pools()
+    second = row.delegate_subnet(24, "cab2")
+    assert second.network == "10.0.1.0/24"
+    row.release_delegation(cabinet)
+    assert row.delegate_subnet(24, "cab3").network == "10.0.0.0/24"
+
+
+def test_pools_round_trip_with_snapshot(tmp_path):
+    dc, dcn, _, _, _, _ = basic_networking_configuration()
+    pool, _, cabinet = datacenter_pools()
+    cabinet.allocate(32)
+    store = SnapshotStore(str(tmp_path))
+    store.put(1.0, dc, [dcn], [pool])
+    (loaded,) = store.load(1.0).pools
+    assert loaded.network == "10.0.0.0/8"
+    (loaded_row,) = loaded.delegations
+    (loaded_cabinet,) = loaded_row.delegations
+    assert loaded_row.parent is loaded and loaded_cabinet.parent is loaded_row
+    assert loaded_cabinet.allocations == ["10.0.0.0/32"]
+    assert loaded.utilization() == pool.utilization()
@@ -0,0 +1,67 @@
+# -*- coding: utf-8 -*-#
+import pytest
+
+from horao.models import CPU, RAM, DataCenter, DeviceStatus, Server
+from horao.models.journal import DeviceAdded, DeviceRemoved, Journal, StatusSet
+from horao.models.network import SwitchType
+from horao.models.serialization import Decoder, Encoder
+from tests import basic_networking_configuration, create_switch
+
+
+def test_replay_reproduces_final_state():
+    dc, _, _, _, _, srv = basic_networking_configuration()
+    base = Decoder().decode(Encoder().encode(dc), DataCenter)
+    cabinet = dc.rows[0].cabinets[0]
+    added = Server("srv2", "srv2", "srv", 2, [], [], [], [], [], DeviceStatus.Up)
+    journal = Journal()
+    for change in [
+        DeviceAdded(cabinet.serial_number, added),
+        DeviceAdded(
+            cabinet.serial_number, create_switch("tor", SwitchType.Access, 4, 2)
+        ),
+        StatusSet("srv2", DeviceStatus.Down),
+        StatusSet("tor", DeviceStatus.Down),
+        DeviceRemoved(srv.serial_number),
+    ]:
+        journal.record(change)
+        change.apply(dc)
+    assert [e.sequence for e in journal] == [1, 2, 3, 4, 5]
+    replayed = journal.replay(base)
+    assert Encoder().encode(replayed) == Encoder().encode(dc)
+    assert [s.serial_number for s in base.servers()] == ["srv"]
+    base.apply_journal(journal)
+    assert Encoder().encode(base) == Encoder().encode(dc)
+
+
+def test_journal_records_devices_as_added():
+    dc, _, _, _, _, _ = basic_networking_configuration()
+    base = Decoder().decode(Encoder().encode(dc), DataCenter)
+    added = Server("srv2", "srv2", "srv", 2, [], [], [], [], [], DeviceStatus.Up)
+    journal = Journal()
+    journal.record(DeviceAdded(dc.rows[0].cabinets[0].serial_number, added))
+    added.name = "renamed"
+    assert journal.replay(base).find_device("srv2", []).name == "srv2"
+    journal.record(DeviceRemoved("unknown"))
+    with pytest.raises(ValueError, match="entry 2"):
+        journal.replay(base)
+
+
+def test_capacity_delta_of_removing_a_server():
+    dc, _, _, _, _, srv = basic_networking_configuration()
+    srv.cpu = [CPU(f"cpu{i}", "cpu", "cpu", i, 3000, 64, None) for i in range(8)]
+    srv.ram = [RAM("ram0", "ram", "ram", 1, 256, None)]
+    journal = Journal()
+    journal.record(DeviceRemoved(srv.serial_number))
+    delta = journal.capacity_delta(dc)
+    assert (delta.servers, delta.cpus, delta.cores) == (-1, -8, -512)
+    assert (delta.ram_gb, delta.disk_gb) == (-256, 0)
+    assert delta.describe() == (
+        "removes 1 server, removes 512 cores, removes 256 GB RAM"
+    )
+    # the base is left untouched
+    assert [s.serial_number for s in dc.servers()] == [srv.serial_number]
+    added = Server("srv2", "srv2", "srv", 2, [], [], [], [], [], DeviceStatus.Up)
+    journal = Journal()
+    journal.record(DeviceAdded(dc.rows[0].cabinets[0].serial_number, added))
+    assert journal.capacity_delta(dc).describe() == "adds 1 server"
+    assert Journal().capacity_delta(dc).describe() == "no capacity change"
@@ -0,0 +1,119 @@
+# -*- coding: utf-8 -*-#
+import pytest
+
+from horao.models import CPU, DeviceStatus, LifecycleState, Server
+from horao.models.hardware import AffinityGroup, AffinityPolicy, TransactionAborted
+from horao.models.serialization import Encoder
+from tests import basic_networking_configuration
+
+
+def test_decommission_blocked_by_active_links():
+    dc, dcn, _, lsl, _, srv = basic_networking_configuration()
+    dcn.link(srv.nic[0], lsl)
+    with pytest.raises(ValueError, match="active links"):
+        dc.decommission(srv.serial_number, [dcn])
+    assert srv.lifecycle == LifecycleState.Active
+
+
+def test_forced_decommission_reports_detached_links():
+    dc, dcn, _, lsl, _, srv = basic_networking_configuration()
+    dcn.link(srv.nic[0], lsl)
+    detached = dc.decommission(srv.serial_number, [dcn], force=True)
+    assert detached == ["srv_nic - ls1 (dcn)"]
+    assert srv.lifecycle == LifecycleState.Decommissioning
+    assert dcn.attachments(srv.nic[0]) == []
+    assert srv.nic[0].lan_ports[0].status == DeviceStatus.Down
+
+
+def test_decommissioned_server_excluded_from_capacity():
+    dc, dcn, _, _, _, srv = basic_networking_configuration()
+    srv.cpu.append(CPU("cpu", "cpu", "cpu", 1, 3000, 32, None))
+    assert dc.summary().cores == 32
+    dc.decommission(srv.serial_number, [dcn])
+    assert dc.summary().cores == 32
+    dc.complete_decommission(srv.serial_number, [dcn])
+    assert dc.summary().cores == 0
+    assert dc.summary(include_decommissioned=True).cores == 32
+    assert list(dc.servers()) == []
+    assert list(dc.servers(include_decommissioned=True)) == [srv]
+
+
+def test_reactivation_rules():
+    dc, dcn, cs, _, _, _ = basic_networking_configuration()
+    dc.decommission(cs.serial_number, [dcn])
+    assert cs.lifecycle == LifecycleState.Decommissioning
+    dc.reactivate(cs.serial_number, [dcn])
+    assert cs.lifecycle == LifecycleState.Active
+    dc.decommission(cs.serial_number, [dcn])
+    dc.complete_decommission(cs.serial_number, [dcn])
+    with pytest.raises(ValueError):
+        dc.reactivate(cs.serial_number, [dcn])
+    assert cs.lifecycle == LifecycleState.Decommissioned
+    with pytest.raises(ValueError, match="does not exist"):
+        dc.reactivate("unknown", [dcn])
+
+
+def test_decommission_safe_with_remaining_ha_member():
+    dc, dcn, _, _, _, srv = basic_networking_configuration()
+    dns = AffinityGroup("dns", AffinityPolicy.HighAvailability)
+    srv.affinity_groups.append(dns)
+    replica = Server(
+        "srv2",
+        "srv2",
+        "srv",
+        2,
+        [],
+        [],
+        [],
+        [],
+        [],
+        DeviceStatus.Up,
+        affinity_groups=[dns],
+    )
+    dc.rows[0].cabinets[0].servers.append(replica)
+    assert dc.can_decommission(srv.serial_number, [dcn]) == []
+    dc.decommission(srv.serial_number, [dcn])
+    assert srv.lifecycle == LifecycleState.Decommissioning
+
+
+def test_decommission_blocked_by_last_ha_member():
+    dc, dcn, _, _, _, srv = basic_networking_configuration()
+    srv.affinity_groups.append(AffinityGroup("dns", AffinityPolicy.HighAvailability))
+    assert dc.can_decommission(srv.serial_number, [dcn]) == [
+        "srv is the last active server of HA group dns"
+    ]
+    with pytest.raises(ValueError, match="HA group dns"):
+        dc.decommission(srv.serial_number, [dcn])
+    assert srv.lifecycle == LifecycleState.Active
+    dc.decommission(srv.serial_number, [dcn], force=True)
+    assert srv.lifecycle == LifecycleState.Decommissioning
+
+
+def test_failing_transaction_leaves_datacenter_unchanged():
+    dc, dcn, _, lsl, _, srv = basic_networking_configuration()
+    dcn.link(srv.nic[0], lsl)
+    cabinet = dc.rows[0].cabinets[0]
+    before = Encoder().encode(dc)
+
+    def change(datacenter):
+        srv.status = DeviceStatus.Down
+        srv.labels["role"] = "storage"
+        cabinet.servers.remove(srv)
+        raise ValueError("import failed half way")
+
+    with pytest.raises(ValueError, match="half way"):
+        dc.transaction(change)
+    assert Encoder().encode(dc) == before
+    assert cabinet.servers == [srv]
+    assert dcn.attachments(srv.nic[0])[0][0] is lsl
+
+    def duplicate(datacenter):
+        srv.status = DeviceStatus.Down
+        cabinet.servers.append(
+            Server("srv", "copy", "srv", 2, [], [], [], [], [], DeviceStatus.Up)
+        )
+
+    with pytest.raises(TransactionAborted, match="serial number srv"):
+        dc.transaction(duplicate)
+    assert Encoder().encode(dc) == before
+    assert dc.transaction(lambda d: d.name) == dc.name
@@ -0,0 +1,97 @@
+# -*- coding: utf-8 -*-#
+import pytest
+
+from horao.models import Cabinet, DataCenter, DeviceStatus, Row, Server
+from horao.models.naming import NamingConventions, NamingError, check_naming
+from horao.models.network import SwitchType
+from horao.settings import Settings
+from tests import create_switch
+
+CONVENTIONS = """
+[switch]
+pattern = "^sw-(?P<row>\\\\d+)-(?P<cabinet>\\\\d+)-(?P<number>\\\\d+)$"
+template = "sw-{row}-{cabinet}-{number}"
+
+[server]
+pattern = "^(?P<role>[a-z]+)(?P<number>\\\\d{3})\\\\.(?P<site>[a-z0-9-]+)$"
+template = "{role}{number:03d}.{site}"
+"""
+
+
+def named_datacenter():
+    switch = create_switch("ser1", SwitchType.Access, 2, 0)
+    switch.name = "sw-2-3-1"
+    server = Server(
+        "ser2",
+        "cn007.ams",
+        "srv",
+        7,
+        [],
+        [],
+        [],
+        [],
+        [],
+        DeviceStatus.Up,
+        labels={"role": "cn"},
+    )
+    cabinet = Cabinet("cab", "cab", "cab", 3, [server], [], [switch])
+    return DataCenter("ams", 1, [Row("row", 2, [cabinet])]), switch, server
+
+
+def load_conventions(tmp_path) -> NamingConventions:
+    path = tmp_path / "naming.toml"
+    path.write_text(CONVENTIONS)
+    return NamingConventions.from_settings(Settings(naming_conventions_path=str(path)))
+
+
+def test_conforming_names_have_no_findings(tmp_path):
+    conventions = load_conventions(tmp_path)
+    dc, _, _ = named_datacenter()
+    assert check_naming(dc, conventions) == []
+
+
+def test_non_matching_name_is_reported(tmp_path):
+    conventions = load_conventions(tmp_path)
+    dc, _, server = named_datacenter()
+    server.name = "node-7"
+    (finding,) = check_naming(dc, conventions)
+    assert finding.serial_number == "ser2"
+    assert finding.contradictions == {}
+    assert "does not match" in finding.reason
+    assert finding.suggestion == "cn007.ams"
+
+
+def test_name_contradicting_the_location_is_reported(tmp_path):
+    conventions = load_conventions(tmp_path)
+    dc, switch, _ = named_datacenter()
+    # the switch was moved to another row, its name still says row 2
+    dc.rows[0].number = 4
+    (finding,) = check_naming(dc, conventions)
+    assert finding.serial_number == switch.serial_number
+    assert finding.contradictions == {"row": ("2", 4)}
+    assert "row is 4" in finding.reason
+    assert finding.suggestion == "sw-4-3-1"
+
+
+def test_suggest_name(tmp_path):
+    conventions = load_conventions(tmp_path)
+    dc, switch, server = named_datacenter()
+    assert conventions.suggest_name(dc, switch) == "sw-2-3-1"
+    del server.labels["role"]
+    with pytest.raises(ValueError):
+        conventions.suggest_name(dc, server)
+    with pytest.raises(ValueError):
+        NamingConventions().suggest_name(dc, switch)
+
+
+def test_invalid_pattern_is_reported_cleanly(tmp_path):
+    path = tmp_path / "naming.toml"
+    path.write_text('[switch]\npattern = "^sw-(?P<row>\\\\d+$"\n')
+    with pytest.raises(NamingError) as e:
+        NamingConventions.load(str(path))
+    assert e.value.key == "switch.pattern"
+    assert "invalid pattern" in e.value.detail
+    path.write_text('[switch]\npattern = "^(?P<rack>\\\\d+)$"\n')
+    with pytest.raises(NamingError, match="unknown attributes rack"):
+        NamingConventions.load(str(path))
+    assert NamingConventions.from_settings(Settings()).conventions == {}
@@ -0,0 +1,51 @@
+# -*- coding: utf-8 -*-#
+import json
+
+from horao.models import Cabinet, DeviceStatus, Row
+from horao.models.hardware import Chassis
+from horao.models.netbox import slug
+from horao.models.status import LifecycleState
+from tests import basic_networking_configuration
+
+
+def test_netbox_export():
+    dc, _, cs, lsl, lsr, srv = basic_networking_configuration()
+    srv.tenant = "physics"
+    srv.status = DeviceStatus.Down
+    lsr.lifecycle = LifecycleState.Provisioning
+    cabinet = dc.rows[0].cabinets[0]
+    dc.rows.append(Row("row2", 2, [Cabinet("cab2", "cab2", "cab", 1, [], [], [])]))
+    document = json.loads(dc.to_netbox_json())
+    assert document["sites"] == [
+        {"name": dc.name, "slug": slug(dc.name), "status": "active"}
+    ]
+    assert [r["name"] for r in document["racks"]] == [cabinet.name, "cab2"]
+    assert document["racks"][1]["location"] == "row2"
+    devices = {d["serial"]: d for d in document["devices"]}
+    # the server and the three switches
+    assert len(document["devices"]) == 4
+    assert devices[srv.serial_number]["status"] == "failed"
+    assert devices[srv.serial_number]["tenant"] == "physics"
+    assert devices[srv.serial_number]["rack"] == cabinet.name
+    assert devices[lsr.serial_number]["status"] == "planned"
+    assert devices[cs.serial_number]["role"] == "switch"
+    ports = len(srv.nic[0].lan_ports) + sum(
+        len(s.lan_ports) + len(s.uplink_ports) for s in [cs, lsl, lsr]
+    )
+    assert len(document["interfaces"]) == ports
+    assert {i["device"] for i in document["interfaces"]} == {
+        srv.name,
+        cs.name,
+        lsl.name,
+        lsr.name,
+    }
+    # blades refer to their chassis
+    cabinet.servers.remove(srv)
+    cabinet.chassis.append(Chassis("ch1", "chassis 1", "ch", 1, [srv]))
+    devices = {d["serial"]: d for d in json.loads(dc.to_netbox_json())["devices"]}
+    assert devices["ch1"]["role"] == "chassis"
+    assert devices[srv.serial_number]["parent_device"] == "chassis 1"
+
+
+def test_slug():
+    assert slug("DC 1 / Amsterdam") == "dc-1-amsterdam"
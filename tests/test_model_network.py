@@ -1,4 +1,11 @@
 # -*- coding: utf-8 -*-#
+import ipaddress
+import itertools
+import sys
+
+import networkx as nx  # type: ignore
+import pytest
+
 from horao.models import (
     Switch,
     LinkLayer,
@@ -9,14 +16,24 @@ from horao.models import (
     DataCenter,
     Row,
     Cabinet,
+    IpAddress,
+    Link,
+    PortTable,
 )
 from horao.models.network import (
     SwitchType,
     DataCenterNetwork,
+    LldpNeighbor,
     NetworkType,
     NetworkTopology,
+    Router,
+    RouterType,
+    validate_management_coverage,
 )
-from tests import basic_networking_configuration
+from horao.models.serialization import Decoder, Encoder
+from horao.models.status import LifecycleState
+from horao.models.generators import clos, fat_tree, tree
+from tests import basic_networking_configuration, create_switch, two_pod_network
 
 
 def test_network_topology_detection_tree():
@@ -48,8 +65,441 @@ def test_link_server_to_switch_ports_up():
 
 
 def test_unlink_server_from_switch_downs_ports():
-    pass
+    _, dcn, _, lsl, _, srv = basic_networking_configuration()
+    server_nic = srv.nic[0]
+    dcn.link(server_nic, lsl)
+    assert server_nic.lan_ports[0].status == DeviceStatus.Up
+    dcn.unlink(server_nic, lsl)
+    assert server_nic.lan_ports[0].status == DeviceStatus.Down
+    assert all(p.status == DeviceStatus.Down for p in lsl.lan_ports)
 
 
 def test_downing_switch_downs_all_ports():
     pass
+
+
+def test_generate_address_plan_two_pods():
+    dcn = two_pod_network()
+    plan = dcn.generate_address_plan(IpAddress("10.1.0.0", "255.255.0.0", "10.1.0.1"))
+    assert len(plan.pods) == 2
+    assert plan.pods[0].devices == ["acc_a", "agg_a1", "agg_a2"]
+    base = ipaddress.ip_network("10.1.0.0/16")
+    subnets = [
+        ipaddress.ip_network(f"{s.address}/{s.netmask}")
+        for s in [plan.management] + [p.subnet for p in plan.pods]
+    ]
+    assert all(s.subnet_of(base) for s in subnets)
+    assert not any(a.overlaps(b) for a, b in itertools.combinations(subnets, 2))
+    # 7 switches + gateway require 8 usable addresses
+    assert subnets[0].num_addresses - 2 >= 8
+    for pod, subnet in zip(plan.pods, subnets[1:]):
+        assert subnet.num_addresses - 2 >= len(pod.devices) + 1
+
+
+def test_generate_address_plan_base_too_small():
+    dcn = two_pod_network()
+    with pytest.raises(ValueError, match="too small"):
+        dcn.generate_address_plan(IpAddress("10.1.0.0", "255.255.255.248", ""))
+
+
+def test_link_resolves_interned_ports():
+    _, dcn, cs, lsl, lsr, _ = basic_networking_configuration()
+    dcn.link(lsl, cs)
+    [(_, _, link)] = dcn.links()
+    left, right = link.resolve(dcn.ports)
+    assert left is lsl.uplink_ports[0]
+    assert right is cs.lan_ports[0]
+    assert dcn.ports.owner(link.left) is lsl
+    assert link.is_up(dcn.ports)
+    assert link.health(dcn.ports) == DeviceStatus.Up
+    right.status = DeviceStatus.Down
+    assert not link.is_up(dcn.ports)
+    assert link.health(dcn.ports) == DeviceStatus.Down
+    assert dcn.peer_port(link, cs) is right
+    assert dcn.peer_port(link, lsl) is left
+    with pytest.raises(ValueError):
+        dcn.peer_port(link, lsr)
+
+
+def test_asymmetric_links():
+    _, dcn, cs, lsl, lsr, _ = basic_networking_configuration()
+    dcn.link(lsl, cs)
+    dcn.link(lsr, cs)
+    # both links are up
+    assert dcn.asymmetric_links() == []
+    # both ports of the left link down
+    for port in lsl.uplink_ports + [cs.lan_ports[0]]:
+        port.status = DeviceStatus.Down
+    assert dcn.asymmetric_links() == []
+    # only the core side of the right link down
+    cs.lan_ports[1].status = DeviceStatus.Down
+    [(left, right, _)] = dcn.asymmetric_links()
+    assert {left, right} == {lsr, cs}
+
+
+def test_reconcile_with_hardware():
+    dc, dcn, cs, lsl, _, srv = basic_networking_configuration()
+    dcn.link(srv.nic[0], lsl)
+    dcn.link(lsl, cs)
+    assert dcn.reconcile_with_hardware(dc) == []
+    # a partial update downed the server, but not its port
+    srv.status = DeviceStatus.Down
+    cs.status = DeviceStatus.Down
+    assert [
+        (i.device, i.port, i.detail) for i in dcn.reconcile_with_hardware(dc)
+    ] == [
+        (cs.serial_number, "cp1", "port is up but its device is down"),
+        ("srv", "srv_port", "port is up but its device is down"),
+    ]
+    srv.nic[0].lan_ports[0].status = DeviceStatus.Down
+    assert [i.device for i in dcn.reconcile_with_hardware(dc)] == [cs.serial_number]
+    # the server is no longer part of the datacenter
+    dc.rows[0].cabinets[0].servers.remove(srv)
+    (_, missing) = dcn.reconcile_with_hardware(dc)
+    assert (missing.device, missing.detail) == (
+        "srv_nic",
+        "NIC is not installed in a server of dc",
+    )
+
+
+def test_te_weights_inverse_to_bandwidth():
+    _, dcn, cs, lsl, lsr, _ = basic_networking_configuration()
+    dcn.link(lsl, cs)
+    dcn.link(lsr, cs)
+    lsl.uplink_ports[0].speed_gb = 40
+    lsr.uplink_ports[0].speed_gb = 10
+    weights = dict(dcn.te_weights())
+    left = next(link for l, r, link in dcn.links() if lsl in (l, r))
+    right = next(link for l, r, link in dcn.links() if lsr in (l, r))
+    assert weights[left] < weights[right]
+    assert (weights[left], weights[right]) == (2, 10)
+
+
+def test_cost_override_reroutes_the_cheapest_path():
+    network = fat_tree(4)
+    devices = {d.serial_number: d for d in network.devices()}
+    direct = network.cheapest_path("pod0-acc0", "pod0-agg0")
+    assert direct == ["pod0-acc0", "pod0-agg0"]
+    link = network.graph.edges[devices["pod0-acc0"], devices["pod0-agg0"]]["link"]
+    link.cost_override = 10
+    assert dict(network.te_weights())[link] == 10
+    path = network.cheapest_path("pod0-acc0", "pod0-agg0")
+    assert len(path) == 4 and (path[0], path[-1]) == ("pod0-acc0", "pod0-agg0")
+    # survives a round trip
+    decoded = Decoder().decode(Encoder().encode(network), DataCenterNetwork)
+    overrides = [l.cost_override for _, _, l in decoded.links() if l.cost_override]
+    assert overrides == [10]
+    with pytest.raises(ValueError):
+        Link(link.left, link.right, 0)
+    with pytest.raises(ValueError):
+        network.cheapest_path("pod0-acc0", "unknown")
+
+
+def test_diameter_and_average_path_length():
+    _, dcn, cs, lsl, lsr, _ = basic_networking_configuration()
+    dcn.link(lsl, cs)
+    dcn.link(lsr, cs)
+    # ls1 - core - ls2
+    assert dcn.diameter() == 2
+    assert dcn.average_path_length() == pytest.approx(4 / 3)
+    cs.lan_ports[1].status = DeviceStatus.Down
+    assert dcn.diameter() is None
+    assert dcn.average_path_length() is None
+
+
+def test_are_isolated():
+    _, dcn, cs, lsl, lsr, _ = basic_networking_configuration()
+    dcn.link(lsl, cs)
+    # ls2 is deliberately left unconnected
+    assert dcn.are_isolated(lsl.serial_number, lsr.serial_number)
+    assert not dcn.are_isolated(lsl.serial_number, cs.serial_number)
+    dcn.link(lsr, cs)
+    assert not dcn.are_isolated(lsl.serial_number, lsr.serial_number)
+    cs.status = DeviceStatus.Down
+    assert dcn.are_isolated(lsl.serial_number, lsr.serial_number)
+    with pytest.raises(ValueError):
+        dcn.are_isolated(lsl.serial_number, "unknown")
+
+
+def test_reachability_matrix_matches_are_isolated():
+    network = tree(3, 2)
+    devices = list(network.graph.nodes)
+    devices[1].status = DeviceStatus.Down
+    link = next(iter(network.graph.edges(data="link")))[2]
+    link.resolve(network.ports)[0].status = DeviceStatus.Down
+    matrix = network.reachability_matrix()
+    for left, right in itertools.product(devices, repeat=2):
+        assert matrix.reachable(
+            left.serial_number, right.serial_number
+        ) != network.are_isolated(left.serial_number, right.serial_number)
+    assert not matrix.reachable(devices[1].serial_number, devices[1].serial_number)
+    with pytest.raises(ValueError):
+        matrix.reachable(devices[0].serial_number, "unknown")
+
+
+def test_articulation_points():
+    _, dcn, cs, lsl, lsr, _ = basic_networking_configuration()
+    dcn.link(lsl, cs)
+    dcn.link(lsr, cs)
+    # ls1 - core - ls2, the core is the only path between the leaves
+    assert dcn.articulation_points() == [cs.serial_number]
+    dcn.link(lsl, lsr)
+    assert dcn.articulation_points() == []
+    # a link that is down does not count as redundancy
+    lsr.lan_ports[0].status = DeviceStatus.Down
+    assert dcn.articulation_points() == [cs.serial_number]
+    # every leaf is linked to every spine
+    assert clos(2, 4, 1).articulation_points() == []
+
+
+def test_reachable_via_waypoint_that_cannot_be_bypassed():
+    dcn = tree(2, 2)
+    assert dcn.reachable_via("l1-s0", "l1-s1", "l0-s0")
+    assert not dcn.reachable_via("l1-s0", "l0-s0", "l1-s1")
+    with pytest.raises(ValueError):
+        dcn.reachable_via("l1-s0", "l1-s1", "unknown")
+
+
+def test_reachable_via_waypoint_that_can_be_bypassed():
+    dcn = clos(2, 2, 1)
+    # leaf0 reaches leaf1 through either spine
+    assert not dcn.reachable_via("leaf0", "leaf1", "spine0")
+    assert dcn.reachable_via("leaf0", "leaf1", "spine0", all_paths=False)
+    (spine1,) = [n for n in dcn.graph.nodes if n.serial_number == "spine1"]
+    spine1.status = DeviceStatus.Down
+    assert dcn.reachable_via("leaf0", "leaf1", "spine0")
+
+
+def test_physical_loops():
+    _, dcn, cs, lsl, lsr, _ = basic_networking_configuration()
+    dcn.link(lsl, cs)
+    dcn.link(lsr, cs)
+    assert dcn.physical_loops() == []
+    # the leaves are cabled back to back, closing a triangle with the core
+    dcn.link(lsl, lsr)
+    assert dcn.physical_loops() == [["ser3", "ser5", "ser7"]]
+    dcn.unlink(lsl, lsr)
+    # an uplink cabled into a lan port of the same switch
+    dcn.link(lsl, lsl)
+    assert dcn.physical_loops() == [["ser5"]]
+    assert tree(3, 2).physical_loops() == []
+
+
+def test_path_mtu_is_capped_by_the_smallest_hop():
+    _, dcn, cs, lsl, lsr, _ = basic_networking_configuration()
+    for switch in [cs, lsl, lsr]:
+        for port in switch.lan_ports + switch.uplink_ports:
+            port.mtu = 9000
+    dcn.link(lsl, cs)
+    assert dcn.path_mtu(lsl.serial_number, lsr.serial_number) is None
+    dcn.link(lsr, cs)
+    assert dcn.path_mtu(lsl.serial_number, lsr.serial_number) == 9000
+    lsr.uplink_ports[0].mtu = 1500
+    assert dcn.path_mtu(lsl.serial_number, lsr.serial_number) == 1500
+    assert dcn.path_mtu(lsl.serial_number, cs.serial_number) == 9000
+
+
+def test_flapping_ports():
+    _, dcn, cs, lsl, _, _ = basic_networking_configuration()
+    cs.lan_ports[0].link_flaps = 12
+    cs.lan_ports[1].link_flaps = 3
+    lsl.uplink_ports[0].link_flaps = 11
+    assert cs.lan_ports[0].is_flapping(10)
+    assert not cs.lan_ports[1].is_flapping(10)
+    assert dcn.flapping_ports(10) == [
+        (cs.serial_number, cs.lan_ports[0].name),
+        (lsl.serial_number, lsl.uplink_ports[0].name),
+    ]
+    assert dcn.flapping_ports(20) == []
+
+
+def test_overprovisioned_switches():
+    _, dcn, cs, lsl, lsr, _ = basic_networking_configuration()
+    ports = len(lsl.lan_ports) + len(lsl.uplink_ports)
+    lsl.port_capacity = ports - 1
+    lsr.port_capacity = ports
+    assert lsl.is_overprovisioned()
+    assert not lsr.is_overprovisioned()
+    # without a capacity there is nothing to validate against
+    assert not cs.is_overprovisioned()
+    assert dcn.overprovisioned_switches() == [lsl]
+
+
+def test_undersized_uplinks_at_3_to_1():
+    undersized = create_switch("acc1", SwitchType.Access, 48, 2, 25)
+    adequate = create_switch("acc2", SwitchType.Access, 48, 4, 25)
+    for port in adequate.uplink_ports:
+        port.speed_gb = 100
+    core = create_switch("core", SwitchType.Core, 4, 0, 25)
+    # 1200G of hosts at 3:1 need 400G of uplinks
+    assert undersized.uplink_undersized(3.0)
+    assert not adequate.uplink_undersized(3.0)
+    assert adequate.uplink_undersized(2.0)
+    assert not core.uplink_undersized(3.0)
+    dcn = DataCenterNetwork("dcn", NetworkType.Data)
+    dcn.add_multiple([undersized, adequate, core])
+    assert dcn.undersized_uplinks(3.0) == [undersized]
+
+
+def test_interned_links_are_small():
+    ports = PortTable()
+    port_list = [
+        Port(f"p{i}", f"p{i}", "p", i, f"m{i}", DeviceStatus.Up, 100)
+        for i in range(1000)
+    ]
+    links = [
+        Link(
+            ports.intern(port_list[i % 1000], None),
+            ports.intern(port_list[(i * 7) % 1000], None),
+        )
+        for i in range(10000)
+    ]
+    # ports are stored once, no matter how often they are linked
+    assert len(ports) == len(port_list)
+    assert not hasattr(links[0], "__dict__")
+    assert sys.getsizeof(links[0]) < sys.getsizeof(port_list[0].__dict__)
+
+
+def test_links_from_lldp_neighbors():
+    left = create_switch("sw1", SwitchType.Access, 4, 2)
+    right = create_switch("sw2", SwitchType.Access, 4, 2)
+    core = create_switch("core", SwitchType.Core, 4, 0)
+    dcn = DataCenterNetwork("dcn", NetworkType.Data)
+    dcn.add_multiple([left, right, core])
+    problems = dcn.apply_lldp(
+        [
+            # both ends report the link, by name and by MAC
+            LldpNeighbor("sw1", "up0", "core", "lp0"),
+            LldpNeighbor("core", "lp0", "sw1-m0", "sw1-um0"),
+            LldpNeighbor("sw2", "up0", "core", "lp1"),
+            LldpNeighbor("sw1", "up1", "unknown", "lp0"),
+            LldpNeighbor("sw2", "up1", "core", "lp0"),
+        ]
+    )
+    assert len(dcn.links()) == 2
+    assert problems == [
+        "<LldpNeighbor sw1:up1 -> unknown:lp0>: device unknown is not part of dcn",
+        "<LldpNeighbor sw2:up1 -> core:lp0>: conflicts with an existing link",
+    ]
+    link = dcn.graph.edges[left, core]["link"]
+    assert link.resolve(dcn.ports) == (left.uplink_ports[0], core.lan_ports[0])
+    assert link.is_up(dcn.ports)
+    assert not dcn.are_isolated("sw1", "sw2")
+    assert dcn.apply_lldp([LldpNeighbor("sw1", "up0", "core", "lp0")]) == []
+    assert len(dcn.links()) == 2
+
+
+def test_lldp_between_switches_without_uplinks():
+    left = create_switch("sw1", SwitchType.Access, 2, 0)
+    right = create_switch("sw2", SwitchType.Access, 2, 0)
+    left.uplink_ports = right.uplink_ports = None
+    dcn = DataCenterNetwork("dcn", NetworkType.Data)
+    dcn.add_multiple([left, right])
+    assert dcn.apply_lldp([LldpNeighbor("sw1", "lp0", "sw2", "lp1")]) == []
+    [(_, _, link)] = dcn.links()
+    assert link.resolve(dcn.ports) == (left.lan_ports[0], right.lan_ports[1])
+
+
+def test_lldp_resolves_wan_ports():
+    core = create_switch("core", SwitchType.Core, 2, 0)
+    wan = Port("r1-wp0", "wp0", "wan", 0, "r1-wm0", DeviceStatus.Down, 100)
+    router = Router("r1", "r1", "r", 1, RouterType.Edge, DeviceStatus.Up, [], [wan])
+    dcn = DataCenterNetwork("dcn", NetworkType.Data)
+    dcn.add_multiple([core, router])
+    # the router is found by the MAC of its WAN port
+    assert dcn.apply_lldp([LldpNeighbor("core", "lp0", "r1-wm0", "wp0")]) == []
+    [(_, _, link)] = dcn.links()
+    assert set(map(id, link.resolve(dcn.ports))) == {id(core.lan_ports[0]), id(wan)}
+
+
+def test_link_and_its_reverse_are_one_link():
+    _, dcn, cs, lsl, _, _ = basic_networking_configuration()
+    dcn.link(lsl, cs)
+    # the same cable reported from the other end
+    ports = set(dcn.links()[0][2].resolve(dcn.ports))
+    assert dcn.apply_lldp(
+        [LldpNeighbor(cs.serial_number, cs.lan_ports[0].name, lsl.name, "m3")]
+    ) == []
+    [(_, _, link)] = dcn.links()
+    assert set(link.resolve(dcn.ports)) == ports
+
+
+def test_duplicate_links_are_merged():
+    _, dcn, cs, lsl, lsr, _ = basic_networking_configuration()
+    dcn.link(lsl, cs)
+    dcn.link(lsr, cs)
+    [link, _] = [link for _, _, link in dcn.links()]
+    # the same cable recorded in reverse on another edge, as a merged document can do
+    dcn.graph.add_edge(lsr, lsl, link=Link(link.right, link.left))
+    assert dcn.dedup_links() == 1
+    assert len(dcn.links()) == 2
+    assert dcn.graph.edges[lsl, cs]["link"] is link
+    assert link.is_up(dcn.ports)
+    assert dcn.dedup_links() == 0
+
+
+def test_linking_a_linked_pair_again_is_rejected():
+    _, dcn, cs, lsl, _, _ = basic_networking_configuration()
+    dcn.link(lsl, cs)
+    for left, right in [(lsl, cs), (cs, lsl)]:
+        with pytest.raises(ValueError):
+            dcn.link(left, right)
+    # a second cable reported by LLDP conflicts with the first
+    (problem,) = dcn.apply_lldp(
+        [LldpNeighbor(cs.serial_number, cs.lan_ports[1].name, lsl.name, "m3")]
+    )
+    assert "conflicts with an existing link" in problem
+    # no port is left up without a link
+    [(_, _, link)] = dcn.links()
+    linked = set(map(id, link.resolve(dcn.ports)))
+    for port in cs.lan_ports + lsl.uplink_ports:
+        assert (port.status == DeviceStatus.Up) == (id(port) in linked)
+    assert dcn.asymmetric_links() == []
+
+
+def test_devices_of_all_kinds():
+    dcn = two_pod_network()
+    router = Router("r1", "r1", "r", 1, RouterType.Core, DeviceStatus.Up, [], [])
+    dcn.add(router)
+    _, _, _, _, _, srv = basic_networking_configuration()
+    dcn.add(srv.nic[0])
+    devices = list(dcn.devices())
+    switches = [n for n in dcn.graph.nodes if isinstance(n, Switch)]
+    assert len(devices) == len(switches) + 1
+    assert router in devices and srv.nic[0] not in devices
+    assert devices == list(dcn.devices())
+    switches[0].lifecycle = LifecycleState.Decommissioned
+    assert len(list(dcn.devices())) == len(switches)
+    assert len(list(dcn.devices(include_decommissioned=True))) == len(switches) + 1
+
+
+def test_managed_switches_need_a_management_network():
+    _, dcn, cs, lsl, lsr, _ = basic_networking_configuration()
+    lsr.managed = False
+    management = DataCenterNetwork("mgmt", NetworkType.Management)
+    management.add(cs)
+    assert validate_management_coverage([dcn, management]) == [lsl.serial_number]
+    management.add(lsl)
+    assert validate_management_coverage([dcn, management]) == []
+    assert validate_management_coverage([dcn]) == [
+        cs.serial_number,
+        lsl.serial_number,
+    ]
+
+
+def test_safe_maintenance_set():
+    network = fat_tree(4)
+    switches = {d.serial_number: d for d in network.devices()}
+    batches = network.safe_maintenance_set(4)
+    assert sorted(s for batch in batches for s in batch) == sorted(switches)
+    assert all(0 < len(batch) <= 4 for batch in batches)
+    for batch in batches:
+        remaining = [d for s, d in switches.items() if s not in batch]
+        assert nx.is_connected(network.graph.subgraph(remaining))
+    assert all(len(batch) == 1 for batch in network.safe_maintenance_set(1))
+    # the root of a tree disconnects the fabric on its own
+    network = tree(2, 2)
+    (root,) = [d for d in network.devices() if d.switch_type == SwitchType.Core]
+    assert [root.serial_number] in network.safe_maintenance_set(10)
+    with pytest.raises(ValueError):
+        network.safe_maintenance_set(0)
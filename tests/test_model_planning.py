@@ -0,0 +1,156 @@
+# -*- coding: utf-8 -*-#
+import pytest
+
+from horao.models import (
+    CPU,
+    RAM,
+    Cabinet,
+    DataCenter,
+    Row,
+)
+from horao.models.hardware import AffinityPolicy
+from horao.models.network import DataCenterNetwork, NetworkType, SwitchType
+from horao.models.planning import (
+    Binding,
+    PeerTraffic,
+    ResourceRequest,
+    WorkloadProfile,
+    deploy_profile,
+    place,
+    recommend_clos,
+)
+from horao.models.quotas import Quota, QuotaExceeded, Quotas, ResourceKind
+from tests import create_server, create_switch
+
+
+def fabric():
+    """
+    Two cabinets with two servers each (32 cores, 25 Gb), leaves connect to the spine at 100 Gb
+    """
+    network = DataCenterNetwork("dcn", NetworkType.Data)
+    spine = create_switch("spine", SwitchType.Core, 2, 0)
+    network.add(spine)
+    cabinets = []
+    for c in range(2):
+        leaf = create_switch(f"leaf{c}", SwitchType.Access, 2, 1)
+        servers = [
+            create_server(
+                f"srv{c * 2 + i}",
+                [CPU(f"srv{c * 2 + i}-cpu", "cpu", "cpu", 1, 3000, 32, None)],
+                [RAM(f"srv{c * 2 + i}-ram", "ram", "ram", 1, 256, None)],
+                25,
+            )
+            for i in range(2)
+        ]
+        network.add_multiple([leaf] + [s.nic[0] for s in servers])
+        for server in servers:
+            network.link(server.nic[0], leaf)
+        network.link(leaf, spine)
+        cabinets.append(Cabinet(f"cab{c}", f"cab{c}", "cab", c, servers, [], [leaf]))
+    return DataCenter("dc", 1, [Row("row", 1, cabinets)]), network
+
+
+def test_profile_that_fits():
+    dc, network = fabric()
+    profile = WorkloadProfile(
+        "web",
+        2,
+        ResourceRequest(16, 64),
+        traffic=PeerTraffic(10, gateway="spine", gateway_gbps=5),
+    )
+    verdict = deploy_profile(dc, network, profile)
+    assert verdict.feasible and verdict.binding is None
+    # both instances fit on the first server, only the gateway traffic reaches the fabric
+    assert verdict.placement == ["srv0", "srv0"]
+    assert verdict.matrix.demand("srv0", "spine") == 10
+    assert verdict.max_utilization == pytest.approx(10 / 25)
+
+
+def test_profile_fails_on_network_despite_fitting_on_compute():
+    dc, network = fabric()
+    profile = WorkloadProfile(
+        "db",
+        2,
+        ResourceRequest(16, 64),
+        AffinityPolicy.AntiAffinity,
+        PeerTraffic(30),
+    )
+    verdict = deploy_profile(dc, network, profile)
+    assert not verdict.feasible
+    assert verdict.binding == Binding.Network
+    assert verdict.placement == ["srv0", "srv2"]
+    assert verdict.max_utilization == pytest.approx(30 / 25)
+
+
+def test_scale_up_headroom():
+    dc, network = fabric()
+    profile = WorkloadProfile(
+        "batch", 1, ResourceRequest(32, 0), traffic=PeerTraffic(10)
+    )
+    verdict = deploy_profile(dc, network, profile)
+    assert verdict.feasible
+    # with four instances the first server carries 30 Gb of peer traffic over its 25 Gb link
+    assert verdict.headroom == 2
+    assert verdict.scale_limit == Binding.Network
+    profile.instances = 5
+    verdict = deploy_profile(dc, network, profile)
+    assert verdict.binding == Binding.Compute
+    assert verdict.placement == []
+    # without traffic only compute limits the scale up
+    quiet = WorkloadProfile("quiet", 1, ResourceRequest(32, 0))
+    verdict = deploy_profile(dc, network, quiet)
+    assert (verdict.headroom, verdict.scale_limit) == (3, Binding.Compute)
+
+
+def test_anti_affinity_shapes_traffic_matrix():
+    dc, network = fabric()
+    packed = WorkloadProfile(
+        "cache", 2, ResourceRequest(8, 16), traffic=PeerTraffic(10)
+    )
+    assert len(deploy_profile(dc, network, packed).matrix) == 0
+    packed.policy = AffinityPolicy.AntiAffinity
+    verdict = deploy_profile(dc, network, packed)
+    assert verdict.placement == ["srv0", "srv2"]
+    assert verdict.matrix.demand("srv2", "srv0") == 10
+    assert verdict.max_utilization == pytest.approx(10 / 25)
+
+
+def test_affinity_keeps_instances_in_one_cabinet():
+    dc, network = fabric()
+    profile = WorkloadProfile(
+        "pair", 3, ResourceRequest(32, 0), AffinityPolicy.Affinity
+    )
+    assert deploy_profile(dc, network, profile).binding == Binding.Compute
+    profile.instances = 2
+    assert deploy_profile(dc, network, profile).placement == ["srv0", "srv1"]
+
+
+def test_tenant_placement_is_subject_to_its_quota():
+    dc, network = fabric()
+    servers = list(dc.servers())
+    servers[0].tenant = "payments"
+    servers[1].tenant = "search"
+    profile = WorkloadProfile("web", 2, ResourceRequest(32, 0))
+    quotas = Quotas([Quota("payments", ResourceKind.Servers, 1)])
+    # srv1 belongs to another tenant, the placement would claim srv2
+    assert [s.serial_number for s in place(dc, profile, tenant="payments")] == [
+        "srv0",
+        "srv2",
+    ]
+    with pytest.raises(QuotaExceeded):
+        place(dc, profile, network=network, tenant="payments", quotas=quotas)
+    profile.instances = 1
+    assert place(dc, profile, tenant="payments", quotas=quotas) == [servers[0]]
+
+
+def test_recommend_clos_for_1000_servers_at_25g():
+    design = recommend_clos(1000, 25, 64)
+    assert 2 <= design.spines <= 8
+    assert design.leaves * design.server_ports_per_leaf >= 1000
+    assert design.leaves <= 64
+    assert 1.0 <= design.oversubscription <= 3.0
+    assert design.server_ports_per_leaf + design.uplinks_per_leaf == 64
+    strict = recommend_clos(1000, 25, 64, max_oversubscription=1.0)
+    assert strict.spines > design.spines and strict.oversubscription <= 1.0
+    with pytest.raises(ValueError, match="more than two tiers"):
+        recommend_clos(10_000, 25, 64)
@@ -0,0 +1,25 @@
+# -*- coding: utf-8 -*-#
+import pytest
+
+from horao.models import Cabinet
+from horao.models.network import SwitchType
+from tests import basic_networking_configuration, create_switch
+
+
+def test_high_density_cabinets():
+    dc, _, core, leaf_left, leaf_right, server = basic_networking_configuration()
+    dense = dc.rows[0].cabinets[0]
+    server.rated_power_watts = 12_000
+    core.rated_power_watts = 500
+    leaf_left.rated_power_watts = leaf_right.rated_power_watts = 250
+    switch = create_switch("tor", SwitchType.Access, 2, 1)
+    switch.rated_power_watts = 400
+    sparse = Cabinet("sparse", "sparse", "cab", 2, [], [], [switch])
+    dc.rows[0].cabinets.append(sparse)
+    assert dense.power_watts() == 13_000
+    assert dense.power_density_watts_per_u(40) == 325.0
+    assert sparse.power_density_watts_per_u(40) == 10.0
+    assert dc.high_density_cabinets(300, total_u=40) == [dense]
+    assert dc.high_density_cabinets(400, total_u=40) == []
+    with pytest.raises(ValueError):
+        dense.power_density_watts_per_u(0)
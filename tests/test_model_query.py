@@ -0,0 +1,231 @@
+# -*- coding: utf-8 -*-#
+import base64
+import json
+import random
+from typing import List
+
+import pytest
+
+from horao.models import (
+    CPU,
+    Cabinet,
+    DataCenter,
+    DeviceStatus,
+    IpAddress,
+    Row,
+    Server,
+)
+from horao.models.hardware import InvalidCursor
+from horao.models.serialization import Decoder, Encoder
+from horao.models.status import LifecycleState
+from tests import basic_networking_configuration, create_server
+
+
+def cpus(serial_number: str, cores: int) -> List[CPU]:
+    return [
+        CPU(f"{serial_number}-cpu{i}", "cpu", "cpu", i, 3000, cores // 2, None)
+        for i in range(2)
+    ]
+
+
+def inventory() -> DataCenter:
+    servers = [
+        create_server(
+            "gpu1",
+            cpus("gpu1", 64),
+            status=DeviceStatus.Up,
+            labels={"gpu": "a100", "role": "compute"},
+        ),
+        create_server(
+            "gpu2",
+            cpus("gpu2", 16),
+            status=DeviceStatus.Up,
+            labels={"gpu": "a100", "role": "compute"},
+        ),
+        create_server(
+            "gpu3",
+            cpus("gpu3", 64),
+            status=DeviceStatus.Down,
+            labels={"gpu": "h100", "role": "compute"},
+        ),
+        create_server(
+            "cpu1",
+            cpus("cpu1", 64),
+            status=DeviceStatus.Up,
+            labels={"role": "compute"},
+        ),
+        create_server(
+            "st1",
+            cpus("st1", 32),
+            status=DeviceStatus.Up,
+            labels={"role": "storage"},
+        ),
+    ]
+    cabinet = Cabinet("cab", "cab", "cab", 1, servers, [], [])
+    return DataCenter("dc", 1, [Row("row", 1, [cabinet])])
+
+
+def serials(servers):
+    return sorted(s.serial_number for s in servers)
+
+
+def test_predicates_are_combined():
+    dc = inventory()
+    query = dc.query().with_min_cores(32).with_tag("gpu").with_status(DeviceStatus.Up)
+    assert serials(query.collect()) == ["gpu1"]
+    assert serials(dc.query().with_min_cores(32).collect()) == [
+        "cpu1",
+        "gpu1",
+        "gpu3",
+        "st1",
+    ]
+    assert serials(dc.query().collect()) == serials(dc.servers())
+
+
+def test_tags_match_keys_and_values():
+    dc = inventory()
+    assert serials(dc.query().with_tag("gpu=h100").collect()) == ["gpu3"]
+    assert serials(
+        dc.query().with_tag("role=compute").with_status(DeviceStatus.Up).collect()
+    ) == ["cpu1", "gpu1", "gpu2"]
+    assert dc.query().with_tag("fpga").collect() == []
+
+
+def test_custom_predicates_and_decommissioned_servers():
+    dc = inventory()
+    dc.rows[0].cabinets[0].servers[0].lifecycle = LifecycleState.Decommissioned
+    assert "gpu1" not in serials(dc.query().collect())
+    query = dc.query(include_decommissioned=True).where(
+        lambda s: s.labels.get("gpu") == "a100"
+    )
+    assert serials(query.with_min_cores(64).collect()) == ["gpu1"]
+
+
+def walk(dc: DataCenter, page_size: int, between=None):
+    serial_numbers, cursor = [], None
+    while True:
+        page = dc.query().with_tag("role=compute").page(page_size, cursor)
+        serial_numbers += [s.serial_number for s in page.items]
+        cursor = page.next_cursor
+        if cursor is None:
+            return serial_numbers
+        if between is not None:
+            between(len(serial_numbers))
+
+
+def test_walking_all_pages_returns_every_match_once():
+    dc = inventory()
+    assert walk(dc, 1) == ["cpu1", "gpu1", "gpu2", "gpu3"]
+    assert walk(dc, 3) == walk(dc, 10)
+    page = dc.query().with_tag("role=compute").page(3, total=True)
+    assert (len(page.items), page.total) == (3, 4)
+    assert dc.query().page(3).total is None
+    with pytest.raises(ValueError):
+        dc.query().page(0)
+
+
+def test_pages_are_stable_when_the_model_changes():
+    dc = inventory()
+    servers = dc.rows[0].cabinets[0].servers
+
+    def remove_gpu2(seen: int):
+        if seen == 1:
+            servers.remove(next(s for s in servers if s.serial_number == "gpu2"))
+
+    assert walk(dc, 1, remove_gpu2) == ["cpu1", "gpu1", "gpu3"]
+    dc = inventory()
+    servers = dc.rows[0].cabinets[0].servers
+
+    def add_servers(seen: int):
+        if seen == 2:
+            # one sorts before the cursor (not returned), one after it
+            servers.append(
+                create_server(
+                    "aaa1",
+                    cpus("aaa1", 8),
+                    status=DeviceStatus.Up,
+                    labels={"role": "compute"},
+                )
+            )
+            servers.append(
+                create_server(
+                    "zzz1",
+                    cpus("zzz1", 8),
+                    status=DeviceStatus.Up,
+                    labels={"role": "compute"},
+                )
+            )
+
+    assert walk(dc, 2, add_servers) == ["cpu1", "gpu1", "gpu2", "gpu3", "zzz1"]
+
+
+def test_invalid_cursor():
+    dc = inventory()
+    for cursor in ["not a cursor", "e30=", _cursor_of(1)]:
+        with pytest.raises(InvalidCursor):
+            dc.query().page(2, cursor)
+
+
+def _cursor_of(value) -> str:
+    return base64.urlsafe_b64encode(json.dumps({"after": value}).encode()).decode()
+
+
+def test_find_devices_by_metadata():
+    dc, dcn, core, lsl, _, srv = basic_networking_configuration()
+    srv.set_meta("cost_center", "hpc")
+    core.set_meta("cost_center", "hpc")
+    lsl.set_meta("cost_center", "web")
+    assert srv.get_meta("cost_center") == "hpc"
+    assert srv.get_meta("asset_tag") is None
+    assert srv.get_meta("asset_tag", "-") == "-"
+    assert dc.find_by_meta("cost_center", "hpc", [dcn]) == [srv, core]
+    assert dc.find_by_meta("cost_center", "web", [dcn]) == [lsl]
+    assert dc.find_by_meta("owner", "hpc", [dcn]) == []
+    decoded = Decoder().decode(Encoder().encode(srv), Server)
+    assert decoded.metadata == {"cost_center": "hpc"}
+
+
+def test_find_server_by_ip():
+    dc, _, _, _, _, srv = basic_networking_configuration()
+    assert srv.ip_addresses() == []
+    assert dc.find_server_by_ip("10.0.0.5") is None
+    (nic,) = srv.nic
+    nic.addresses.append(IpAddress("10.0.0.5", "255.255.255.0", "10.0.0.1"))
+    assert [a.address for a in srv.ip_addresses()] == ["10.0.0.5"]
+    assert dc.find_server_by_ip("10.0.0.5") is srv
+    assert dc.find_server_by_ip("10.0.0.6") is None
+    with pytest.raises(ValueError):
+        dc.find_server_by_ip("not-an-address")
+    decoded = Decoder().decode(Encoder().encode(srv), Server)
+    assert [a.address for a in decoded.ip_addresses()] == ["10.0.0.5"]
+    srv.lifecycle = LifecycleState.Decommissioned
+    assert dc.find_server_by_ip("10.0.0.5") is None
+    assert dc.find_server_by_ip("10.0.0.5", include_decommissioned=True) is srv
+
+
+def test_weighted_sample_of_servers():
+    dc = inventory()
+
+    def free_cores(s: Server) -> float:
+        return 0 if s.status == DeviceStatus.Down else sum(c.cores for c in s.cpu)
+
+    picked = dc.sample_servers(2, free_cores, random.Random(42))
+    assert len(picked) == 2
+    assert picked == dc.sample_servers(2, free_cores, random.Random(42))
+    assert all(s.serial_number != "gpu3" for s in picked)
+    # asking for more than there are returns every server with a weight
+    assert serials(dc.sample_servers(10, free_cores, random.Random(1))) == [
+        "cpu1",
+        "gpu1",
+        "gpu2",
+        "st1",
+    ]
+    assert dc.sample_servers(0, free_cores, random.Random(1)) == []
+    # heavier servers are picked more often
+    counts = {s.serial_number: 0 for s in dc.servers()}
+    rng = random.Random(7)
+    for _ in range(500):
+        for s in dc.sample_servers(1, free_cores, rng):
+            counts[s.serial_number] += 1
+    assert counts["gpu3"] == 0
+    assert counts["gpu1"] > counts["gpu2"]
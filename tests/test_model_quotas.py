@@ -0,0 +1,88 @@
+# -*- coding: utf-8 -*-#
+import pytest
+
+from horao.models import CPU
+from horao.models.quotas import (
+    Quota,
+    QuotaExceeded,
+    Quotas,
+    ResourceKind,
+    usage_vs_quota,
+)
+from horao.models.store import ModelStore, QuotaWarning
+from tests import basic_networking_configuration
+
+
+def payments_store(*quotas: Quota):
+    """
+    Server srv of payments with 32 cores, its NIC is not attached yet
+    """
+    dc, dcn, _, _, _, server = basic_networking_configuration()
+    server.tenant = "payments"
+    server.cpu = [CPU("c1", "c1", "xeon", 1, 2800, 32, None)]
+    dcn.add(server.nic[0])
+    return ModelStore(dc, [dcn], quotas=Quotas(list(quotas))), server
+
+
+def test_usage_report():
+    store, _ = payments_store(
+        Quota("payments", ResourceKind.Servers, 200),
+        Quota("payments", ResourceKind.Cores, 5000),
+        Quota("payments", ResourceKind.Ports, 96, speed_gb=25),
+        Quota("search", ResourceKind.Servers, 10),
+    )
+    store.link("dcn", "srv_nic", "ser5", "netadm")
+    report = usage_vs_quota(store.datacenter, store.networks, store.quotas)
+    assert report.violations == []
+    usage = report.to_dict()["payments"]["usage"]
+    assert [(u["resource"], u["used"], u["quota"], u["remaining"]) for u in usage] == [
+        ("servers", 1, 200, 199),
+        ("cores", 32, 5000, 4968),
+        ("ports of 25G", 1, 96, 95),
+    ]
+    assert all(u["hard"] for u in usage)
+    assert report.to_dict()["search"]["usage"][0]["used"] == 0
+
+
+def test_hard_quota_refuses_an_attachment():
+    store, server = payments_store(Quota("payments", ResourceKind.Ports, 0))
+    with pytest.raises(QuotaExceeded) as e:
+        store.link("dcn", "srv_nic", "ser5", "netadm")
+    assert e.value.tenant == "payments"
+    assert store.networks[0].attachments(server.nic[0]) == []
+    # servers of other tenants are not limited by the quota
+    server.tenant = None
+    store.link("dcn", "srv_nic", "ser5", "netadm")
+    with pytest.raises(QuotaExceeded):
+        store.set_tenant("srv", "payments", "admin")
+    assert server.tenant is None
+
+
+def test_soft_quota_warns_but_allows():
+    store, server = payments_store(
+        Quota("payments", ResourceKind.Ports, 0, hard=False)
+    )
+    warnings = []
+    store.subscribe(
+        lambda e: warnings.append(e) if isinstance(e, QuotaWarning) else None
+    )
+    store.link("dcn", "srv_nic", "ser5", "netadm")
+    assert len(store.networks[0].attachments(server.nic[0])) == 1
+    (warning,) = warnings
+    assert "soft quota of payments for ports exceeded" in warning.message
+    store.set_tenant("srv", None, "admin")
+    store.set_tenant("srv", "payments", "admin")
+    assert len(warnings) == 2
+
+
+def test_quota_below_usage_is_reported_as_violation():
+    store, _ = payments_store(Quota("payments", ResourceKind.Cores, 64))
+    (violation,) = store.quotas.set(
+        Quota("payments", ResourceKind.Cores, 16),
+        store.datacenter,
+        store.networks,
+    )
+    assert (violation.used, violation.remaining) == (32, -16)
+    report = usage_vs_quota(store.datacenter, store.networks, store.quotas)
+    assert report.to_dict()["payments"]["violations"] == ["cores"]
+    assert len(store.quotas.quotas["payments"]) == 1
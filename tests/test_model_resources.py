@@ -0,0 +1,79 @@
+# -*- coding: utf-8 -*-#
+import pytest
+
+from horao.models import DeviceStatus, LinkLayer, Port
+from horao.models.compliance import Deviation, check_compliance
+from horao.models.network import NIC, DataCenterNetwork, NetworkType, SwitchType
+from horao.models.osi_layers import Segment
+from horao.models.planning import check_vlan_plan
+from horao.models.resources import TableResource, resources
+from tests import create_switch
+
+
+def access_network():
+    dcn = DataCenterNetwork("dcn", NetworkType.Data)
+    core = create_switch("core", SwitchType.Core, 2, 0)
+    access = create_switch("acc", SwitchType.Access, 3, 1)
+    routed = create_switch("l3", SwitchType.Access, 2, 0)
+    routed.layer = LinkLayer.Layer3
+    nics = [
+        NIC(
+            f"nic{i}",
+            f"nic{i}",
+            "nic",
+            1,
+            [Port(f"nic{i}-p", "p", "nic", 1, f"mac{i}", DeviceStatus.Down, 100)],
+        )
+        for i in range(3)
+    ]
+    dcn.add_multiple([core, access, routed] + nics)
+    dcn.link(access, core)
+    dcn.link(routed, core)
+    dcn.link(nics[0], access)
+    dcn.link(nics[1], access)
+    dcn.link(nics[2], routed)
+    access.lan_ports[0].access_vlan = 10
+    access.lan_ports[1].access_vlan = 10
+    access.uplink_ports[0].trunk_vlans = [10, 20, 30]
+    return dcn, core, access, routed
+
+
+def test_resource_utilization_per_resource():
+    dcn, core, access, _ = access_network()
+    access.max_mac_entries = 4
+    access.max_vlans = 4
+    report = resources(dcn)
+    # the layer 3 switch ends the layer 2 domain, its NIC is not learned
+    assert report.utilization(access.serial_number, TableResource.MacEntries) == 0.5
+    assert report.utilization(access.serial_number, TableResource.Vlans) == 0.75
+    assert report.warnings == []
+    with pytest.raises(ValueError):
+        report.utilization(core.serial_number, TableResource.Vlans)
+
+
+def test_resource_warning_above_threshold():
+    dcn, _, access, _ = access_network()
+    access.max_vlans = 3
+    report = resources(dcn, threshold=0.9)
+    assert len(report.warnings) == 1
+    assert "3 of 3 Vlans (100%)" in report.warnings[0]
+    assert resources(dcn, threshold=1.0).warnings == []
+
+
+def test_switches_without_capacity_are_unknown():
+    dcn, core, access, routed = access_network()
+    routed.max_acl_entries = 512
+    report = resources(dcn)
+    assert report.unknown == [access.serial_number, core.serial_number]
+    assert report.usage == {routed.serial_number: []}
+
+
+def test_vlan_plan_exceeding_access_switch_capacity_is_rejected():
+    dcn, _, access, _ = access_network()
+    access.max_vlans = 4
+    assert check_vlan_plan(dcn, [Segment("prod", vlans=[10, 40])]) == []
+    warnings = check_vlan_plan(dcn, [Segment("prod", vlans=[40, 50])])
+    assert warnings == ["acc (acc) would carry 5 VLANs, at most 4 supported"]
+    access.max_vlans = 2
+    findings = check_compliance(dcn, []).findings[access.serial_number]
+    assert [f.deviation for f in findings] == [Deviation.Capacity]
@@ -0,0 +1,126 @@
+# -*- coding: utf-8 -*-#
+import pytest
+
+from horao.models import DeviceStatus, Firewall, FirewallRule, IpAddress, Packet
+from horao.models.network import NIC, DataCenterNetwork, NetworkType, SwitchType
+from horao.models.osi_layers import Protocol, Segment
+from horao.models.segmentation import segment_reachability, segment_report, trace
+from horao.models.serialization import from_json, to_json
+from tests import cable, create_switch, port
+
+
+def subnet(address: str) -> IpAddress:
+    return IpAddress(address, "255.255.255.0", "")
+
+
+def segmented_network():
+    """
+    Hosts in prod (VLAN 10) and dev (VLAN 20) on separate switches, only the firewall connects the segments
+    """
+    network = DataCenterNetwork("dcn", NetworkType.Data)
+    prod, dev = [create_switch(n, SwitchType.Access, 1, 2) for n in ("prod", "dev")]
+    for switch, vlan in ((prod, 10), (dev, 20)):
+        switch.lan_ports[0].access_vlan = switch.uplink_ports[0].access_vlan = vlan
+    firewall = Firewall(
+        "fw",
+        "fw",
+        "fw",
+        1,
+        DeviceStatus.Up,
+        [port("fw-0", 10, "10.10.0.1"), port("fw-1", 10, "10.20.0.1")],
+        [],
+        [
+            FirewallRule(
+                "prod to dev https",
+                "allow",
+                subnet("10.10.0.0"),
+                subnet("10.20.0.0"),
+                Protocol.TCP,
+                443,
+            )
+        ],
+    )
+    prod_host = NIC("pn", "pn", "nic", 1, [port("pn-0", 25, "10.10.0.5")])
+    dev_host = NIC("dn", "dn", "nic", 1, [port("dn-0", 25, "10.20.0.5")])
+    network.add_multiple([prod, dev, firewall, prod_host, dev_host])
+    cable(network, prod_host, prod_host.lan_ports[0], prod, prod.lan_ports[0])
+    cable(network, dev_host, dev_host.lan_ports[0], dev, dev.lan_ports[0])
+    cable(network, prod, prod.uplink_ports[0], firewall, firewall.lan_ports[0])
+    cable(network, dev, dev.uplink_ports[0], firewall, firewall.lan_ports[1])
+    network.add_segment(Segment("prod", [10], [subnet("10.10.0.0")], ["fw"]))
+    network.add_segment(Segment("dev", [20], [subnet("10.20.0.0")], ["fw"]))
+    return network, prod, dev
+
+
+def test_compliant_segments():
+    network, _, _ = segmented_network()
+    (result,) = segment_reachability(network)
+    assert (result.left, result.right) == ("prod", "dev")
+    assert result.reachable
+    assert result.firewalls == ["fw"]
+    assert result.compliant
+
+
+def test_bypass_via_mis_vlaned_trunk_is_detected():
+    network, prod, dev = segmented_network()
+    # trunk between the switches with mismatching native VLANs, untagged prod traffic ends up in dev
+    prod.uplink_ports[1].trunk_vlans, prod.uplink_ports[1].access_vlan = [10], 10
+    dev.uplink_ports[1].trunk_vlans, dev.uplink_ports[1].access_vlan = [20], 20
+    cable(network, prod, prod.uplink_ports[1], dev, dev.uplink_ports[1])
+    (result,) = segment_reachability(network)
+    assert not result.compliant
+    assert result.bypass == ["prod", "dev"]
+
+
+def test_matching_trunk_is_not_a_bypass():
+    network, prod, dev = segmented_network()
+    for switch in (prod, dev):
+        switch.uplink_ports[1].trunk_vlans = [10, 20]
+    cable(network, prod, prod.uplink_ports[1], dev, dev.uplink_ports[1])
+    assert segment_reachability(network)[0].compliant
+
+
+def test_trace_crosses_segments_through_the_firewall():
+    network, _, _ = segmented_network()
+    https = trace(network, Packet("10.10.0.5", "10.20.0.5", Protocol.TCP, 443))
+    assert https.delivered
+    assert https.path == ["pn", "prod", "fw", "dev", "dn"]
+    ssh = trace(network, Packet("10.10.0.5", "10.20.0.5", Protocol.TCP, 22))
+    assert not ssh.delivered
+    assert ssh.denied_by == ["fw"]
+    firewall = next(n for n in network.graph.nodes if isinstance(n, Firewall))
+    assert [r.hit_count for r in firewall.rules] == [0]
+
+
+def test_segment_report():
+    network, prod, _ = segmented_network()
+    prod.lan_ports[0].status = DeviceStatus.Down
+    report = segment_report(network)
+    assert set(report["prod"].devices) == {"prod", "fw", "pn"}
+    assert (report["prod"].ports, report["prod"].ports_up) == (4, 3)
+    assert report["prod"].capacity_gb == 100 + 10 + 25
+    assert report["dev"].ports_up == 4
+
+
+def test_segments_are_serialized():
+    network, _, _ = segmented_network()
+    decoded = from_json(to_json(network), DataCenterNetwork)
+    assert [(s.name, s.vlans, s.firewalls) for s in decoded.segments] == [
+        ("prod", [10], ["fw"]),
+        ("dev", [20], ["fw"]),
+    ]
+    assert segment_reachability(decoded)[0].compliant
+
+
+def test_assigned_port_overrides_vlan_and_address():
+    network, _, _ = segmented_network()
+    host = next(n for n in network.graph.nodes if n.name == "pn")
+    network.assign_segment(host.lan_ports[0], "dev")
+    (result,) = segment_reachability(network)
+    assert result.bypass == ["prod", "pn"]
+    network.assign_segment(host.lan_ports[0], None)
+    assert segment_reachability(network)[0].compliant
+    with pytest.raises(ValueError):
+        network.assign_segment(host.lan_ports[0], "storage")
+    with pytest.raises(ValueError):
+        network.add_segment(Segment("storage", [20]))
@@ -0,0 +1,213 @@
+# -*- coding: utf-8 -*-#
+import json
+
+import pytest
+
+from horao.models import DeviceStatus, Port, SwitchType
+from horao.models.binary import pack, unpack
+from horao.models.hardware import DataCenter
+from horao.models.health import health_report
+from horao.models.network import DataCenterNetwork, NetworkTopology, NetworkType
+from horao.models.osi_layers import LinkLayer, Protocol
+from horao.models.serialization import (
+    ENUM_CODES,
+    Decoder,
+    Encoder,
+    UnknownVariant,
+    from_binary,
+    from_json,
+    to_binary,
+    to_json,
+)
+from horao.models.store import ModelStore
+from tests import basic_networking_configuration
+
+
+def linked_configuration():
+    dc, dcn, core, leaf_left, leaf_right, server = basic_networking_configuration()
+    dcn.add(server.nic[0])
+    dcn.link(server.nic[0], leaf_left)
+    dcn.link(leaf_left, core)
+    return dc, dcn
+
+
+def decode_document(document, decoder):
+    document = json.loads(json.dumps(document))
+    return (
+        decoder.decode(document[0], DataCenter),
+        decoder.decode(document[1], DataCenterNetwork),
+    )
+
+
+def round_trip(dc, dcn):
+    return decode_document(Encoder().encode([dc, dcn]), Decoder())
+
+
+def test_round_trip_keeps_shared_objects():
+    dc, dcn = round_trip(*linked_configuration())
+    server = next(dc.servers())
+    assert server.nic[0] in dcn.graph.nodes
+    cabinet_switches = dc.rows[0].cabinets[0].switches
+    assert all(s in dcn.graph.nodes for s in cabinet_switches)
+    assert len(dcn.links()) == 2
+    assert all(link.is_up(dcn.ports) for _, _, link in dcn.links())
+    assert [p for p, _ in dcn.attachments(server.nic[0])] == [cabinet_switches[1]]
+    assert to_json([dc, dcn]) == to_json(list(round_trip(dc, dcn)))
+
+
+def test_status_batch_skips_future_variant():
+    decoder = Decoder()
+    changes = decoder.status_batch(
+        [
+            {"serial_number": "ser3", "status": "Down", "timestamp": 1},
+            {"serial_number": "ser5", "status": "Maintenance", "timestamp": 1},
+            {"serial_number": "srv", "status": "Down", "timestamp": 1},
+        ]
+    )
+    assert [(s, st) for s, st, _ in changes] == [
+        ("ser3", DeviceStatus.Down),
+        ("srv", DeviceStatus.Down),
+    ]
+    assert decoder.unknown_variants["DeviceStatus"] == 1
+    dc, dcn, _, _, _, server = basic_networking_configuration()
+    ModelStore(dc, [dcn]).apply_status_batch(changes)
+    assert server.status == DeviceStatus.Down
+
+
+def test_unknown_variants_are_conservative_and_preserved():
+    dc, dcn = linked_configuration()
+    document = Encoder().encode([dc, dcn])
+    cabinet = document[0]["rows"][0]["cabinets"][0]
+    cabinet["servers"][0]["status"] = "Maintenance"
+    # the core switch is written as part of the cabinet, the network refers to it
+    cabinet["switches"][0]["switch_type"] = "SuperSpine"
+    decoder = Decoder()
+    dc, dcn = decode_document(document, decoder)
+    assert decoder.unknown_variants == {"DeviceStatus": 1, "SwitchType": 1}
+    server = next(dc.servers())
+    assert isinstance(server.status, UnknownVariant)
+    assert server.status != DeviceStatus.Up
+    assert health_report(dc, dcn).servers_down == ["srv"]
+    assert dc.servers_sorted_by_health()[0] is server
+    core = next(n for n in dcn.graph.nodes if n.serial_number == "ser3")
+    assert core.switch_type != SwitchType.Core
+    cabinet = json.loads(to_json([dc, dcn]))[0]["rows"][0]["cabinets"][0]
+    assert cabinet["servers"][0]["status"] == "Maintenance"
+    assert cabinet["switches"][0]["switch_type"] == "SuperSpine"
+
+
+def test_from_json_single_object():
+    dc, _ = linked_configuration()
+    assert next(from_json(to_json(dc), DataCenter).servers()).serial_number == "srv"
+
+
+def test_port_counters_default_to_zero():
+    _, _, core, _, _, _ = basic_networking_configuration()
+    core.lan_ports[0].link_flaps = 4
+    data = json.loads(to_json(core.lan_ports[0]))
+    assert from_json(json.dumps(data), Port).link_flaps == 4
+    # documents written before the counters existed
+    del data["crc_errors"], data["link_flaps"]
+    port = from_json(json.dumps(data), Port)
+    assert port.crc_errors == 0 and port.link_flaps == 0
+
+
+def test_binary_round_trip_equals_json_round_trip():
+    dc, dcn = linked_configuration()
+    dcn.segments = []
+    via_json = from_json(to_json([dc, dcn]), list)
+    via_binary = from_binary(to_binary([dc, dcn]), list)
+    assert via_binary == via_json
+    decoder = Decoder()
+    datacenter = from_binary(to_binary(dc), DataCenter, decoder)
+    network = from_binary(to_binary(dcn), DataCenterNetwork)
+    assert to_json(datacenter) == to_json(from_json(to_json(dc), DataCenter))
+    assert to_json(network) == to_json(from_json(to_json(dcn), DataCenterNetwork))
+    assert len(to_binary([dc, dcn])) < len(to_json([dc, dcn]))
+
+
+def test_binary_values():
+    values = [None, True, False, 0, -1, 300, -(2**40), 1.5, "", "é", [1, [2]], {}]
+    assert unpack(pack(values)) == values
+    assert unpack(pack({"a": "x", "b": "x", "x": {"a": 1}})) == {
+        "a": "x",
+        "b": "x",
+        "x": {"a": 1},
+    }
+    with pytest.raises(ValueError):
+        pack({1: "not a string key"})
+    with pytest.raises(ValueError):
+        unpack(b"not binary")
+    with pytest.raises(ValueError):
+        unpack(pack(["truncated"])[:-2])
+    with pytest.raises(ValueError):
+        unpack(pack(1) + b"trailing")
+
+
+def test_adjacently_tagged_enums_round_trip():
+    dc, dcn = linked_configuration()
+    document = json.loads(to_json([dc, dcn], tagged_enums=True))
+    assert document[1]["network_type"] == {"type": "NetworkType", "content": "Data"}
+    assert document[0]["rows"][0]["cabinets"][0]["servers"][0]["status"] == {
+        "type": "DeviceStatus",
+        "content": "Up",
+    }
+    decoded = decode_document(document, Decoder())
+    assert to_json(list(decoded)) == to_json([dc, dcn])
+    assert to_json(list(decoded), tagged_enums=True) == to_json(
+        [dc, dcn], tagged_enums=True
+    )
+    # the default stays by name
+    assert json.loads(to_json(dcn))["network_type"] == "Data"
+    unknown = {"type": "DeviceStatus", "content": "Degraded"}
+    assert isinstance(Decoder().enum(DeviceStatus, unknown), UnknownVariant)
+    assert Encoder(tagged_enums=True).encode(
+        Decoder().enum(DeviceStatus, unknown)
+    ) == unknown
+    with pytest.raises(ValueError, match="Expected a DeviceStatus"):
+        Decoder().decode({"type": "SwitchType", "content": "Core"}, DeviceStatus)
+
+
+def test_integer_enum_codes_round_trip_and_are_stable():
+    dc, dcn = linked_configuration()
+    document = json.loads(to_json([dc, dcn], int_enums=True))
+    assert document[0]["rows"][0]["cabinets"][0]["servers"][0]["status"] == 1
+    # enums without codes are still written by name
+    assert document[1]["network_type"] == "Data"
+    decoded = decode_document(document, Decoder())
+    assert to_json(list(decoded)) == to_json([dc, dcn])
+    assert from_binary(to_binary(dcn, int_enums=True), DataCenterNetwork)
+    # the codes are stored data, changing them breaks existing documents
+    assert ENUM_CODES[DeviceStatus] == {"Up": 1, "Down": 2}
+    assert ENUM_CODES[Protocol] == {"TCP": 1, "UDP": 2, "ICMP": 3}
+    assert ENUM_CODES[NetworkTopology]["Tree"] == 1
+    assert ENUM_CODES[NetworkTopology]["Undefined"] == 16
+    for enum_type, codes in ENUM_CODES.items():
+        assert set(codes) == set(enum_type.__members__)
+        assert len(set(codes.values())) == len(codes)
+        for member in enum_type:
+            encoded = Encoder(int_enums=True).encode(member)
+            assert encoded == codes[member.name]
+            assert Decoder().enum(enum_type, encoded) is member
+    assert Encoder(tagged_enums=True, int_enums=True).encode(DeviceStatus.Down) == {
+        "type": "DeviceStatus",
+        "content": 2,
+    }
+    assert isinstance(Decoder().enum(DeviceStatus, 99), UnknownVariant)
+    assert Encoder(int_enums=True).encode(Decoder().enum(DeviceStatus, 99)) == 99
+
+
+def test_enum_defaults():
+    assert DeviceStatus.default() == DeviceStatus.Up
+    assert NetworkTopology.default() == NetworkTopology.Undefined
+    assert LinkLayer.default() == LinkLayer.Layer2
+    assert NetworkType.default() == NetworkType.Data
+    assert Protocol.default() == Protocol.TCP
+    # a document written by hand leaves out the status of the port
+    document = {"serial_number": "p1", "name": "p1", "model": "p", "number": 1}
+    port = Decoder().decode(dict(document, mac="m", speed_gb=10), Port)
+    assert port.status == DeviceStatus.Up
+    assert Decoder().decode(Encoder().encode(port), Port).status == DeviceStatus.Up
+    document = {"name": "n", "nodes": [], "links": []}
+    network = Decoder().decode(document, DataCenterNetwork)
+    assert network.network_type == NetworkType.Data
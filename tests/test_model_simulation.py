@@ -0,0 +1,72 @@
+# -*- coding: utf-8 -*-#
+from horao.models import NIC, DeviceStatus
+from horao.models.simulation import Invariant, InvariantType, rolling_maintenance
+from tests import port, two_pod_network
+
+
+def test_rolling_maintenance_safe_plan_passes():
+    dcn = two_pod_network()
+    results = rolling_maintenance(
+        dcn,
+        [["agg_a1", "agg_b1"], ["agg_a2", "agg_b2"]],
+        [
+            Invariant(InvariantType.MinUplinksPerAccess, 1),
+            Invariant(InvariantType.MinBisectionFraction, 0.5),
+            Invariant(InvariantType.NoPartition),
+        ],
+    )
+    assert len(results) == 2
+    assert all(r.passed for r in results)
+    # the overlay never changes the model
+    assert all(d.status.name == "Up" for d in dcn.graph.nodes)
+
+
+def test_rolling_maintenance_both_aggregation_switches_of_pod_violate_uplinks():
+    dcn = two_pod_network()
+    results = rolling_maintenance(
+        dcn,
+        [["agg_a1"], ["agg_a1", "agg_a2"]],
+        [Invariant(InvariantType.MinUplinksPerAccess, 1)],
+    )
+    assert results[0].passed
+    assert not results[1].passed
+    assert len(results[1].violations) == 1
+    assert "acc_a" in results[1].violations[0]
+
+
+def test_rolling_maintenance_no_partition_trips():
+    dcn = two_pod_network()
+    results = rolling_maintenance(
+        dcn,
+        [["core"]],
+        [
+            Invariant(InvariantType.MinUplinksPerAccess, 1),
+            Invariant(InvariantType.NoPartition),
+        ],
+    )
+    assert not results[0].passed
+    assert len(results[0].violations) == 1
+    assert "partitioned" in results[0].violations[0]
+
+
+def test_rolling_maintenance_ignores_links_that_are_down():
+    dcn = two_pod_network()
+    nodes = {n.serial_number: n for n in dcn.graph.nodes}
+    link = dcn.graph.edges[nodes["acc_a"], nodes["agg_a1"]]["link"]
+    link.resolve(dcn.ports)[0].status = DeviceStatus.Down
+    results = rolling_maintenance(
+        dcn,
+        [["agg_a2"]],
+        [Invariant(InvariantType.MinUplinksPerAccess, 1)],
+    )
+    assert not results[0].passed
+    assert "acc_a" in results[0].violations[0]
+
+
+def test_rolling_maintenance_ignores_devices_that_are_not_cabled():
+    dcn = two_pod_network()
+    dcn.add(NIC("spare", "spare", "nic", 1, [port("spare-p")]))
+    results = rolling_maintenance(
+        dcn, [["agg_a1", "agg_b1"]], [Invariant(InvariantType.NoPartition)]
+    )
+    assert results[0].passed
@@ -0,0 +1,185 @@
+# -*- coding: utf-8 -*-#
+import json
+import os
+
+import pytest
+
+from horao.cli import main
+from horao.models import DeviceStatus
+from horao.models.hardware import DataCenter, Disk
+from horao.models.serialization import Encoder
+from horao.models.snapshot import BrokenDeltaChain, SnapshotStore
+from horao.settings import Settings
+from tests import basic_networking_configuration
+
+
+def disk_usage(server) -> float:
+    return sum(d.usage_gb for d in server.disk) / sum(d.size_gb for d in server.disk)
+
+
+def store_with_history(path) -> SnapshotStore:
+    store = SnapshotStore(str(path))
+    dc, dcn, core, _, _, server = basic_networking_configuration()
+    server.disk = [Disk("d1", "d1", "ssd", 1, 1000, 0)]
+    for i, usage in enumerate([500, 650, 810, 900, 700]):
+        server.disk[0].usage_gb = usage
+        core.status = DeviceStatus.Down if i == 2 else DeviceStatus.Up
+        store.put(100.0 * (i + 1), dc, [dcn])
+    return store
+
+
+def test_device_at_selects_nearest_snapshot_at_or_before(tmp_path):
+    store = store_with_history(tmp_path)
+    assert store.device_at("ser3", 300).status == DeviceStatus.Down
+    assert store.device_at("ser3", 399.9).status == DeviceStatus.Down
+    assert store.device_at("ser3", 400).status == DeviceStatus.Up
+    assert store.device_at("ser3", 10_000).status == DeviceStatus.Up
+    with pytest.raises(ValueError):
+        store.device_at("ser3", 99.9)
+
+
+def test_history_of_disk_usage(tmp_path):
+    store = store_with_history(tmp_path)
+    assert list(store.history_of("srv", disk_usage)) == [
+        (100.0, 0.5),
+        (200.0, 0.65),
+        (300.0, 0.81),
+        (400.0, 0.9),
+        (500.0, 0.7),
+    ]
+    assert [v for _, v in store.history_of("ser3", "status", 200, 300)] == [
+        DeviceStatus.Up,
+        DeviceStatus.Down,
+    ]
+
+
+def test_first_time_when_and_missing_snapshots(tmp_path):
+    store = store_with_history(tmp_path)
+    os.remove(os.path.join(str(tmp_path), f"{200.0:.6f}.snapshot"))
+    with open(os.path.join(str(tmp_path), f"{300.0:.6f}.snapshot"), "wb") as f:
+        f.write(b"garbage")
+    assert store.first_time_when("srv", lambda s: disk_usage(s) >= 0.8) == 400.0
+    assert store.first_time_when("srv", lambda s: disk_usage(s) >= 0.95) is None
+    assert store.device_at("ser3", 350).status == DeviceStatus.Up
+
+
+def test_serial_missing_in_older_snapshots(tmp_path):
+    store = store_with_history(tmp_path)
+    dc, dcn, _, _, _, server = basic_networking_configuration()
+    server.serial_number = "srv_new"
+    store.put(600.0, dc, [dcn])
+    assert store.device_at("srv_new", 500) is None
+    assert [t for t, _ in store.history_of("srv_new", "status")] == [600.0]
+
+
+def test_cli_history(tmp_path, capsys):
+    store_with_history(tmp_path)
+    assert main(["history", "ser3", "--since", "250", "--store", str(tmp_path)]) == 0
+    lines = capsys.readouterr().out.splitlines()
+    assert [line.split("\t")[1] for line in lines] == ["Down", "Up", "Up"]
+
+
+def model_of(snapshot) -> dict:
+    return Encoder().encode([snapshot.datacenter, snapshot.networks])
+
+
+def store_with_deltas(path, settings=None) -> SnapshotStore:
+    store = SnapshotStore(str(path), settings)
+    dc, dcn, core, _, _, server = basic_networking_configuration()
+    server.disk = [Disk("d1", "d1", "ssd", 1, 1000, 0)]
+    store.put(100.0, dc, [dcn])
+    for i, usage in enumerate([500, 650, 810]):
+        server.disk[0].usage_gb = usage
+        core.status = DeviceStatus.Down if i == 1 else DeviceStatus.Up
+        store.put_delta(100.0 * (i + 1), 100.0 * (i + 2), dc, [dcn])
+    return store
+
+
+def test_delta_snapshots_reconstruct_full_snapshots(tmp_path):
+    store = store_with_deltas(tmp_path / "deltas")
+    assert store.timestamps() == [100.0, 200.0, 300.0, 400.0]
+    assert os.path.exists(tmp_path / "deltas" / f"{400.0:.6f}.delta")
+    full = SnapshotStore(str(tmp_path / "full"))
+    dc, dcn, _, _, _, server = basic_networking_configuration()
+    server.disk = [Disk("d1", "d1", "ssd", 1, 1000, 810)]
+    full.put(400.0, dc, [dcn])
+    assert model_of(store.load(400.0)) == model_of(full.load(400.0))
+    assert store.device_at("ser3", 300).status == DeviceStatus.Down
+    assert store.device_at("ser3", 400).status == DeviceStatus.Up
+
+
+def test_delta_chain_compaction(tmp_path):
+    settings = Settings.builder().snapshot_compaction_deltas(3).build()
+    store = store_with_deltas(tmp_path, settings)
+    written = sorted(os.listdir(str(tmp_path)))
+    assert [os.path.splitext(n)[1] for n in written] == [
+        ".snapshot",
+        ".delta",
+        ".delta",
+        ".delta",
+    ]
+    # the chain holds 3 deltas, the next one is written in full
+    dc, dcn, _, _, _, server = basic_networking_configuration()
+    server.disk = [Disk("d1", "d1", "ssd", 1, 1000, 900)]
+    store.put_delta(400.0, 500.0, dc, [dcn])
+    assert os.path.exists(tmp_path / f"{500.0:.6f}.snapshot")
+    # with a single delta per chain, every other snapshot is a delta
+    single = SnapshotStore(
+        str(tmp_path / "single"),
+        Settings.builder().snapshot_compaction_deltas(1).build(),
+    )
+    single.put(100.0, dc, [dcn])
+    single.put_delta(100.0, 200.0, dc, [dcn])
+    single.put_delta(200.0, 300.0, dc, [dcn])
+    assert [os.path.splitext(n)[1] for n in sorted(os.listdir(single.path))] == [
+        ".snapshot",
+        ".delta",
+        ".snapshot",
+    ]
+    store = SnapshotStore(str(tmp_path / "other"))
+    dc, dcn, _, _, _, _ = basic_networking_configuration()
+    store.put(100.0, DataCenter("empty", 1, []))
+    # the changes would be larger than a full snapshot
+    store.put_delta(100.0, 200.0, dc, [dcn])
+    assert os.path.exists(tmp_path / "other" / f"{200.0:.6f}.snapshot")
+
+
+def test_broken_delta_chain_lists_loadable_snapshots(tmp_path):
+    store = store_with_deltas(tmp_path)
+    corrupted = os.path.join(str(tmp_path), f"{300.0:.6f}.delta")
+    with open(corrupted) as f:
+        delta = json.load(f)
+    with open(corrupted, "w") as f:
+        json.dump({**delta, "changes": []}, f)
+    with pytest.raises(BrokenDeltaChain) as e:
+        store.load(400.0)
+    assert (e.value.timestamp, e.value.reason) == (300.0, "checksum mismatch")
+    assert e.value.loadable == [100.0, 200.0]
+    with open(corrupted, "w") as f:
+        json.dump(delta, f)
+    os.remove(os.path.join(str(tmp_path), f"{200.0:.6f}.delta"))
+    with pytest.raises(BrokenDeltaChain, match="missing") as e:
+        store.load(400.0)
+    assert e.value.timestamp == 200.0
+    assert e.value.loadable == [100.0]
+
+
+def test_delta_since_brings_a_peer_up_to_date(tmp_path):
+    local = store_with_deltas(tmp_path / "local")
+    peer = SnapshotStore(str(tmp_path / "peer"))
+    peer.put(100.0, *load_model(local.load(100.0)))
+    delta = local.delta_since(100.0)
+    assert delta["timestamp"] == 400.0
+    peer.apply_delta(delta)
+    assert model_of(peer.load(400.0)) == model_of(local.load(400.0))
+    with pytest.raises(ValueError, match="No snapshot newer"):
+        local.delta_since(400.0)
+    stale = SnapshotStore(str(tmp_path / "stale"))
+    dc, dcn, _, _, _, _ = basic_networking_configuration()
+    stale.put(100.0, dc, [dcn])
+    with pytest.raises(ValueError, match="does not apply"):
+        stale.apply_delta(delta)
+
+
+def load_model(snapshot):
+    return snapshot.datacenter, snapshot.networks
@@ -0,0 +1,71 @@
+# -*- coding: utf-8 -*-#
+import pytest
+
+from horao.models import Cabinet, DataCenter, DeviceStatus, Row
+from horao.models.network import DataCenterNetwork, NetworkType, SwitchType
+from horao.models.startup import startup_plan, verify_ready
+from tests import create_server, create_switch
+
+
+def powered_fabric():
+    """
+    Core - distribution - access switch with a storage and a compute server of the same pool
+    """
+    core = create_switch("core", SwitchType.Core, 2, 0)
+    aggregation = create_switch("agg", SwitchType.Distribution, 2, 1)
+    access = create_switch("acc", SwitchType.Access, 4, 1)
+    storage = create_server(
+        "st1", nic_speed_gb=25, labels={"role": "storage", "storage_pool": "p1"}
+    )
+    compute = create_server(
+        "cp1", nic_speed_gb=25, labels={"role": "compute", "storage_pool": "p1"}
+    )
+    dcn = DataCenterNetwork("dcn", NetworkType.Data)
+    dcn.add_multiple([core, aggregation, access, storage.nic[0], compute.nic[0]])
+    dcn.link(access, aggregation)
+    dcn.link(aggregation, core)
+    dcn.link(storage.nic[0], access)
+    dcn.link(compute.nic[0], access)
+    dc = DataCenter(
+        "dc",
+        1,
+        [Row("row", 1, [Cabinet("cab", "cab", "cab", 1, [storage, compute], [], [])])],
+    )
+    return dc, dcn
+
+
+def test_stages_follow_the_tiers_and_attachments():
+    dc, dcn = powered_fabric()
+    plan = startup_plan(dc, [dcn])
+    assert plan.stages == [["core"], ["agg"], ["acc"], ["st1"], ["cp1"]]
+    assert plan.to_dict() == {"stages": plan.stages}
+    assert plan.render().splitlines()[0] == "stage 1: core"
+
+
+def test_compute_starts_after_the_storage_of_its_pool():
+    dc, dcn = powered_fabric()
+    plan = startup_plan(dc, [dcn])
+    assert plan.stage_of("cp1") > plan.stage_of("st1")
+    for s in dc.servers():
+        s.labels["storage_pool"] = "other" if s.serial_number == "cp1" else "p1"
+    plan = startup_plan(dc, [dcn])
+    assert plan.stage_of("cp1") == plan.stage_of("st1")
+
+
+def test_dependency_cycles_are_errors():
+    dc, dcn = powered_fabric()
+    with pytest.raises(ValueError, match="cycle"):
+        startup_plan(dc, [dcn], [("core", "cp1")])
+
+
+def test_verify_ready_waits_for_earlier_stages():
+    dc, dcn = powered_fabric()
+    plan = startup_plan(dc, [dcn])
+    (aggregation,) = [n for n in dcn.graph.nodes if n.serial_number == "agg"]
+    aggregation.status = DeviceStatus.Down
+    assert verify_ready(plan, 1, dc, [dcn]) == []
+    assert verify_ready(plan, 2, dc, [dcn]) == ["agg"]
+    aggregation.status = DeviceStatus.Up
+    assert verify_ready(plan, 4, dc, [dcn]) == []
+    with pytest.raises(ValueError):
+        verify_ready(plan, 5, dc, [dcn])
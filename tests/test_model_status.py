@@ -0,0 +1,56 @@
+# -*- coding: utf-8 -*-#
+from horao.models import DeviceStatus, Server, DataCenter, Row, Cabinet
+from horao.models.hardware import StatusEvent
+from tests import basic_networking_configuration
+
+
+def test_device_status_ordering():
+    assert DeviceStatus.Down < DeviceStatus.Up
+    assert DeviceStatus.Up > DeviceStatus.Down
+    assert DeviceStatus.Up >= DeviceStatus.Up
+    assert sorted([DeviceStatus.Up, DeviceStatus.Down, DeviceStatus.Up]) == [
+        DeviceStatus.Down,
+        DeviceStatus.Up,
+        DeviceStatus.Up,
+    ]
+
+
+def test_servers_sorted_by_health():
+    servers = [
+        Server(f"s{i}", f"s{i}", "srv", i, [], [], [], [], [], status)
+        for i, status in enumerate(
+            [DeviceStatus.Up, DeviceStatus.Down, DeviceStatus.Up, DeviceStatus.Down]
+        )
+    ]
+    dc = DataCenter(
+        "dc", 1, [Row("row", 1, [Cabinet("cab", "cab", "cab", 1, servers, [], [])])]
+    )
+    assert [s.name for s in dc.servers_sorted_by_health()] == ["s1", "s3", "s0", "s2"]
+
+
+def test_apply_status_feed_last_writer_wins():
+    dc, dcn, core, _, _, server = basic_networking_configuration()
+    report = dc.apply_status_feed(
+        [
+            StatusEvent("srv", DeviceStatus.Down, 20),
+            StatusEvent("ser3", DeviceStatus.Down, 10),
+            # stale, older than the event applied before
+            StatusEvent("srv", DeviceStatus.Up, 15),
+            StatusEvent("unknown", DeviceStatus.Down, 10),
+        ]
+    )
+    assert report.applied == ["srv", "ser3"]
+    assert report.stale == ["srv"]
+    assert report.unknown == ["unknown"]
+    assert server.status == DeviceStatus.Down
+    assert core.status == DeviceStatus.Down
+    # a later feed only overrides with newer events
+    report = dc.apply_status_feed(
+        [
+            StatusEvent("srv", DeviceStatus.Up, 19),
+            StatusEvent("ser3", DeviceStatus.Up, 30),
+        ]
+    )
+    assert report.stale == ["srv"]
+    assert server.status == DeviceStatus.Down
+    assert core.status == DeviceStatus.Up
@@ -0,0 +1,85 @@
+# -*- coding: utf-8 -*-#
+import threading
+
+from horao.models import DeviceStatus, LifecycleState
+from horao.models.store import (
+    BatchStatusChanged,
+    ModelStore,
+    StatusChangeContext,
+    StatusChanged,
+)
+from tests import basic_networking_configuration
+
+
+def create_store():
+    dc, dcn, core, leaf_left, leaf_right, server = basic_networking_configuration()
+    return ModelStore(dc, [dcn]), core, leaf_left, leaf_right, server
+
+
+def mixed_batch():
+    context = StatusChangeContext("snmp", timestamp=1)
+    return [
+        ("ser3", DeviceStatus.Down, context),
+        ("unknown", DeviceStatus.Down, context),
+        ("srv", DeviceStatus.Down, context),
+    ]
+
+
+def test_mixed_batch_applies_valid_subset():
+    store, core, _, _, server = create_store()
+    result = store.apply_status_batch(mixed_batch())
+    assert [c.serial_number for c in result.applied] == ["ser3", "srv"]
+    assert [s for s, _ in result.failures] == ["unknown"]
+    assert core.status == DeviceStatus.Down
+    assert server.status == DeviceStatus.Down
+    assert [c.status for c in store.history("srv")] == [DeviceStatus.Down]
+
+
+def test_mixed_batch_strict_applies_nothing():
+    store, core, _, _, server = create_store()
+    server.lifecycle = LifecycleState.Decommissioned
+    result = store.apply_status_batch(mixed_batch(), strict=True)
+    assert result.applied == []
+    assert sorted(s for s, _ in result.failures) == ["srv", "unknown"]
+    assert core.status == DeviceStatus.Up
+    assert store.history("ser3") == []
+
+
+def test_batch_publishes_single_event():
+    store, _, _, _, _ = create_store()
+    batches, individual = [], []
+    store.subscribe(batches.append)
+    store.subscribe(individual.append, individual=True)
+    store.apply_status_batch(mixed_batch())
+    assert len(batches) == 1
+    assert isinstance(batches[0], BatchStatusChanged)
+    assert [(c.serial_number, c.previous) for c in batches[0].changes] == [
+        ("ser3", DeviceStatus.Up),
+        ("srv", DeviceStatus.Up),
+    ]
+    assert isinstance(individual[0], BatchStatusChanged)
+    assert [type(e) for e in individual[1:]] == [StatusChanged, StatusChanged]
+
+
+def test_reader_never_observes_partial_batch():
+    store, core, leaf_left, leaf_right, server = create_store()
+    devices = [core, leaf_left, leaf_right, server]
+    observed = set()
+    done = threading.Event()
+
+    def reader():
+        while not done.is_set():
+            with store.read():
+                observed.add(tuple(d.status for d in devices))
+
+    thread = threading.Thread(target=reader)
+    thread.start()
+    context = StatusChangeContext("snmp")
+    for status in [DeviceStatus.Down, DeviceStatus.Up] * 50:
+        store.apply_status_batch([(d.serial_number, status, context) for d in devices])
+    done.set()
+    thread.join()
+    assert observed <= {
+        tuple([DeviceStatus.Up] * 4),
+        tuple([DeviceStatus.Down] * 4),
+    }
@@ -0,0 +1,220 @@
+# -*- coding: utf-8 -*-#
+import networkx as nx  # type: ignore
+import pytest
+
+from horao.models.budget import AnalysisBudget
+from horao.models.generators import clos, fat_tree, tree
+from horao.models import NIC, DeviceStatus, Port
+from horao.models.network import NetworkTopology, SwitchType
+from horao.models.topology import (
+    Confidence,
+    DetectionResult,
+    DetectorRegistry,
+    TopologyDetector,
+    TopologyInput,
+)
+from horao.settings import Settings
+from tests import basic_networking_configuration
+
+
+class LeafSpineDetector(TopologyDetector):
+    """In-house detector: every leaf is linked to every spine."""
+
+    name = "leaf-spine"
+
+    def detect(self, topology: TopologyInput):
+        graph = nx.Graph((left, right) for left, right, _ in topology.links)
+        if graph.number_of_nodes() == 0 or not nx.is_bipartite(graph):
+            return None
+        spines, leaves = nx.bipartite.sets(graph)
+        if graph.number_of_edges() != len(spines) * len(leaves):
+            return None
+        return DetectionResult(NetworkTopology.VL2, f"{len(spines)}x{len(leaves)}")
+
+
+class EverythingDetector(TopologyDetector):
+    name = "everything"
+
+    def detect(self, topology: TopologyInput):
+        return DetectionResult(NetworkTopology.Helios)
+
+
+def test_custom_detector_wins_on_its_input():
+    network = clos(2, 4, 8)
+    assert network.get_topology() == NetworkTopology.Undefined
+    registry = DetectorRegistry.default()
+    registry.register(LeafSpineDetector())
+    report = network.get_topology_detailed(registry=registry)
+    assert report.detector == "leaf-spine"
+    assert report.result.topology == NetworkTopology.VL2
+    assert report.result.detail == "2x4"
+    with pytest.raises(ValueError):
+        registry.register(LeafSpineDetector())
+
+
+def test_matching_detectors_are_resolved_by_priority():
+    network = tree(3, 2)
+    registry = DetectorRegistry.default()
+    registry.register(EverythingDetector(), 300)
+    report = network.get_topology_detailed(registry=registry)
+    assert report.result.topology == NetworkTopology.Tree
+    assert [(n, r.topology) for n, r in report.alternatives] == [
+        ("everything", NetworkTopology.Helios)
+    ]
+    registry = DetectorRegistry.default()
+    registry.register(EverythingDetector(), 50)
+    assert network.get_topology(registry=registry) == NetworkTopology.Helios
+
+
+def test_built_in_detector_can_be_disabled():
+    network = tree(2, 3)
+    registry = DetectorRegistry.default(disabled=["tree"])
+    assert registry.detectors == ["fat-tree"]
+    assert network.get_topology(registry=registry) == NetworkTopology.Undefined
+    with pytest.raises(ValueError):
+        registry.disable("unknown")
+
+
+def test_default_registry_is_unchanged():
+    _, dcn, cs, lsl, lsr, _ = basic_networking_configuration()
+    dcn.link(lsl, cs)
+    dcn.link(lsr, cs)
+    assert dcn.get_topology() == NetworkTopology.Tree
+    report = fat_tree(4).get_topology_detailed()
+    assert report.detector == "fat-tree" and report.result.detail == "k=4"
+    assert report.alternatives == []
+
+
+def test_clean_fat_tree_is_exact():
+    assert fat_tree(4).get_topology_with_fallback() == (
+        NetworkTopology.FatTree,
+        Confidence.Exact,
+    )
+
+
+def test_detection_within_budget():
+    network = fat_tree(16)
+    report = network.get_topology_timed(1e-9)
+    assert report.timed_out
+    assert report.result.topology == NetworkTopology.Undefined
+    assert report.detector is None
+    # the fat tree detector runs out of budget while walking the switches
+    registry = DetectorRegistry.default(disabled=["tree"])
+    nodes = network.graph.number_of_nodes()
+    report = network.get_topology_detailed(
+        registry=registry, budget=AnalysisBudget(max_nodes_visited=nodes + 10)
+    )
+    assert report.timed_out
+    assert report.result.topology == NetworkTopology.Undefined
+    report = fat_tree(4).get_topology_timed(60)
+    assert not report.timed_out
+    assert report.result.topology == NetworkTopology.FatTree
+
+
+def test_tree_of_switches_with_dual_homed_device_is_likely():
+    network = tree(2, 2)
+    port = Port("p", "p", "p", 1, "", DeviceStatus.Down, 25)
+    nic = NIC("nic", "nic", "nic", 1, [port])
+    network.add(nic)
+    for access in [n for n in network.graph.nodes if n.serial_number.startswith("l1")]:
+        network.link(nic, access)
+    assert network.get_topology() == NetworkTopology.Undefined
+    assert network.get_topology_with_fallback() == (
+        NetworkTopology.Tree,
+        Confidence.Likely,
+    )
+
+
+def test_ambiguous_shapes_are_guessed_or_unknown():
+    # leaf-spine, every leaf has multiple uplinks to the core
+    assert clos(2, 3, 1).get_topology_with_fallback() == (
+        NetworkTopology.FatTree,
+        Confidence.Guess,
+    )
+    _, dcn, cs, lsl, lsr, _ = basic_networking_configuration()
+    dcn.link(lsl, cs)
+    dcn.link(lsr, cs)
+    dcn.link(lsl, lsr)
+    # a triangle of core switches has no tiers to go by
+    assert dcn.get_topology_with_fallback() == (
+        NetworkTopology.Undefined,
+        Confidence.Unknown,
+    )
+
+
+def test_convergence_estimate_depends_on_protocol_timers():
+    network = fat_tree(4)
+    core = next(n for n in network.graph.nodes if n.switch_type == SwitchType.Core)
+    fast = (
+        Settings.builder()
+        .routing_protocol("BGP")
+        .routing_bgp_hold_time(3.0)
+        .build()
+    )
+    slow = (
+        Settings.builder()
+        .routing_protocol("OSPF")
+        .routing_ospf_dead_interval(40.0)
+        .build()
+    )
+    fast_ms = network.estimated_convergence_ms(core.serial_number, fast)
+    slow_ms = network.estimated_convergence_ms(core.serial_number, slow)
+    assert 3000 <= fast_ms < 3100
+    assert 40000 <= slow_ms < 40100
+    # without equal cost paths the update travels across the tree, a failed inner switch splits it
+    branches = tree(3, 2)
+    assert branches.estimated_convergence_ms("l1-s0", fast) is None
+    assert branches.estimated_convergence_ms("l2-s0", fast) == 3000 + 3 + 10
+
+
+def test_topology_diff_of_a_removed_core_switch():
+    before, after = fat_tree(4), fat_tree(4)
+    assert not before.topology_diff(after)
+    (core,) = [n for n in after.graph.nodes if n.serial_number == "core0"]
+    links = sorted(
+        tuple(sorted((core.serial_number, peer.serial_number)))
+        for peer in after.graph.adj[core]
+    )
+    after.graph.remove_node(core)
+    change = before.topology_diff(after)
+    assert change
+    assert change.removed_devices == ["core0"]
+    assert change.added_devices == [] and change.added_links == []
+    assert change.removed_links == links
+    assert (change.topology_before, change.topology_after) == (
+        NetworkTopology.FatTree,
+        NetworkTopology.Undefined,
+    )
+    assert change.topology_changed
+    # and the other way around
+    reverse = after.topology_diff(before)
+    assert reverse.added_devices == ["core0"]
+    assert reverse.added_links == links
+
+
+def test_relaxed_fat_tree_with_a_missing_link():
+    network = fat_tree(4)
+    assert network.get_topology_relaxed(0).violations == []
+    switches = {n.serial_number: n for n in network.graph.nodes}
+    network.graph.remove_edge(switches["pod0-acc0"], switches["pod0-agg0"])
+    assert network.get_topology() == NetworkTopology.Undefined
+    assert network.get_topology_relaxed(0).topology == NetworkTopology.Undefined
+    result = network.get_topology_relaxed(1)
+    assert (result.topology, result.detail) == (NetworkTopology.FatTree, "k=4")
+    assert result.violations == ["missing link pod0-acc0 - pod0-agg0"]
+    with pytest.raises(ValueError):
+        network.get_topology_relaxed(-1)
+
+
+def test_relaxed_fat_tree_with_a_miswired_core_link():
+    network = fat_tree(4)
+    switches = {n.serial_number: n for n in network.graph.nodes}
+    core = switches["core0"]
+    (agg,) = [n for n in network.graph.adj[core] if n.serial_number.startswith("pod1")]
+    link = network.graph.edges[core, agg]["link"]
+    network.graph.remove_edge(core, agg)
+    network.graph.add_edge(core, switches["pod0-agg1"], link=link)
+    assert network.get_topology_relaxed(2).topology == NetworkTopology.Undefined
+    result = network.get_topology_relaxed(3)
+    assert result.topology == NetworkTopology.FatTree
+    assert len(result.violations) == 3
@@ -0,0 +1,76 @@
+# -*- coding: utf-8 -*-#
+import hashlib
+import json
+import os
+import re
+from typing import Any, Dict
+
+from horao.models.versioning import (
+    GOSSIP_PROTOCOL_VERSION,
+    MODEL_SCHEMA_VERSION,
+    can_read,
+    parse_version,
+    version_info,
+)
+from horao.models.serialization import Encoder
+from tests import basic_networking_configuration
+
+SCHEMA_FIXTURES = os.path.join(os.path.dirname(__file__), "fixtures", "schema")
+# sha256 of the serialization fixtures per schema version, a change of the serialized form needs a new schema version
+# (a new minor version if older readers can ignore the change), regenerate the fixtures and record their hash
+FIXTURE_HASHES = {
+    "1.0": "be8459b03f5354d90d5e0614a709926180302e5ec455e95c9684af2cecc24f1e",
+}
+
+
+def test_can_read_compatibility_matrix():
+    major, minor = parse_version(MODEL_SCHEMA_VERSION)
+    assert can_read(MODEL_SCHEMA_VERSION)
+    assert can_read(f"{major}.{minor + 1}")
+    assert can_read(f"{major}.0")
+    assert not can_read(f"{major + 1}.{minor}")
+    assert not can_read(f"{major + 1}.0")
+    assert not can_read("")
+    assert not can_read("one.two")
+    # as read by a peer of another version
+    assert can_read("1.0", "1.3")
+    assert not can_read("1.0", "2.0")
+
+
+def test_version_info_matches_setup():
+    with open(os.path.join(os.path.dirname(__file__), "..", "setup.py")) as f:
+        (version,) = re.findall(r'^VERSION = "(.+)"$', f.read(), re.MULTILINE)
+    info = version_info()
+    assert info.package_version == version
+    assert info.gossip_protocol_version == GOSSIP_PROTOCOL_VERSION
+    assert info.to_dict()["schema_version"] == MODEL_SCHEMA_VERSION
+
+
+def fixture_documents() -> Dict[str, Any]:
+    # the serialization fixtures: a datacenter and its network, with links between all kinds of devices
+    dc, dcn, core, leaf_left, leaf_right, server = basic_networking_configuration()
+    dcn.add(server.nic[0])
+    dcn.link(server.nic[0], leaf_left)
+    dcn.link(leaf_left, core)
+    dcn.link(leaf_right, core)
+    return {
+        "datacenter.json": Encoder().encode(dc),
+        "network.json": Encoder().encode(dcn),
+    }
+
+
+def test_fixtures_match_the_serialized_form():
+    for name, document in fixture_documents().items():
+        with open(os.path.join(SCHEMA_FIXTURES, name)) as f:
+            assert json.load(f) == document, f"{name} is outdated, regenerate it"
+
+
+def test_fixtures_change_only_with_a_schema_version_bump():
+    digest = hashlib.sha256()
+    for name in sorted(os.listdir(SCHEMA_FIXTURES)):
+        with open(os.path.join(SCHEMA_FIXTURES, name), "rb") as f:
+            digest.update(name.encode() + f.read())
+    assert FIXTURE_HASHES.get(MODEL_SCHEMA_VERSION) == digest.hexdigest(), (
+        "The serialization fixtures changed, bump MODEL_SCHEMA_VERSION and record the "
+        "hash of the fixtures for the new version"
+    )
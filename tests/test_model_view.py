@@ -0,0 +1,79 @@
+# -*- coding: utf-8 -*-#
+import json
+
+from horao.models import CPU, DeviceStatus, Server
+from horao.models.store import ModelStore
+from horao.models.view import EntityKind, Scope, ScopedView
+from tests import basic_networking_configuration
+
+
+def tenant_store():
+    dc, dcn, _, leaf_left, _, server = basic_networking_configuration()
+    server.tenant = "payments"
+    server.cost = 12000.0
+    server.cpu = [CPU("c1", "c1", "xeon", 1, 2800, 32, None)]
+    dcn.add(server.nic[0])
+    dcn.link(server.nic[0], leaf_left)
+    other = Server(
+        "srv2",
+        "srv2",
+        "srv",
+        2,
+        [CPU("c2", "c2", "xeon", 1, 2800, 64, None)],
+        [],
+        [],
+        [],
+        [],
+        DeviceStatus.Up,
+        tenant="search",
+    )
+    dc.rows[0].cabinets[0].servers.append(other)
+    return ModelStore(dc, [dcn]), server, leaf_left
+
+
+def test_tenant_scoped_capacity_matches_tenant_report():
+    store, _, _ = tenant_store()
+    view = ScopedView(store, Scope(tenants=["payments"]))
+    assert [s.name for s in view.servers()] == ["srv"]
+    assert view.network_devices() == []
+    expected = store.datacenter.summary_by_tenant()["payments"]
+    assert vars(view.summary()) == vars(expected)
+    assert view.summary().cores == 32
+
+
+def test_cross_boundary_link_is_opaque():
+    store, server, leaf_left = tenant_store()
+    view = ScopedView(store, Scope(tenants=["payments"]))
+    [attachment] = view.attachments(server)
+    assert attachment["nic"] == "srv_nic"
+    assert set(attachment["peer"]) == {"opaque"}
+    exported = json.dumps(view.export())
+    assert leaf_left.name not in exported
+    assert leaf_left.serial_number not in exported
+    network_view = ScopedView(store, Scope(kinds=[EntityKind.Switch]))
+    assert leaf_left in network_view.network_devices()
+
+
+def test_redacted_fields_are_left_out():
+    store, _, _ = tenant_store()
+    scope = Scope(tenants=["payments"], redact=["cost", "serial_number"])
+    exported = json.dumps(ScopedView(store, scope).export())
+    assert '"cost"' not in exported
+    assert '"serial_number"' not in exported
+    assert "12000" not in exported
+    unredacted = ScopedView(store, Scope(tenants=["payments"])).export()
+    assert unredacted["servers"][0]["cost"] == 12000.0
+
+
+def test_empty_scope_sees_nothing():
+    store, _, _ = tenant_store()
+    view = ScopedView(store, Scope())
+    assert view.servers() == []
+    assert view.network_devices() == []
+    assert view.export() == {"servers": [], "network_devices": [], "attachments": {}}
+
+
+def test_scope_round_trip():
+    scope = Scope(["payments"], {"role": "db"}, [EntityKind.Server], ["cost"])
+    again = Scope.from_dict(json.loads(json.dumps(scope.to_dict())))
+    assert again.to_dict() == scope.to_dict()
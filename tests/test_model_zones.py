@@ -0,0 +1,104 @@
+# -*- coding: utf-8 -*-#
+from horao.models import DeviceStatus, Firewall
+from horao.models.hardware import Cabinet, DataCenter, Row
+from horao.models.network import (
+    DataCenterNetwork,
+    NetworkType,
+    Router,
+    RouterType,
+    SwitchType,
+)
+from horao.models.zones import Zone, server_zones
+from tests import cable, create_server, create_switch, port
+
+
+def zoned_fabric():
+    """
+    Edge router - firewall (WAN to LAN) - access switch with a web server, a management switch with a host that is
+    not connected to the rest of the fabric
+    """
+    network = DataCenterNetwork("dcn", NetworkType.Data)
+    edge = Router(
+        "edge",
+        "edge",
+        "rtr",
+        1,
+        RouterType.Edge,
+        DeviceStatus.Up,
+        [port("e-l")],
+        [port("e-w")],
+    )
+    firewall = Firewall(
+        "fw",
+        "fw",
+        "fw",
+        1,
+        DeviceStatus.Up,
+        [port("fw-l"), port("fw-l2")],
+        [port("fw-w")],
+    )
+    access = create_switch("acc", SwitchType.Access, 2, 1)
+    management = create_switch("mgmt", SwitchType.Access, 1, 1)
+    web = create_server("web", nic_speed_gb=100)
+    host = create_server("host", nic_speed_gb=100)
+    web_nic, host_nic = web.nic[0], host.nic[0]
+    network.add_multiple([edge, firewall, access, management, web_nic, host_nic])
+    cable(network, edge, edge.lan_ports[0], firewall, firewall.wan_ports[0])
+    cable(network, firewall, firewall.lan_ports[0], access, access.uplink_ports[0])
+    cable(network, web_nic, web_nic.lan_ports[0], access, access.lan_ports[0])
+    cable(
+        network, host_nic, host_nic.lan_ports[0], management, management.lan_ports[0]
+    )
+    dc = DataCenter(
+        "dc",
+        1,
+        [Row("row", 1, [Cabinet("cab", "cab", "cab", 1, [web, host], [], [])])],
+    )
+    return dc, network, edge, firewall, access
+
+
+def test_server_behind_firewall_is_protected():
+    dc, network, _, _, _ = zoned_fabric()
+    report = server_zones(dc, network)
+    (web,) = [s for s in report.servers if s.serial_number == "web"]
+    assert web.zone == Zone.Protected
+    assert web.firewalls == ["fw"]
+    assert report.edge_routers == ["edge"]
+
+
+def test_bypass_link_exposes_server():
+    dc, network, edge, _, access = zoned_fabric()
+    bypass = port("e-l2")
+    edge.lan_ports.append(bypass)
+    cable(network, edge, bypass, access, access.lan_ports[1])
+    (web,) = server_zones(dc, network).of(Zone.Exposed)
+    assert web.example_path == ["edge", "acc", "web-nic"]
+
+
+def test_lan_to_lan_through_firewall_is_not_mediated():
+    dc, network, edge, firewall, _ = zoned_fabric()
+    network.graph.remove_edge(edge, firewall)
+    cable(network, edge, edge.lan_ports[0], firewall, firewall.lan_ports[1])
+    (web,) = server_zones(dc, network).of(Zone.Exposed)
+    assert web.example_path == ["edge", "fw", "acc", "web-nic"]
+
+
+def test_management_only_host_is_isolated():
+    dc, network, _, _, _ = zoned_fabric()
+    report = server_zones(dc, network)
+    assert [s.serial_number for s in report.of(Zone.Isolated)] == ["host"]
+    assert report.to_dict()["servers"][1] == {
+        "serial_number": "host",
+        "name": "host",
+        "zone": "isolated",
+        "firewalls": [],
+        "example_path": [],
+    }
+
+
+def test_down_devices_can_be_ignored():
+    dc, network, _, firewall, _ = zoned_fabric()
+    firewall.status = DeviceStatus.Down
+    assert server_zones(dc, network).of(Zone.Isolated)[0].serial_number == "web"
+    report = server_zones(dc, network, ignore_status=True)
+    assert [s.serial_number for s in report.of(Zone.Protected)] == ["web"]
@@ -0,0 +1,138 @@
+# -*- coding: utf-8 -*-#
+import os
+
+import pytest
+
+from horao.models import (
+    DeviceStatus,
+    Firewall,
+    Packet,
+    PortMatcher,
+    Protocol,
+    Router,
+    RouterType,
+)
+from horao.models.network import SwitchType
+from horao.parse.vendor import apply, parse, wildcard_to_netmask
+from tests import create_switch
+
+FIXTURES = os.path.join(os.path.dirname(__file__), "fixtures", "vendor")
+
+
+def read(name: str) -> str:
+    with open(os.path.join(FIXTURES, name)) as f:
+        return f.read()
+
+
+def test_wildcard_to_netmask():
+    assert wildcard_to_netmask("0.0.0.255") == "255.255.255.0"
+    assert wildcard_to_netmask("0.0.255.255") == "255.255.0.0"
+    assert wildcard_to_netmask("0.0.0.0") == "255.255.255.255"
+    assert wildcard_to_netmask("255.255.255.255") == "0.0.0.0"
+    with pytest.raises(ValueError):
+        wildcard_to_netmask("0.255.0.255")
+    with pytest.raises(ValueError):
+        wildcard_to_netmask("not-a-mask")
+
+
+def test_parse_interfaces():
+    config = parse(read("interfaces.cfg"))
+    interfaces = {i.name: i for i in config.interfaces}
+    assert list(interfaces) == ["Ethernet1", "Ethernet2", "Ethernet49", "Ethernet50"]
+    assert interfaces["Ethernet1"].description == "server rack 1"
+    assert interfaces["Ethernet1"].speed_gb == 10
+    assert interfaces["Ethernet1"].access_vlan == 100
+    assert interfaces["Ethernet2"].trunk_vlans == [100, 200, 201, 202]
+    assert interfaces["Ethernet2"].shutdown is True
+    assert interfaces["Ethernet49"].speed_gb == 100
+    assert interfaces["Ethernet49"].address.address == "10.0.0.1"
+    assert interfaces["Ethernet49"].address.netmask == "255.255.255.252"
+    assert interfaces["Ethernet49"].shutdown is False
+    assert interfaces["Ethernet50"].address.netmask == "255.255.255.252"
+    assert interfaces["Ethernet50"].shutdown is None
+
+
+def test_parse_routes():
+    config = parse(read("routes.cfg"))
+    assert [
+        (r.destination.address, r.destination.netmask, r.gateway.address, r.metric)
+        for r in config.routes
+    ] == [
+        ("0.0.0.0", "0.0.0.0", "192.168.1.1", 1),
+        ("10.1.0.0", "255.255.0.0", "10.0.0.2", 20),
+    ]
+
+
+def test_parse_access_lists():
+    config = parse(read("acl.cfg"))
+    rules = {r.name: r for r in config.rules}
+    assert list(rules) == [
+        "101 10",
+        "101 20",
+        "10 10",
+        "WEB 10",
+        "WEB 20",
+        "WEB 30",
+        "WEB 50",
+    ]
+    web = rules["101 10"]
+    assert (web.action, web.protocol) == ("allow", Protocol.TCP)
+    assert web.port == PortMatcher.single(80)
+    assert (web.source.address, web.source.netmask) == ("10.0.0.0", "255.255.255.0")
+    assert (web.destination.address, web.destination.netmask) == ("0.0.0.0", "0.0.0.0")
+    assert rules["101 20"].action == "deny"
+    assert rules["101 20"].protocol is None
+    assert rules["10 10"].port is None
+    assert rules["WEB 10"].destination.netmask == "255.255.255.255"
+    assert rules["WEB 20"].source.netmask == "255.255.0.0"
+    assert rules["WEB 30"].port == PortMatcher.range(8000, 8100)
+
+
+def test_unknown_lines_are_reported():
+    assert [(u.number, u.text) for u in parse(read("interfaces.cfg")).unknown] == [
+        (1, "hostname access-1"),
+        (21, "spanning-tree portfast"),
+    ]
+    routes = parse(read("routes.cfg")).unknown
+    assert [u.number for u in routes] == [3, 4]
+    assert routes[1].reason == "unknown statement"
+    acl = parse(read("acl.cfg")).unknown
+    assert [u.number for u in acl] == [10]
+
+
+def test_apply_onto_switch():
+    switch = create_switch("access-1", SwitchType.Access, 2, 2)
+    for port, name in zip(
+        switch.lan_ports + switch.uplink_ports,
+        ["Ethernet1", "Ethernet2", "Ethernet49", "Ethernet51"],
+    ):
+        port.name = name
+    assert apply(switch, parse(read("interfaces.cfg"))) == ["Ethernet50"]
+    ethernet1, ethernet2 = switch.lan_ports
+    assert (ethernet1.speed_gb, ethernet1.access_vlan) == (10, 100)
+    assert ethernet2.trunk_vlans == [100, 200, 201, 202]
+    assert not ethernet2.admin_up
+    assert switch.uplink_ports[0].address.address == "10.0.0.1"
+    # not configured, left as is
+    assert switch.uplink_ports[1].speed_gb == 100
+    assert switch.uplink_ports[1].address is None
+    with pytest.raises(ValueError):
+        apply(switch, parse(read("routes.cfg")))
+
+
+def test_apply_onto_router_and_firewall():
+    router = Router("r1", "r1", "rtr", 1, RouterType.Edge, DeviceStatus.Up, [], [])
+    apply(router, parse(read("routes.cfg")))
+    apply(router, parse("ip route 0.0.0.0 0.0.0.0 192.168.1.254\n"))
+    assert [(r.destination.address, r.gateway.address) for r in router.routes] == [
+        ("10.1.0.0", "10.0.0.2"),
+        ("0.0.0.0", "192.168.1.254"),
+    ]
+    firewall = Firewall("fw1", "fw1", "fw", 1, DeviceStatus.Up, [], [])
+    apply(firewall, parse(read("acl.cfg")))
+    apply(firewall, parse("access-list 101 permit tcp any any eq 80\n"))
+    assert len(firewall.rules) == 7
+    assert firewall.rules[0].source.netmask == "0.0.0.0"
+    # the replaced rule now allows any source, the deny of 101 precedes the WEB rules
+    assert firewall.evaluate(Packet("8.8.8.8", "10.1.0.5", Protocol.TCP, 80)) == "allow"
+    assert firewall.evaluate(Packet("8.8.8.8", "10.1.0.5", Protocol.TCP, 443)) == "deny"
@@ -0,0 +1,47 @@
+# -*- coding: utf-8 -*-#
+import os
+
+from horao.models import DeviceStatus
+from horao.report.html import ReportOptions, render_html
+from tests import basic_networking_configuration
+
+FIXTURES = os.path.join(os.path.dirname(__file__), "fixtures", "report")
+
+
+def report_configuration():
+    dc, dcn, core, leaf_left, leaf_right, server = basic_networking_configuration()
+    dcn.link(leaf_left, core)
+    dcn.link(leaf_right, core)
+    leaf_right.status = DeviceStatus.Down
+    server.size_ru = 2
+    server.rated_power_watts = 800
+    server.cost = 12500.0
+    return dc, dcn, server
+
+
+def test_report_matches_golden_file():
+    dc, dcn, _ = report_configuration()
+    with open(os.path.join(FIXTURES, "basic.html")) as f:
+        assert render_html(dc, [dcn]) == f.read()
+    # rendering is deterministic
+    other_dc, other_dcn, _ = report_configuration()
+    assert render_html(other_dc, [other_dcn]) == render_html(dc, [dcn])
+
+
+def test_report_escapes_device_names():
+    dc, dcn, server = report_configuration()
+    server.name = '<script>alert("x")</script>'
+    html = render_html(dc, [dcn])
+    assert '<script>alert("x")</script>' not in html
+    assert "&lt;script&gt;alert(&quot;x&quot;)&lt;/script&gt;" in html
+
+
+def test_report_sections_can_be_toggled():
+    dc, dcn, _ = report_configuration()
+    html = render_html(dc, [dcn])
+    assert "12500.00" not in html and "<svg" in html and 'id="devices"' in html
+    html = render_html(dc, [dcn], ReportOptions(costs=True, networks=False))
+    assert "12500.00" in html and "<svg" not in html
+    html = render_html(dc, [dcn], ReportOptions(tree=False, devices=False))
+    assert "<details" not in html and "<script>" not in html
+    assert "<h2>Cabinets</h2>" in html
@@ -0,0 +1,43 @@
+# -*- coding: utf-8 -*-#
+import json
+
+from horao.api.servers_controller import servers_response
+from horao.models.store import ModelStore
+from tests.test_model_query import inventory
+
+
+def test_pages_carry_the_next_cursor():
+    store = ModelStore(inventory())
+    status_code, body = servers_response(
+        store, {"page_size": "2", "tag": "gpu", "status": "up", "total": "true"}
+    )
+    assert status_code == 200
+    content = json.loads(body)
+    assert [s["serial_number"] for s in content["items"]] == ["gpu1", "gpu2"]
+    assert content["total"] == 2
+    assert content["next_cursor"] is None
+    status_code, body = servers_response(store, {"page_size": "3"})
+    content = json.loads(body)
+    assert content["items"][0] == {
+        "serial_number": "cpu1",
+        "name": "cpu1",
+        "status": "Up",
+        "tenant": None,
+        "cores": 64,
+        "labels": {"role": "compute"},
+    }
+    _, body = servers_response(
+        store, {"page_size": "3", "cursor": content["next_cursor"]}
+    )
+    assert [s["serial_number"] for s in json.loads(body)["items"]] == ["gpu3", "st1"]
+
+
+def test_invalid_parameters_are_bad_requests():
+    store = ModelStore(inventory())
+    assert servers_response(store, {"cursor": "garbage"}) == (
+        400,
+        json.dumps({"error": "invalid cursor"}),
+    )
+    assert servers_response(store, {"page_size": "a"})[0] == 400
+    assert servers_response(store, {"page_size": "0"})[0] == 400
+    assert servers_response(store, {"status": "sideways"})[0] == 400
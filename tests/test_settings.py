@@ -0,0 +1,125 @@
+# -*- coding: utf-8 -*-#
+import inspect
+import os
+
+import pytest
+
+from horao.models import CPU, RAM, Disk
+from horao.settings import (
+    CapacityUnits,
+    ComputeUnit,
+    ConfigError,
+    Settings,
+    SettingsBuilder,
+    StorageUnit,
+)
+from tests import basic_networking_configuration
+
+FIXTURES = os.path.join(os.path.dirname(__file__), "fixtures")
+
+
+def test_settings_from_file():
+    settings = Settings.from_file(os.path.join(FIXTURES, "settings.toml"))
+    assert settings.retry_max_attempts == 5
+    assert settings.retry_base_delay == 1
+    assert settings.retry_jitter == Settings().retry_jitter
+    assert settings.snapshot_path == "/var/lib/horao/snapshots"
+
+
+def test_malformed_settings_file_names_the_file():
+    path = os.path.join(FIXTURES, "settings_invalid.toml")
+    with pytest.raises(ConfigError) as e:
+        Settings.from_file(path)
+    assert e.value.source_file == path
+    assert "settings_invalid.toml" in str(e.value)
+
+
+def test_mistyped_setting_names_the_key(tmp_path):
+    path = os.path.join(str(tmp_path), "settings.toml")
+    with open(path, "w") as f:
+        f.write('retry_max_attempts = "three"\n')
+    with pytest.raises(ConfigError) as e:
+        Settings.from_file(path)
+    assert e.value.key == "retry_max_attempts"
+
+
+def test_mistyped_environment_variable_names_the_variable():
+    os.environ["RETRY_MAX_ATTEMPTS"] = "three"
+    try:
+        with pytest.raises(ConfigError) as e:
+            Settings.from_env()
+        assert e.value.source_file == "environment"
+        assert e.value.key == "RETRY_MAX_ATTEMPTS"
+    finally:
+        del os.environ["RETRY_MAX_ATTEMPTS"]
+
+
+def test_summary_respects_capacity_units():
+    dc, _, _, _, _, srv = basic_networking_configuration()
+    srv.cpu += [CPU(f"cpu{i}", "cpu", "cpu", i, 3000, 32, None) for i in range(2)]
+    srv.ram.append(RAM("ram", "ram", "ram", 1, 512, None))
+    srv.disk.append(Disk("disk", "disk", "disk", 1, 4000))
+    decimal = dc.summary(
+        units=Settings(capacity_compute_unit="Cores").capacity_units
+    )
+    assert (decimal.compute, decimal.ram_gb, decimal.disk_gb) == (64, 512, 4000)
+    binary = dc.summary(
+        units=Settings(
+            capacity_compute_unit="Sockets", capacity_storage_unit="GiB"
+        ).capacity_units
+    )
+    # 512 GB is 476.84 GiB, 4 TB is 3725.29 GiB
+    assert (binary.compute, binary.ram_gb, binary.disk_gb) == (2, 476.84, 3725.29)
+    assert binary.units == CapacityUnits(ComputeUnit.Sockets, StorageUnit.GiB)
+    assert dc.summary().units == CapacityUnits()
+
+
+def test_unknown_capacity_unit_names_the_key(tmp_path):
+    path = os.path.join(str(tmp_path), "settings.toml")
+    with open(path, "w") as f:
+        f.write('capacity_storage_unit = "TB"\n')
+    with pytest.raises(ConfigError) as e:
+        Settings.from_file(path)
+    assert e.value.key == "capacity_storage_unit"
+    os.environ["CAPACITY_COMPUTE_UNIT"] = "threads"
+    try:
+        with pytest.raises(ConfigError) as e:
+            Settings.from_env()
+        assert e.value.key == "CAPACITY_COMPUTE_UNIT"
+    finally:
+        del os.environ["CAPACITY_COMPUTE_UNIT"]
+
+
+def test_settings_builder():
+    settings = (
+        Settings.builder()
+        .retry_max_attempts(7)
+        .analysis_max_duration(2)
+        .capacity_storage_unit("GiB")
+        .build()
+    )
+    assert settings.retry_max_attempts == 7
+    assert settings.analysis_max_duration == 2
+    assert settings.capacity_units.storage == StorageUnit.GiB
+    assert settings.snapshot_path == Settings().snapshot_path
+    with pytest.raises(AttributeError):
+        Settings.builder().log_colour("red")
+
+
+def test_settings_builder_has_a_method_per_setting():
+    methods = {
+        name
+        for name, _ in inspect.getmembers(SettingsBuilder, inspect.isfunction)
+        if not name.startswith("_")
+    }
+    assert methods - {"build"} == set(Settings._defaults())
+
+
+def test_settings_builder_validates():
+    with pytest.raises(ConfigError) as e:
+        Settings.builder().capacity_compute_unit("Threads").build()
+    assert e.value.source_file == "builder"
+    assert e.value.key == "capacity_compute_unit"
+    with pytest.raises(ConfigError) as e:
+        Settings.builder().retry_max_attempts("three").build()
+    assert e.value.key == "retry_max_attempts"
@@ -0,0 +1,23 @@
+# -*- coding: utf-8 -*-#
+from starlette.testclient import TestClient
+
+from horao import init_api
+from horao.models.versioning import MODEL_SCHEMA_VERSION
+from tests import basic_auth
+
+
+def test_get_version_unauthorized():
+    ia = init_api()
+    with TestClient(ia) as client:
+        lg = client.get("/version")
+        assert 403 == lg.status_code
+
+
+def test_get_version_authorized():
+    ia = init_api()
+    with TestClient(ia) as client:
+        lg = client.get(
+            "/version", headers={"Authorization": basic_auth("netadm", "secret")}
+        )
+        assert 200 == lg.status_code
+        assert lg.json() == {"schema_version": MODEL_SCHEMA_VERSION}